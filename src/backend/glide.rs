@@ -0,0 +1,189 @@
+//! Gliding pitch bends: Instead of jumping to a new bend value, send a time-interpolated series
+//! of pitch bends. The intermediate values are sent on the ticks of the timer.
+
+use std::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use midi_msg::{Channel, ChannelVoiceMsg, MidiMsg};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::msg::FromBackend;
+
+/// How often intermediate pitch bends are sent.
+const TICK: Duration = Duration::from_millis(5);
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct GlideConfig {
+    pub duration_ms: u64,
+    pub curve: GlideCurve,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum GlideCurve {
+    /// Move at constant speed (in cents per second).
+    Linear,
+    /// Move fast at first and slow down towards the end, like an analogue portamento.
+    Exponential,
+}
+
+impl GlideCurve {
+    /// The fraction of the way covered after the fraction `x` of the duration. Both are between 0
+    /// and 1.
+    fn progress(&self, x: f64) -> f64 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            GlideCurve::Linear => x,
+            GlideCurve::Exponential => (1.0 - (-5.0 * x).exp()) / (1.0 - (-5.0f64).exp()),
+        }
+    }
+}
+
+struct Glide {
+    channel: Channel,
+    from: u16,
+    to: u16,
+    start: Instant,
+    duration: Duration,
+    curve: GlideCurve,
+    /// The last bend that was actually sent.
+    last_sent: u16,
+}
+
+impl Glide {
+    fn bend_at(&self, time: Instant) -> u16 {
+        if self.duration.is_zero() {
+            return self.to;
+        }
+        let x =
+            time.saturating_duration_since(self.start).as_secs_f64() / self.duration.as_secs_f64();
+        let p = self.curve.progress(x);
+        (self.from as f64 + p * (self.to as f64 - self.from as f64)).round() as u16
+    }
+}
+
+/// Runs glides on a number of slots (usually, one per channel). There's at most one glide per
+/// slot: starting a new one supersedes the old one. While glides are running, the [Glider] asks
+/// for a [ToBackend::Tick][crate::msg::ToBackend::Tick] every few milliseconds, and sends the
+/// intermediate bends on [Glider::tick].
+pub struct Glider {
+    config: GlideConfig,
+    /// One slot per channel that may glide.
+    glides: Vec<Option<Glide>>,
+    /// The time of the tick that was asked for last, if it hasn't come yet.
+    next_tick: Option<Instant>,
+}
+
+impl Glider {
+    pub fn new(config: GlideConfig, slots: usize) -> Self {
+        Self {
+            config,
+            glides: (0..slots).map(|_| None {}).collect(),
+            next_tick: None {},
+        }
+    }
+
+    pub fn config(&self) -> GlideConfig {
+        self.config
+    }
+
+    /// Stop the glide on the given slot, if there's one. Returns the last bend that was sent in
+    /// that glide.
+    pub fn cancel(&mut self, slot: usize) -> Option<u16> {
+        self.glides[slot].take().map(|g| g.last_sent)
+    }
+
+    /// Stop all glides. Returns the slots on which glides were running.
+    pub fn cancel_all(&mut self) -> Vec<usize> {
+        self.glides
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(slot, g)| g.take().map(|_| slot))
+            .collect()
+    }
+
+    /// Start gliding from the bend `from` to the bend `to` on the given slot, superseding any
+    /// glide that may already be running there. The caller is responsible for having sent `from`
+    /// already.
+    pub fn start(
+        &mut self,
+        slot: usize,
+        channel: Channel,
+        from: u16,
+        to: u16,
+        time: Instant,
+        forward: &mpsc::Sender<FromBackend>,
+    ) {
+        self.glides[slot] = Some(Glide {
+            channel,
+            from,
+            to,
+            start: time,
+            duration: Duration::from_millis(self.config.duration_ms),
+            curve: self.config.curve,
+            last_sent: from,
+        });
+        if self.next_tick.is_none() {
+            self.ask_for_tick(time + TICK, forward);
+        }
+    }
+
+    /// Send the bends of all glides that are due at `time`, and remove the glides that have
+    /// ended. Ticks that weren't asked for by this [Glider] are ignored.
+    pub fn tick(&mut self, time: Instant, forward: &mpsc::Sender<FromBackend>) {
+        match self.next_tick {
+            Some(next_tick) if next_tick <= time => {}
+            _ => return,
+        }
+        self.next_tick = None {};
+        for slot in self.glides.iter_mut() {
+            let Some(glide) = slot else {
+                continue;
+            };
+            let bend = glide.bend_at(time);
+            if bend != glide.last_sent {
+                let _ = forward.send(FromBackend::OutgoingMidi {
+                    time,
+                    bytes: MidiMsg::ChannelVoice {
+                        channel: glide.channel,
+                        msg: ChannelVoiceMsg::PitchBend { bend },
+                    }
+                    .to_midi(),
+                });
+                glide.last_sent = bend;
+            }
+            if bend == glide.to {
+                *slot = None {};
+            }
+        }
+        if self.glides.iter().any(Option::is_some) {
+            self.ask_for_tick(time + TICK, forward);
+        }
+    }
+
+    fn ask_for_tick(&mut self, time: Instant, forward: &mpsc::Sender<FromBackend>) {
+        self.next_tick = Some(time);
+        let _ = forward.send(FromBackend::WakeUp { time });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_curves() {
+        for curve in [GlideCurve::Linear, GlideCurve::Exponential] {
+            assert_eq!(curve.progress(0.0), 0.0);
+            assert!((curve.progress(1.0) - 1.0).abs() < 1e-12);
+            assert_eq!(curve.progress(2.0), curve.progress(1.0));
+        }
+        assert_eq!(GlideCurve::Linear.progress(0.5), 0.5);
+        assert!(GlideCurve::Exponential.progress(0.5) > 0.9);
+    }
+}
@@ -1,3 +1,82 @@
+use std::sync::mpsc;
+
+use crate::{
+    config::{BackendConfig, ExtractConfig, FromConfigAndState},
+    msg::{FromBackend, HandleMsg, ToBackend},
+};
+
+pub mod glide;
+pub mod mpe;
+pub mod mts;
 pub mod onlyforward;
-// pub mod pitchbend; // temporarily disabled because it's buggy
 pub mod pitchbend12;
+
+/// The backend selected by the [BackendConfig]. Restarting with a different kind of
+/// [BackendConfig] switches the backend.
+pub enum SomeBackend {
+    Pitchbend12(Box<pitchbend12::Pitchbend12>),
+    Mts(Box<mts::Mts>),
+    Mpe(Box<mpe::Mpe>),
+    /// Messages for a zone go to its backend, all others go to every backend.
+    Zoned(Vec<SomeBackend>),
+}
+
+impl HandleMsg<ToBackend, FromBackend> for SomeBackend {
+    fn handle_msg(&mut self, msg: ToBackend, forward: &mpsc::Sender<FromBackend>) {
+        match (msg, self) {
+            (ToBackend::RestartWithConfig { config, time }, this) => {
+                *this = <Self as FromConfigAndState<_, _>>::initialise(config, ());
+                this.handle_msg(ToBackend::Reset { time }, forward);
+            }
+            (ToBackend::InZone { zone, msg }, SomeBackend::Zoned(backends)) => {
+                if let Some(backend) = backends.get_mut(zone) {
+                    backend.handle_msg(*msg, forward);
+                }
+            }
+            // One backend for all zones
+            (ToBackend::InZone { msg, .. }, this) => this.handle_msg(*msg, forward),
+            (ToBackend::GetCurrentConfig, this @ SomeBackend::Zoned(_)) => {
+                let _ = forward.send(FromBackend::CurrentConfig(this.extract_config()));
+            }
+            (msg, SomeBackend::Zoned(backends)) => {
+                for backend in backends {
+                    backend.handle_msg(msg.clone(), forward);
+                }
+            }
+            (msg, SomeBackend::Pitchbend12(backend)) => backend.handle_msg(msg, forward),
+            (msg, SomeBackend::Mts(backend)) => backend.handle_msg(msg, forward),
+            (msg, SomeBackend::Mpe(backend)) => backend.handle_msg(msg, forward),
+        }
+    }
+}
+
+impl ExtractConfig<BackendConfig> for SomeBackend {
+    fn extract_config(&self) -> BackendConfig {
+        match self {
+            SomeBackend::Pitchbend12(backend) => backend.extract_config(),
+            SomeBackend::Mts(backend) => backend.extract_config(),
+            SomeBackend::Mpe(backend) => backend.extract_config(),
+            SomeBackend::Zoned(backends) => {
+                BackendConfig::Zoned(backends.iter().map(|b| b.extract_config()).collect())
+            }
+        }
+    }
+}
+
+impl<S> FromConfigAndState<BackendConfig, S> for SomeBackend {
+    fn initialise(config: BackendConfig, _state: S) -> Self {
+        match config {
+            BackendConfig::Pitchbend12(config) => {
+                SomeBackend::Pitchbend12(Box::new(pitchbend12::Pitchbend12::new(config)))
+            }
+            BackendConfig::Mts(config) => SomeBackend::Mts(Box::new(mts::Mts::new(config))),
+            BackendConfig::Mpe(config) => SomeBackend::Mpe(Box::new(mpe::Mpe::new(config))),
+            BackendConfig::Zoned(configs) => SomeBackend::Zoned(
+                configs
+                    .into_iter()
+                    .map(|config| Self::initialise(config, ()))
+                    .collect(),
+            ),
+        }
+    }
+}
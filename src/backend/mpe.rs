@@ -0,0 +1,622 @@
+//! A backend for synths that understand MIDI Polyphonic Expression (MPE). Every sounding note gets
+//! its own member channel of an MPE zone, and thus its own pitch bend. This works for all tuning
+//! systems, also the ones that aren't octave-periodic, as long as not more notes sound at the same
+//! time than there are member channels.
+//!
+//! - Member channels are assigned in rotation, so that the release of a note is not cut short by
+//!   the pitch bend of the next one on the same channel.
+//!
+//! - If all member channels are in use, a voice is stolen: preferably the oldest one whose key was
+//!   already released (but which is held by the sustain pedal), otherwise the oldest one. Its
+//!   member channel gets an "all sound off" after the note-off, so that the pedal on the master
+//!   channel (which applies to the whole zone) doesn't keep it sounding under the bend of the new
+//!   note.
+//!
+//! - Notes will be considered "on" exactly if there's at least one input channel on which they are
+//!   sounding, like in the other backends.
+
+use std::{sync::mpsc, time::Instant};
+
+use midi_msg::{Channel, ChannelModeMsg, ChannelVoiceMsg, ControlChange, MidiMsg, Parameter};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    config::{BackendConfig, ExtractConfig},
+    interval::base::Semitones,
+    keystate::KeyState,
+    msg::{self, FromBackend, HandleMsg, ToBackend},
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum MpeZone {
+    /// Master channel 1, member channels from 2 upwards
+    Lower,
+    /// Master channel 16, member channels from 15 downwards
+    Upper,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[derive(Clone, PartialEq)]
+pub struct MpeConfig {
+    pub zone: MpeZone,
+    /// The number of member channels, 1 to 15.
+    pub member_channels: u8,
+    /// The pitch bend range of the member channels, in semitones. MPE synths use 48 by default.
+    pub bend_range: u8,
+}
+
+/// A note sounding on a member channel.
+#[derive(Clone, Copy)]
+struct Voice {
+    /// The incoming note
+    key: u8,
+    /// The outgoing note, i.e. the one closest to the tuning of the `key` when it started
+    note: u8,
+    started: Instant,
+}
+
+struct MemberChannel {
+    channel: Channel,
+    bend: u16,
+    voice: Option<Voice>,
+}
+
+pub struct Mpe {
+    config: MpeConfig,
+    master: Channel,
+    members: Vec<MemberChannel>,
+    /// index into `members` of the last channel that got a new voice
+    last_assigned: usize,
+    key_state: [KeyState; 128],
+    /// is the sustain pedal held at the moment? (for each input channel)
+    pedal_hold: [bool; 16],
+}
+
+impl MpeConfig {
+    /// The master channel, and the member channels in the order in which they're used.
+    pub fn channels(&self) -> (Channel, Vec<Channel>) {
+        let n = self.member_channels.clamp(1, 15);
+        match self.zone {
+            MpeZone::Lower => (Channel::Ch1, (1..=n).map(Channel::from_u8).collect()),
+            MpeZone::Upper => (
+                Channel::Ch16,
+                (1..=n).map(|i| Channel::from_u8(15 - i)).collect(),
+            ),
+        }
+    }
+}
+
+impl Mpe {
+    pub fn new(config: MpeConfig) -> Self {
+        let now = Instant::now();
+        let (master, members) = config.channels();
+        Self {
+            config,
+            master,
+            last_assigned: members.len() - 1,
+            members: members
+                .into_iter()
+                .map(|channel| MemberChannel {
+                    channel,
+                    bend: 8192,
+                    voice: None {},
+                })
+                .collect(),
+            key_state: core::array::from_fn(|_| KeyState::new(now)),
+            pedal_hold: [false; 16],
+        }
+    }
+
+    fn bend_from_semitones(&self, semitones: Semitones) -> u16 {
+        (8191.0 * semitones / self.config.bend_range as Semitones + 8192.0).clamp(0.0, 16383.0)
+            as u16
+    }
+
+    fn semitones_from_bend(&self, bend: u16) -> Semitones {
+        (bend as Semitones - 8192.0) / 8191.0 * self.config.bend_range as Semitones
+    }
+
+    fn member_index_of_key(&self, key: u8) -> Option<usize> {
+        self.members
+            .iter()
+            .position(|m| m.voice.is_some_and(|v| v.key == key))
+    }
+
+    /// The next member channel in rotation that has no voice. If there's none, the channel of the
+    /// voice that should be stolen.
+    fn choose_member_index(&self) -> (usize, bool) {
+        let n = self.members.len();
+        let rotation = (1..=n).map(|i| (self.last_assigned + i) % n);
+        for i in rotation {
+            if self.members[i].voice.is_none() {
+                return (i, false);
+            }
+        }
+        let oldest = |pressed: bool| {
+            self.members
+                .iter()
+                .enumerate()
+                .filter_map(|(i, m)| m.voice.map(|v| (i, v)))
+                .filter(|(_, v)| self.key_state[v.key as usize].is_pressed() == pressed)
+                .min_by_key(|(_, v)| v.started)
+                .map(|(i, _)| i)
+        };
+        (oldest(false).or_else(|| oldest(true)).unwrap_or(0), true)
+    }
+
+    fn send_bend(
+        &mut self,
+        member_index: usize,
+        key: u8,
+        tuning: Semitones,
+        time: Instant,
+        forward: &mpsc::Sender<FromBackend>,
+    ) {
+        let Some(voice) = self.members[member_index].voice else {
+            return;
+        };
+        let offset = tuning - voice.note as Semitones;
+        let desired_bend = self.bend_from_semitones(offset);
+        let member = &mut self.members[member_index];
+        if member.bend != desired_bend {
+            let _ = forward.send(msg::FromBackend::OutgoingMidi {
+                time,
+                bytes: MidiMsg::ChannelVoice {
+                    channel: member.channel,
+                    msg: ChannelVoiceMsg::PitchBend { bend: desired_bend },
+                }
+                .to_midi(),
+            });
+            member.bend = desired_bend;
+        }
+        if offset.abs() > self.config.bend_range as Semitones {
+            let _ = forward.send(FromBackend::DetunedNote {
+                note: key,
+                should_be: tuning,
+                actual: voice.note as Semitones + self.semitones_from_bend(desired_bend),
+                explanation: "exceeded bend range",
+            });
+        }
+    }
+
+    fn tuned_note_on(
+        &mut self,
+        input_channel: Channel,
+        key: u8,
+        velocity: u8,
+        tuning: Semitones,
+        time: Instant,
+        forward: &mpsc::Sender<FromBackend>,
+    ) {
+        let send_midi = |msg: MidiMsg| {
+            let _ = forward.send(msg::FromBackend::OutgoingMidi {
+                time,
+                bytes: msg.to_midi(),
+            });
+        };
+
+        self.key_state[key as usize].note_on(input_channel, time);
+
+        // The key is already sounding (for example, from another input channel, or held by the
+        // pedal): retrigger it on the same member channel.
+        if let Some(i) = self.member_index_of_key(key) {
+            let member = &mut self.members[i];
+            let voice = member.voice.unwrap(); // ok because of `member_index_of_key`
+            send_midi(MidiMsg::ChannelVoice {
+                channel: member.channel,
+                msg: ChannelVoiceMsg::NoteOn {
+                    note: voice.note,
+                    velocity,
+                },
+            });
+            self.send_bend(i, key, tuning, time, forward);
+            return;
+        }
+
+        let (i, steal) = self.choose_member_index();
+        if steal {
+            let member = &self.members[i];
+            let stolen = member.voice.unwrap(); // ok, we only steal channels with voices
+            send_midi(MidiMsg::ChannelVoice {
+                channel: member.channel,
+                msg: ChannelVoiceMsg::NoteOff {
+                    note: stolen.note,
+                    velocity: 64,
+                },
+            });
+            send_midi(MidiMsg::ChannelMode {
+                channel: member.channel,
+                msg: ChannelModeMsg::AllSoundOff,
+            });
+        }
+
+        let note = tuning.round().clamp(0.0, 127.0) as u8;
+        self.members[i].voice = Some(Voice {
+            key,
+            note,
+            started: time,
+        });
+        self.last_assigned = i;
+
+        // set the bend before the note starts
+        self.send_bend(i, key, tuning, time, forward);
+        send_midi(MidiMsg::ChannelVoice {
+            channel: self.members[i].channel,
+            msg: ChannelVoiceMsg::NoteOn { note, velocity },
+        });
+    }
+
+    fn note_off(
+        &mut self,
+        input_channel: Channel,
+        key: u8,
+        velocity: u8,
+        time: Instant,
+        forward: &mpsc::Sender<FromBackend>,
+    ) {
+        let pedal_hold = self.pedal_hold[input_channel as usize];
+        let stopped = self.key_state[key as usize].note_off(input_channel, pedal_hold, time);
+        let Some(i) = self.member_index_of_key(key) else {
+            // the voice was stolen
+            return;
+        };
+        if stopped {
+            self.send_note_off(i, velocity, time, forward, true);
+        } else if !self.key_state[key as usize].is_pressed() {
+            // Only held by the pedal, which the synth also knows about. The member channel stays
+            // reserved until the pedal is released.
+            self.send_note_off(i, velocity, time, forward, false);
+        }
+    }
+
+    /// If `free` is true, the member channel can be used for the next note.
+    fn send_note_off(
+        &mut self,
+        member_index: usize,
+        velocity: u8,
+        time: Instant,
+        forward: &mpsc::Sender<FromBackend>,
+        free: bool,
+    ) {
+        let member = &mut self.members[member_index];
+        if let Some(voice) = member.voice {
+            let _ = forward.send(msg::FromBackend::OutgoingMidi {
+                time,
+                bytes: MidiMsg::ChannelVoice {
+                    channel: member.channel,
+                    msg: ChannelVoiceMsg::NoteOff {
+                        note: voice.note,
+                        velocity,
+                    },
+                }
+                .to_midi(),
+            });
+        }
+        if free {
+            member.voice = None {};
+        }
+    }
+
+    fn reset(&mut self, time: Instant, forward: &mpsc::Sender<FromBackend>) {
+        let send_midi = |msg: MidiMsg| {
+            let _ = forward.send(msg::FromBackend::OutgoingMidi {
+                time,
+                bytes: msg.to_midi(),
+            });
+        };
+        let control = |channel: Channel, control: ControlChange| MidiMsg::ChannelVoice {
+            channel,
+            msg: ChannelVoiceMsg::ControlChange { control },
+        };
+
+        // the same initialisations as in [Mpe::new].
+        *self = Self::new(self.config.clone());
+
+        // The MPE Configuration Message resets the bend range of the members to the default of
+        // 48 semitones, so it has to come first.
+        send_midi(control(
+            self.master,
+            ControlChange::Parameter(Parameter::PolyphonicExpressionEntry(
+                self.members.len() as u8
+            )),
+        ));
+        send_midi(control(self.master, ControlChange::Hold(0)));
+        send_midi(MidiMsg::ChannelMode {
+            channel: self.master,
+            msg: ChannelModeMsg::AllSoundOff,
+        });
+        for member in self.members.iter() {
+            send_midi(control(
+                member.channel,
+                ControlChange::Parameter(Parameter::PitchBendSensitivityEntry(
+                    self.config.bend_range,
+                    0,
+                )),
+            ));
+            send_midi(MidiMsg::ChannelVoice {
+                channel: member.channel,
+                msg: ChannelVoiceMsg::PitchBend { bend: member.bend },
+            });
+            send_midi(MidiMsg::ChannelMode {
+                channel: member.channel,
+                msg: ChannelModeMsg::AllSoundOff,
+            });
+        }
+    }
+}
+
+impl HandleMsg<ToBackend, FromBackend> for Mpe {
+    fn handle_msg(&mut self, msg: ToBackend, forward: &mpsc::Sender<FromBackend>) {
+        let master = self.master;
+        let send_to_master = |msg: ChannelVoiceMsg, time: Instant| {
+            let _ = forward.send(msg::FromBackend::OutgoingMidi {
+                time,
+                bytes: MidiMsg::ChannelVoice {
+                    channel: master,
+                    msg,
+                }
+                .to_midi(),
+            });
+        };
+
+        match msg {
+            ToBackend::Start { time } | ToBackend::Reset { time } => {
+                self.reset(time, forward);
+            }
+
+            ToBackend::Stop => {}
+
+            ToBackend::NoteOn {
+                channel,
+                note,
+                velocity,
+                time,
+            } => self.tuned_note_on(channel, note, velocity, note as Semitones, time, forward),
+
+            ToBackend::TunedNoteOn {
+                channel,
+                note,
+                velocity,
+                tuning,
+                time,
+            } => self.tuned_note_on(channel, note, velocity, tuning, time, forward),
+
+            ToBackend::NoteOff {
+                channel,
+                note,
+                velocity,
+                time,
+            } => self.note_off(channel, note, velocity, time, forward),
+
+            ToBackend::Retune { note, tuning, time } => {
+                if let Some(i) = self.member_index_of_key(note) {
+                    self.send_bend(i, note, tuning, time, forward);
+                }
+            }
+
+            ToBackend::PedalHold {
+                channel,
+                value,
+                time,
+            } => {
+                // In MPE, the sustain pedal applies to the whole zone if it is sent on the master
+                // channel.
+                send_to_master(
+                    ChannelVoiceMsg::ControlChange {
+                        control: ControlChange::Hold(value),
+                    },
+                    time,
+                );
+
+                self.pedal_hold[channel as usize] = value != 0;
+
+                if value == 0 {
+                    for key in 0..128 {
+                        if self.key_state[key].pedal_off(channel, time) {
+                            if let Some(i) = self.member_index_of_key(key as u8) {
+                                self.members[i].voice = None {};
+                            }
+                        }
+                    }
+                }
+            }
+
+            ToBackend::ProgramChange { program, time, .. } => {
+                send_to_master(ChannelVoiceMsg::ProgramChange { program }, time)
+            }
+
+            // the bend range of the member channels is part of the configuration, and the
+            // channels are determined by the zone.
+            ToBackend::BendRange { .. }
+            | ToBackend::ChannelsToUse { .. }
+            | ToBackend::Glide { .. }
+            | ToBackend::Tick { .. } => {}
+
+            ToBackend::InZone { msg, .. } => self.handle_msg(*msg, forward),
+
+            ToBackend::GetCurrentConfig => {
+                let _ = forward.send(FromBackend::CurrentConfig(self.extract_config()));
+            }
+            ToBackend::RestartWithConfig { .. } => {
+                unreachable!("restarts with a new configuration are handled by SomeBackend")
+            }
+            ToBackend::RestartWithCurrentConfig { time } => {
+                self.reset(time, forward);
+            }
+        }
+    }
+}
+
+impl ExtractConfig<BackendConfig> for Mpe {
+    fn extract_config(&self) -> BackendConfig {
+        BackendConfig::Mpe(self.config.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn mpe(member_channels: u8) -> Mpe {
+        Mpe::new(MpeConfig {
+            zone: MpeZone::Lower,
+            member_channels,
+            bend_range: 48,
+        })
+    }
+
+    fn sounding(mpe: &Mpe) -> Vec<(u8, Option<u8>)> {
+        mpe.members
+            .iter()
+            .map(|m| (m.channel as u8 + 1, m.voice.map(|v| v.key)))
+            .collect()
+    }
+
+    #[test]
+    fn test_rotation_and_stealing() {
+        let (tx, _rx) = mpsc::channel();
+        let mut mpe = mpe(3);
+        let start = Instant::now();
+        let t = |ms| start + Duration::from_millis(ms);
+
+        mpe.tuned_note_on(Channel::Ch1, 60, 100, 60.1, t(0), &tx);
+        mpe.tuned_note_on(Channel::Ch1, 64, 100, 63.86, t(1), &tx);
+        mpe.note_off(Channel::Ch1, 60, 0, t(2), &tx);
+        // the channel freed by the 60 is not re-used immediately
+        mpe.tuned_note_on(Channel::Ch1, 67, 100, 67.02, t(3), &tx);
+        assert_eq!(
+            sounding(&mpe),
+            vec![(2, None {}), (3, Some(64)), (4, Some(67))]
+        );
+        mpe.tuned_note_on(Channel::Ch1, 70, 100, 69.69, t(4), &tx);
+        assert_eq!(
+            sounding(&mpe),
+            vec![(2, Some(70)), (3, Some(64)), (4, Some(67))]
+        );
+
+        // all channels are in use: the oldest voice is stolen...
+        mpe.tuned_note_on(Channel::Ch1, 72, 100, 72.0, t(5), &tx);
+        assert_eq!(
+            sounding(&mpe),
+            vec![(2, Some(70)), (3, Some(72)), (4, Some(67))]
+        );
+
+        // ... unless there are voices that are only held by the pedal
+        mpe.handle_msg(
+            ToBackend::PedalHold {
+                channel: Channel::Ch1,
+                value: 127,
+                time: t(6),
+            },
+            &tx,
+        );
+        mpe.note_off(Channel::Ch1, 70, 0, t(7), &tx);
+        mpe.tuned_note_on(Channel::Ch1, 74, 100, 74.0, t(8), &tx);
+        assert_eq!(
+            sounding(&mpe),
+            vec![(2, Some(74)), (3, Some(72)), (4, Some(67))]
+        );
+    }
+
+    #[test]
+    fn test_presses_and_pedal() {
+        let (tx, rx) = mpsc::channel();
+        let mut mpe = mpe(2);
+        let start = Instant::now();
+        let t = |ms| start + Duration::from_millis(ms);
+        let sent = || -> Vec<MidiMsg> {
+            rx.try_iter()
+                .map(|msg| match msg {
+                    FromBackend::OutgoingMidi { bytes, .. } => {
+                        MidiMsg::from_midi(&bytes).unwrap().0
+                    }
+                    _ => panic!("unexpected message"),
+                })
+                .collect()
+        };
+        let voice = |channel, msg| MidiMsg::ChannelVoice { channel, msg };
+
+        mpe.tuned_note_on(Channel::Ch1, 60, 100, 60.0, t(0), &tx);
+        mpe.tuned_note_on(Channel::Ch2, 60, 100, 60.0, t(1), &tx);
+        mpe.tuned_note_on(Channel::Ch1, 62, 100, 62.0, t(2), &tx);
+        mpe.handle_msg(
+            ToBackend::PedalHold {
+                channel: Channel::Ch1,
+                value: 127,
+                time: t(3),
+            },
+            &tx,
+        );
+        sent();
+
+        // the 60 is still pressed on the second input channel
+        mpe.note_off(Channel::Ch1, 60, 0, t(4), &tx);
+        assert_eq!(sent(), vec![]);
+
+        // the 62 is only held by the pedal
+        mpe.note_off(Channel::Ch1, 62, 0, t(5), &tx);
+        assert_eq!(
+            sent(),
+            vec![voice(
+                Channel::Ch3,
+                ChannelVoiceMsg::NoteOff {
+                    note: 62,
+                    velocity: 0
+                }
+            )]
+        );
+
+        // so it is the one that's stolen, and silenced although the pedal is still down
+        mpe.tuned_note_on(Channel::Ch1, 64, 100, 64.0, t(6), &tx);
+        assert_eq!(sounding(&mpe), vec![(2, Some(60)), (3, Some(64))]);
+        assert_eq!(
+            sent(),
+            vec![
+                voice(
+                    Channel::Ch3,
+                    ChannelVoiceMsg::NoteOff {
+                        note: 62,
+                        velocity: 64
+                    }
+                ),
+                MidiMsg::ChannelMode {
+                    channel: Channel::Ch3,
+                    msg: ChannelModeMsg::AllSoundOff
+                },
+                voice(
+                    Channel::Ch3,
+                    ChannelVoiceMsg::NoteOn {
+                        note: 64,
+                        velocity: 100
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bend() {
+        let (tx, _rx) = mpsc::channel();
+        let mut mpe = mpe(2);
+        let t = Instant::now();
+
+        mpe.tuned_note_on(Channel::Ch1, 60, 100, 59.7, t, &tx);
+        assert_eq!(mpe.members[0].voice.unwrap().note, 60);
+        assert_eq!(mpe.members[0].bend, mpe.bend_from_semitones(-0.3));
+
+        mpe.handle_msg(
+            ToBackend::Retune {
+                note: 60,
+                tuning: 61.0,
+                time: t,
+            },
+            &tx,
+        );
+        assert_eq!(mpe.members[0].bend, mpe.bend_from_semitones(1.0));
+    }
+}
@@ -0,0 +1,351 @@
+//! A backend that uses MIDI Tuning Standard (MTS) "single note tuning change" SysEx messages to
+//! retune every key individually. Only one output channel is needed, and there is no limit on the
+//! number of distinct pitches that sound at the same time.
+
+use std::{sync::mpsc, time::Instant};
+
+use midi_msg::{
+    Channel, ChannelModeMsg, ChannelVoiceMsg, ControlChange, DeviceID, MidiMsg, Parameter,
+    SystemExclusiveMsg, Tuning, TuningNoteChange, UniversalNonRealTimeMsg, UniversalRealTimeMsg,
+};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    config::{BackendConfig, ExtractConfig},
+    custom_serde::common::{
+        deserialize_channel, deserialize_optional_data_byte, serialize_channel,
+    },
+    interval::base::Semitones,
+    msg::{self, FromBackend, HandleMsg, ToBackend},
+};
+
+pub struct Mts {
+    config: MtsConfig,
+
+    /// The last tuning sent for each key. `None` if nothing was sent since the last reset.
+    tunings: [Option<Tuning>; 128],
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[derive(Clone, PartialEq)]
+pub struct MtsConfig {
+    #[serde(
+        deserialize_with = "deserialize_channel",
+        serialize_with = "serialize_channel"
+    )]
+    pub channel: Channel,
+
+    /// Use the real-time variant of the messages, which also retunes sounding notes. Some synths
+    /// only understand the non-real-time variant.
+    pub realtime: bool,
+
+    /// The tuning program that is changed. If this is set, it is also selected (with RPN 3) on
+    /// startup.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_data_byte",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub program: Option<u8>,
+
+    /// The tuning bank that contains the [MtsConfig::program]. If this is set, it is also
+    /// selected (with RPN 4) on startup.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_data_byte",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bank: Option<u8>,
+}
+
+/// Returns `None` if the tuning is not in the range of MIDI notes.
+fn tuning_from_semitones(tuning: Semitones) -> Option<Tuning> {
+    if !(0.0..128.0).contains(&tuning) {
+        return None {};
+    }
+    let semitone = tuning.floor();
+    let fraction = ((tuning - semitone) * 16384.0).round() as u16;
+    Some(if fraction >= 16384 {
+        if semitone >= 127.0 {
+            // 0x7F 0x7F 0x7F means "no change", so the highest usable value is one step lower
+            Tuning {
+                semitone: 127,
+                fraction: 16382,
+            }
+        } else {
+            Tuning {
+                semitone: semitone as u8 + 1,
+                fraction: 0,
+            }
+        }
+    } else {
+        Tuning {
+            semitone: semitone as u8,
+            fraction: fraction.min(if semitone >= 127.0 { 16382 } else { 16383 }),
+        }
+    })
+}
+
+fn semitones_from_tuning(tuning: &Tuning) -> Semitones {
+    tuning.semitone as Semitones + tuning.fraction as Semitones / 16384.0
+}
+
+impl Mts {
+    pub fn new(config: MtsConfig) -> Self {
+        Self {
+            config,
+            tunings: [None {}; 128],
+        }
+    }
+
+    fn tuning_message(&self, note: u8, tuning: Tuning) -> MidiMsg {
+        let change = TuningNoteChange {
+            tuning_program_num: self.config.program.unwrap_or(0),
+            tuning_bank_num: self.config.bank,
+            tunings: vec![(note, Some(tuning))],
+        };
+        MidiMsg::SystemExclusive {
+            msg: if self.config.realtime {
+                SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalRealTimeMsg::TuningNoteChange(change),
+                }
+            } else {
+                SystemExclusiveMsg::UniversalNonRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalNonRealTimeMsg::TuningNoteChange(change),
+                }
+            },
+        }
+    }
+
+    fn handle_retune(
+        &mut self,
+        note: u8,
+        tuning: Semitones,
+        time: Instant,
+        forward: &mpsc::Sender<FromBackend>,
+    ) {
+        let desired = tuning_from_semitones(tuning).unwrap_or_else(|| {
+            let clamped = tuning_from_semitones(tuning.clamp(0.0, 127.99)).unwrap();
+            let _ = forward.send(FromBackend::DetunedNote {
+                note,
+                should_be: tuning,
+                actual: semitones_from_tuning(&clamped),
+                explanation: "outside of the MIDI Tuning Standard range",
+            });
+            clamped
+        });
+        if self.tunings[note as usize] != Some(desired) {
+            let _ = forward.send(FromBackend::OutgoingMidi {
+                time,
+                bytes: self.tuning_message(note, desired).to_midi(),
+            });
+            self.tunings[note as usize] = Some(desired);
+        }
+    }
+
+    fn reset(&mut self, time: Instant, forward: &mpsc::Sender<FromBackend>) {
+        let send_midi = |msg: MidiMsg, original_time: Instant| {
+            let _ = forward.send(msg::FromBackend::OutgoingMidi {
+                time: original_time,
+                bytes: msg.to_midi(),
+            });
+        };
+
+        // the same initialisation as in [Mts::new].
+        self.tunings = [None {}; 128];
+
+        let channel = self.config.channel;
+        if let Some(bank) = self.config.bank {
+            send_midi(
+                MidiMsg::ChannelVoice {
+                    channel,
+                    msg: ChannelVoiceMsg::ControlChange {
+                        control: ControlChange::Parameter(Parameter::TuningBankSelectEntry(bank)),
+                    },
+                },
+                time,
+            );
+        }
+        if let Some(program) = self.config.program {
+            send_midi(
+                MidiMsg::ChannelVoice {
+                    channel,
+                    msg: ChannelVoiceMsg::ControlChange {
+                        control: ControlChange::Parameter(Parameter::TuningProgramSelectEntry(
+                            program,
+                        )),
+                    },
+                },
+                time,
+            );
+        }
+        send_midi(
+            MidiMsg::ChannelVoice {
+                channel,
+                msg: ChannelVoiceMsg::ControlChange {
+                    control: ControlChange::Hold(0),
+                },
+            },
+            time,
+        );
+        send_midi(
+            MidiMsg::ChannelMode {
+                channel,
+                msg: ChannelModeMsg::AllSoundOff,
+            },
+            time,
+        );
+    }
+}
+
+impl HandleMsg<ToBackend, FromBackend> for Mts {
+    fn handle_msg(&mut self, msg: ToBackend, forward: &mpsc::Sender<FromBackend>) {
+        let channel = self.config.channel;
+        let send_midi = |msg: ChannelVoiceMsg, original_time: Instant| {
+            let _ = forward.send(msg::FromBackend::OutgoingMidi {
+                time: original_time,
+                bytes: MidiMsg::ChannelVoice { channel, msg }.to_midi(),
+            });
+        };
+
+        match msg {
+            msg::ToBackend::Start { time } | msg::ToBackend::Reset { time } => {
+                self.reset(time, forward);
+            }
+
+            msg::ToBackend::Stop => {}
+
+            ToBackend::NoteOn {
+                note,
+                velocity,
+                time,
+                ..
+            } => send_midi(ChannelVoiceMsg::NoteOn { note, velocity }, time),
+
+            ToBackend::NoteOff {
+                note,
+                velocity,
+                time,
+                ..
+            } => send_midi(ChannelVoiceMsg::NoteOff { note, velocity }, time),
+
+            ToBackend::PedalHold { value, time, .. } => send_midi(
+                ChannelVoiceMsg::ControlChange {
+                    control: ControlChange::Hold(value),
+                },
+                time,
+            ),
+
+            ToBackend::ProgramChange { program, time, .. } => {
+                send_midi(ChannelVoiceMsg::ProgramChange { program }, time)
+            }
+
+            ToBackend::Retune { note, tuning, time } => {
+                self.handle_retune(note, tuning, time, forward);
+            }
+
+            ToBackend::TunedNoteOn {
+                note,
+                velocity,
+                tuning,
+                time,
+                ..
+            } => {
+                // retune first, so that the note starts with the correct pitch
+                self.handle_retune(note, tuning, time, forward);
+                send_midi(ChannelVoiceMsg::NoteOn { note, velocity }, time);
+            }
+
+            // there's no pitch bend and only one channel
+            ToBackend::BendRange { .. }
+            | ToBackend::ChannelsToUse { .. }
+            | ToBackend::Glide { .. }
+            | ToBackend::Tick { .. } => {}
+
+            ToBackend::InZone { msg, .. } => self.handle_msg(*msg, forward),
+
+            ToBackend::GetCurrentConfig => {
+                let _ = forward.send(FromBackend::CurrentConfig(self.extract_config()));
+            }
+            ToBackend::RestartWithConfig { .. } => {
+                unreachable!("restarts with a new configuration are handled by SomeBackend")
+            }
+            ToBackend::RestartWithCurrentConfig { time } => {
+                *self = Self::new(self.config.clone());
+                self.reset(time, forward);
+            }
+        }
+    }
+}
+
+impl ExtractConfig<BackendConfig> for Mts {
+    fn extract_config(&self) -> BackendConfig {
+        BackendConfig::Mts(self.config.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_tuning_from_semitones() {
+        let t = |s| tuning_from_semitones(s).map(|t| (t.semitone, t.fraction));
+        assert_eq!(t(60.0), Some((60, 0)));
+        assert_eq!(t(60.5), Some((60, 8192)));
+        assert_eq!(t(60.99999), Some((61, 0)));
+        assert_eq!(t(127.99999), Some((127, 16382)));
+        assert_eq!(t(-0.1), None {});
+        assert_eq!(t(128.0), None {});
+    }
+
+    #[test]
+    fn test_config() {
+        let parse = |s: &str| serde_yml::from_str::<MtsConfig>(s);
+        let config = parse("channel: 2\nrealtime: false\nprogram: 127\nbank: 0").unwrap();
+        assert_eq!(config.channel, Channel::Ch2);
+        assert_eq!((config.program, config.bank), (Some(127), Some(0)));
+        let config = parse("channel: 2\nrealtime: false").unwrap();
+        assert_eq!((config.program, config.bank), (None {}, None {}));
+
+        for s in [
+            "channel: 2\nrealtime: false\nprogram: 128",
+            "channel: 2\nrealtime: false\nbank: 200",
+        ] {
+            let Err(e) = parse(s) else {
+                panic!("'{s}' should be rejected");
+            };
+            assert!(e
+                .to_string()
+                .contains("is not in the (inclusive) range 0...127"));
+        }
+    }
+
+    #[test]
+    fn test_tuning_message() {
+        let mut mts = Mts::new(MtsConfig {
+            channel: Channel::Ch1,
+            realtime: true,
+            program: None {},
+            bank: None {},
+        });
+        let tuning = tuning_from_semitones(61.5).unwrap();
+        assert_eq!(
+            mts.tuning_message(60, tuning).to_midi(),
+            vec![0xF0, 0x7F, 0x7F, 0x08, 0x02, 0x00, 0x01, 60, 61, 0x40, 0x00, 0xF7]
+        );
+
+        mts.config.realtime = false;
+        mts.config.program = Some(3);
+        mts.config.bank = Some(1);
+        assert_eq!(
+            mts.tuning_message(60, tuning).to_midi(),
+            vec![0xF0, 0x7E, 0x7F, 0x08, 0x07, 0x01, 0x03, 0x01, 60, 61, 0x40, 0x00, 0xF7]
+        );
+    }
+}
@@ -8,7 +8,8 @@ use midi_msg::{Channel, ChannelModeMsg, ChannelVoiceMsg, ControlChange, MidiMsg}
 use serde_derive::{Deserialize, Serialize};
 
 use crate::{
-    config::{BackendConfig, ExtractConfig, FromConfigAndState},
+    backend::glide::{GlideConfig, Glider},
+    config::{BackendConfig, ExtractConfig},
     custom_serde::common::{deserialize_channel, serialize_channel},
     interval::base::Semitones,
     keystate::KeyState,
@@ -29,6 +30,9 @@ pub struct Pitchbend12 {
 
     /// the current bend range
     bend_range: Semitones,
+
+    /// Re-tunings of sounding notes glide, if this is set. The slots are the channel indices.
+    glider: Option<Glider>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -62,6 +66,8 @@ impl From<Channel> for WrappedChannel {
 pub struct Pitchbend12Config {
     pub bend_range: Semitones,
     pub channels: [WrappedChannel; 12],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glide: Option<GlideConfig>,
 }
 
 impl Pitchbend12 {
@@ -73,6 +79,7 @@ impl Pitchbend12 {
             key_state: core::array::from_fn(|_| KeyState::new(now)),
             pedal_hold: [false; 16],
             bend_range: config.bend_range,
+            glider: config.glide.map(|glide| Glider::new(glide, 12)),
         }
     }
 
@@ -102,17 +109,32 @@ impl Pitchbend12 {
 
         let channel_index = note as usize % 12;
         let desired_bend = self.bend_from_semitones(tuning - note as Semitones);
-        let current_bend = self.bends[channel_index];
+        // If there's a glide running on the channel, it is superseded.
+        let current_bend = self
+            .glider
+            .as_mut()
+            .and_then(|glider| glider.cancel(channel_index))
+            .unwrap_or(self.bends[channel_index]);
         if current_bend != desired_bend {
-            send_midi(
-                MidiMsg::ChannelVoice {
-                    channel: self.channels[channel_index],
-                    msg: ChannelVoiceMsg::PitchBend { bend: desired_bend },
-                },
-                time,
-            );
-            self.bends[channel_index] = desired_bend;
+            match &mut self.glider {
+                Some(glider) if self.key_state[note as usize].is_sounding() => glider.start(
+                    channel_index,
+                    self.channels[channel_index],
+                    current_bend,
+                    desired_bend,
+                    time,
+                    forward,
+                ),
+                _ => send_midi(
+                    MidiMsg::ChannelVoice {
+                        channel: self.channels[channel_index],
+                        msg: ChannelVoiceMsg::PitchBend { bend: desired_bend },
+                    },
+                    time,
+                ),
+            }
         }
+        self.bends[channel_index] = desired_bend;
         if (tuning - note as Semitones).abs() > self.bend_range {
             let _ = forward.send(FromBackend::DetunedNote {
                 note,
@@ -132,6 +154,9 @@ impl Pitchbend12 {
         };
 
         // the same initialisations as in [Pitchbend12::new].
+        if let Some(glider) = &mut self.glider {
+            glider.cancel_all();
+        }
         self.bends = [8192; 12];
         self.key_state = core::array::from_fn(|_| KeyState::new(time));
         self.pedal_hold = [false; 16];
@@ -290,6 +315,33 @@ impl HandleMsg<ToBackend, FromBackend> for Pitchbend12 {
                 self.reset(time, forward);
             }
 
+            ToBackend::InZone { msg, .. } => self.handle_msg(*msg, forward),
+
+            ToBackend::Glide { glide, time } => {
+                // The glides that are still running jump to their targets, which are already in
+                // `self.bends`.
+                if let Some(mut glider) = self.glider.take() {
+                    for channel_index in glider.cancel_all() {
+                        send_midi(
+                            MidiMsg::ChannelVoice {
+                                channel: self.channels[channel_index],
+                                msg: ChannelVoiceMsg::PitchBend {
+                                    bend: self.bends[channel_index],
+                                },
+                            },
+                            time,
+                        );
+                    }
+                }
+                self.glider = glide.map(|glide| Glider::new(glide, 12));
+            }
+
+            ToBackend::Tick { time } => {
+                if let Some(glider) = &mut self.glider {
+                    glider.tick(time, forward);
+                }
+            }
+
             ToBackend::ChannelsToUse { channels, time } => {
                 let mut i = 0;
                 for (ch, used) in channels.iter().enumerate() {
@@ -303,12 +355,14 @@ impl HandleMsg<ToBackend, FromBackend> for Pitchbend12 {
             ToBackend::GetCurrentConfig => {
                 let _ = forward.send(FromBackend::CurrentConfig(self.extract_config()));
             }
-            ToBackend::RestartWithConfig { config, time } => {
-                *self = <Self as FromConfigAndState<_, _>>::initialise(config, ());
-                self.reset(time, forward);
+            ToBackend::RestartWithConfig { .. } => {
+                unreachable!("restarts with a new configuration are handled by SomeBackend")
             }
             ToBackend::RestartWithCurrentConfig { time } => {
-                *self = <Self as FromConfigAndState<_, _>>::initialise(self.extract_config(), ());
+                let BackendConfig::Pitchbend12(config) = self.extract_config() else {
+                    unreachable!()
+                };
+                *self = Self::new(config);
                 self.reset(time, forward);
             }
         }
@@ -320,14 +374,182 @@ impl ExtractConfig<BackendConfig> for Pitchbend12 {
         BackendConfig::Pitchbend12(Pitchbend12Config {
             bend_range: self.bend_range,
             channels: core::array::from_fn(|i| WrappedChannel(self.channels[i])),
+            glide: self.glider.as_ref().map(|glider| glider.config()),
         })
     }
 }
 
-impl<S> FromConfigAndState<BackendConfig, S> for Pitchbend12 {
-    fn initialise(config: BackendConfig, _state: S) -> Self {
-        match config {
-            BackendConfig::Pitchbend12(config) => Self::new(config),
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::glide::GlideCurve;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn pitchbend12(glide: Option<GlideConfig>) -> Pitchbend12 {
+        Pitchbend12::new(Pitchbend12Config {
+            bend_range: 2.0,
+            channels: core::array::from_fn(|i| Channel::from_u8(i as u8).into()),
+            glide,
+        })
+    }
+
+    /// The bends sent on the channel of middle C so far.
+    fn c_bends(rx: &mpsc::Receiver<FromBackend>) -> Vec<u16> {
+        rx.try_iter()
+            .filter_map(|msg| match msg {
+                FromBackend::OutgoingMidi { bytes, .. } => match MidiMsg::from_midi(&bytes) {
+                    Ok((
+                        MidiMsg::ChannelVoice {
+                            channel: Channel::Ch1,
+                            msg: ChannelVoiceMsg::PitchBend { bend },
+                        },
+                        _,
+                    )) => Some(bend),
+                    _ => None {},
+                },
+                _ => None {},
+            })
+            .collect()
+    }
+
+    /// Send ticks in steps of 5 ms from `from` to `to` (in ms after `start`), collecting the bends
+    /// on the channel of middle C.
+    fn glide_bends(
+        backend: &mut Pitchbend12,
+        start: Instant,
+        from: u64,
+        to: u64,
+        tx: &mpsc::Sender<FromBackend>,
+        rx: &mpsc::Receiver<FromBackend>,
+    ) -> Vec<u16> {
+        let mut res = c_bends(rx);
+        for ms in (from..=to).step_by(5) {
+            backend.handle_msg(
+                ToBackend::Tick {
+                    time: start + Duration::from_millis(ms),
+                },
+                tx,
+            );
+            res.extend(c_bends(rx));
         }
+        res
+    }
+
+    fn play_and_retune(backend: &mut Pitchbend12, time: Instant, tx: &mpsc::Sender<FromBackend>) {
+        backend.handle_msg(
+            ToBackend::TunedNoteOn {
+                channel: Channel::Ch1,
+                note: 60,
+                velocity: 100,
+                tuning: 60.0,
+                time,
+            },
+            tx,
+        );
+        backend.handle_msg(
+            ToBackend::Retune {
+                note: 60,
+                tuning: 60.5,
+                time,
+            },
+            tx,
+        );
+    }
+
+    #[test]
+    fn test_no_glide() {
+        let (tx, rx) = mpsc::channel();
+        let mut backend = pitchbend12(None {});
+        play_and_retune(&mut backend, Instant::now(), &tx);
+        assert_eq!(c_bends(&rx), vec![10239]);
+    }
+
+    #[test]
+    fn test_glide() {
+        let (tx, rx) = mpsc::channel();
+        let mut backend = pitchbend12(Some(GlideConfig {
+            duration_ms: 50,
+            curve: GlideCurve::Linear,
+        }));
+        let start = Instant::now();
+        play_and_retune(&mut backend, start, &tx);
+        let bends = glide_bends(&mut backend, start, 5, 100, &tx, &rx);
+        assert_eq!(
+            bends,
+            vec![8397, 8601, 8806, 9011, 9216, 9420, 9625, 9830, 10034, 10239]
+        );
+    }
+
+    #[test]
+    fn test_supersede_glide() {
+        let (tx, rx) = mpsc::channel();
+        let mut backend = pitchbend12(Some(GlideConfig {
+            duration_ms: 100,
+            curve: GlideCurve::Linear,
+        }));
+        let start = Instant::now();
+        play_and_retune(&mut backend, start, &tx);
+        let up = glide_bends(&mut backend, start, 5, 20, &tx, &rx);
+        assert!(up.windows(2).all(|w| w[0] < w[1]));
+
+        // the first glide is superseded before it ends
+        backend.handle_msg(
+            ToBackend::Retune {
+                note: 60,
+                tuning: 59.5,
+                time: start + Duration::from_millis(20),
+            },
+            &tx,
+        );
+        let down = glide_bends(&mut backend, start, 25, 200, &tx, &rx);
+        assert!(down.windows(2).all(|w| w[0] > w[1]));
+        assert!(down[0] < *up.last().unwrap());
+        assert_eq!(down.last(), Some(&6144));
+        // nothing is left to do
+        backend.handle_msg(
+            ToBackend::Tick {
+                time: start + Duration::from_secs(1),
+            },
+            &tx,
+        );
+        assert_eq!(c_bends(&rx), vec![]);
+    }
+
+    #[test]
+    fn test_change_glide_during_glide() {
+        let (tx, rx) = mpsc::channel();
+        let mut backend = pitchbend12(Some(GlideConfig {
+            duration_ms: 100,
+            curve: GlideCurve::Linear,
+        }));
+        let start = Instant::now();
+        play_and_retune(&mut backend, start, &tx);
+        let up = glide_bends(&mut backend, start, 5, 20, &tx, &rx);
+        assert!(*up.last().unwrap() < 10239);
+
+        // the running glide jumps to its target
+        backend.handle_msg(
+            ToBackend::Glide {
+                glide: Some(GlideConfig {
+                    duration_ms: 50,
+                    curve: GlideCurve::Exponential,
+                }),
+                time: start + Duration::from_millis(20),
+            },
+            &tx,
+        );
+        assert_eq!(c_bends(&rx), vec![10239]);
+
+        // retuning to the same target sends nothing
+        backend.handle_msg(
+            ToBackend::Retune {
+                note: 60,
+                tuning: 60.5,
+                time: start + Duration::from_millis(30),
+            },
+            &tx,
+        );
+        assert_eq!(c_bends(&rx), vec![]);
     }
 }
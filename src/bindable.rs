@@ -1,4 +1,4 @@
-use std::{collections::BTreeMap, fmt};
+use std::{collections::BTreeMap, fmt, str::FromStr};
 
 use eframe::egui;
 use serde_derive::{Deserialize, Serialize};
@@ -8,14 +8,312 @@ use crate::{
     strategy::r#trait::StrategyAction,
 };
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-#[serde(deny_unknown_fields)]
-#[serde(rename_all = "kebab-case")]
+/// Which crossing of the threshold of a controller triggers a [MidiBindable::Controller].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Edge {
+    /// The value goes from below the threshold to at least the threshold.
+    Rising,
+    /// The value goes from at least the threshold to below the threshold.
+    Falling,
+}
+
+/// Channels are stored 0-based, and `None {}` means "any channel". Bindables are written as
+/// strings in configuration files, see the [FromStr] implementation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum MidiBindable {
     SostenutoPedalDown,
     SostenutoPedalUp,
     SoftPedalDown,
     SoftPedalUp,
+    /// All messages of the controller are kept from further processing.
+    Controller {
+        channel: Option<u8>,
+        controller: u8,
+        threshold: u8,
+        edge: Edge,
+    },
+    /// A note on triggers the action. If `forward` is false, the note on and off are kept from
+    /// further processing, so that the note can be used as a keyswitch.
+    Note {
+        channel: Option<u8>,
+        note: u8,
+        forward: bool,
+    },
+    ProgramChange {
+        channel: Option<u8>,
+        program: u8,
+    },
+}
+
+/// The incoming MIDI events that can trigger [MidiBindable]s other than the pedals (which are
+/// handled together with their normal function).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiEvent {
+    NoteOn {
+        channel: u8,
+        note: u8,
+    },
+    NoteOff {
+        channel: u8,
+        note: u8,
+    },
+    Controller {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+    ProgramChange {
+        channel: u8,
+        program: u8,
+    },
+}
+
+impl MidiEvent {
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let status = *bytes.first()?;
+        let channel = status & 0x0F;
+        match (status & 0xF0, bytes.get(1), bytes.get(2)) {
+            (0x80, Some(&note), Some(_)) => Some(MidiEvent::NoteOff { channel, note }),
+            (0x90, Some(&note), Some(&0)) => Some(MidiEvent::NoteOff { channel, note }),
+            (0x90, Some(&note), Some(_)) => Some(MidiEvent::NoteOn { channel, note }),
+            (0xB0, Some(&controller), Some(&value)) => Some(MidiEvent::Controller {
+                channel,
+                controller,
+                value,
+            }),
+            (0xC0, Some(&program), _) => Some(MidiEvent::ProgramChange { channel, program }),
+            _ => None {},
+        }
+    }
+
+    fn channel(&self) -> u8 {
+        match self {
+            MidiEvent::NoteOn { channel, .. } => *channel,
+            MidiEvent::NoteOff { channel, .. } => *channel,
+            MidiEvent::Controller { channel, .. } => *channel,
+            MidiEvent::ProgramChange { channel, .. } => *channel,
+        }
+    }
+}
+
+impl MidiBindable {
+    /// The bindable that "MIDI learn" makes of the event. Note offs can't be learned.
+    pub fn learn(event: &MidiEvent) -> Option<Self> {
+        match *event {
+            MidiEvent::NoteOn { channel, note } => Some(MidiBindable::Note {
+                channel: Some(channel),
+                note,
+                forward: false,
+            }),
+            MidiEvent::NoteOff { .. } => None {},
+            MidiEvent::Controller {
+                channel,
+                controller,
+                ..
+            } => Some(MidiBindable::Controller {
+                channel: Some(channel),
+                controller,
+                threshold: 64,
+                edge: Edge::Rising,
+            }),
+            MidiEvent::ProgramChange { channel, program } => Some(MidiBindable::ProgramChange {
+                channel: Some(channel),
+                program,
+            }),
+        }
+    }
+
+    fn channel(&self) -> Option<u8> {
+        match self {
+            MidiBindable::Controller { channel, .. } => *channel,
+            MidiBindable::Note { channel, .. } => *channel,
+            MidiBindable::ProgramChange { channel, .. } => *channel,
+            _ => None {},
+        }
+    }
+
+    fn listens_to(&self, event: &MidiEvent) -> bool {
+        self.channel().is_none_or(|c| c == event.channel())
+    }
+
+    /// Does the event trigger the action bound to this bindable? For controllers, `previous` is
+    /// the value the controller had before the event.
+    pub fn triggered_by(&self, event: &MidiEvent, previous: u8) -> bool {
+        if !self.listens_to(event) {
+            return false;
+        }
+        match (self, event) {
+            (
+                MidiBindable::Controller {
+                    controller,
+                    threshold,
+                    edge,
+                    ..
+                },
+                MidiEvent::Controller {
+                    controller: c,
+                    value,
+                    ..
+                },
+            ) if controller == c => match edge {
+                Edge::Rising => previous < *threshold && *value >= *threshold,
+                Edge::Falling => previous >= *threshold && *value < *threshold,
+            },
+            (MidiBindable::Note { note, .. }, MidiEvent::NoteOn { note: n, .. }) => note == n,
+            (
+                MidiBindable::ProgramChange { program, .. },
+                MidiEvent::ProgramChange { program: p, .. },
+            ) => program == p,
+            _ => false,
+        }
+    }
+
+    /// Must the event be kept from further processing, because it belongs to this bindable?
+    pub fn swallows(&self, event: &MidiEvent) -> bool {
+        if !self.listens_to(event) {
+            return false;
+        }
+        match (self, event) {
+            (
+                MidiBindable::Controller { controller, .. },
+                MidiEvent::Controller { controller: c, .. },
+            ) => controller == c,
+            (
+                MidiBindable::Note {
+                    note,
+                    forward: false,
+                    ..
+                },
+                MidiEvent::NoteOn { note: n, .. } | MidiEvent::NoteOff { note: n, .. },
+            ) => note == n,
+            (
+                MidiBindable::ProgramChange { program, .. },
+                MidiEvent::ProgramChange { program: p, .. },
+            ) => program == p,
+            _ => false,
+        }
+    }
+
+    /// The string used in configuration files.
+    pub fn to_config_string(&self) -> String {
+        let channel_prefix = |channel: &Option<u8>| match channel {
+            Some(c) => format!("ch{}-", c + 1),
+            None {} => String::new(),
+        };
+        match self {
+            MidiBindable::SostenutoPedalDown => "sostenuto-pedal-down".into(),
+            MidiBindable::SostenutoPedalUp => "sostenuto-pedal-up".into(),
+            MidiBindable::SoftPedalDown => "soft-pedal-down".into(),
+            MidiBindable::SoftPedalUp => "soft-pedal-up".into(),
+            MidiBindable::Controller {
+                channel,
+                controller,
+                threshold,
+                edge,
+            } => format!(
+                "{}cc-{controller}-{}-{threshold}",
+                channel_prefix(channel),
+                match edge {
+                    Edge::Rising => "rising",
+                    Edge::Falling => "falling",
+                }
+            ),
+            MidiBindable::Note {
+                channel,
+                note,
+                forward,
+            } => format!(
+                "{}note-{note}{}",
+                channel_prefix(channel),
+                if *forward { "-forwarded" } else { "" }
+            ),
+            MidiBindable::ProgramChange { channel, program } => {
+                format!("{}program-{program}", channel_prefix(channel))
+            }
+        }
+    }
+}
+
+/// Parses the strings produced by [MidiBindable::to_config_string]:
+/// - `sostenuto-pedal-down`, `sostenuto-pedal-up`, `soft-pedal-down`, `soft-pedal-up`
+/// - `cc-<controller>-rising-<threshold>`, `cc-<controller>-falling-<threshold>`
+/// - `note-<note>`, `note-<note>-forwarded`
+/// - `program-<program>`
+///
+/// The last three may be prefixed by `ch<channel>-` (with channels numbered from 1 to 16) to only
+/// listen to one channel.
+impl FromStr for MidiBindable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sostenuto-pedal-down" => return Ok(MidiBindable::SostenutoPedalDown),
+            "sostenuto-pedal-up" => return Ok(MidiBindable::SostenutoPedalUp),
+            "soft-pedal-down" => return Ok(MidiBindable::SoftPedalDown),
+            "soft-pedal-up" => return Ok(MidiBindable::SoftPedalUp),
+            _ => {}
+        }
+
+        let invalid = || format!("'{s}' is not a MIDI bindable");
+        let number = |x: &str, max: u8| match x.parse::<u8>() {
+            Ok(n) if n <= max => Ok(n),
+            _ => Err(invalid()),
+        };
+
+        let mut parts: Vec<&str> = s.split('-').collect();
+        let channel = match parts.first().and_then(|p| p.strip_prefix("ch")) {
+            Some(c) => {
+                let c = number(c, 16)?;
+                if c == 0 {
+                    return Err(invalid());
+                }
+                parts.remove(0);
+                Some(c - 1)
+            }
+            None {} => None {},
+        };
+
+        match parts[..] {
+            ["cc", controller, edge, threshold] => Ok(MidiBindable::Controller {
+                channel,
+                controller: number(controller, 127)?,
+                threshold: number(threshold, 127)?,
+                edge: match edge {
+                    "rising" => Edge::Rising,
+                    "falling" => Edge::Falling,
+                    _ => return Err(invalid()),
+                },
+            }),
+            ["note", note] => Ok(MidiBindable::Note {
+                channel,
+                note: number(note, 127)?,
+                forward: false,
+            }),
+            ["note", note, "forwarded"] => Ok(MidiBindable::Note {
+                channel,
+                note: number(note, 127)?,
+                forward: true,
+            }),
+            ["program", program] => Ok(MidiBindable::ProgramChange {
+                channel,
+                program: number(program, 127)?,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl serde::Serialize for MidiBindable {
+    fn serialize<S: serde::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        ser.serialize_str(&self.to_config_string())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for MidiBindable {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <String as serde::Deserialize<'de>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -33,11 +331,42 @@ pub enum Bindable {
 
 impl fmt::Display for MidiBindable {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let channel_suffix = |channel: &Option<u8>| match channel {
+            Some(c) => format!(" on channel {}", c + 1),
+            None {} => String::new(),
+        };
         match self {
             MidiBindable::SostenutoPedalDown => write!(f, "sostenuto pedal down"),
             MidiBindable::SostenutoPedalUp => write!(f, "sostenuto pedal up"),
             MidiBindable::SoftPedalDown => write!(f, "soft pedal down"),
             MidiBindable::SoftPedalUp => write!(f, "soft pedal up"),
+            MidiBindable::Controller {
+                channel,
+                controller,
+                threshold,
+                edge,
+            } => write!(
+                f,
+                "controller {controller} {} {threshold}{}",
+                match edge {
+                    Edge::Rising => "rising to",
+                    Edge::Falling => "falling below",
+                },
+                channel_suffix(channel)
+            ),
+            MidiBindable::Note {
+                channel,
+                note,
+                forward,
+            } => write!(
+                f,
+                "note {note}{}{}",
+                channel_suffix(channel),
+                if *forward { " (also played)" } else { "" }
+            ),
+            MidiBindable::ProgramChange { channel, program } => {
+                write!(f, "program change to {program}{}", channel_suffix(channel))
+            }
         }
     }
 }
@@ -51,45 +380,288 @@ impl fmt::Display for Bindable {
     }
 }
 
+/// A continuously variable parameter that can be swept with a [ControllerMapping].
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub enum MappedParameter {
+    /// The time window (in milliseconds) in which notes are grouped, for strategies that have one.
+    GroupMs,
+    /// The frequency (in Hertz) of the tuning reference.
+    TuningReferenceFrequency,
+    /// The pitch bend range (in semitones) of the backend.
+    BendRange,
+    /// How much of the temperament is applied to the current neighbourhood, between 0 (pure) and
+    /// 1 (fully tempered).
+    TemperamentAmount { temperament: usize },
+}
+
+/// Maps the values of a MIDI controller linearly to the range from `min` to `max` of a
+/// [MappedParameter].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(try_from = "UncheckedControllerMapping")]
+#[serde(rename_all = "kebab-case")]
+pub struct ControllerMapping {
+    /// Numbered from 1 to 16. If this is absent, the controller is read on all channels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u8>,
+    pub controller: u8,
+    /// If this is set, `controller` is the most significant byte of a 14 bit value, and
+    /// `controller + 32` the least significant one.
+    #[serde(default)]
+    pub high_resolution: bool,
+    pub parameter: MappedParameter,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// A [ControllerMapping] as it is written in configuration files, before its numbers are checked.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+struct UncheckedControllerMapping {
+    #[serde(default)]
+    channel: Option<u8>,
+    controller: u8,
+    #[serde(default)]
+    high_resolution: bool,
+    parameter: MappedParameter,
+    min: f64,
+    max: f64,
+}
+
+impl TryFrom<UncheckedControllerMapping> for ControllerMapping {
+    type Error = String;
+
+    fn try_from(m: UncheckedControllerMapping) -> Result<Self, Self::Error> {
+        if let Some(channel) = m.channel {
+            if !(1..=16).contains(&channel) {
+                return Err(format!("there is no MIDI channel {channel}"));
+            }
+        }
+        if m.controller > 127 {
+            return Err(format!("there is no MIDI controller {}", m.controller));
+        }
+        if m.high_resolution && m.controller >= 32 {
+            return Err(format!(
+                "controller {} can't have a high resolution: only the controllers 0 to 31 have \
+                a least significant byte",
+                m.controller
+            ));
+        }
+        Ok(ControllerMapping {
+            channel: m.channel,
+            controller: m.controller,
+            high_resolution: m.high_resolution,
+            parameter: m.parameter,
+            min: m.min,
+            max: m.max,
+        })
+    }
+}
+
+impl ControllerMapping {
+    /// Does the controller message concern this mapping (either as MSB or as LSB)?
+    pub fn listens_to(&self, channel: u8, controller: u8) -> bool {
+        self.channel.is_none_or(|c| c == channel + 1)
+            && (controller == self.controller
+                || (self.high_resolution && controller.checked_sub(32) == Some(self.controller)))
+    }
+
+    /// The value of the parameter, given the MSB and LSB of the controller. The LSB is ignored
+    /// unless the mapping has a high resolution.
+    pub fn value(&self, msb: u8, lsb: u8) -> f64 {
+        let x = if self.high_resolution {
+            ((msb as u16) << 7 | lsb as u16) as f64 / 16383.0
+        } else {
+            msb as f64 / 127.0
+        };
+        self.min + x * (self.max - self.min)
+    }
+}
+
+/// The [ControllerMapping]s are not part of the (de)serialised representation: In configuration
+/// files, they are written next to the bindings, as `controllers`.
 #[derive(Clone, Serialize, Deserialize)]
-pub struct Bindings<K: Ord>(BTreeMap<K, StrategyAction>);
+#[serde(transparent)]
+pub struct Bindings<K: Ord> {
+    actions: BTreeMap<K, StrategyAction>,
+    #[serde(skip)]
+    pub controllers: Vec<ControllerMapping>,
+}
 
 impl Bindings<Bindable> {
     pub fn only_midi(&self) -> Bindings<MidiBindable> {
-        let Bindings(m) = self;
         let mut res = BTreeMap::new();
-        m.iter().for_each(|(k, v)| match k {
+        self.actions.iter().for_each(|(k, v)| match k {
             Bindable::Midi(k) => {
                 res.insert(*k, *v);
             }
             _ => {}
         });
-        Bindings(res)
+        Bindings {
+            actions: res,
+            controllers: self.controllers.clone(),
+        }
     }
 }
 
 impl<K: Ord> Bindings<K> {
     pub fn empty() -> Self {
-        Bindings(BTreeMap::new())
+        Bindings {
+            actions: BTreeMap::new(),
+            controllers: vec![],
+        }
+    }
+
+    pub fn with_controllers(mut self, controllers: Vec<ControllerMapping>) -> Self {
+        self.controllers = controllers;
+        self
     }
 
     pub fn get(&self, bindable: &K) -> Option<&StrategyAction> {
-        let Bindings(m) = self;
-        m.get(bindable)
+        self.actions.get(bindable)
     }
 
     pub fn insert(&mut self, bindable: K, action: StrategyAction) -> Option<StrategyAction> {
-        let Bindings(m) = self;
-        m.insert(bindable, action)
+        self.actions.insert(bindable, action)
     }
 
     pub fn remove(&mut self, bindable: &K) -> Option<StrategyAction> {
-        let Bindings(m) = self;
-        m.remove(bindable)
+        self.actions.remove(bindable)
     }
 
     pub fn iter(&mut self) -> std::collections::btree_map::Iter<'_, K, StrategyAction> {
-        let Bindings(m) = self;
-        m.iter()
+        self.actions.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_config_string_roundtrip() {
+        for b in [
+            MidiBindable::SoftPedalUp,
+            MidiBindable::Controller {
+                channel: None {},
+                controller: 20,
+                threshold: 64,
+                edge: Edge::Rising,
+            },
+            MidiBindable::Controller {
+                channel: Some(15),
+                controller: 127,
+                threshold: 0,
+                edge: Edge::Falling,
+            },
+            MidiBindable::Note {
+                channel: Some(9),
+                note: 36,
+                forward: true,
+            },
+            MidiBindable::ProgramChange {
+                channel: None {},
+                program: 3,
+            },
+        ] {
+            assert_eq!(b.to_config_string().parse(), Ok(b));
+        }
+        assert_eq!(
+            "ch10-note-36".parse(),
+            Ok(MidiBindable::Note {
+                channel: Some(9),
+                note: 36,
+                forward: false
+            })
+        );
+        for s in [
+            "ch0-note-36",
+            "ch17-note-36",
+            "note-128",
+            "cc-1-up-64",
+            "Space",
+        ] {
+            assert!(s.parse::<MidiBindable>().is_err());
+        }
+    }
+
+    #[test]
+    fn test_controller_mapping_value() {
+        let mut mapping = ControllerMapping {
+            channel: Some(1),
+            controller: 7,
+            high_resolution: false,
+            parameter: MappedParameter::TuningReferenceFrequency,
+            min: 430.0,
+            max: 450.0,
+        };
+        assert!(mapping.listens_to(0, 7));
+        assert!(!mapping.listens_to(1, 7));
+        assert!(!mapping.listens_to(0, 39));
+        assert_eq!(mapping.value(0, 127), 430.0);
+        assert_eq!(mapping.value(127, 0), 450.0);
+
+        mapping.high_resolution = true;
+        assert!(mapping.listens_to(0, 39));
+        assert_eq!(mapping.value(127, 127), 450.0);
+        assert_eq!(mapping.value(64, 0), 430.0 + 20.0 * 8192.0 / 16383.0);
+    }
+
+    #[test]
+    fn test_controller_mapping_checks() {
+        let parse = |s: &str| serde_yml::from_str::<ControllerMapping>(s);
+        let mapping = parse(
+            "channel: 2\ncontroller: 1\nhigh-resolution: true\nparameter: bend-range\nmin: 1\nmax: 2",
+        )
+        .unwrap();
+        assert!(mapping.listens_to(1, 33));
+        assert!(!mapping.listens_to(1, 0));
+
+        for (s, message) in [
+            (
+                "channel: 17\ncontroller: 1\nparameter: bend-range\nmin: 1\nmax: 2",
+                "there is no MIDI channel 17",
+            ),
+            (
+                "controller: 128\nparameter: bend-range\nmin: 1\nmax: 2",
+                "there is no MIDI controller 128",
+            ),
+            (
+                "controller: 40\nhigh-resolution: true\nparameter: bend-range\nmin: 1\nmax: 2",
+                "controller 40 can't have a high resolution",
+            ),
+        ] {
+            assert!(parse(s).unwrap_err().to_string().contains(message));
+        }
+
+        // controllers that are too high for a least significant byte don't overflow
+        let mapping = ControllerMapping {
+            controller: 250,
+            ..mapping
+        };
+        assert!(!mapping.listens_to(1, 26));
+    }
+
+    #[test]
+    fn test_controller_edges() {
+        let rising = MidiBindable::Controller {
+            channel: Some(0),
+            controller: 20,
+            threshold: 64,
+            edge: Edge::Rising,
+        };
+        let event = |channel, value| MidiEvent::Controller {
+            channel,
+            controller: 20,
+            value,
+        };
+        assert!(rising.triggered_by(&event(0, 64), 63));
+        assert!(!rising.triggered_by(&event(0, 100), 64));
+        assert!(!rising.triggered_by(&event(0, 10), 100));
+        assert!(!rising.triggered_by(&event(1, 127), 0));
+        assert!(rising.swallows(&event(0, 10)));
+        assert!(!rising.swallows(&event(1, 10)));
     }
 }
@@ -0,0 +1,155 @@
+use std::{fmt, path::PathBuf};
+
+pub const USAGE: &str = "\
+usage: adaptuner [OPTIONS] [CONFIG]
+
+Start adaptuner with the configuration file CONFIG. Without CONFIG, the built-in template
+configuration is used.
+
+options:
+  --strategy <NAME>   start with the strategy called NAME
+  --input <PORT>      connect to the first MIDI input whose name contains PORT. This can be
+                      given several times, then the MIDI from all inputs is merged
+  --output <PORT>     connect to the first MIDI output whose name contains PORT. This can be
+                      given several times, then all outputs get the same MIDI
+  --list-ports        print the names of the available MIDI ports and exit
+  --retune <FILE>     retune the Standard MIDI File FILE with the strategy and backend from the
+                      configuration, without opening any MIDI ports or windows, and exit
+  --retune-to <FILE>  where to write the result of --retune. The default is FILE with the
+                      extension '.retuned.mid'
+  --help              print this message and exit";
+
+/// The command line arguments.
+#[derive(Debug, PartialEq, Default)]
+pub struct Args {
+    pub config: Option<PathBuf>,
+    pub strategy: Option<String>,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub list_ports: bool,
+    pub retune: Option<PathBuf>,
+    pub retune_to: Option<PathBuf>,
+    pub help: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ArgsErr {
+    MissingValue(String),
+    UnknownOption(String),
+    TooManyConfigs,
+}
+
+impl fmt::Display for ArgsErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgsErr::MissingValue(option) => write!(f, "option '{option}' needs a value"),
+            ArgsErr::UnknownOption(option) => write!(f, "unknown option '{option}'"),
+            ArgsErr::TooManyConfigs => write!(f, "only one configuration file can be given"),
+        }?;
+        write!(f, "\n\n{USAGE}")
+    }
+}
+
+impl std::error::Error for ArgsErr {}
+
+impl Args {
+    /// Parse the arguments, without the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsErr> {
+        let mut res = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value_for = |option: &str| {
+                args.next()
+                    .ok_or_else(|| ArgsErr::MissingValue(option.into()))
+            };
+            match arg.as_str() {
+                "--strategy" => res.strategy = Some(value_for(&arg)?),
+                "--input" => res.inputs.push(value_for(&arg)?),
+                "--output" => res.outputs.push(value_for(&arg)?),
+                "--list-ports" => res.list_ports = true,
+                "--retune" => res.retune = Some(value_for(&arg)?.into()),
+                "--retune-to" => res.retune_to = Some(value_for(&arg)?.into()),
+                "--help" | "-h" => res.help = true,
+                _ if arg.starts_with('-') => return Err(ArgsErr::UnknownOption(arg)),
+                _ => {
+                    if res.config.is_some() {
+                        return Err(ArgsErr::TooManyConfigs);
+                    }
+                    res.config = Some(arg.into());
+                }
+            }
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn parse(args: &[&str]) -> Result<Args, ArgsErr> {
+        Args::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(&[]), Ok(Args::default()));
+        assert_eq!(
+            parse(&[
+                "--input",
+                "Digital Piano",
+                "gig.yaml",
+                "--strategy",
+                "static meantone",
+                "--output",
+                "FLUID",
+                "--input",
+                "Wind",
+            ]),
+            Ok(Args {
+                config: Some("gig.yaml".into()),
+                strategy: Some("static meantone".into()),
+                inputs: vec!["Digital Piano".into(), "Wind".into()],
+                outputs: vec!["FLUID".into()],
+                list_ports: false,
+                retune: None {},
+                retune_to: None {},
+                help: false,
+            })
+        );
+        assert_eq!(
+            parse(&["gig.yaml", "--retune", "take1.mid", "--strategy", "springs"]),
+            Ok(Args {
+                config: Some("gig.yaml".into()),
+                strategy: Some("springs".into()),
+                retune: Some("take1.mid".into()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse(&["--list-ports"]),
+            Ok(Args {
+                list_ports: true,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse(&["--strategy"]),
+            Err(ArgsErr::MissingValue("--strategy".into()))
+        );
+        assert_eq!(
+            parse(&["--retune-to"]),
+            Err(ArgsErr::MissingValue("--retune-to".into()))
+        );
+        assert_eq!(
+            parse(&["--verbose"]),
+            Err(ArgsErr::UnknownOption("--verbose".into()))
+        );
+        assert_eq!(parse(&["a.yaml", "b.yaml"]), Err(ArgsErr::TooManyConfigs));
+    }
+}
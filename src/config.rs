@@ -1,29 +1,44 @@
+use midi_msg::Channel;
+use num_rational::Ratio;
 use serde_derive::{Deserialize, Serialize};
 
 use crate::{
-    backend::pitchbend12::Pitchbend12Config,
-    bindable::{Bindable, Bindings, MidiBindable},
-    custom_serde::common::deserialize_nonempty,
+    backend::{mpe::MpeConfig, mts::MtsConfig, pitchbend12::Pitchbend12Config},
+    bindable::{Bindable, Bindings, ControllerMapping, MidiBindable},
+    custom_serde::{
+        common::{deserialize_nonempty, serialize_ratio},
+        migration::Upgrade,
+    },
     gui::{
         backend::BackendWindowConfig,
         editor::{reference::ReferenceEditorConfig, tuning::TuningEditorConfig},
         lattice::LatticeWindowConfig,
     },
     interval::{
+        base::IntervalDefinition,
         stack::Stack,
-        stacktype::r#trait::{IntervalBasis, NamedInterval, StackType},
+        stacktype::r#trait::{
+            IntervalBasis, IntervalBasisName, NamedInterval, Reloadable, StackCoeff, StackType,
+            StackTypeInitialisationErr,
+        },
         temperament::TemperamentDefinition,
     },
     neighbourhood::{SomeCompleteNeighbourhood, SomeNeighbourhood},
+    process::zones::ZoneConfig,
     reference::Reference,
     strategy::{
+        drift::{Drift, DriftConfig},
+        hysteresis::{Hysteresis, HysteresisConfig},
         r#static::{StaticTuning, StaticTuningConfig},
         r#trait::{Strategy, StrategyAction},
+        sketch::{Sketch, SketchConfig},
+        springs::{deserialize_stiffness, SpringInterval, Springs, SpringsConfig},
         twostep::{
             harmony::chordlist::{keyshape::KeyShape, ChordListConfig, PatternConfig},
             melody::neighbourhoods::NeighbourhoodsConfig,
             TwoStep,
         },
+        walking::{Walking, WalkingConfig},
     },
 };
 
@@ -49,6 +64,11 @@ pub enum MelodyStrategyConfig<T: IntervalBasis> {
 pub enum StrategyConfig<T: IntervalBasis> {
     StaticTuning(StaticTuningConfig<T>),
     TwoStep(HarmonyStrategyConfig<T>, MelodyStrategyConfig<T>),
+    Springs(SpringsConfig<T>),
+    Walking(WalkingConfig<T>),
+    Sketch(SketchConfig<T>),
+    Hysteresis(HysteresisConfig<T>),
+    Drift(DriftConfig<T>),
 }
 
 impl<T: StackType> StrategyConfig<T> {
@@ -56,6 +76,26 @@ impl<T: StackType> StrategyConfig<T> {
         match self {
             StrategyConfig::StaticTuning(config) => Box::new(StaticTuning::new(config)),
             StrategyConfig::TwoStep(harmony, melody) => Box::new(TwoStep::new(harmony, melody)),
+            StrategyConfig::Springs(config) => Box::new(Springs::new(config)),
+            StrategyConfig::Walking(config) => Box::new(Walking::new(config)),
+            StrategyConfig::Sketch(config) => Box::new(Sketch::new(config)),
+            StrategyConfig::Hysteresis(config) => Box::new(Hysteresis::new(config)),
+            StrategyConfig::Drift(config) => Box::new(Drift::new(config)),
+        }
+    }
+
+    /// The configuration of the [StaticTuning] that holds the neighbourhoods, if there is one.
+    pub fn static_tuning(&self) -> Option<&StaticTuningConfig<T>> {
+        match self {
+            StrategyConfig::StaticTuning(config) => Some(config),
+            StrategyConfig::TwoStep(_, MelodyStrategyConfig::Neighbourhoods(config)) => {
+                Some(&config.inner)
+            }
+            StrategyConfig::Springs(_) => None {},
+            StrategyConfig::Walking(config) => Some(&config.inner),
+            StrategyConfig::Sketch(config) => Some(&config.inner),
+            StrategyConfig::Hysteresis(config) => config.inner.static_tuning(),
+            StrategyConfig::Drift(config) => config.inner.static_tuning(),
         }
     }
 }
@@ -63,6 +103,8 @@ impl<T: StackType> StrategyConfig<T> {
 #[derive(Clone)]
 pub struct ProcessConfig<T: IntervalBasis> {
     pub strategies: Vec<(StrategyConfig<T>, Bindings<MidiBindable>)>,
+    /// If this is empty, the whole keyboard is one zone.
+    pub zones: Vec<ZoneConfig>,
 }
 
 #[derive(Clone)]
@@ -95,6 +137,46 @@ pub struct GuiConfigWithoutStrategies {
 #[serde(rename_all = "kebab-case")]
 pub enum BackendConfig {
     Pitchbend12(Pitchbend12Config),
+    Mts(MtsConfig),
+    Mpe(MpeConfig),
+    /// One backend for every entry of [ProcessConfig::zones], in the same order. This is how the
+    /// zones get separate groups of output channels.
+    Zoned(Vec<BackendConfig>),
+}
+
+impl BackendConfig {
+    /// The same configuration, but with gliding switched off everywhere. Glides need the ticks of
+    /// the timer, so they can't be used where the backend gets none, as when retuning a file.
+    pub fn without_glide(self) -> Self {
+        match self {
+            BackendConfig::Pitchbend12(config) => BackendConfig::Pitchbend12(Pitchbend12Config {
+                glide: None {},
+                ..config
+            }),
+            BackendConfig::Zoned(configs) => {
+                BackendConfig::Zoned(configs.into_iter().map(Self::without_glide).collect())
+            }
+            other => other,
+        }
+    }
+
+    /// All channels on which the backend sends.
+    pub fn output_channels(&self) -> Vec<Channel> {
+        match self {
+            BackendConfig::Pitchbend12(config) => {
+                config.channels.iter().map(|&c| c.into()).collect()
+            }
+            BackendConfig::Mts(config) => vec![config.channel],
+            BackendConfig::Mpe(config) => {
+                let (master, mut members) = config.channels();
+                members.push(master);
+                members
+            }
+            BackendConfig::Zoned(configs) => {
+                configs.iter().flat_map(Self::output_channels).collect()
+            }
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -119,13 +201,84 @@ pub struct AdaptunerVersion;
 #[serde(rename_all = "kebab-case")]
 pub struct Config<T: IntervalBasis> {
     version: AdaptunerVersion,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intervals: Option<Vec<IntervalDefinition>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_basis: Option<IntervalBasisName>,
     pub temperaments: Vec<TemperamentDefinition<T>>,
     pub named_intervals: Vec<NamedInterval<T>>,
     strategies: Vec<NamedAndDescribed<ExtendedStrategyConfig<T>>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    zones: Vec<ZoneConfig>,
     backend: BackendConfig,
     gui: GuiConfigWithoutStrategies,
 }
 
+/// Only the `intervals` section and the `interval-basis` of a [Config]. Everything else in the
+/// configuration refers to intervals by name, so these have to be read (and passed to
+/// [IntervalsSection::initialise]) first.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct IntervalsSection {
+    #[serde(default)]
+    pub intervals: Option<Vec<IntervalDefinition>>,
+    #[serde(default)]
+    pub interval_basis: Option<IntervalBasisName>,
+}
+
+impl IntervalsSection {
+    /// The fixed interval basis of the configuration, or `None` if it defines its own
+    /// `intervals`.
+    pub fn fixed_basis(&self) -> Result<Option<IntervalBasisName>, StackTypeInitialisationErr> {
+        match (&self.intervals, self.interval_basis) {
+            (Some(_), Some(_)) => Err(StackTypeInitialisationErr::FixedIntervalBasis),
+            (Some(_), None) => Ok(None {}),
+            (None, basis) => Ok(Some(basis.unwrap_or(IntervalBasisName::FiveLimit))),
+        }
+    }
+
+    /// Initialise the intervals of `T` (see [Reloadable::initialise_intervals]), and check that
+    /// `T` is the stack type with the configured `interval-basis`.
+    pub fn initialise<T: Reloadable>(&self) -> Result<(), StackTypeInitialisationErr> {
+        T::initialise_intervals(self.intervals.as_deref())?;
+        let basis = self
+            .fixed_basis()?
+            .filter(|basis| *basis != IntervalBasisName::FiveLimit);
+        if basis != T::interval_basis_name() {
+            return Err(StackTypeInitialisationErr::IntervalBasisChanged);
+        }
+        Ok(())
+    }
+}
+
+impl<T: Reloadable + for<'de> serde::Deserialize<'de>> Config<T> {
+    /// Parse a configuration file, after initialising the intervals of `T` from its `intervals`
+    /// section and `interval-basis`.
+    pub fn from_yaml_str(s: &str) -> Result<Self, serde_yml::Error> {
+        let section: IntervalsSection = serde_yml::from_str(s)?;
+        section
+            .initialise::<T>()
+            .map_err(<serde_yml::Error as serde::de::Error>::custom)?;
+        let mut value: serde_yml::Value = serde_yml::from_str(s)?;
+        crate::custom_serde::action_names::resolve(&mut value)?;
+        let config: Self = serde_yml::from_value(value)?;
+        config
+            .check_zones()
+            .map_err(<serde_yml::Error as serde::de::Error>::custom)?;
+        Ok(config)
+    }
+
+    /// Like [Config::from_yaml_str], but configuration files written for older versions of
+    /// adaptuner are upgraded first. The [Upgrade] describes what was changed.
+    pub fn from_yaml_str_upgrading(s: &str) -> Result<(Self, Option<Upgrade>), serde_yml::Error> {
+        if let Some(upgrade) = crate::custom_serde::migration::upgrade(s)? {
+            Ok((Self::from_yaml_str(&upgrade.after)?, Some(upgrade)))
+        } else {
+            Ok((Self::from_yaml_str(s)?, None {}))
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
@@ -136,6 +289,56 @@ pub struct NamedAndDescribed<X> {
 }
 
 impl<T: IntervalBasis> Config<T> {
+    fn check_zones(&self) -> Result<(), String> {
+        for zone in &self.zones {
+            if zone.lowest > zone.highest || zone.highest > 127 {
+                return Err(format!(
+                    "the keys {}...{} of zone '{}' are not a range of MIDI notes",
+                    zone.lowest, zone.highest, zone.name
+                ));
+            }
+            if zone.strategy >= self.strategies.len() {
+                return Err(format!(
+                    "there is no strategy {} for zone '{}'",
+                    zone.strategy, zone.name
+                ));
+            }
+        }
+        if let BackendConfig::Zoned(backends) = &self.backend {
+            if backends.len() != self.zones.len() {
+                return Err(format!(
+                    "there are {} zones, but {} zoned backends",
+                    self.zones.len(),
+                    backends.len()
+                ));
+            }
+            // the zone that sends on each channel
+            let mut senders: [Option<usize>; 16] = [None {}; 16];
+            for (i, backend) in backends.iter().enumerate() {
+                if let BackendConfig::Zoned(_) = backend {
+                    return Err(format!(
+                        "the backend of zone '{}' is zoned again",
+                        self.zones[i].name
+                    ));
+                }
+                for channel in backend.output_channels() {
+                    match senders[channel as usize] {
+                        Some(j) if j != i => {
+                            return Err(format!(
+                                "the backends of zones '{}' and '{}' both send on channel {}",
+                                self.zones[j].name,
+                                self.zones[i].name,
+                                channel as u8 + 1
+                            ))
+                        }
+                        _ => senders[channel as usize] = Some(i),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn split(&self) -> (ProcessConfig<T>, GuiConfig<T>, BackendConfig) {
         let mut process = Vec::with_capacity(self.strategies.len());
         let mut ui = Vec::with_capacity(self.strategies.len());
@@ -147,6 +350,7 @@ impl<T: IntervalBasis> Config<T> {
         (
             ProcessConfig {
                 strategies: process,
+                zones: self.zones.clone(),
             },
             GuiConfig {
                 strategies: ui,
@@ -166,11 +370,15 @@ impl<T: IntervalBasis> Config<T> {
         mut process: ProcessConfig<T>,
         backend: BackendConfig,
         mut gui: GuiConfig<T>,
+        intervals: Option<Vec<IntervalDefinition>>,
+        interval_basis: Option<IntervalBasisName>,
         temperaments: Vec<TemperamentDefinition<T>>,
         named_intervals: Vec<NamedInterval<T>>,
     ) -> Self {
         Self {
             version: AdaptunerVersion,
+            intervals,
+            interval_basis,
             temperaments,
             named_intervals,
             strategies: if process.strategies.len() != gui.strategies.len() {
@@ -189,6 +397,7 @@ impl<T: IntervalBasis> Config<T> {
                     })
                     .collect()
             },
+            zones: process.zones,
             backend,
             gui: GuiConfigWithoutStrategies {
                 lattice_window: gui.lattice_window,
@@ -215,6 +424,9 @@ pub enum MelodyStrategyKind {
 pub enum StrategyKind {
     StaticTuning,
     TwoStep(HarmonyStrategyKind, MelodyStrategyKind),
+    Springs,
+    Walking,
+    Sketch,
 }
 
 impl StrategyKind {
@@ -223,6 +435,18 @@ impl StrategyKind {
     pub fn action_allowed(&self, action: &StrategyAction) -> bool {
         match (self, action) {
             (_, StrategyAction::Reset) => true,
+            (_, StrategyAction::ToggleRecording) => true,
+            (_, StrategyAction::SwitchToStrategy(_)) => true,
+            (_, StrategyAction::ExportTuning) => true,
+            (
+                StrategyKind::StaticTuning
+                | StrategyKind::TwoStep(_, MelodyStrategyKind::Neighbourhoods)
+                | StrategyKind::Walking
+                | StrategyKind::Sketch,
+                StrategyAction::SwitchToNeighbourhood(_)
+                | StrategyAction::ApplyTemperament(_)
+                | StrategyAction::MakeNeighbourhoodPure,
+            ) => true,
             (StrategyKind::StaticTuning, StrategyAction::IncrementNeighbourhoodIndex(_)) => true,
             (StrategyKind::StaticTuning, StrategyAction::SetReferenceToLowest) => true,
             (StrategyKind::StaticTuning, StrategyAction::SetReferenceToHighest) => true,
@@ -253,6 +477,21 @@ impl StrategyKind {
                 StrategyKind::TwoStep(HarmonyStrategyKind::ChordList, _),
                 StrategyAction::ToggleChordMatching,
             ) => true,
+            (StrategyKind::Springs, StrategyAction::SetReferenceToLowest) => true,
+            (StrategyKind::Springs, StrategyAction::SetReferenceToHighest) => true,
+            (StrategyKind::Springs, _) => false,
+            (StrategyKind::Walking, StrategyAction::IncrementNeighbourhoodIndex(_)) => true,
+            (StrategyKind::Walking, StrategyAction::SetReferenceToLowest) => true,
+            (StrategyKind::Walking, StrategyAction::SetReferenceToHighest) => true,
+            (StrategyKind::Walking, StrategyAction::SetReferenceToCurrent) => true,
+            (StrategyKind::Walking, StrategyAction::ToggleChordMatching) => true,
+            (StrategyKind::Walking, StrategyAction::ToggleReanchor) => true,
+            (StrategyKind::Sketch, StrategyAction::IncrementNeighbourhoodIndex(_)) => true,
+            (StrategyKind::Sketch, StrategyAction::SetReferenceToLowest) => true,
+            (StrategyKind::Sketch, StrategyAction::SetReferenceToHighest) => true,
+            (StrategyKind::Sketch, StrategyAction::SetReferenceToCurrent) => false,
+            (StrategyKind::Sketch, StrategyAction::ToggleChordMatching) => false,
+            (StrategyKind::Sketch, StrategyAction::ToggleReanchor) => false,
         }
     }
 }
@@ -288,6 +527,8 @@ pub struct ExtendedStaticTuningConfig<T: IntervalBasis> {
     tuning_reference: Reference<T>,
     reference: Stack<T>,
     bindings: Bindings<Bindable>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    controllers: Vec<ControllerMapping>,
 }
 
 fn deserialize_nonempty_neighbourhoods<
@@ -332,6 +573,113 @@ pub struct ExtendedNeighbourhoodsConfig<T: IntervalBasis> {
     reference: Stack<T>,
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct NamedSpringInterval<T: IntervalBasis> {
+    pub name: String,
+    pub interval: Stack<T>,
+    #[serde(
+        deserialize_with = "deserialize_stiffness",
+        serialize_with = "serialize_ratio"
+    )]
+    pub stiffness: Ratio<StackCoeff>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExtendedSpringsConfig<T: IntervalBasis> {
+    #[serde(deserialize_with = "deserialize_nonempty_spring_intervals")]
+    intervals: Vec<NamedSpringInterval<T>>,
+    #[serde(
+        deserialize_with = "deserialize_stiffness",
+        serialize_with = "serialize_ratio"
+    )]
+    anchor_stiffness: Ratio<StackCoeff>,
+    tuning_reference: Reference<T>,
+    reference: Stack<T>,
+    bindings: Bindings<Bindable>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    controllers: Vec<ControllerMapping>,
+}
+
+fn deserialize_nonempty_spring_intervals<
+    'de,
+    D: serde::Deserializer<'de>,
+    T: IntervalBasis + serde::Deserialize<'de>,
+>(
+    deserializer: D,
+) -> Result<Vec<NamedSpringInterval<T>>, D::Error> {
+    deserialize_nonempty(
+        "expected a non-empty list of intervals for strategy definition",
+        deserializer,
+    )
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExtendedWalkingConfig<T: IntervalBasis> {
+    walk: bool,
+    temper_patterns: bool,
+    #[serde(deserialize_with = "deserialize_nonempty_neighbourhoods")]
+    neighbourhoods: Vec<NamedCompleteNeighbourhood<T>>,
+    tuning_reference: Reference<T>,
+    /// the initial key centre
+    reference: Stack<T>,
+    enable_patterns: bool,
+    patterns: Vec<NamedPatternConfig<T>>,
+    bindings: Bindings<Bindable>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    controllers: Vec<ControllerMapping>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct NamedKeyShape {
+    pub name: String,
+    pub key_shape: KeyShape,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExtendedSketchConfig<T: IntervalBasis> {
+    minimum_age_ms: u64,
+    #[serde(deserialize_with = "deserialize_nonempty_neighbourhoods")]
+    neighbourhoods: Vec<NamedCompleteNeighbourhood<T>>,
+    tuning_reference: Reference<T>,
+    reference: Stack<T>,
+    key_shapes: Vec<NamedKeyShape>,
+    bindings: Bindings<Bindable>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    controllers: Vec<ControllerMapping>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExtendedHysteresisConfig<T: IntervalBasis> {
+    stable_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_retune_cents: Option<f64>,
+    freeze: bool,
+    inner: Box<ExtendedStrategyConfig<T>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExtendedDriftConfig<T: IntervalBasis> {
+    comma: Stack<T>,
+    max_drift_cents: f64,
+    compensate_at_rests: bool,
+    compensate_at_chord_changes: bool,
+    inner: Box<ExtendedStrategyConfig<T>>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
@@ -341,7 +689,14 @@ pub enum ExtendedStrategyConfig<T: IntervalBasis> {
         harmony: ExtendedHarmonyStrategyConfig<T>,
         melody: ExtendedMelodyStrategyConfig<T>,
         bindings: Bindings<Bindable>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        controllers: Vec<ControllerMapping>,
     },
+    Springs(ExtendedSpringsConfig<T>),
+    Walking(ExtendedWalkingConfig<T>),
+    Sketch(ExtendedSketchConfig<T>),
+    Hysteresis(ExtendedHysteresisConfig<T>),
+    Drift(ExtendedDriftConfig<T>),
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -384,6 +739,42 @@ pub enum StrategyNames<T: IntervalBasis> {
         harmony: HarmonyStrategyNames<T>,
         melody: MelodyStrategyNames,
     },
+    Springs {
+        name: String,
+        description: String,
+        intervals: Vec<NamedSpringInterval<T>>,
+        anchor_stiffness: Ratio<StackCoeff>,
+    },
+    Walking {
+        name: String,
+        description: String,
+        neighbourhood_names: Vec<String>,
+        patterns: Vec<NamedPatternConfig<T>>,
+        walk: bool,
+        temper_patterns: bool,
+    },
+    Sketch {
+        name: String,
+        description: String,
+        neighbourhood_names: Vec<String>,
+        key_shapes: Vec<NamedKeyShape>,
+        minimum_age_ms: u64,
+    },
+    /// The name and description are the ones of the `inner` names.
+    Hysteresis {
+        inner: Box<StrategyNames<T>>,
+        stable_ms: u64,
+        max_retune_cents: Option<f64>,
+        freeze: bool,
+    },
+    /// The name and description are the ones of the `inner` names.
+    Drift {
+        inner: Box<StrategyNames<T>>,
+        comma: Stack<T>,
+        max_drift_cents: f64,
+        compensate_at_rests: bool,
+        compensate_at_chord_changes: bool,
+    },
 }
 
 impl<T: IntervalBasis> StrategyNames<T> {
@@ -398,6 +789,11 @@ impl<T: IntervalBasis> StrategyNames<T> {
                 HarmonyStrategyKind::ChordList,
                 MelodyStrategyKind::Neighbourhoods,
             ),
+            StrategyNames::Springs { .. } => StrategyKind::Springs,
+            StrategyNames::Walking { .. } => StrategyKind::Walking,
+            StrategyNames::Sketch { .. } => StrategyKind::Sketch,
+            StrategyNames::Hysteresis { inner, .. } => inner.strategy_kind(),
+            StrategyNames::Drift { inner, .. } => inner.strategy_kind(),
         }
     }
 
@@ -405,6 +801,11 @@ impl<T: IntervalBasis> StrategyNames<T> {
         match self {
             StrategyNames::StaticTuning { name, .. } => name,
             StrategyNames::TwoStep { name, .. } => name,
+            StrategyNames::Springs { name, .. } => name,
+            StrategyNames::Walking { name, .. } => name,
+            StrategyNames::Sketch { name, .. } => name,
+            StrategyNames::Hysteresis { inner, .. } => inner.name(),
+            StrategyNames::Drift { inner, .. } => inner.name(),
         }
     }
 
@@ -412,6 +813,11 @@ impl<T: IntervalBasis> StrategyNames<T> {
         match self {
             StrategyNames::StaticTuning { name, .. } => name,
             StrategyNames::TwoStep { name, .. } => name,
+            StrategyNames::Springs { name, .. } => name,
+            StrategyNames::Walking { name, .. } => name,
+            StrategyNames::Sketch { name, .. } => name,
+            StrategyNames::Hysteresis { inner, .. } => inner.name_mut(),
+            StrategyNames::Drift { inner, .. } => inner.name_mut(),
         }
     }
 
@@ -419,6 +825,11 @@ impl<T: IntervalBasis> StrategyNames<T> {
         match self {
             StrategyNames::StaticTuning { description, .. } => description,
             StrategyNames::TwoStep { description, .. } => description,
+            StrategyNames::Springs { description, .. } => description,
+            StrategyNames::Walking { description, .. } => description,
+            StrategyNames::Sketch { description, .. } => description,
+            StrategyNames::Hysteresis { inner, .. } => inner.description(),
+            StrategyNames::Drift { inner, .. } => inner.description(),
         }
     }
 
@@ -426,15 +837,20 @@ impl<T: IntervalBasis> StrategyNames<T> {
         match self {
             StrategyNames::StaticTuning { description, .. } => description,
             StrategyNames::TwoStep { description, .. } => description,
+            StrategyNames::Springs { description, .. } => description,
+            StrategyNames::Walking { description, .. } => description,
+            StrategyNames::Sketch { description, .. } => description,
+            StrategyNames::Hysteresis { inner, .. } => inner.description_mut(),
+            StrategyNames::Drift { inner, .. } => inner.description_mut(),
         }
     }
 
-    pub fn neighbourhood_names_mut(&mut self) -> &mut Vec<String> {
+    pub fn neighbourhood_names_mut(&mut self) -> Option<&mut Vec<String>> {
         match self {
             StrategyNames::StaticTuning {
                 neighbourhood_names,
                 ..
-            } => neighbourhood_names,
+            } => Some(neighbourhood_names),
             StrategyNames::TwoStep {
                 melody:
                     MelodyStrategyNames::Neighbourhoods {
@@ -442,7 +858,36 @@ impl<T: IntervalBasis> StrategyNames<T> {
                         ..
                     },
                 ..
-            } => neighbourhood_names,
+            } => Some(neighbourhood_names),
+            StrategyNames::Walking {
+                neighbourhood_names,
+                ..
+            } => Some(neighbourhood_names),
+            StrategyNames::Sketch {
+                neighbourhood_names,
+                ..
+            } => Some(neighbourhood_names),
+            StrategyNames::Springs { .. } => None {},
+            StrategyNames::Hysteresis { inner, .. } => inner.neighbourhood_names_mut(),
+            StrategyNames::Drift { inner, .. } => inner.neighbourhood_names_mut(),
+        }
+    }
+
+    /// Look through wrappers like [StrategyNames::Hysteresis].
+    pub fn innermost(&self) -> &Self {
+        match self {
+            StrategyNames::Hysteresis { inner, .. } => inner.innermost(),
+            StrategyNames::Drift { inner, .. } => inner.innermost(),
+            _ => self,
+        }
+    }
+
+    /// Look through wrappers like [StrategyNames::Hysteresis].
+    pub fn innermost_mut(&mut self) -> &mut Self {
+        match self {
+            StrategyNames::Hysteresis { inner, .. } => inner.innermost_mut(),
+            StrategyNames::Drift { inner, .. } => inner.innermost_mut(),
+            _ => self,
         }
     }
 }
@@ -451,6 +896,7 @@ impl<T: IntervalBasis> ExtendedStaticTuningConfig<T> {
     fn split(&self) -> (StaticTuningConfig<T>, Bindings<Bindable>, Vec<String>) {
         let ExtendedStaticTuningConfig {
             bindings,
+            controllers,
             neighbourhoods,
             tuning_reference,
             reference,
@@ -466,7 +912,7 @@ impl<T: IntervalBasis> ExtendedStaticTuningConfig<T> {
                 tuning_reference: tuning_reference.clone(),
                 reference: reference.clone(),
             },
-            bindings.clone(),
+            bindings.clone().with_controllers(controllers.clone()),
             neighbourhood_names,
         )
     }
@@ -483,6 +929,7 @@ impl<T: IntervalBasis> ExtendedStaticTuningConfig<T> {
         } = strat;
 
         ExtendedStaticTuningConfig {
+            controllers: bindings.controllers.clone(),
             bindings,
             neighbourhoods: if neighbourhoods.len() != neighbourhood_names.len() {
                 panic!(
@@ -506,6 +953,242 @@ impl<T: IntervalBasis> ExtendedStaticTuningConfig<T> {
     }
 }
 
+impl<T: IntervalBasis> ExtendedSpringsConfig<T> {
+    fn split(
+        &self,
+    ) -> (
+        SpringsConfig<T>,
+        Bindings<Bindable>,
+        Vec<NamedSpringInterval<T>>,
+    ) {
+        let ExtendedSpringsConfig {
+            intervals,
+            anchor_stiffness,
+            tuning_reference,
+            reference,
+            bindings,
+            controllers,
+        } = self;
+        (
+            SpringsConfig {
+                intervals: intervals
+                    .iter()
+                    .map(|x| SpringInterval {
+                        interval: x.interval.clone(),
+                        stiffness: x.stiffness,
+                    })
+                    .collect(),
+                anchor_stiffness: *anchor_stiffness,
+                tuning_reference: tuning_reference.clone(),
+                reference: reference.clone(),
+            },
+            bindings.clone().with_controllers(controllers.clone()),
+            intervals.clone(),
+        )
+    }
+
+    /// The names of the intervals are taken from `named_intervals`, everything else from `strat`.
+    fn join(
+        strat: SpringsConfig<T>,
+        bindings: Bindings<Bindable>,
+        named_intervals: Vec<NamedSpringInterval<T>>,
+    ) -> Self {
+        let SpringsConfig {
+            intervals,
+            anchor_stiffness,
+            tuning_reference,
+            reference,
+        } = strat;
+
+        ExtendedSpringsConfig {
+            intervals: if intervals.len() != named_intervals.len() {
+                panic!(
+                    "different number of intervals ({}) and interval names ({})",
+                    intervals.len(),
+                    named_intervals.len(),
+                )
+            } else {
+                intervals
+                    .into_iter()
+                    .zip(named_intervals)
+                    .map(|(inner, named)| NamedSpringInterval {
+                        name: named.name,
+                        interval: inner.interval,
+                        stiffness: inner.stiffness,
+                    })
+                    .collect()
+            },
+            anchor_stiffness,
+            tuning_reference,
+            reference,
+            controllers: bindings.controllers.clone(),
+            bindings,
+        }
+    }
+}
+
+impl<T: IntervalBasis> ExtendedWalkingConfig<T> {
+    fn split(&self) -> (WalkingConfig<T>, Bindings<Bindable>, Vec<String>) {
+        let ExtendedWalkingConfig {
+            walk,
+            temper_patterns,
+            neighbourhoods,
+            tuning_reference,
+            reference,
+            enable_patterns,
+            patterns,
+            bindings,
+            controllers,
+        } = self;
+
+        let neighbourhood_names: Vec<String> =
+            neighbourhoods.iter().map(|x| x.name().into()).collect();
+        let neighbourhoods: Vec<SomeCompleteNeighbourhood<T>> =
+            neighbourhoods.iter().map(|x| x.inner()).collect();
+        (
+            WalkingConfig {
+                patterns: ChordListConfig {
+                    enable: *enable_patterns,
+                    patterns: patterns
+                        .iter()
+                        .map(|p| PatternConfig {
+                            key_shape: p.key_shape.clone(),
+                            neighbourhood: p.neighbourhood.clone(),
+                            allow_extra_high_notes: p.allow_extra_high_notes,
+                        })
+                        .collect(),
+                },
+                inner: StaticTuningConfig {
+                    neighbourhoods,
+                    tuning_reference: tuning_reference.clone(),
+                    reference: reference.clone(),
+                },
+                walk: *walk,
+                temper_patterns: *temper_patterns,
+            },
+            bindings.clone().with_controllers(controllers.clone()),
+            neighbourhood_names,
+        )
+    }
+
+    /// The names of the neighbourhoods and the patterns are taken from the arguments, everything
+    /// else from `strat`.
+    fn join(
+        strat: WalkingConfig<T>,
+        bindings: Bindings<Bindable>,
+        neighbourhood_names: Vec<String>,
+        named_patterns: Vec<NamedPatternConfig<T>>,
+    ) -> Self {
+        let WalkingConfig {
+            patterns: ChordListConfig { enable, patterns },
+            inner,
+            walk,
+            temper_patterns,
+        } = strat;
+        if patterns.len() != named_patterns.len() {
+            panic!("different numbers of patterns in the chord list and names for these patterns");
+        }
+        let ExtendedStaticTuningConfig {
+            neighbourhoods,
+            tuning_reference,
+            reference,
+            bindings,
+            controllers,
+        } = ExtendedStaticTuningConfig::join(inner, bindings, neighbourhood_names);
+
+        ExtendedWalkingConfig {
+            walk,
+            temper_patterns,
+            neighbourhoods,
+            tuning_reference,
+            reference,
+            enable_patterns: enable,
+            patterns: named_patterns,
+            bindings,
+            controllers,
+        }
+    }
+}
+
+impl<T: IntervalBasis> ExtendedSketchConfig<T> {
+    fn split(&self) -> (SketchConfig<T>, Bindings<Bindable>, Vec<String>) {
+        let ExtendedSketchConfig {
+            minimum_age_ms,
+            neighbourhoods,
+            tuning_reference,
+            reference,
+            key_shapes,
+            bindings,
+            controllers,
+        } = self;
+
+        let neighbourhood_names: Vec<String> =
+            neighbourhoods.iter().map(|x| x.name().into()).collect();
+        let neighbourhoods: Vec<SomeCompleteNeighbourhood<T>> =
+            neighbourhoods.iter().map(|x| x.inner()).collect();
+        (
+            SketchConfig {
+                inner: StaticTuningConfig {
+                    neighbourhoods,
+                    tuning_reference: tuning_reference.clone(),
+                    reference: reference.clone(),
+                },
+                key_shapes: key_shapes.iter().map(|x| x.key_shape.clone()).collect(),
+                minimum_age_ms: *minimum_age_ms,
+            },
+            bindings.clone().with_controllers(controllers.clone()),
+            neighbourhood_names,
+        )
+    }
+
+    /// The names of the neighbourhoods and the key shapes are taken from the arguments,
+    /// everything else from `strat`.
+    fn join(
+        strat: SketchConfig<T>,
+        bindings: Bindings<Bindable>,
+        neighbourhood_names: Vec<String>,
+        named_key_shapes: Vec<NamedKeyShape>,
+    ) -> Self {
+        let SketchConfig {
+            inner,
+            key_shapes,
+            minimum_age_ms,
+        } = strat;
+        let ExtendedStaticTuningConfig {
+            neighbourhoods,
+            tuning_reference,
+            reference,
+            bindings,
+            controllers,
+        } = ExtendedStaticTuningConfig::join(inner, bindings, neighbourhood_names);
+
+        ExtendedSketchConfig {
+            minimum_age_ms,
+            neighbourhoods,
+            tuning_reference,
+            reference,
+            key_shapes: if key_shapes.len() != named_key_shapes.len() {
+                panic!(
+                    "different number of key shapes ({}) and key shape names ({})",
+                    key_shapes.len(),
+                    named_key_shapes.len(),
+                )
+            } else {
+                key_shapes
+                    .into_iter()
+                    .zip(named_key_shapes)
+                    .map(|(key_shape, named)| NamedKeyShape {
+                        name: named.name,
+                        key_shape,
+                    })
+                    .collect()
+            },
+            bindings,
+            controllers,
+        }
+    }
+}
+
 impl<T: IntervalBasis> ExtendedHarmonyStrategyConfig<T> {
     fn split(&self) -> (HarmonyStrategyConfig<T>, HarmonyStrategyNames<T>) {
         match self {
@@ -650,6 +1333,7 @@ impl<T: IntervalBasis> NamedAndDescribed<ExtendedStrategyConfig<T>> {
                 config:
                     ExtendedStrategyConfig::TwoStep {
                         bindings,
+                        controllers,
                         harmony,
                         melody,
                     },
@@ -658,7 +1342,7 @@ impl<T: IntervalBasis> NamedAndDescribed<ExtendedStrategyConfig<T>> {
                 let (melody_config, melody_names) = melody.split();
                 (
                     StrategyConfig::TwoStep(harmony_config, melody_config),
-                    bindings.clone(),
+                    bindings.clone().with_controllers(controllers.clone()),
                     StrategyNames::TwoStep {
                         name: name.clone(),
                         description: description.clone(),
@@ -667,6 +1351,132 @@ impl<T: IntervalBasis> NamedAndDescribed<ExtendedStrategyConfig<T>> {
                     },
                 )
             }
+            NamedAndDescribed {
+                name,
+                description,
+                config: ExtendedStrategyConfig::Springs(inner),
+            } => {
+                let (c, b, intervals) = inner.split();
+                let anchor_stiffness = c.anchor_stiffness;
+                (
+                    StrategyConfig::Springs(c),
+                    b,
+                    StrategyNames::Springs {
+                        name: name.clone(),
+                        description: description.clone(),
+                        anchor_stiffness,
+                        intervals,
+                    },
+                )
+            }
+            NamedAndDescribed {
+                name,
+                description,
+                config: ExtendedStrategyConfig::Walking(inner),
+            } => {
+                let (c, b, neighbourhood_names) = inner.split();
+                let (walk, temper_patterns) = (c.walk, c.temper_patterns);
+                (
+                    StrategyConfig::Walking(c),
+                    b,
+                    StrategyNames::Walking {
+                        name: name.clone(),
+                        description: description.clone(),
+                        neighbourhood_names,
+                        patterns: inner.patterns.clone(),
+                        walk,
+                        temper_patterns,
+                    },
+                )
+            }
+            NamedAndDescribed {
+                name,
+                description,
+                config:
+                    ExtendedStrategyConfig::Hysteresis(ExtendedHysteresisConfig {
+                        stable_ms,
+                        max_retune_cents,
+                        freeze,
+                        inner,
+                    }),
+            } => {
+                let (c, b, n) = NamedAndDescribed {
+                    name: name.clone(),
+                    description: description.clone(),
+                    config: (**inner).clone(),
+                }
+                .split();
+                (
+                    StrategyConfig::Hysteresis(HysteresisConfig {
+                        inner: Box::new(c),
+                        stable_ms: *stable_ms,
+                        max_retune_cents: *max_retune_cents,
+                        freeze: *freeze,
+                    }),
+                    b,
+                    StrategyNames::Hysteresis {
+                        inner: Box::new(n),
+                        stable_ms: *stable_ms,
+                        max_retune_cents: *max_retune_cents,
+                        freeze: *freeze,
+                    },
+                )
+            }
+            NamedAndDescribed {
+                name,
+                description,
+                config:
+                    ExtendedStrategyConfig::Drift(ExtendedDriftConfig {
+                        comma,
+                        max_drift_cents,
+                        compensate_at_rests,
+                        compensate_at_chord_changes,
+                        inner,
+                    }),
+            } => {
+                let (c, b, n) = NamedAndDescribed {
+                    name: name.clone(),
+                    description: description.clone(),
+                    config: (**inner).clone(),
+                }
+                .split();
+                (
+                    StrategyConfig::Drift(DriftConfig {
+                        inner: Box::new(c),
+                        comma: comma.clone(),
+                        max_drift_cents: *max_drift_cents,
+                        compensate_at_rests: *compensate_at_rests,
+                        compensate_at_chord_changes: *compensate_at_chord_changes,
+                    }),
+                    b,
+                    StrategyNames::Drift {
+                        inner: Box::new(n),
+                        comma: comma.clone(),
+                        max_drift_cents: *max_drift_cents,
+                        compensate_at_rests: *compensate_at_rests,
+                        compensate_at_chord_changes: *compensate_at_chord_changes,
+                    },
+                )
+            }
+            NamedAndDescribed {
+                name,
+                description,
+                config: ExtendedStrategyConfig::Sketch(inner),
+            } => {
+                let (c, b, neighbourhood_names) = inner.split();
+                let minimum_age_ms = c.minimum_age_ms;
+                (
+                    StrategyConfig::Sketch(c),
+                    b,
+                    StrategyNames::Sketch {
+                        name: name.clone(),
+                        description: description.clone(),
+                        neighbourhood_names,
+                        key_shapes: inner.key_shapes.clone(),
+                        minimum_age_ms,
+                    },
+                )
+            }
         }
     }
 
@@ -704,12 +1514,234 @@ impl<T: IntervalBasis> NamedAndDescribed<ExtendedStrategyConfig<T>> {
                 name,
                 description,
                 config: ExtendedStrategyConfig::TwoStep {
+                    controllers: bindings.controllers.clone(),
                     bindings,
                     harmony: ExtendedHarmonyStrategyConfig::join(harmony_config, harmony_names),
                     melody: ExtendedMelodyStrategyConfig::join(melody_config, melody_names),
                 },
             },
+            (
+                StrategyConfig::Springs(c),
+                StrategyNames::Springs {
+                    name,
+                    description,
+                    intervals,
+                    ..
+                },
+            ) => Self {
+                name,
+                description,
+                config: ExtendedStrategyConfig::Springs(ExtendedSpringsConfig::join(
+                    c, bindings, intervals,
+                )),
+            },
+            (
+                StrategyConfig::Walking(c),
+                StrategyNames::Walking {
+                    name,
+                    description,
+                    neighbourhood_names,
+                    patterns,
+                    ..
+                },
+            ) => Self {
+                name,
+                description,
+                config: ExtendedStrategyConfig::Walking(ExtendedWalkingConfig::join(
+                    c,
+                    bindings,
+                    neighbourhood_names,
+                    patterns,
+                )),
+            },
+            (
+                StrategyConfig::Sketch(c),
+                StrategyNames::Sketch {
+                    name,
+                    description,
+                    neighbourhood_names,
+                    key_shapes,
+                    ..
+                },
+            ) => Self {
+                name,
+                description,
+                config: ExtendedStrategyConfig::Sketch(ExtendedSketchConfig::join(
+                    c,
+                    bindings,
+                    neighbourhood_names,
+                    key_shapes,
+                )),
+            },
+            (StrategyConfig::Hysteresis(c), StrategyNames::Hysteresis { inner, .. }) => {
+                let NamedAndDescribed {
+                    name,
+                    description,
+                    config,
+                } = Self::join(*c.inner, bindings, *inner);
+                Self {
+                    name,
+                    description,
+                    config: ExtendedStrategyConfig::Hysteresis(ExtendedHysteresisConfig {
+                        stable_ms: c.stable_ms,
+                        max_retune_cents: c.max_retune_cents,
+                        freeze: c.freeze,
+                        inner: Box::new(config),
+                    }),
+                }
+            }
+            (StrategyConfig::Drift(c), StrategyNames::Drift { inner, .. }) => {
+                let NamedAndDescribed {
+                    name,
+                    description,
+                    config,
+                } = Self::join(*c.inner, bindings, *inner);
+                Self {
+                    name,
+                    description,
+                    config: ExtendedStrategyConfig::Drift(ExtendedDriftConfig {
+                        comma: c.comma,
+                        max_drift_cents: c.max_drift_cents,
+                        compensate_at_rests: c.compensate_at_rests,
+                        compensate_at_chord_changes: c.compensate_at_chord_changes,
+                        inner: Box::new(config),
+                    }),
+                }
+            }
             _ => panic!("strategy config and strategy names don't have matching types"),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        backend::mpe::MpeZone,
+        interval::stacktype::{
+            fivelimit::TheFiveLimitStackType, sevenlimit::TheSevenLimitStackType,
+        },
+    };
+
+    #[test]
+    fn test_shipped_configs() {
+        for s in [
+            include_str!("../configs/template.yaml"),
+            include_str!("../configs/cembalo_cromatico.yaml"),
+        ] {
+            Config::<TheFiveLimitStackType>::from_yaml_str(s).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_interval_basis() {
+        let template = include_str!("../configs/template.yaml");
+        // The temperaments of the template only have equations for three intervals.
+        let (_, rest) = template.split_once("named-intervals:").unwrap();
+        let seven_limit = format!(
+            "version: 0.2.2\ninterval-basis: seven-limit\ntemperaments: []\nnamed-intervals:{rest}"
+        );
+        let config = Config::<TheSevenLimitStackType>::from_yaml_str(&seven_limit).unwrap();
+        assert_eq!(config.interval_basis, Some(IntervalBasisName::SevenLimit));
+        assert!(Config::<TheFiveLimitStackType>::from_yaml_str(&seven_limit).is_err());
+        assert!(Config::<TheSevenLimitStackType>::from_yaml_str(
+            &seven_limit.replace("interval-basis: seven-limit\n", "")
+        )
+        .is_err());
+
+        let section = |s: &str| {
+            serde_yml::from_str::<IntervalsSection>(s)
+                .unwrap()
+                .fixed_basis()
+        };
+        assert!(matches!(
+            section("version: 0.2.2"),
+            Ok(Some(IntervalBasisName::FiveLimit))
+        ));
+        assert!(matches!(
+            section(&seven_limit),
+            Ok(Some(IntervalBasisName::SevenLimit))
+        ));
+        assert!(matches!(
+            section("intervals: []\ninterval-basis: seven-limit"),
+            Err(StackTypeInitialisationErr::FixedIntervalBasis)
+        ));
+    }
+
+    #[test]
+    fn test_check_zones() {
+        let mut config = Config::<TheFiveLimitStackType>::from_yaml_str(include_str!(
+            "../configs/template.yaml"
+        ))
+        .unwrap();
+        let zone = |name: &str, lowest, highest| ZoneConfig {
+            name: name.into(),
+            lowest,
+            highest,
+            channels: vec![],
+            strategy: 0,
+            share_reference: false,
+        };
+        let mts = |channel| {
+            BackendConfig::Mts(MtsConfig {
+                channel,
+                realtime: true,
+                program: None {},
+                bank: None {},
+            })
+        };
+        let mpe = |zone| {
+            BackendConfig::Mpe(MpeConfig {
+                zone,
+                member_channels: 3,
+                bend_range: 48,
+            })
+        };
+        config.zones = vec![zone("left", 0, 59), zone("right", 60, 127)];
+
+        config.backend = BackendConfig::Zoned(vec![mpe(MpeZone::Lower), mpe(MpeZone::Upper)]);
+        assert_eq!(config.check_zones(), Ok(()));
+
+        config.backend = BackendConfig::Zoned(vec![mpe(MpeZone::Lower), mts(Channel::Ch3)]);
+        assert_eq!(
+            config.check_zones(),
+            Err("the backends of zones 'left' and 'right' both send on channel 3".into())
+        );
+
+        config.backend = BackendConfig::Zoned(vec![
+            mts(Channel::Ch1),
+            BackendConfig::Zoned(vec![mts(Channel::Ch2)]),
+        ]);
+        assert_eq!(
+            config.check_zones(),
+            Err("the backend of zone 'right' is zoned again".into())
+        );
+    }
+
+    #[test]
+    fn test_without_glide() {
+        let pitchbend12 = || {
+            BackendConfig::Pitchbend12(Pitchbend12Config {
+                bend_range: 2.0,
+                channels: core::array::from_fn(|i| midi_msg::Channel::from_u8(i as u8).into()),
+                glide: Some(crate::backend::glide::GlideConfig {
+                    duration_ms: 100,
+                    curve: crate::backend::glide::GlideCurve::Linear,
+                }),
+            })
+        };
+        let config = BackendConfig::Zoned(vec![pitchbend12(), pitchbend12()]).without_glide();
+        let BackendConfig::Zoned(zones) = config else {
+            panic!("the zones should be kept");
+        };
+        assert_eq!(zones.len(), 2);
+        for zone in zones {
+            match zone {
+                BackendConfig::Pitchbend12(Pitchbend12Config { glide, .. }) => {
+                    assert_eq!(glide, None {})
+                }
+                _ => panic!("the backends should be kept"),
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Referring to strategies, neighbourhoods, and temperaments by name in
+//! [StrategyAction][crate::strategy::r#trait::StrategyAction]s.
+//!
+//! The actions only store indices, but in the configuration file one can also write
+//!
+//! ```yaml
+//! bindings:
+//!   program-1: !switch-to-strategy walking
+//!   note-36: !switch-to-neighbourhood sharps
+//!   note-38: !apply-temperament 1/4-comma meantone
+//! ```
+//!
+//! Before the configuration is deserialised, [resolve] replaces such names by the indices of the
+//! strategy in `strategies`, of the neighbourhood in the strategy's `neighbourhoods`, and of the
+//! temperament in `temperaments`. The same goes for the `strategy` of a
+//! [ZoneConfig][crate::process::zones::ZoneConfig].
+
+use serde_yml::Value;
+
+/// The first `neighbourhoods` sequence in the configuration of a strategy, searching depth-first
+/// and looking through YAML tags. This finds the neighbourhoods of wrapped strategies (in the
+/// `inner` field) and the melody neighbourhoods of two-step strategies.
+fn find_neighbourhoods(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Tagged(tagged) => find_neighbourhoods(&tagged.value),
+        Value::Mapping(m) => {
+            if let Some(ns @ Value::Sequence(_)) = m.get("neighbourhoods") {
+                return Some(ns);
+            }
+            m.values().find_map(find_neighbourhoods)
+        }
+        _ => None {},
+    }
+}
+
+/// Replace names by indices in all `bindings` mappings below `value`.
+fn resolve_bindings(
+    value: &mut Value,
+    strategies: &[String],
+    neighbourhoods: &[String],
+    temperaments: &[String],
+) -> Result<(), String> {
+    match value {
+        Value::Tagged(tagged) => {
+            resolve_bindings(&mut tagged.value, strategies, neighbourhoods, temperaments)
+        }
+        Value::Mapping(m) => {
+            for (k, v) in m.iter_mut() {
+                if k.as_str() == Some("bindings") {
+                    if let Value::Mapping(bindings) = v {
+                        for action in bindings.values_mut() {
+                            resolve_action(action, strategies, neighbourhoods, temperaments)?;
+                        }
+                    }
+                } else {
+                    resolve_bindings(v, strategies, neighbourhoods, temperaments)?;
+                }
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn resolve_action(
+    action: &mut Value,
+    strategies: &[String],
+    neighbourhoods: &[String],
+    temperaments: &[String],
+) -> Result<(), String> {
+    let Value::Tagged(tagged) = action else {
+        return Ok(());
+    };
+    let Some(name) = tagged.value.as_str() else {
+        return Ok(());
+    };
+    let (what, names) = if tagged.tag == "switch-to-strategy" {
+        ("strategy", strategies)
+    } else if tagged.tag == "switch-to-neighbourhood" {
+        ("neighbourhood", neighbourhoods)
+    } else if tagged.tag == "apply-temperament" {
+        ("temperament", temperaments)
+    } else {
+        return Ok(());
+    };
+    let index = names
+        .iter()
+        .position(|n| n == name)
+        .ok_or_else(|| format!("there is no {what} called '{name}'"))?;
+    tagged.value = Value::Number(index.into());
+    Ok(())
+}
+
+/// The `name` fields of the elements of a sequence.
+fn names(list: Option<&Value>) -> Vec<String> {
+    match list {
+        Some(Value::Sequence(xs)) => xs
+            .iter()
+            .map(|x| {
+                x.get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string()
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn resolve_zones(zones: &mut [Value], strategies: &[String]) -> Result<(), String> {
+    for zone in zones {
+        let Some(strategy) = zone.get_mut("strategy") else {
+            continue;
+        };
+        let Some(name) = strategy.as_str() else {
+            continue;
+        };
+        let index = strategies
+            .iter()
+            .position(|n| n == name)
+            .ok_or_else(|| format!("there is no strategy called '{name}'"))?;
+        *strategy = Value::Number(index.into());
+    }
+    Ok(())
+}
+
+/// Replace the names of strategies, neighbourhoods, and temperaments in the `bindings` of all
+/// strategies and in the `zones` by their indices.
+pub fn resolve(config: &mut Value) -> Result<(), serde_yml::Error> {
+    let strategies = names(config.get("strategies"));
+    let temperaments = names(config.get("temperaments"));
+    if let Some(Value::Sequence(zones)) = config.get_mut("zones") {
+        resolve_zones(zones, &strategies).map_err(|e| {
+            <serde_yml::Error as serde::de::Error>::custom(format!("in the zones: {e}"))
+        })?;
+    }
+    let Some(Value::Sequence(list)) = config.get_mut("strategies") else {
+        return Ok(());
+    };
+    for (i, strategy) in list.iter_mut().enumerate() {
+        let Some(strategy_config) = strategy.get_mut("config") else {
+            continue;
+        };
+        let neighbourhoods = names(find_neighbourhoods(strategy_config));
+        resolve_bindings(strategy_config, &strategies, &neighbourhoods, &temperaments).map_err(
+            |e| {
+                <serde_yml::Error as serde::de::Error>::custom(format!(
+                    "in the bindings of strategy '{}': {e}",
+                    strategies[i]
+                ))
+            },
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_resolve() {
+        let mut config: Value = serde_yml::from_str(
+            r#"
+temperaments:
+- name: equal temperament
+- name: meantone
+strategies:
+- name: static
+  config: !static-tuning
+    neighbourhoods:
+    - name: flats
+    - name: sharps
+    bindings:
+      note-36: !switch-to-neighbourhood sharps
+      note-37: !switch-to-neighbourhood 0
+      note-38: !apply-temperament meantone
+      program-1: !switch-to-strategy two-step
+      Escape: reset
+- name: two-step
+  config: !two-step
+    melody: !neighbourhoods
+      neighbourhoods:
+      - name: pure
+    bindings:
+      note-36: !switch-to-neighbourhood pure
+      program-0: !switch-to-strategy static
+zones:
+- name: left
+  strategy: static
+- name: right
+  strategy: two-step
+"#,
+        )
+        .unwrap();
+
+        resolve(&mut config).unwrap();
+
+        assert_eq!(
+            config,
+            serde_yml::from_str::<Value>(
+                r#"
+temperaments:
+- name: equal temperament
+- name: meantone
+strategies:
+- name: static
+  config: !static-tuning
+    neighbourhoods:
+    - name: flats
+    - name: sharps
+    bindings:
+      note-36: !switch-to-neighbourhood 1
+      note-37: !switch-to-neighbourhood 0
+      note-38: !apply-temperament 1
+      program-1: !switch-to-strategy 1
+      Escape: reset
+- name: two-step
+  config: !two-step
+    melody: !neighbourhoods
+      neighbourhoods:
+      - name: pure
+    bindings:
+      note-36: !switch-to-neighbourhood 0
+      program-0: !switch-to-strategy 0
+zones:
+- name: left
+  strategy: 0
+- name: right
+  strategy: 1
+"#,
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_resolve_unknown_name() {
+        let mut config: Value = serde_yml::from_str(
+            r#"
+strategies:
+- name: static
+  config: !static-tuning
+    neighbourhoods: []
+    bindings:
+      note-36: !switch-to-neighbourhood sharps
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            resolve(&mut config).err().unwrap().to_string(),
+            "in the bindings of strategy 'static': there is no neighbourhood called 'sharps'"
+        );
+    }
+}
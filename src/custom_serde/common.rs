@@ -174,7 +174,7 @@ where
             write!(f, "string, name of key")
         }
 
-        fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
         where
             E: serde::de::Error,
         {
@@ -214,9 +214,76 @@ pub fn deserialize_channel<'de, D: serde::Deserializer<'de>>(
     }
 }
 
+/// For optional MIDI data bytes, like program numbers.
+pub fn deserialize_optional_data_byte<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u8>, D::Error> {
+    match <Option<u8> as serde::Deserialize<'de>>::deserialize(deserializer)? {
+        Some(x) if x > 127 => Err(serde::de::Error::custom(format!(
+            "{x} is not in the (inclusive) range 0...127"
+        ))),
+        x => Ok(x),
+    }
+}
+
 pub fn serialize_channel<S: serde::Serializer>(
     channel: &Channel,
     ser: S,
 ) -> Result<S::Ok, S::Error> {
     ser.serialize_u8(*channel as u8 + 1)
 }
+
+/// Accepts integers and strings like `"3/2"`.
+pub fn deserialize_ratio<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Ratio<StackCoeff>, D::Error> {
+    struct RatioVisitor {}
+    impl<'de> serde::de::Visitor<'de> for RatioVisitor {
+        type Value = Ratio<StackCoeff>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "integer, or string describing a fraction")
+        }
+
+        fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            Ok(Ratio::from_integer(v))
+        }
+
+        fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            match StackCoeff::try_from(v) {
+                Ok(v) => Ok(Ratio::from_integer(v)),
+                Err(_) => Err(serde::de::Error::custom(format!("{v} is too large"))),
+            }
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            match v.parse() {
+                Ok(x) => Ok(x),
+                Err(_) => Err(serde::de::Error::custom(format!(
+                    "'{v}' is not a well-formed number"
+                ))),
+            }
+        }
+    }
+    deserializer.deserialize_any(RatioVisitor {})
+}
+
+pub fn serialize_ratio<S: serde::Serializer>(
+    ratio: &Ratio<StackCoeff>,
+    ser: S,
+) -> Result<S::Ok, S::Error> {
+    if ratio.is_integer() {
+        ser.serialize_i64(*ratio.numer())
+    } else {
+        ser.serialize_str(&ratio.to_string())
+    }
+}
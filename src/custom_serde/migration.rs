@@ -0,0 +1,372 @@
+//! Upgrading configuration files written for older versions of adaptuner.
+//!
+//! The upgrade works on the YAML tree of the file, one [Step] at a time, until the tree is in the
+//! format of the current version. When the configuration format changes, add a [Step] from the
+//! last released version to the new one to [STEPS]. The helpers [rename_field] and [rename_tag]
+//! cover most changes: renamed fields, and renamed variants of enums (like strategy kinds or
+//! [KeyShape][crate::strategy::twostep::harmony::chordlist::keyshape::KeyShape]s), which are
+//! written as YAML tags.
+
+use serde_yml::{value::Tag, Mapping, Value};
+
+use super::version::VERSION;
+
+/// What was done to upgrade a configuration file.
+pub struct Upgrade {
+    /// The version the file was written for.
+    pub from_version: String,
+    /// Descriptions of all changes, in the order they were made.
+    pub changes: Vec<String>,
+    /// The configuration before the upgrade.
+    pub before: String,
+    /// The configuration after the upgrade.
+    pub after: String,
+}
+
+struct Step {
+    from: &'static str,
+    to: &'static str,
+    apply: fn(&mut Value, &mut Vec<String>),
+}
+
+/// The first version in this list is the first one that used YAML configuration files.
+const STEPS: &[Step] = &[
+    Step {
+        from: "0.2.0",
+        to: "0.2.1",
+        apply: no_changes,
+    },
+    Step {
+        from: "0.2.1",
+        to: "0.2.2",
+        apply: no_changes,
+    },
+];
+
+fn no_changes(_config: &mut Value, _changes: &mut Vec<String>) {}
+
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    version.split('.').map(|x| x.parse().ok()).collect()
+}
+
+fn version_of(config: &Value) -> Result<String, serde_yml::Error> {
+    match config.get("version") {
+        Some(Value::String(s)) => Ok(s.clone()),
+        Some(Value::Number(n)) => Ok(n.to_string()),
+        _ => Err(serde::de::Error::custom(
+            "the configuration file has no 'version' field",
+        )),
+    }
+}
+
+/// Upgrade a configuration file to the current version. Returns `None` if the file already is for
+/// the current version.
+pub fn upgrade(s: &str) -> Result<Option<Upgrade>, serde_yml::Error> {
+    let mut config: Value = serde_yml::from_str(s)?;
+    let from_version = version_of(&config)?;
+    if from_version == VERSION {
+        return Ok(None {});
+    }
+
+    if let (Some(from), Some(current)) = (parse_version(&from_version), parse_version(VERSION)) {
+        if from > current {
+            return Err(serde::de::Error::custom(format!(
+                "the configuration file is for adaptuner version {from_version}, which is newer \
+                than this version ({VERSION})"
+            )));
+        }
+    }
+
+    let before = serde_yml::to_string(&config)?;
+    let mut changes = vec![];
+    let mut version = from_version.as_str();
+    while version != VERSION {
+        let step = STEPS.iter().find(|s| s.from == version).ok_or_else(|| {
+            <serde_yml::Error as serde::de::Error>::custom(format!(
+                "configuration files for adaptuner version {from_version} can't be upgraded to \
+                version {VERSION}"
+            ))
+        })?;
+        (step.apply)(&mut config, &mut changes);
+        version = step.to;
+    }
+    if let Some(v) = config.get_mut("version") {
+        *v = Value::String(VERSION.into());
+    }
+
+    Ok(Some(Upgrade {
+        from_version,
+        changes,
+        before,
+        after: serde_yml::to_string(&config)?,
+    }))
+}
+
+/// Call `f` on all values at `path`, with a human-readable description of their location. The
+/// path consists of field names, and `"*"` stands for all elements of a sequence. YAML tags are
+/// looked through.
+fn for_each_at_path(
+    value: &mut Value,
+    path: &[&str],
+    location: &str,
+    f: &mut impl FnMut(&mut Value, &str),
+) {
+    let Some((first, rest)) = path.split_first() else {
+        f(value, location);
+        return;
+    };
+    match value {
+        Value::Tagged(tagged) => for_each_at_path(&mut tagged.value, path, location, f),
+        Value::Sequence(elems) if *first == "*" => {
+            for (i, x) in elems.iter_mut().enumerate() {
+                for_each_at_path(x, rest, &format!("{location}[{i}]"), f);
+            }
+        }
+        Value::Mapping(m) => {
+            if let Some(x) = m.get_mut(*first) {
+                let location = if location.is_empty() {
+                    first.to_string()
+                } else {
+                    format!("{location}.{first}")
+                };
+                for_each_at_path(x, rest, &location, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Rename the field `old` to `new` in all mappings at `path` (see [for_each_at_path]). The order
+/// of fields is kept.
+pub fn rename_field(
+    config: &mut Value,
+    path: &[&str],
+    old: &str,
+    new: &str,
+    changes: &mut Vec<String>,
+) {
+    for_each_at_path(config, path, "", &mut |value, location| {
+        let mut value = &mut *value;
+        while let Value::Tagged(tagged) = value {
+            value = &mut tagged.value;
+        }
+        let Value::Mapping(m) = value else {
+            return;
+        };
+        if !m.contains_key(old) {
+            return;
+        }
+        *m = std::mem::take(m)
+            .into_iter()
+            .map(|(k, v)| {
+                if k.as_str() == Some(old) {
+                    (Value::String(new.into()), v)
+                } else {
+                    (k, v)
+                }
+            })
+            .collect::<Mapping>();
+        changes.push(format!("renamed '{old}' to '{new}' in '{location}'"));
+    });
+}
+
+/// Replace the YAML tag `!old` by `!new` on all values at `path` (see [for_each_at_path]).
+pub fn rename_tag(
+    config: &mut Value,
+    path: &[&str],
+    old: &str,
+    new: &str,
+    changes: &mut Vec<String>,
+) {
+    for_each_at_path(config, path, "", &mut |value, location| {
+        if let Value::Tagged(tagged) = value {
+            if tagged.tag == old {
+                tagged.tag = Tag::new(new);
+                changes.push(format!("replaced '!{old}' by '!{new}' in '{location}'"));
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_upgrade() {
+        let current = format!("version: {VERSION}\nfoo: 1\n");
+        assert!(upgrade(&current).unwrap().is_none());
+
+        let upgrade = upgrade("version: 0.2.0\nfoo: 1\n").unwrap().unwrap();
+        assert_eq!(upgrade.from_version, "0.2.0");
+        assert_eq!(upgrade.before, "version: '0.2.0'\nfoo: 1\n");
+        assert_eq!(
+            serde_yml::from_str::<Value>(&upgrade.after).unwrap(),
+            serde_yml::from_str::<Value>(&current).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_upgrade_errors() {
+        let message = |s| upgrade(s).err().unwrap().to_string();
+        assert_eq!(
+            message("foo: 1"),
+            "the configuration file has no 'version' field"
+        );
+        assert_eq!(
+            message("version: 0.1.0"),
+            format!(
+                "configuration files for adaptuner version 0.1.0 can't be upgraded to version \
+                {VERSION}"
+            )
+        );
+        assert_eq!(
+            message("version: 1000.0.0"),
+            format!(
+                "the configuration file is for adaptuner version 1000.0.0, which is newer than \
+                this version ({VERSION})"
+            )
+        );
+    }
+
+    #[test]
+    fn test_rename() {
+        let mut config: Value = serde_yml::from_str(
+            r#"
+strategies:
+- config: !static-tuning
+    neighbourhoods: []
+    old-name: 1
+    other: 2
+- config: !two-step
+    old-name: 3
+"#,
+        )
+        .unwrap();
+        let mut changes = vec![];
+
+        rename_field(
+            &mut config,
+            &["strategies", "*", "config"],
+            "old-name",
+            "new-name",
+            &mut changes,
+        );
+        rename_tag(
+            &mut config,
+            &["strategies", "*", "config"],
+            "static-tuning",
+            "static",
+            &mut changes,
+        );
+
+        assert_eq!(
+            config,
+            serde_yml::from_str::<Value>(
+                r#"
+strategies:
+- config: !static
+    neighbourhoods: []
+    new-name: 1
+    other: 2
+- config: !two-step
+    new-name: 3
+"#,
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            changes,
+            vec![
+                "renamed 'old-name' to 'new-name' in 'strategies[0].config'",
+                "renamed 'old-name' to 'new-name' in 'strategies[1].config'",
+                "replaced '!static-tuning' by '!static' in 'strategies[0].config'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rename_nothing_to_do() {
+        let yaml = r#"
+version: 0.2.0
+gui:
+  lattice-window:
+    a: 1
+    old-name: 2
+    b: 3
+  tuning-editor: !tagged
+    old-name: 4
+strategies:
+- 1
+- !two-step 2
+"#;
+        let mut config: Value = serde_yml::from_str(yaml).unwrap();
+        let mut changes = vec![];
+
+        // paths that don't exist, and values that aren't mappings or have other tags
+        rename_field(
+            &mut config,
+            &["gui", "nowhere"],
+            "old-name",
+            "x",
+            &mut changes,
+        );
+        rename_field(
+            &mut config,
+            &["strategies", "*"],
+            "old-name",
+            "x",
+            &mut changes,
+        );
+        rename_tag(
+            &mut config,
+            &["strategies", "*"],
+            "static-tuning",
+            "x",
+            &mut changes,
+        );
+        rename_tag(
+            &mut config,
+            &["gui", "nowhere"],
+            "tagged",
+            "x",
+            &mut changes,
+        );
+        assert_eq!(config, serde_yml::from_str::<Value>(yaml).unwrap());
+        assert_eq!(changes, Vec::<String>::new());
+
+        // the renamed field keeps its position, also in tagged mappings
+        rename_field(&mut config, &["gui", "*"], "old-name", "x", &mut changes);
+        assert_eq!(changes, Vec::<String>::new());
+        for window in ["lattice-window", "tuning-editor"] {
+            rename_field(
+                &mut config,
+                &["gui", window],
+                "old-name",
+                "new-name",
+                &mut changes,
+            );
+        }
+        rename_tag(
+            &mut config,
+            &["gui", "tuning-editor"],
+            "tagged",
+            "retagged",
+            &mut changes,
+        );
+        assert_eq!(
+            serde_yml::to_string(&config["gui"]).unwrap(),
+            "lattice-window:\n  a: 1\n  new-name: 2\n  b: 3\ntuning-editor: !retagged\n  \
+            new-name: 4\n"
+        );
+        assert_eq!(
+            changes,
+            vec![
+                "renamed 'old-name' to 'new-name' in 'gui.lattice-window'",
+                "renamed 'old-name' to 'new-name' in 'gui.tuning-editor'",
+                "replaced '!tagged' by '!retagged' in 'gui.tuning-editor'",
+            ]
+        );
+    }
+}
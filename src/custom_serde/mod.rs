@@ -1,4 +1,6 @@
+pub mod action_names;
 pub mod common;
+pub mod migration;
 pub mod named_interval;
 pub mod neighbourhood;
 pub mod stack;
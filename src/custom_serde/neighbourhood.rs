@@ -2,8 +2,7 @@ use std::collections::BTreeMap;
 
 use serde::ser::SerializeMap;
 use serde::Deserializer;
-use serde_derive::Deserialize;
-use serde_with::{serde_as, DeserializeAs, MapPreventDuplicates};
+use serde_with::{DeserializeAs, MapPreventDuplicates, Same};
 
 use crate::interval::stacktype::r#trait::StackCoeff;
 use crate::neighbourhood::{Partial, PeriodicPartial};
@@ -42,38 +41,64 @@ impl<T: IntervalBasis + serde::Serialize> serde::Serialize for Partial<T> {
 }
 
 /// needed for the DeserializeAs magic. Otherwise, serde_as will try to deserialize map keys as
-/// Strings...
-struct AStackCoeff<'de>(&'de str);
+/// Strings... Keys that come from a YAML [Value][serde_yml::Value] are already integers, so
+/// both forms are accepted.
+struct AStackCoeff(StackCoeff);
 
-impl<'de> serde::Deserialize<'de> for AStackCoeff<'de> {
+impl<'de> serde::Deserialize<'de> for AStackCoeff {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let i = <_ as serde::Deserialize<'de>>::deserialize(deserializer)?;
-        Ok(AStackCoeff(i))
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = AStackCoeff;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("an integer")
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                StackCoeff::try_from(v)
+                    .map(AStackCoeff)
+                    .map_err(serde::de::Error::custom)
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                StackCoeff::try_from(v)
+                    .map(AStackCoeff)
+                    .map_err(serde::de::Error::custom)
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map(AStackCoeff).map_err(serde::de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
     }
 }
 
-impl<'de> DeserializeAs<'de, StackCoeff> for AStackCoeff<'de> {
+impl<'de> DeserializeAs<'de, StackCoeff> for AStackCoeff {
     fn deserialize_as<D>(deserializer: D) -> Result<StackCoeff, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let AStackCoeff(str) = <AStackCoeff as serde::Deserialize<'de>>::deserialize(deserializer)?;
-        match str.parse() {
-            Ok(i) => Ok(i),
-            Err(e) => Err(serde::de::Error::custom(e)),
-        }
+        let AStackCoeff(i) = <AStackCoeff as serde::Deserialize<'de>>::deserialize(deserializer)?;
+        Ok(i)
     }
 }
 
-#[serde_as]
-#[derive(Deserialize)]
-struct NoDuplicates<T: IntervalBasis> {
-    #[serde(flatten)]
-    #[serde_as(as = "MapPreventDuplicates<AStackCoeff, _>")]
-    stacks: BTreeMap<StackCoeff, Stack<T>>,
+/// The entries of a neighbourhood, as a map from key offsets to stacks, without duplicate keys.
+fn deserialize_entries<'de, D, T>(
+    deserializer: D,
+) -> Result<BTreeMap<StackCoeff, Stack<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: IntervalBasis + serde::Deserialize<'de>,
+{
+    MapPreventDuplicates::<AStackCoeff, Same>::deserialize_as(deserializer)
 }
 
 impl<'de, T: IntervalBasis + serde::Deserialize<'de>> serde::Deserialize<'de>
@@ -81,7 +106,7 @@ impl<'de, T: IntervalBasis + serde::Deserialize<'de>> serde::Deserialize<'de>
 {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         if let Some(n) = T::try_period_keys() {
-            let NoDuplicates { stacks: mut map } = <_>::deserialize(deserializer)?;
+            let mut map = deserialize_entries(deserializer)?;
 
             if map.len() != n as usize {
                 return Err(serde::de::Error::custom(format!(
@@ -106,9 +131,7 @@ impl<'de, T: IntervalBasis + serde::Deserialize<'de>> serde::Deserialize<'de>
                 }
             }
 
-            if let Some((offset, stack)) = map
-                .iter()
-                .find(|(i, stack)| stack.key_distance() != **i)
+            if let Some((offset, stack)) = map.iter().find(|(i, stack)| stack.key_distance() != **i)
             {
                 return Err(serde::de::Error::custom(format!(
                     "the stack for entry {offset} describes an interval spanning {} keys",
@@ -141,7 +164,7 @@ impl<'de, T: IntervalBasis + serde::Deserialize<'de>> serde::Deserialize<'de>
 {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         if let Some(n) = T::try_period_keys() {
-            let NoDuplicates { mut stacks } = <_>::deserialize(deserializer)?;
+            let mut stacks = deserialize_entries(deserializer)?;
 
             if stacks.len() > n as usize {
                 return Err(serde::de::Error::custom(format!(
@@ -166,9 +189,8 @@ impl<'de, T: IntervalBasis + serde::Deserialize<'de>> serde::Deserialize<'de>
                 }
             }
 
-            if let Some((offset, stack)) = stacks
-                .iter()
-                .find(|(i, stack)| stack.key_distance() != **i)
+            if let Some((offset, stack)) =
+                stacks.iter().find(|(i, stack)| stack.key_distance() != **i)
             {
                 return Err(serde::de::Error::custom(format!(
                     "the stack for entry {offset} describes an interval spanning {} keys",
@@ -198,11 +220,9 @@ impl<'de, T: IntervalBasis + serde::Deserialize<'de>> serde::Deserialize<'de>
 
 impl<'de, T: IntervalBasis + serde::Deserialize<'de>> serde::Deserialize<'de> for Partial<T> {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        let NoDuplicates { stacks } = <_>::deserialize(deserializer)?;
+        let stacks = deserialize_entries(deserializer)?;
 
-        if let Some((offset, stack)) = stacks
-            .iter()
-            .find(|(i, stack)| stack.key_distance() != **i)
+        if let Some((offset, stack)) = stacks.iter().find(|(i, stack)| stack.key_distance() != **i)
         {
             return Err(serde::de::Error::custom(format!(
                 "the stack for entry {offset} describes an interval spanning {} keys",
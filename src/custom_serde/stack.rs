@@ -1,19 +1,73 @@
-use std::{fmt, marker::PhantomData};
+use std::{cell::Cell, fmt, marker::PhantomData};
 
 use ndarray::Array1;
 use num_rational::Ratio;
 use serde::{de::Visitor, ser::SerializeStruct, Serializer};
 use serde_derive::Deserialize;
 
-use crate::interval::{
-    stack::Stack,
-    stacktype::r#trait::{IntervalBasis, StackCoeff},
+use crate::{
+    interval::{
+        stack::Stack,
+        stacktype::r#trait::{IntervalBasis, StackCoeff},
+    },
+    notename::johnston::fivelimit,
 };
 
 use super::common::{NamedCoefficients, NamedCoefficientsView};
 
+thread_local! {
+    static WRITE_NOTE_NAMES: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f`. If `enable` is true, [Stack]s that are serialised meanwhile (on this thread) are
+/// written as note names like `Bb-3`, if they can be: They may not have temperaments, and may only
+/// contain octaves, fifths, and major thirds.
+pub fn with_note_names<R>(enable: bool, f: impl FnOnce() -> R) -> R {
+    let old = WRITE_NOTE_NAMES.replace(enable);
+    let res = f();
+    WRITE_NOTE_NAMES.set(old);
+    res
+}
+
+/// The positions of the octave, the fifth, and the major third among the intervals of `T`, if
+/// it has all three. Only stacks of these can be written as note names.
+fn five_limit_positions<T: IntervalBasis>() -> Option<[usize; 3]> {
+    let position = |ratio: Ratio<StackCoeff>| T::intervals().iter().position(|i| i.ratio == ratio);
+    Some([
+        position(Ratio::from_integer(2))?,
+        position(Ratio::new(3, 2))?,
+        position(Ratio::new(5, 4))?,
+    ])
+}
+
+fn note_name<T: IntervalBasis>(stack: &Stack<T>) -> Option<String> {
+    if !stack.is_target() {
+        return None {};
+    }
+    let positions = five_limit_positions::<T>()?;
+    let only_five_limit = stack
+        .target
+        .iter()
+        .enumerate()
+        .all(|(i, c)| *c == 0 || positions.contains(&i));
+    if !only_five_limit {
+        return None {};
+    }
+    let [octaves, fifths, thirds] = positions.map(|i| stack.target[i]);
+    let mut res = String::new();
+    fivelimit::NoteName::new_from_values(octaves, fifths, thirds)
+        .write_ascii(&mut res)
+        .ok()?;
+    Some(res)
+}
+
 impl<T: IntervalBasis> serde::Serialize for Stack<T> {
     fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        if WRITE_NOTE_NAMES.get() {
+            if let Some(name) = note_name(self) {
+                return ser.serialize_str(&name);
+            }
+        }
         if self.is_target() {
             NamedCoefficientsView::<T, _>::new(self.target.view()).serialize(ser)
         } else {
@@ -64,7 +118,35 @@ impl<'de, T: IntervalBasis> serde::Deserialize<'de> for Stack<T> {
             type Value = Stack<T>;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                write!(formatter, "stack")
+                write!(formatter, "stack or note name")
+            }
+
+            /// Empty documents are read as the zero stack.
+            fn visit_none<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+                Ok(Stack::new_zero())
+            }
+
+            fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+                Ok(Stack::new_zero())
+            }
+
+            /// Note names are read by [fivelimit::NoteName::from_str][std::str::FromStr], and
+            /// describe the interval from middle C to the note.
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                let Some(positions) = five_limit_positions::<T>() else {
+                    return Err(E::custom(format!(
+                        "the note name '{v}' can't be used without octaves, fifths, and thirds"
+                    )));
+                };
+                let (octaves, fifths, thirds) = v
+                    .parse::<fivelimit::NoteName>()
+                    .map_err(E::custom)?
+                    .values();
+                let mut target = Array1::zeros(T::num_intervals());
+                target[positions[0]] = octaves;
+                target[positions[1]] = fifths;
+                target[positions[2]] = thirds;
+                Ok(Stack::from_target(target))
             }
 
             fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
@@ -156,7 +238,8 @@ impl<'de, T: IntervalBasis> serde::Deserialize<'de> for Stack<T> {
             }
         }
 
-        deserializer.deserialize_struct("stack", &["target", "actual"], StackVisitor::new())
+        // Not `deserialize_struct`, because stacks may also be note names.
+        deserializer.deserialize_any(StackVisitor::new())
     }
 }
 
@@ -264,6 +347,51 @@ actual:
         );
     }
 
+    #[test]
+    fn test_note_names() {
+        for (name, target) in [
+            ("C", [0, 0, 0]),
+            ("E4", [0, 0, 1]),
+            ("G3", [-1, 1, 0]),
+            ("Bb-", [2, -2, 0]),
+            ("F# 5", [2, -2, 2]),
+        ] {
+            assert_eq!(
+                serde_yml::from_str::<Stack<MockFiveLimitStackType>>(name).unwrap(),
+                Stack::from_target(target.to_vec()),
+            );
+        }
+
+        let stack = Stack::<MockFiveLimitStackType>::from_target(vec![2, -2, 0]);
+        assert_eq!(
+            serde_yml::to_string(&stack).unwrap(),
+            "octave: 2\nfifth: -2\n"
+        );
+        assert_eq!(
+            with_note_names(true, || serde_yml::to_string(&stack)).unwrap(),
+            "Bb-4\n"
+        );
+        assert_eq!(
+            serde_yml::to_string(&stack).unwrap(),
+            "octave: 2\nfifth: -2\n"
+        );
+
+        let tempered = Stack::<MockFiveLimitStackType>::from_target_and_actual(
+            arr1(&[1, 0, 0]),
+            arr1(&[Ratio::new(1, 2), 0.into(), 0.into()]),
+        );
+        assert_eq!(
+            with_note_names(true, || serde_yml::to_string(&tempered)).unwrap(),
+            serde_yml::to_string(&tempered).unwrap(),
+        );
+
+        assert!(format!(
+            "{:?}",
+            serde_yml::from_str::<Stack<MockFiveLimitStackType>>("H4")
+        )
+        .contains("'H4' doesn't start with one of the letters A to G"));
+    }
+
     #[test]
     fn test_deserialize_stack_errors() {
         let test_error_contains = |input, contained| {
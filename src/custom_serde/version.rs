@@ -1,6 +1,6 @@
 use crate::config::AdaptunerVersion;
 
-const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub(crate) const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 impl serde::Serialize for AdaptunerVersion {
     fn serialize<S: serde::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
@@ -10,7 +10,7 @@ impl serde::Serialize for AdaptunerVersion {
 
 impl<'de> serde::Deserialize<'de> for AdaptunerVersion {
     fn deserialize<D: serde::de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        let version_str = <&'de str as serde::Deserialize<'de>>::deserialize(deserializer)?;
+        let version_str = <String as serde::Deserialize<'de>>::deserialize(deserializer)?;
         if version_str == VERSION {
             Ok(AdaptunerVersion)
         } else {
@@ -4,7 +4,12 @@ use eframe::egui;
 use midi_msg::Channel;
 
 use crate::{
-    backend::pitchbend12::Pitchbend12Config,
+    backend::{
+        glide::{GlideConfig, GlideCurve},
+        mpe::{MpeConfig, MpeZone},
+        mts::MtsConfig,
+        pitchbend12::{Pitchbend12Config, WrappedChannel},
+    },
     config::{BackendConfig, ExtractConfig},
     interval::{base::Semitones, stacktype::r#trait::StackType},
     msg::FromUi,
@@ -12,49 +17,253 @@ use crate::{
 
 use super::r#trait::GuiShow;
 
+#[derive(Clone, Copy, PartialEq)]
+enum BackendKind {
+    Pitchbend12,
+    Mts,
+    Mpe,
+}
+
 pub struct BackendWindow {
+    kind: BackendKind,
+    new_kind: BackendKind,
     bend_range: Semitones,
     new_bend_range: Semitones,
     use_channels: [bool; 16],
     new_use_channels: [bool; 16],
+    glide: Option<GlideConfig>,
+    new_glide: Option<GlideConfig>,
+    mts: MtsConfig,
+    new_mts: MtsConfig,
+    mpe: MpeConfig,
+    new_mpe: MpeConfig,
+    /// The backends of the zones, if there are separate ones. They can't be edited here.
+    zoned: Option<Vec<BackendConfig>>,
 }
 
 pub type BackendWindowConfig = BackendConfig;
 
 impl BackendWindow {
     pub fn new(config: BackendWindowConfig) -> Self {
-        match config {
-            BackendConfig::Pitchbend12(config) => {
-                let mut use_channels = [false; 16];
-                for c in config.channels {
-                    use_channels[Into::<Channel>::into(c) as usize] = true;
-                }
-                Self {
-                    bend_range: config.bend_range,
-                    new_bend_range: config.bend_range,
-                    new_use_channels: use_channels.clone(),
-                    use_channels,
-                }
-            }
-        }
+        let mut res = Self {
+            kind: BackendKind::Pitchbend12,
+            new_kind: BackendKind::Pitchbend12,
+            bend_range: 2.0,
+            new_bend_range: 2.0,
+            // all channels but CH10, for GM compatibility
+            use_channels: core::array::from_fn(|i| i < 13 && i != 9),
+            new_use_channels: core::array::from_fn(|i| i < 13 && i != 9),
+            glide: None {},
+            new_glide: None {},
+            mts: MtsConfig {
+                channel: Channel::Ch1,
+                realtime: true,
+                program: None {},
+                bank: None {},
+            },
+            new_mts: MtsConfig {
+                channel: Channel::Ch1,
+                realtime: true,
+                program: None {},
+                bank: None {},
+            },
+            mpe: MpeConfig {
+                zone: MpeZone::Lower,
+                member_channels: 15,
+                bend_range: 48,
+            },
+            new_mpe: MpeConfig {
+                zone: MpeZone::Lower,
+                member_channels: 15,
+                bend_range: 48,
+            },
+            zoned: None {},
+        };
+        res.restart_from_config(config, Instant::now());
+        res
     }
 
     pub fn restart_from_config(&mut self, config: BackendWindowConfig, _time: Instant) {
+        self.zoned = None {};
         match config {
             BackendConfig::Pitchbend12(config) => {
+                self.kind = BackendKind::Pitchbend12;
                 self.bend_range = config.bend_range;
+                self.new_bend_range = config.bend_range;
                 self.use_channels.iter_mut().for_each(|b| *b = false);
                 for c in config.channels {
                     self.use_channels[Into::<Channel>::into(c) as usize] = true;
                 }
                 self.new_use_channels.clone_from(&self.use_channels);
+                self.glide = config.glide;
+                self.new_glide = config.glide;
+            }
+            BackendConfig::Mts(config) => {
+                self.kind = BackendKind::Mts;
+                self.new_mts = config.clone();
+                self.mts = config;
             }
+            BackendConfig::Mpe(config) => {
+                self.kind = BackendKind::Mpe;
+                self.new_mpe = config.clone();
+                self.mpe = config;
+            }
+            BackendConfig::Zoned(configs) => self.zoned = Some(configs),
+        }
+        self.new_kind = self.kind;
+    }
+
+    fn new_config(&self) -> BackendConfig {
+        match self.new_kind {
+            BackendKind::Pitchbend12 => BackendConfig::Pitchbend12(Pitchbend12Config {
+                bend_range: self.new_bend_range,
+                channels: channels_from_toggles(&self.new_use_channels),
+                glide: self.new_glide,
+            }),
+            BackendKind::Mts => BackendConfig::Mts(self.new_mts.clone()),
+            BackendKind::Mpe => BackendConfig::Mpe(self.new_mpe.clone()),
         }
     }
 }
 
+fn channels_from_toggles(use_channels: &[bool; 16]) -> [WrappedChannel; 12] {
+    let mut channels = [Channel::Ch1.into(); 12];
+    for (i, j) in (0..16).filter(|&j| use_channels[j]).take(12).enumerate() {
+        channels[i] = Channel::from_u8(j as u8).into();
+    }
+    channels
+}
+
 impl<T: StackType> GuiShow<T> for BackendWindow {
     fn show(&mut self, ui: &mut egui::Ui, forward: &mpsc::Sender<FromUi<T>>) {
+        if let Some(zones) = &self.zoned {
+            ui.label(format!(
+                "The {} keyboard zones have separate backends, which can only be changed in the \
+                 configuration file.",
+                zones.len()
+            ));
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(
+                &mut self.new_kind,
+                BackendKind::Pitchbend12,
+                "pitch bend on 12 channels",
+            );
+            ui.selectable_value(&mut self.new_kind, BackendKind::Mts, "MIDI Tuning Standard");
+            ui.selectable_value(&mut self.new_kind, BackendKind::Mpe, "MPE");
+        });
+        ui.separator();
+
+        if self.new_kind != self.kind {
+            ui.label("The backend will be restarted.");
+            match self.new_kind {
+                BackendKind::Pitchbend12 => {}
+                BackendKind::Mts => self.show_mts(ui),
+                BackendKind::Mpe => self.show_mpe(ui),
+            }
+            if ui.button("switch").clicked() {
+                self.switch(forward);
+            }
+            return;
+        }
+
+        match self.kind {
+            BackendKind::Pitchbend12 => self.show_pitchbend12(ui, forward),
+            BackendKind::Mts => {
+                self.show_mts(ui);
+                if ui
+                    .add(egui::Button::new("update").selected(self.new_mts != self.mts))
+                    .clicked()
+                {
+                    self.switch(forward);
+                }
+            }
+            BackendKind::Mpe => {
+                self.show_mpe(ui);
+                if ui
+                    .add(egui::Button::new("update").selected(self.new_mpe != self.mpe))
+                    .clicked()
+                {
+                    self.switch(forward);
+                }
+            }
+        }
+    }
+}
+
+impl BackendWindow {
+    fn switch<T: StackType>(&mut self, forward: &mpsc::Sender<FromUi<T>>) {
+        let config = self.new_config();
+        let _ = forward.send(FromUi::RestartBackendWithConfig {
+            config: config.clone(),
+            time: Instant::now(),
+        });
+        self.restart_from_config(config, Instant::now());
+    }
+
+    fn show_mts(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("output channel:");
+            let mut channel = self.new_mts.channel as u8 + 1;
+            if ui
+                .add(egui::DragValue::new(&mut channel).range(1..=16))
+                .changed()
+            {
+                self.new_mts.channel = Channel::from_u8(channel - 1);
+            }
+        });
+        ui.checkbox(
+            &mut self.new_mts.realtime,
+            "real-time messages (also retune sounding notes)",
+        );
+        for (label, value) in [
+            ("tuning program:", &mut self.new_mts.program),
+            ("tuning bank:", &mut self.new_mts.bank),
+        ] {
+            ui.horizontal(|ui| {
+                let mut select = value.is_some();
+                ui.checkbox(&mut select, label);
+                if !select {
+                    *value = None {};
+                } else {
+                    ui.add(egui::DragValue::new(value.get_or_insert(0)).range(0..=127));
+                }
+            });
+        }
+    }
+
+    fn show_mpe(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("zone:");
+            ui.selectable_value(
+                &mut self.new_mpe.zone,
+                MpeZone::Lower,
+                "lower (master channel 1)",
+            );
+            ui.selectable_value(
+                &mut self.new_mpe.zone,
+                MpeZone::Upper,
+                "upper (master channel 16)",
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("member channels:");
+            ui.add(egui::DragValue::new(&mut self.new_mpe.member_channels).range(1..=15));
+        });
+        ui.horizontal(|ui| {
+            ui.label("pitch bend range of member channels:");
+            ui.add(egui::DragValue::new(&mut self.new_mpe.bend_range).range(1..=96));
+            ui.label("semitones");
+        });
+    }
+
+    fn show_pitchbend12<T: StackType>(
+        &mut self,
+        ui: &mut egui::Ui,
+        forward: &mpsc::Sender<FromUi<T>>,
+    ) {
         ui.vertical(|ui| {
             ui.horizontal(|ui| {
                 ui.label("pitch bend range:");
@@ -81,13 +290,41 @@ impl<T: StackType> GuiShow<T> for BackendWindow {
                 }
             }
 
+            let mut glide = self.new_glide.is_some();
+            if ui
+                .checkbox(&mut glide, "glide when re-tuning sounding notes")
+                .clicked()
+            {
+                self.new_glide = if glide {
+                    Some(GlideConfig {
+                        duration_ms: 50,
+                        curve: GlideCurve::Linear,
+                    })
+                } else {
+                    None {}
+                };
+            }
+            if let Some(GlideConfig { duration_ms, curve }) = &mut self.new_glide {
+                ui.horizontal(|ui| {
+                    ui.label("glide duration:");
+                    ui.add(egui::DragValue::new(duration_ms).range(1..=2000));
+                    ui.label("ms");
+                });
+                ui.horizontal(|ui| {
+                    ui.radio_value(curve, GlideCurve::Linear, "linear");
+                    ui.radio_value(curve, GlideCurve::Exponential, "exponential");
+                });
+            }
+
             let bend_range_changed = self.new_bend_range != self.bend_range;
             let use_channels_changed = self.new_use_channels != self.use_channels;
+            let glide_changed = self.new_glide != self.glide;
 
             if ui
                 .add_enabled(
                     n_enabled == 12,
-                    egui::Button::new("update").selected(bend_range_changed | use_channels_changed),
+                    egui::Button::new("update")
+                        .selected(bend_range_changed | use_channels_changed | glide_changed),
                 )
                 .clicked()
             {
@@ -105,6 +342,13 @@ impl<T: StackType> GuiShow<T> for BackendWindow {
                         time: Instant::now(),
                     });
                 }
+                if glide_changed {
+                    self.glide = self.new_glide;
+                    let _ = forward.send(FromUi::Glide {
+                        glide: self.glide,
+                        time: Instant::now(),
+                    });
+                }
             }
         });
     }
@@ -112,19 +356,17 @@ impl<T: StackType> GuiShow<T> for BackendWindow {
 
 impl ExtractConfig<BackendWindowConfig> for BackendWindow {
     fn extract_config(&self) -> BackendWindowConfig {
-        BackendWindowConfig::Pitchbend12(Pitchbend12Config {
-            bend_range: self.bend_range,
-            channels: {
-                let mut channels = [Channel::Ch1.into(); 12];
-                let mut i = 0;
-                for j in 0..16 {
-                    if self.use_channels[j] {
-                        channels[i] = Channel::from_u8(j as u8).into();
-                        i += 1;
-                    }
-                }
-                channels
-            },
-        })
+        if let Some(zones) = &self.zoned {
+            return BackendWindowConfig::Zoned(zones.clone());
+        }
+        match self.kind {
+            BackendKind::Pitchbend12 => BackendWindowConfig::Pitchbend12(Pitchbend12Config {
+                bend_range: self.bend_range,
+                channels: channels_from_toggles(&self.use_channels),
+                glide: self.glide,
+            }),
+            BackendKind::Mts => BackendWindowConfig::Mts(self.mts.clone()),
+            BackendKind::Mpe => BackendWindowConfig::Mpe(self.mpe.clone()),
+        }
     }
 }
@@ -9,7 +9,7 @@ use crate::{
         stack::Stack,
         stacktype::r#trait::{IntervalBasis, StackCoeff, StackType},
     },
-    notename::correction::Correction,
+    notename::{correction::Correction, NoteNameStyle},
     util::list_action::ListAction,
 };
 
@@ -422,9 +422,13 @@ pub fn show_hide_button(
     clicked
 }
 
+/// How notes are written in the lattice, the chord list editor, and the notifications.
 pub struct CorrectionSystemChooser<T: IntervalBasis> {
     _phantom: PhantomData<T>,
     pub use_cent_values: bool,
+    /// The [LatticeWindowConfig::notenamestyle][crate::gui::lattice::LatticeWindowConfig].
+    /// Other windows only use its notation, and decide themselves whether to write octaves.
+    pub notenamestyle: NoteNameStyle,
     preference_order: OwningListEdit<usize>,
     id_salt: &'static str,
 }
@@ -436,10 +440,11 @@ impl<T: IntervalBasis> ExtractConfig<bool> for CorrectionSystemChooser<T> {
 }
 
 impl<T: StackType> CorrectionSystemChooser<T> {
-    pub fn new(id_salt: &'static str, use_cent_values: bool) -> Self {
+    pub fn new(id_salt: &'static str, use_cent_values: bool, notenamestyle: NoteNameStyle) -> Self {
         Self {
             _phantom: PhantomData,
             use_cent_values,
+            notenamestyle,
             preference_order: {
                 let mut v = Vec::with_capacity(T::num_named_intervals());
                 (0..T::num_named_intervals()).for_each(|i| v.push(i));
@@ -6,8 +6,9 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     config::{BackendConfig, Config, GuiConfig, ProcessConfig},
+    custom_serde::{migration::Upgrade, stack::with_note_names},
     gui::diffshow::DiffShow,
-    interval::stacktype::r#trait::{IntervalBasis, StackType},
+    interval::stacktype::r#trait::{IntervalBasis, Reloadable, StackType},
 };
 
 enum Phase {
@@ -16,14 +17,19 @@ enum Phase {
     Closed,
 }
 
+type ConfigInFile<T> = Result<(Config<T>, Option<Upgrade>), serde_yml::Error>;
+
 pub struct ConfigFileDialog<T: IntervalBasis> {
     phase: Phase,
     as_load: bool,
     file_dialog: FileDialog,
-    considered: Option<(DirectoryEntry, Result<Config<T>, serde_yml::Error>)>,
+    considered: Option<(DirectoryEntry, ConfigInFile<T>)>,
     considered_time: SystemTime,
     diffshow: DiffShow,
+    upgrade_diffshow: DiffShow,
     error: Option<String>,
+    /// Write stacks as note names when saving, see [with_note_names].
+    note_names: bool,
 }
 
 impl<T: StackType + Serialize> ConfigFileDialog<T> {
@@ -51,7 +57,9 @@ impl<T: StackType + Serialize> ConfigFileDialog<T> {
             considered_time: SystemTime::now(),
             // current_config: UpdateCell::new(ConfigByParts::empty()),
             diffshow: DiffShow::new(),
+            upgrade_diffshow: DiffShow::new(),
             error: None {},
+            note_names: false,
         }
     }
 
@@ -88,7 +96,7 @@ fn show_waiting(ui: &mut egui::Ui) {
         .show(ui.ctx(), |ui| ui.spinner());
 }
 
-impl<T: StackType + Serialize + for<'a> Deserialize<'a>> ConfigFileDialog<T> {
+impl<T: Reloadable + Serialize + for<'a> Deserialize<'a>> ConfigFileDialog<T> {
     fn show_config_file_dialog(
         &mut self,
         ui: &mut egui::Ui,
@@ -100,6 +108,14 @@ impl<T: StackType + Serialize + for<'a> Deserialize<'a>> ConfigFileDialog<T> {
             ui.ctx(),
             &mut |ui: &mut egui::Ui, file_dialog| {
                 ui.set_min_width(200.0);
+                if !self.as_load {
+                    ui.checkbox(&mut self.note_names, "write notes as names")
+                        .on_hover_text(
+                            "Write notes like \"Bb-3\" instead of counting octaves, fifths, and \
+                            thirds. Tempered notes are always written as numbers.",
+                        );
+                    ui.separator();
+                }
                 if let Some(selected_entry) = file_dialog.selected_entry() {
                     if !selected_entry.is_file() {
                         self.considered = None {};
@@ -122,16 +138,25 @@ impl<T: StackType + Serialize + for<'a> Deserialize<'a>> ConfigFileDialog<T> {
                     if update {
                         self.considered = None {};
                         self.considered_time = SystemTime::now();
-                        if let Ok(file) = std::fs::File::open(selected_entry.as_path()) {
+                        if let Ok(contents) = std::fs::read_to_string(selected_entry.as_path()) {
                             let config_or_err_in_file =
-                                serde_yml::from_reader::<_, Config<T>>(file);
-                            if let Ok(config_in_file) = &config_or_err_in_file {
+                                Config::<T>::from_yaml_str_upgrading(&contents);
+                            if let Ok((config_in_file, upgrade)) = &config_or_err_in_file {
+                                if let Some(upgrade) = upgrade {
+                                    self.upgrade_diffshow.update(
+                                        &upgrade.before,
+                                        &upgrade.after,
+                                        ui,
+                                    );
+                                }
                                 self.diffshow.update(
                                     &serde_yml::to_string(config_in_file).unwrap(),
                                     &serde_yml::to_string(&Config::join(
                                         process_config.clone(),
                                         backend_config.clone(),
                                         gui_config.clone(),
+                                        T::interval_definitions(),
+                                        T::interval_basis_name(),
                                         T::temperament_definitions().clone(),
                                         T::named_intervals().clone(),
                                     ))
@@ -162,6 +187,25 @@ impl<T: StackType + Serialize + for<'a> Deserialize<'a>> ConfigFileDialog<T> {
                                 ui.label(format!("line {line}\ncolumn {column}\n\n{e}",));
                             });
                         } else {
+                            if let Ok((_, Some(upgrade))) = file_config {
+                                ui.label(format!(
+                                    "The file '{file_name}' is for adaptuner version {}. It will \
+                                    be upgraded to the current version.",
+                                    upgrade.from_version
+                                ));
+                                for change in &upgrade.changes {
+                                    ui.label(format!("• {change}"));
+                                }
+                                egui::CollapsingHeader::new("show upgrade").show(ui, |ui| {
+                                    self.upgrade_diffshow.show(
+                                        "before upgrade",
+                                        "after upgrade",
+                                        "The upgrade only changes the version.",
+                                        ui,
+                                    );
+                                });
+                                ui.separator();
+                            }
                             self.diffshow.show(
                                 &format!("in '{file_name}'"),
                                 "in current configuration",
@@ -181,7 +225,7 @@ impl<T: StackType + Serialize + for<'a> Deserialize<'a>> ConfigFileDialog<T> {
 
         if self.as_load {
             if let Some(path) = self.file_dialog.take_picked() {
-                if let Some((_, Ok(config))) = self.considered.take() {
+                if let Some((_, Ok((config, _)))) = self.considered.take() {
                     self.phase = Phase::Closed;
                     return Some(config);
                 } else {
@@ -198,13 +242,18 @@ impl<T: StackType + Serialize + for<'a> Deserialize<'a>> ConfigFileDialog<T> {
                     process_config.clone(),
                     backend_config.clone(),
                     gui_config.clone(),
+                    T::interval_definitions(),
+                    T::interval_basis_name(),
                     T::temperament_definitions().clone(),
                     T::named_intervals().clone(),
                 );
 
                 let res: Result<(), String> = {
                     match std::fs::File::create(path) {
-                        Ok(file) => serde_yml::to_writer(file, &config).map_err(|e| format!("{e}")),
+                        Ok(file) => {
+                            with_note_names(self.note_names, || serde_yml::to_writer(file, &config))
+                                .map_err(|e| format!("{e}"))
+                        }
                         Err(e) => Err(format!("{e}")),
                     }
                 };
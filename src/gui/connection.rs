@@ -1,6 +1,7 @@
 use std::{sync::mpsc, time::Instant};
 
 use eframe::egui;
+use midi_msg::Channel;
 use midir::{MidiInputPort, MidiOutputPort};
 
 use crate::{
@@ -14,18 +15,35 @@ pub struct Output {}
 
 pub trait IO {
     type Port;
+    /// What can be set for every connected port.
+    type Settings;
     fn direction_string() -> &'static str;
+    fn default_settings() -> Self::Settings;
+    fn list_ports_msg<T: StackType>() -> FromUi<T>;
     fn connect_msg<T: StackType>(port: Self::Port, portname: String) -> FromUi<T>;
-    fn disconnect_msg<T: StackType>() -> FromUi<T>;
+    fn disconnect_msg<T: StackType>(portname: String) -> FromUi<T>;
+    fn settings_msg<T: StackType>(portname: String, settings: &Self::Settings) -> FromUi<T>;
+    /// Returns true iff the settings were changed.
+    fn show_settings(portname: &str, settings: &mut Self::Settings, ui: &mut egui::Ui) -> bool;
 }
 
 impl IO for Input {
     type Port = MidiInputPort;
+    /// The channel all channel messages from the port are moved to.
+    type Settings = Option<Channel>;
 
     fn direction_string() -> &'static str {
         "input"
     }
 
+    fn default_settings() -> Self::Settings {
+        None {}
+    }
+
+    fn list_ports_msg<T: StackType>() -> FromUi<T> {
+        FromUi::ListInputPorts
+    }
+
     fn connect_msg<T: StackType>(port: Self::Port, portname: String) -> FromUi<T> {
         FromUi::ConnectInput {
             port,
@@ -34,18 +52,53 @@ impl IO for Input {
         }
     }
 
-    fn disconnect_msg<T: StackType>() -> FromUi<T> {
-        FromUi::DisconnectInput
+    fn disconnect_msg<T: StackType>(portname: String) -> FromUi<T> {
+        FromUi::DisconnectInput { portname }
+    }
+
+    fn settings_msg<T: StackType>(portname: String, settings: &Self::Settings) -> FromUi<T> {
+        FromUi::RemapInput {
+            portname,
+            channel: *settings,
+        }
+    }
+
+    fn show_settings(portname: &str, settings: &mut Self::Settings, ui: &mut egui::Ui) -> bool {
+        let show = |channel: &Option<Channel>| match channel {
+            Some(c) => format!("to channel {}", *c as u8 + 1),
+            None {} => "channels unchanged".into(),
+        };
+        let old = *settings;
+        egui::ComboBox::from_id_salt(format!("remap input {portname}"))
+            .selected_text(show(settings))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(settings, None {}, show(&None {}));
+                for i in 0..16 {
+                    let c = Some(Channel::from_u8(i));
+                    ui.selectable_value(settings, c, show(&c));
+                }
+            });
+        *settings != old
     }
 }
 
 impl IO for Output {
     type Port = MidiOutputPort;
+    /// The channels that are routed to the port.
+    type Settings = [bool; 16];
 
     fn direction_string() -> &'static str {
         "output"
     }
 
+    fn default_settings() -> Self::Settings {
+        [true; 16]
+    }
+
+    fn list_ports_msg<T: StackType>() -> FromUi<T> {
+        FromUi::ListOutputPorts
+    }
+
     fn connect_msg<T: StackType>(port: Self::Port, portname: String) -> FromUi<T> {
         FromUi::ConnectOutput {
             port,
@@ -54,30 +107,56 @@ impl IO for Output {
         }
     }
 
-    fn disconnect_msg<T: StackType>() -> FromUi<T> {
-        FromUi::DisconnectOutput
+    fn disconnect_msg<T: StackType>(portname: String) -> FromUi<T> {
+        FromUi::DisconnectOutput { portname }
+    }
+
+    fn settings_msg<T: StackType>(portname: String, settings: &Self::Settings) -> FromUi<T> {
+        FromUi::RouteOutput {
+            portname,
+            channels: *settings,
+        }
+    }
+
+    fn show_settings(_portname: &str, settings: &mut Self::Settings, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        ui.label("channels:");
+        for (i, routed) in settings.iter_mut().enumerate() {
+            changed |= ui.toggle_value(routed, format!("{}", i + 1)).changed();
+        }
+        changed
     }
 }
 
-pub enum ConnectionWindow<X: IO> {
-    Connected {
-        portname: String,
-    },
-    Unconnected {
-        error: Option<String>,
-        available_ports: Vec<(X::Port, String)>,
-    },
+/// The connected ports with their settings, and a selector to connect more.
+pub struct ConnectionWindow<X: IO> {
+    connected: Vec<(String, X::Settings)>,
+    error: Option<String>,
+    available_ports: Vec<(X::Port, String)>,
 }
 
 impl<X: IO> ConnectionWindow<X> {
     pub fn new() -> Self {
-        Self::Unconnected {
+        Self {
+            connected: vec![],
             error: None {},
             available_ports: vec![],
         }
     }
+
+    fn receive_connected(&mut self, portname: &str) {
+        self.error = None {};
+        self.available_ports.retain(|(_, name)| name != portname);
+        self.connected
+            .push((portname.to_string(), X::default_settings()));
+    }
+
+    fn receive_disconnected(&mut self, portname: &str) {
+        self.connected.retain(|(name, _)| name != portname);
+    }
 }
 
+/// Clicking the selector asks for the list of available ports again.
 pub fn port_selector<X, T: StackType>(
     available_ports: &[(X::Port, String)],
     ui: &mut egui::Ui,
@@ -90,7 +169,7 @@ where
     let mut selected_port = None {};
     if egui::ComboBox::from_id_salt(format!("select {}", X::direction_string()))
         .selected_text(egui::RichText::new(format!(
-            "select {}",
+            "connect {}",
             X::direction_string()
         )))
         .show_ui(ui, |ui| {
@@ -105,23 +184,12 @@ where
         .response
         .clicked()
     {
-        let _ = forward.send(X::disconnect_msg());
+        let _ = forward.send(X::list_ports_msg());
     }
 
     selected_port
 }
 
-pub fn disconnector<X: IO>(portname: &str, ui: &mut egui::Ui) -> bool {
-    let mut disconnect = false;
-    ui.with_layout(egui::Layout::left_to_right(egui::Align::Min), |ui| {
-        ui.label(format!("{} is \"{}\"", X::direction_string(), portname));
-        if ui.button("disconnect").clicked() {
-            disconnect = true;
-        }
-    });
-    disconnect
-}
-
 impl<X, T> GuiShow<T> for ConnectionWindow<X>
 where
     T: StackType,
@@ -129,31 +197,33 @@ where
     <X as IO>::Port: PartialEq + Clone,
 {
     fn show(&mut self, ui: &mut egui::Ui, forward: &mpsc::Sender<FromUi<T>>) {
-        match self {
-            ConnectionWindow::Connected { portname } => {
-                if disconnector::<X>(&portname, ui) {
-                    let _ = forward.send(X::disconnect_msg());
+        if self.connected.is_empty() {
+            ui.label(format!("no {} is connected", X::direction_string()));
+        }
+        for (portname, settings) in &mut self.connected {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} \"{}\"", X::direction_string(), portname));
+                if X::show_settings(portname, settings, ui) {
+                    let _ = forward.send(X::settings_msg(portname.clone(), settings));
                 }
-            }
-            ConnectionWindow::Unconnected {
-                error,
-                available_ports,
-            } => {
-                if let Some(str) = error {
-                    ui.label(
-                        egui::RichText::new(format!(
-                            "{} connection error:\n{str}",
-                            X::direction_string()
-                        ))
-                        .color(ui.style().visuals.warn_fg_color),
-                    );
+                if ui.button("disconnect").clicked() {
+                    let _ = forward.send(X::disconnect_msg(portname.clone()));
                 }
+            });
+        }
 
-                if let Some((port, portname)) = port_selector::<X, T>(&available_ports, ui, forward)
-                {
-                    let _ = forward.send(X::connect_msg(port, portname));
-                }
-            }
+        if let Some(str) = &self.error {
+            ui.label(
+                egui::RichText::new(format!(
+                    "{} connection error:\n{str}",
+                    X::direction_string()
+                ))
+                .color(ui.style().visuals.warn_fg_color),
+            );
+        }
+
+        if let Some((port, portname)) = port_selector::<X, T>(&self.available_ports, ui, forward) {
+            let _ = forward.send(X::connect_msg(port, portname));
         }
     }
 }
@@ -161,24 +231,15 @@ where
 impl<T: StackType> ReceiveMsgRef<ToUi<T>> for ConnectionWindow<Input> {
     fn receive_msg_ref(&mut self, msg: &ToUi<T>) {
         match msg {
-            ToUi::InputConnectionError { reason } => match self {
-                ConnectionWindow::Unconnected { error, .. } => *error = Some(reason.clone()),
-                ConnectionWindow::Connected { .. } => unreachable!(),
-            },
-            ToUi::InputConnected { portname } => {
-                *self = Self::Connected {
-                    portname: portname.clone(),
-                }
-            }
-            ToUi::InputDisconnected { available_ports } => {
-                let error = match self {
-                    ConnectionWindow::Connected { .. } => None {},
-                    ConnectionWindow::Unconnected { error, .. } => error.clone(),
-                };
-                *self = Self::Unconnected {
-                    error,
-                    available_ports: available_ports.clone(),
-                };
+            ToUi::InputConnectionError { reason } => self.error = Some(reason.clone()),
+            ToUi::InputConnected { portname } => self.receive_connected(portname),
+            ToUi::InputDisconnected { portname } => self.receive_disconnected(portname),
+            ToUi::InputPorts { available_ports } => self.available_ports = available_ports.clone(),
+            ToUi::InputFeedbackLoop { portname } => {
+                self.error = Some(format!(
+                    "\"{portname}\" received adaptuner's own output, so there's a MIDI feedback \
+                    loop. It was disconnected."
+                ))
             }
             _ => {}
         }
@@ -188,26 +249,10 @@ impl<T: StackType> ReceiveMsgRef<ToUi<T>> for ConnectionWindow<Input> {
 impl<T: StackType> ReceiveMsgRef<ToUi<T>> for ConnectionWindow<Output> {
     fn receive_msg_ref(&mut self, msg: &ToUi<T>) {
         match msg {
-            ToUi::OutputConnectionError { reason } => match self {
-                ConnectionWindow::Unconnected { error, .. } => *error = Some(reason.clone()),
-                ConnectionWindow::Connected { .. } => unreachable!(),
-            },
-            ToUi::OutputConnected { portname } => {
-                *self = Self::Connected {
-                    portname: portname.clone(),
-                }
-            }
-            ToUi::OutputDisconnected { available_ports } => {
-                let error = match self {
-                    ConnectionWindow::Connected { .. } => None {},
-                    ConnectionWindow::Unconnected { error, .. } => error.clone(),
-                };
-                *self = Self::Unconnected {
-                    error,
-                    available_ports: available_ports.clone(),
-                };
-            }
-
+            ToUi::OutputConnectionError { reason } => self.error = Some(reason.clone()),
+            ToUi::OutputConnected { portname } => self.receive_connected(portname),
+            ToUi::OutputDisconnected { portname } => self.receive_disconnected(portname),
+            ToUi::OutputPorts { available_ports } => self.available_ports = available_ports.clone(),
             _ => {}
         }
     }
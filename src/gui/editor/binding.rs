@@ -3,10 +3,10 @@ use std::sync::mpsc;
 use eframe::egui;
 
 use crate::{
-    bindable::{Bindable, Bindings, MidiBindable},
+    bindable::{Bindable, Bindings, Edge, MidiBindable},
     config::StrategyKind,
     interval::stacktype::r#trait::StackType,
-    msg::FromUi,
+    msg::{FromUi, ReceiveMsgRef, ToUi},
     strategy::r#trait::StrategyAction,
 };
 
@@ -16,6 +16,8 @@ pub struct BindingEditor {
     tmp_key_name_invalid: bool,
     tmp_strategy_action: Option<StrategyAction>,
     changed_binding: Option<(Bindable, Option<StrategyAction>)>,
+    /// Are we waiting for the next incoming MIDI event to become `tmp_bindable`?
+    learning: bool,
 }
 
 impl BindingEditor {
@@ -26,6 +28,7 @@ impl BindingEditor {
             tmp_key_name_invalid: true,
             tmp_strategy_action: None {},
             changed_binding: None {},
+            learning: false,
         }
     }
 
@@ -76,6 +79,18 @@ impl BindingEditor {
                     &mut self.tmp_key_name,
                     &mut self.tmp_key_name_invalid,
                 );
+                if ui
+                    .selectable_label(self.learning, "learn")
+                    .on_hover_text_at_pointer(
+                        "Use the next incoming note, controller, or program change.",
+                    )
+                    .clicked()
+                {
+                    self.learning = !self.learning;
+                    let _ = forward.send(FromUi::LearnBindable {
+                        learn: self.learning,
+                    });
+                }
                 self.tmp_strategy_action = bindings.get(&self.tmp_bindable).map(|x| *x);
                 if strategy_action_selector(
                     ui,
@@ -102,10 +117,86 @@ impl BindingEditor {
                     self.changed_binding = None {}
                 }
             });
+
+            midi_bindable_details(ui, &mut self.tmp_bindable);
         });
     }
 }
 
+impl<T: StackType> ReceiveMsgRef<ToUi<T>> for BindingEditor {
+    fn receive_msg_ref(&mut self, msg: &ToUi<T>) {
+        if let ToUi::LearnedBindable { bindable } = msg {
+            self.tmp_bindable = Bindable::Midi(*bindable);
+            self.learning = false;
+        }
+    }
+}
+
+/// Channel filter, thresholds and so on of the bindables that have them.
+fn midi_bindable_details(ui: &mut egui::Ui, tmp_bindable: &mut Bindable) {
+    let channel = match tmp_bindable {
+        Bindable::Midi(MidiBindable::Controller {
+            channel,
+            controller,
+            threshold,
+            edge,
+        }) => {
+            ui.horizontal(|ui| {
+                ui.label("controller");
+                ui.add(egui::DragValue::new(controller).range(0..=127));
+                egui::ComboBox::from_id_salt("edge selector")
+                    .selected_text(match edge {
+                        Edge::Rising => "rising to",
+                        Edge::Falling => "falling below",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(edge, Edge::Rising, "rising to");
+                        ui.selectable_value(edge, Edge::Falling, "falling below");
+                    });
+                ui.add(egui::DragValue::new(threshold).range(0..=127));
+            });
+            channel
+        }
+        Bindable::Midi(MidiBindable::Note {
+            channel,
+            note,
+            forward,
+        }) => {
+            ui.horizontal(|ui| {
+                ui.label("note");
+                ui.add(egui::DragValue::new(note).range(0..=127));
+                ui.checkbox(forward, "also play the note");
+            });
+            channel
+        }
+        Bindable::Midi(MidiBindable::ProgramChange { channel, program }) => {
+            ui.horizontal(|ui| {
+                ui.label("program");
+                ui.add(egui::DragValue::new(program).range(0..=127));
+            });
+            channel
+        }
+        _ => return,
+    };
+
+    ui.horizontal(|ui| {
+        let mut any = channel.is_none();
+        if ui.checkbox(&mut any, "on any channel").clicked() {
+            *channel = if any { None {} } else { Some(0) };
+        }
+        if let Some(c) = channel {
+            let mut one_based = *c + 1;
+            ui.label("on channel");
+            if ui
+                .add(egui::DragValue::new(&mut one_based).range(1..=16))
+                .changed()
+            {
+                *c = one_based - 1;
+            }
+        }
+    });
+}
+
 pub fn bindable_selector(
     ui: &mut egui::Ui,
     tmp_bindable: &mut Bindable,
@@ -135,6 +226,30 @@ pub fn bindable_selector(
                     Bindable::Midi(MidiBindable::SoftPedalUp),
                     "If this is set, the soft pedal will lose its normal function.",
                 ),
+                (
+                    Bindable::Midi(MidiBindable::Controller {
+                        channel: None {},
+                        controller: 20,
+                        threshold: 64,
+                        edge: Edge::Rising,
+                    }),
+                    "If this is set, the controller will lose its normal function.",
+                ),
+                (
+                    Bindable::Midi(MidiBindable::Note {
+                        channel: None {},
+                        note: 36,
+                        forward: false,
+                    }),
+                    "A keyswitch. Unless it is also played, the note will lose its normal function.",
+                ),
+                (
+                    Bindable::Midi(MidiBindable::ProgramChange {
+                        channel: None {},
+                        program: 0,
+                    }),
+                    "If this is set, the program change will not be sent on.",
+                ),
             ] {
                 let r = ui
                     .selectable_value(tmp_bindable, bindable, format!("{bindable}"))
@@ -242,6 +357,57 @@ pub fn strategy_action_selector(
                 });
             }
 
+            index_action_entry(
+                ui,
+                strategy_kind,
+                tmp_strategy_action,
+                &mut changed,
+                "switch to neighbourhood",
+                StrategyAction::SwitchToNeighbourhood,
+                |action| match action {
+                    StrategyAction::SwitchToNeighbourhood(i) => Some(i),
+                    _ => None,
+                },
+            );
+
+            index_action_entry(
+                ui,
+                strategy_kind,
+                tmp_strategy_action,
+                &mut changed,
+                "apply to current neighbourhood the temperament",
+                StrategyAction::ApplyTemperament,
+                |action| match action {
+                    StrategyAction::ApplyTemperament(i) => Some(i),
+                    _ => None,
+                },
+            );
+
+            if strategy_kind.action_allowed(&StrategyAction::MakeNeighbourhoodPure) {
+                let r = ui.selectable_value(
+                    tmp_strategy_action,
+                    Some(StrategyAction::MakeNeighbourhoodPure),
+                    "make current neighbourhood pure",
+                );
+                if r.clicked() {
+                    changed = r.changed();
+                    close_popup(ui);
+                }
+            }
+
+            index_action_entry(
+                ui,
+                strategy_kind,
+                tmp_strategy_action,
+                &mut changed,
+                "switch to strategy",
+                StrategyAction::SwitchToStrategy,
+                |action| match action {
+                    StrategyAction::SwitchToStrategy(i) => Some(i),
+                    _ => None,
+                },
+            );
+
             if strategy_kind.action_allowed(&StrategyAction::SetReferenceToLowest) {
                 let r = ui.selectable_value(
                     tmp_strategy_action,
@@ -313,7 +479,69 @@ pub fn strategy_action_selector(
                     close_popup(ui);
                 }
             }
+
+            if strategy_kind.action_allowed(&StrategyAction::ToggleRecording) {
+                let r = ui.selectable_value(
+                    tmp_strategy_action,
+                    Some(StrategyAction::ToggleRecording),
+                    "start or stop recording",
+                );
+                if r.clicked() {
+                    changed = r.changed();
+                    close_popup(ui);
+                }
+            }
+
+            if strategy_kind.action_allowed(&StrategyAction::ExportTuning) {
+                let r = ui.selectable_value(
+                    tmp_strategy_action,
+                    Some(StrategyAction::ExportTuning),
+                    "export current tuning",
+                );
+                if r.clicked() {
+                    changed = r.changed();
+                    close_popup(ui);
+                }
+            }
         });
 
     changed
 }
+
+/// A selector entry for an action that takes an index (of a neighbourhood, temperament, or
+/// strategy).
+fn index_action_entry(
+    ui: &mut egui::Ui,
+    strategy_kind: StrategyKind,
+    tmp_strategy_action: &mut Option<StrategyAction>,
+    changed: &mut bool,
+    label: &str,
+    make: fn(usize) -> StrategyAction,
+    index_of: fn(&mut StrategyAction) -> Option<&mut usize>,
+) {
+    if !strategy_kind.action_allowed(&make(0)) {
+        return;
+    }
+    ui.horizontal(|ui| {
+        if let Some(i) = tmp_strategy_action.as_mut().and_then(index_of) {
+            let mut b = true;
+            ui.selectable_value(&mut b, true, label);
+            let r = ui.add(egui::DragValue::new(i));
+            if r.changed() {
+                *changed = true;
+            }
+            if r.lost_focus() || r.drag_stopped() {
+                ui.memory_mut(|m| m.close_popup());
+            }
+        } else {
+            if ui
+                .selectable_value(tmp_strategy_action, Some(make(0)), label)
+                .changed()
+            {
+                *changed = true;
+            }
+            let mut i = 0;
+            ui.add_enabled(false, egui::DragValue::new(&mut i));
+        }
+    });
+}
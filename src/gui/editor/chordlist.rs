@@ -14,7 +14,7 @@ use crate::{
     },
     msg::{FromUi, ReceiveMsgRef, ToUi},
     neighbourhood::{Neighbourhood, SomeNeighbourhood},
-    notename::{HasNoteNames, NoteNameStyle},
+    notename::HasNoteNames,
     strategy::twostep::harmony::chordlist::{keyshape::KeyShape, PatternConfig},
 };
 
@@ -48,7 +48,7 @@ fn describe_pattern<T: StackType + HasNoteNames>(
                 ui.label(format!(
                     "  {}",
                     stack.corrected_notename(
-                        &NoteNameStyle::Class,
+                        &correction_system_chooser.notenamestyle.with_octave(false),
                         correction_system_chooser.preference_order(),
                         correction_system_chooser.use_cent_values
                     )
@@ -63,7 +63,7 @@ fn describe_pattern<T: StackType + HasNoteNames>(
                 ui.label(format!(
                     "  {}",
                     tmp_stack.corrected_notename(
-                        &NoteNameStyle::Class,
+                        &correction_system_chooser.notenamestyle.with_octave(false),
                         correction_system_chooser.preference_order(),
                         correction_system_chooser.use_cent_values
                     )
@@ -78,7 +78,7 @@ fn describe_pattern<T: StackType + HasNoteNames>(
                 ui.label(format!(
                     "  {}",
                     tmp_stack.corrected_notename(
-                        &NoteNameStyle::Full,
+                        &correction_system_chooser.notenamestyle.with_octave(true),
                         correction_system_chooser.preference_order(),
                         correction_system_chooser.use_cent_values
                     )
@@ -91,7 +91,7 @@ fn describe_pattern<T: StackType + HasNoteNames>(
                 ui.label(format!(
                     "  {}",
                     stack.corrected_notename(
-                        &NoteNameStyle::Full,
+                        &correction_system_chooser.notenamestyle.with_octave(true),
                         correction_system_chooser.preference_order(),
                         correction_system_chooser.use_cent_values
                     )
@@ -113,7 +113,7 @@ fn describe_pattern<T: StackType + HasNoteNames>(
                             ui.label(format!(
                                 "  {}",
                                 tmp_stack.corrected_notename(
-                                    &NoteNameStyle::Class,
+                                    &correction_system_chooser.notenamestyle.with_octave(false),
                                     correction_system_chooser.preference_order(),
                                     correction_system_chooser.use_cent_values
                                 )
@@ -137,7 +137,7 @@ fn describe_pattern<T: StackType + HasNoteNames>(
                             ui.label(format!(
                                 "  {}",
                                 tmp_stack.corrected_notename(
-                                    &NoteNameStyle::Class,
+                                    &correction_system_chooser.notenamestyle.with_octave(false),
                                     correction_system_chooser.preference_order(),
                                     correction_system_chooser.use_cent_values
                                 )
@@ -0,0 +1,147 @@
+use std::{
+    collections::VecDeque,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use eframe::egui;
+
+use crate::{
+    interval::{stack::Stack, stacktype::r#trait::StackType},
+    msg::{FromUi, ReceiveMsgRef, ToUi},
+};
+
+/// How much drift history the meter shows.
+const HISTORY: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+pub struct DriftEditor {
+    /// Times and drifts (in cents) of all drift changes in the last [HISTORY].
+    history: VecDeque<(Instant, f64)>,
+}
+
+impl DriftEditor {
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::new(),
+        }
+    }
+
+    fn show_meter(&self, ui: &mut egui::Ui, max_drift_cents: f64) {
+        let current = self.history.back().map_or(0.0, |(_, cents)| *cents);
+        ui.label(format!("current drift: {current:+.1}ct"));
+
+        let (rect, _) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), 4.0 * ui.spacing().interact_size.y),
+            egui::Sense::hover(),
+        );
+        let painter = ui.painter().with_clip_rect(rect);
+        let visuals = ui.visuals();
+        painter.rect_filled(rect, 0.0, visuals.extreme_bg_color);
+
+        // The vertical range is at least twice the maximum drift, so that the limits are visible.
+        let range = self
+            .history
+            .iter()
+            .fold(2.0 * max_drift_cents, |acc, (_, cents)| acc.max(cents.abs()))
+            .max(1.0);
+        let y = |cents: f64| rect.center().y - (cents / range) as f32 * 0.5 * rect.height();
+        let now = Instant::now();
+        let x = |time: Instant| {
+            rect.right()
+                - (now.saturating_duration_since(time).as_secs_f32() / HISTORY.as_secs_f32())
+                    * rect.width()
+        };
+
+        painter.hline(
+            rect.x_range(),
+            y(0.0),
+            egui::Stroke::new(1.0, visuals.weak_text_color()),
+        );
+        for limit in [max_drift_cents, -max_drift_cents] {
+            painter.hline(
+                rect.x_range(),
+                y(limit),
+                egui::Stroke::new(1.0, visuals.warn_fg_color),
+            );
+        }
+
+        // The drift is constant between changes, so draw a step function.
+        let mut points = vec![];
+        let mut last = None {};
+        for (time, cents) in &self.history {
+            if let Some(previous) = last {
+                points.push(egui::pos2(x(*time), y(previous)));
+            }
+            points.push(egui::pos2(x(*time), y(*cents)));
+            last = Some(*cents);
+        }
+        if let Some(cents) = last {
+            points.push(egui::pos2(rect.right(), y(cents)));
+        }
+        painter.add(egui::Shape::line(
+            points,
+            egui::Stroke::new(1.5, visuals.strong_text_color()),
+        ));
+    }
+
+    pub fn show<T: StackType>(
+        &mut self,
+        ui: &mut egui::Ui,
+        comma: &Stack<T>,
+        max_drift_cents: &mut f64,
+        compensate_at_rests: &mut bool,
+        compensate_at_chord_changes: &mut bool,
+        forward: &mpsc::Sender<FromUi<T>>,
+    ) {
+        self.show_meter(ui, *max_drift_cents);
+
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            ui.label("Compensate drift by a comma of");
+            ui.label(format!("{:.1}ct", 100.0 * comma.semitones()));
+        });
+        changed |= ui
+            .checkbox(compensate_at_rests, "during rests")
+            .clicked();
+        changed |= ui
+            .checkbox(compensate_at_chord_changes, "at chord changes")
+            .clicked();
+        ui.horizontal(|ui| {
+            ui.label("when the drift exceeds");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(max_drift_cents)
+                        .range(0.0..=100.0)
+                        .speed(0.1)
+                        .max_decimals(1),
+                )
+                .changed();
+            ui.label("ct.");
+        });
+
+        if changed {
+            let _ = forward.send(FromUi::SetDriftCompensation {
+                max_drift_cents: *max_drift_cents,
+                at_rests: *compensate_at_rests,
+                at_chord_changes: *compensate_at_chord_changes,
+            });
+        }
+    }
+}
+
+impl<T: StackType> ReceiveMsgRef<ToUi<T>> for DriftEditor {
+    fn receive_msg_ref(&mut self, msg: &ToUi<T>) {
+        if let ToUi::Drift { cents, time } = msg {
+            self.history.push_back((*time, *cents));
+            while self
+                .history
+                .front()
+                .is_some_and(|(t, _)| time.saturating_duration_since(*t) > HISTORY)
+            {
+                self.history.pop_front();
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::mpsc;
+
+use eframe::egui;
+
+use crate::{interval::stacktype::r#trait::StackType, msg::FromUi};
+
+#[derive(Default)]
+pub struct HysteresisEditor {}
+
+impl HysteresisEditor {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn show<T: StackType>(
+        &mut self,
+        ui: &mut egui::Ui,
+        stable_ms: &mut u64,
+        max_retune_cents: &mut Option<f64>,
+        freeze: &mut bool,
+        forward: &mpsc::Sender<FromUi<T>>,
+    ) {
+        let mut changed = false;
+
+        changed |= ui
+            .checkbox(freeze, "never re-tune sounding notes")
+            .clicked();
+
+        ui.add_enabled_ui(!*freeze, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Only re-tune sounding notes once the new tuning was stable for");
+                changed |= ui
+                    .add(egui::DragValue::new(stable_ms).range(0..=5000))
+                    .changed();
+                ui.label("ms.");
+            });
+
+            ui.horizontal(|ui| {
+                let mut cap = max_retune_cents.is_some();
+                if ui
+                    .checkbox(&mut cap, "re-tune sounding notes by at most")
+                    .clicked()
+                {
+                    *max_retune_cents = if cap { Some(5.0) } else { None {} };
+                    changed = true;
+                }
+                if let Some(max) = max_retune_cents {
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(max)
+                                .range(0.1..=100.0)
+                                .speed(0.1)
+                                .max_decimals(1),
+                        )
+                        .changed();
+                    ui.label("ct at a time.");
+                }
+            });
+        });
+
+        if changed {
+            let _ = forward.send(FromUi::SetHysteresis {
+                stable_ms: *stable_ms,
+                max_retune_cents: *max_retune_cents,
+                freeze: *freeze,
+            });
+        }
+    }
+}
@@ -1,8 +1,13 @@
 pub mod binding;
 pub mod chordlist;
 pub mod commas;
+pub mod drift;
+pub mod hysteresis;
 pub mod neighbourhood;
 pub mod reference;
+pub mod sketch;
+pub mod springs;
 pub mod temperament;
 pub mod tuning;
 pub mod twostep;
+pub mod walking;
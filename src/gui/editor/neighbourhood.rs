@@ -1,16 +1,22 @@
-use std::{marker::PhantomData, sync::mpsc, time::Instant};
+use std::{marker::PhantomData, path::Path, sync::mpsc, time::Instant};
 
 use eframe::egui::{self, vec2};
+use egui_file_dialog::{FileDialog, FileDialogConfig};
 
 use crate::{
     gui::common::{ListEdit, ListEditOpts, RefListEdit},
     interval::stacktype::r#trait::StackType,
     msg::{FromUi, ReceiveMsgRef, ToUi},
+    scala,
 };
 
 pub struct NeighbourhoodEditor<T: StackType> {
     _phantom: PhantomData<T>,
     current_neighbourhood_index: Option<usize>,
+    scala_dialog: FileDialog,
+    /// What happened during the last import or export of Scala files, if it still has to be
+    /// shown.
+    scala_report: Option<Vec<String>>,
 }
 
 impl<T: StackType> NeighbourhoodEditor<T> {
@@ -18,6 +24,99 @@ impl<T: StackType> NeighbourhoodEditor<T> {
         Self {
             _phantom: PhantomData,
             current_neighbourhood_index: None {},
+            scala_dialog: FileDialog::with_config(
+                FileDialogConfig {
+                    default_file_filter: Some("Scala scales".into()),
+                    ..FileDialogConfig::default()
+                }
+                .add_file_filter_extensions("Scala scales", vec!["scl"]),
+            ),
+            scala_report: None {},
+        }
+    }
+
+    /// Import the scale, and the keyboard mapping with the same name and the extension `.kbm`, if
+    /// there is one.
+    fn import_scala(
+        &mut self,
+        path: &Path,
+        names: &mut Vec<String>,
+        forward: &mpsc::Sender<FromUi<T>>,
+    ) {
+        let scl = match std::fs::read_to_string(path) {
+            Ok(scl) => scl,
+            Err(e) => {
+                self.scala_report = Some(vec![format!("can't read {}: {e}", path.display())]);
+                return;
+            }
+        };
+        let kbm_path = path.with_extension("kbm");
+        let kbm = std::fs::read_to_string(&kbm_path).ok();
+        match scala::import::<T>(&scl, kbm.as_deref()) {
+            Ok(import) => {
+                names.push(
+                    path.file_stem()
+                        .map_or("imported".into(), |s| s.to_string_lossy().into()),
+                );
+                let _ = forward.send(FromUi::AddNeighbourhood {
+                    neighbourhood: import.neighbourhood.into(),
+                    time: Instant::now(),
+                });
+                let mut report = vec![];
+                if kbm.is_some() {
+                    report.push(format!("used the keyboard mapping {}", kbm_path.display()));
+                }
+                report.extend(import.problems.iter().map(|p| p.to_string()));
+                report.extend(import.ignored);
+                if !report.is_empty() {
+                    self.scala_report = Some(report);
+                }
+            }
+            Err(e) => self.scala_report = Some(vec![e.to_string()]),
+        }
+    }
+
+    fn show_scala_files(
+        &mut self,
+        ui: &mut egui::Ui,
+        names: &mut Vec<String>,
+        forward: &mpsc::Sender<FromUi<T>>,
+    ) {
+        ui.horizontal(|ui| {
+            if ui.button("import Scala file").clicked() {
+                self.scala_dialog.pick_file();
+            }
+            if let Some(i) = self.current_neighbourhood_index {
+                if ui.button("export selected neighbourhood").clicked() {
+                    let _ = forward.send(FromUi::ExportTuning {
+                        neighbourhood: Some(i),
+                    });
+                }
+            }
+            if ui.button("export current tuning").clicked() {
+                let _ = forward.send(FromUi::ExportTuning {
+                    neighbourhood: None {},
+                });
+            }
+        });
+        self.scala_dialog.update(ui.ctx());
+        if let Some(path) = self.scala_dialog.take_picked() {
+            self.import_scala(&path, names, forward);
+        }
+
+        let mut open = self.scala_report.is_some();
+        if let Some(report) = &self.scala_report {
+            egui::Window::new("Scala files")
+                .open(&mut open)
+                .collapsible(false)
+                .show(ui.ctx(), |ui| {
+                    for line in report {
+                        ui.label(line);
+                    }
+                });
+        }
+        if !open {
+            self.scala_report = None {};
         }
     }
 }
@@ -104,6 +203,8 @@ impl<T: StackType> NeighbourhoodEditor<T> {
             }
             crate::gui::common::ListEditResult::None => {}
         }
+
+        self.show_scala_files(ui, names, forward);
     }
 }
 
@@ -113,6 +214,15 @@ impl<T: StackType> ReceiveMsgRef<ToUi<T>> for NeighbourhoodEditor<T> {
             ToUi::CurrentNeighbourhoodIndex { index } => {
                 self.current_neighbourhood_index = Some(*index);
             }
+            ToUi::TuningExported { result } => {
+                self.scala_report = Some(match result {
+                    Ok(paths) => paths
+                        .iter()
+                        .map(|p| format!("wrote {}", p.display()))
+                        .collect(),
+                    Err(e) => vec![e.clone()],
+                });
+            }
             _ => {}
         }
     }
@@ -0,0 +1,37 @@
+use std::sync::mpsc;
+
+use eframe::egui;
+
+use crate::{interval::stacktype::r#trait::StackType, msg::FromUi};
+
+#[derive(Default)]
+pub struct SketchEditor {}
+
+impl SketchEditor {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn show<T: StackType>(
+        &mut self,
+        ui: &mut egui::Ui,
+        minimum_age_ms: &mut u64,
+        forward: &mpsc::Sender<FromUi<T>>,
+    ) {
+        ui.horizontal(|ui| {
+            ui.label("Only tune new chords relative to chords that lasted at least");
+            if ui
+                .add(egui::DragValue::new(minimum_age_ms).range(0..=5000))
+                .changed()
+            {
+                let _ = forward.send(FromUi::SetGroupMs {
+                    group_ms: *minimum_age_ms,
+                });
+            }
+            ui.label("ms.");
+        });
+        ui.label(
+            "(Shorter chords still determine the tuning while they sound, but grace notes and fast runs can't move the tuning around.)",
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use std::{sync::mpsc, time::Instant};
+
+use eframe::egui;
+use num_rational::Ratio;
+
+use crate::{
+    config::NamedSpringInterval,
+    gui::common::rational_drag_value,
+    interval::stacktype::r#trait::{StackCoeff, StackType},
+    msg::FromUi,
+};
+
+#[derive(Default)]
+pub struct SpringsEditor {}
+
+/// Edit `value` in place, but only accept positive values. Returns true iff `value` changed.
+fn stiffness_drag_value(ui: &mut egui::Ui, id: egui::Id, value: &mut Ratio<StackCoeff>) -> bool {
+    let mut tmp = *value;
+    if rational_drag_value(ui, id, &mut tmp) && tmp > Ratio::from_integer(0) {
+        *value = tmp;
+        return true;
+    }
+    false
+}
+
+impl SpringsEditor {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn show<T: StackType>(
+        &mut self,
+        ui: &mut egui::Ui,
+        intervals: &mut [NamedSpringInterval<T>],
+        anchor_stiffness: &mut Ratio<StackCoeff>,
+        forward: &mpsc::Sender<FromUi<T>>,
+    ) {
+        ui.label("stiffness of the springs between notes:");
+        egui::Grid::new("springs stiffness grid").show(ui, |ui| {
+            for (index, x) in intervals.iter_mut().enumerate() {
+                ui.label(&x.name);
+                ui.horizontal(|ui| {
+                    if stiffness_drag_value(ui, ui.id().with(index), &mut x.stiffness) {
+                        let _ = forward.send(FromUi::SetSpringStiffness {
+                            index,
+                            stiffness: x.stiffness,
+                            time: Instant::now(),
+                        });
+                    }
+                });
+                ui.end_row();
+            }
+        });
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("stiffness of the springs to the reference:");
+            if stiffness_drag_value(ui, ui.id().with("anchor"), anchor_stiffness) {
+                let _ = forward.send(FromUi::SetAnchorStiffness {
+                    stiffness: *anchor_stiffness,
+                    time: Instant::now(),
+                });
+            }
+        });
+        ui.label(
+            "(Stiffer springs to the reference keep notes closer to their tuning relative to \
+            the reference, softer ones allow more pure intervals between sounding notes.)",
+        );
+    }
+}
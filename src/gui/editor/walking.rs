@@ -0,0 +1,57 @@
+use std::sync::mpsc;
+
+use eframe::egui;
+
+use crate::{interval::stacktype::r#trait::StackType, msg::FromUi};
+
+#[derive(Default)]
+pub struct WalkingEditor {}
+
+impl WalkingEditor {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn show<T: StackType>(
+        &mut self,
+        ui: &mut egui::Ui,
+        walk: &mut bool,
+        temper_patterns: &mut bool,
+        forward: &mpsc::Sender<FromUi<T>>,
+    ) {
+        if ui
+            .radio_value(walk, false, "do not move the key centre on chord matches")
+            .clicked()
+        {
+            let _ = forward.send(FromUi::ReanchorOnMatch { reanchor: *walk });
+        }
+
+        if ui
+            .radio_value(
+                walk,
+                true,
+                "whenever a new chord matches, move the key centre to that chord's reference",
+            )
+            .clicked()
+        {
+            let _ = forward.send(FromUi::ReanchorOnMatch { reanchor: *walk });
+        }
+
+        ui.separator();
+
+        if ui
+            .checkbox(
+                temper_patterns,
+                "also apply temperaments of the current neighbourhood to the chord patterns",
+            )
+            .clicked()
+        {
+            let _ = forward.send(FromUi::TemperPatterns {
+                temper: *temper_patterns,
+            });
+        }
+        ui.label(
+            "(This only affects temperaments that are applied or removed after checking the box.)",
+        );
+    }
+}
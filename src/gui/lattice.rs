@@ -75,7 +75,8 @@ impl LatticeWindowConfig {
             project_dimension,
             screen_keyboard_channel,
             screen_keyboard_velocity,
-            notenamestyle,
+            // already in the `correction_system_chooser`
+            notenamestyle: _,
             highlight_playable_keys,
             color_period_ct,
         } = self;
@@ -90,7 +91,6 @@ impl LatticeWindowConfig {
             screen_keyboard_velocity,
             screen_keyboard_pedal_hold: false,
             screen_keyboard_center: 60,
-            notenamestyle,
             correction_system_chooser,
             highlight_playable_keys,
             color_period_ct,
@@ -110,7 +110,6 @@ pub struct LatticeWindowControls<T: StackType> {
     pub screen_keyboard_velocity: u8,
     pub screen_keyboard_pedal_hold: bool,
     pub screen_keyboard_center: u8,
-    pub notenamestyle: NoteNameStyle,
     pub correction_system_chooser: Rc<RefCell<CorrectionSystemChooser<T>>>,
     pub highlight_playable_keys: bool,
     pub color_period_ct: Semitones,
@@ -261,7 +260,7 @@ impl<T: StackType + HasNoteNames> OneNodeDrawState<T> {
         ui.painter().text(
             pos2(hpos, vpos),
             egui::Align2::CENTER_CENTER,
-            stack.notename(&controls.notenamestyle),
+            stack.notename(&controls.correction_system_chooser.borrow().notenamestyle),
             egui::FontId::proportional(first_line_height),
             text_color,
         );
@@ -304,7 +303,12 @@ impl<T: StackType + HasNoteNames> OneNodeDrawState<T> {
                 ui.painter().text(
                     pos2(hpos, third_line_vpos),
                     egui::Align2::CENTER_CENTER,
-                    format!("={}", stack.actual_notename(&controls.notenamestyle)),
+                    format!(
+                        "={}",
+                        stack.actual_notename(
+                            &controls.correction_system_chooser.borrow().notenamestyle
+                        )
+                    ),
                     egui::FontId::proportional(other_lines_height),
                     text_color,
                 );
@@ -356,7 +360,7 @@ impl<T: StackType + HasNoteNames> OneNodeDrawState<T> {
                     Some(&format!(
                         "make pure relative to {}",
                         reference.corrected_notename(
-                            &controls.notenamestyle,
+                            &controls.correction_system_chooser.borrow().notenamestyle,
                             controls
                                 .correction_system_chooser
                                 .borrow()
@@ -1143,7 +1147,7 @@ impl<T: StackType> ExtractConfig<LatticeWindowConfig> for LatticeWindow<T> {
             project_dimension,
             screen_keyboard_channel,
             screen_keyboard_velocity,
-            notenamestyle,
+            correction_system_chooser,
             highlight_playable_keys,
             color_period_ct: color_period,
             ..
@@ -1157,7 +1161,7 @@ impl<T: StackType> ExtractConfig<LatticeWindowConfig> for LatticeWindow<T> {
             project_dimension: *project_dimension,
             screen_keyboard_channel: *screen_keyboard_channel,
             screen_keyboard_velocity: *screen_keyboard_velocity,
-            notenamestyle: *notenamestyle,
+            notenamestyle: correction_system_chooser.borrow().notenamestyle,
             highlight_playable_keys: *highlight_playable_keys,
             color_period_ct: *color_period,
         }
@@ -3,7 +3,11 @@ use std::{sync::mpsc, time::Instant};
 use eframe::egui::{self};
 use midi_msg::Channel;
 
-use crate::{interval::stacktype::r#trait::StackType, msg::FromUi, notename::HasNoteNames};
+use crate::{
+    interval::stacktype::r#trait::StackType,
+    msg::FromUi,
+    notename::{HasNoteNames, Notation},
+};
 
 use super::{common::rational_drag_value, lattice::LatticeWindow, r#trait::GuiShow};
 
@@ -58,6 +62,21 @@ impl<'a, T: StackType + HasNoteNames> AsBigControls<'a, T> {
         let AsBigControls(lw) = self;
         let controls = &mut lw.controls;
 
+        ui.collapsing("how to write note names", |ui| {
+            let notenamestyle = &mut controls
+                .correction_system_chooser
+                .borrow_mut()
+                .notenamestyle;
+            for notation in Notation::ALL {
+                ui.radio_value(
+                    &mut notenamestyle.notation,
+                    notation,
+                    notation.description(),
+                );
+            }
+            ui.checkbox(&mut notenamestyle.octave, "octave numbers");
+        });
+
         ui.collapsing("how to write detuned notes", |ui| {
             controls.correction_system_chooser.borrow_mut().show(ui);
         });
@@ -9,6 +9,7 @@ pub mod lattice;
 pub mod latticecontrol;
 // pub mod notes; // temporaril disabled because its' unfinished
 pub mod notifications;
+pub mod recorder;
 pub mod strategy;
 pub mod toplevel;
 pub mod r#trait;
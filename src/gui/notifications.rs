@@ -11,7 +11,7 @@ use crate::{
     config::{HarmonyStrategyNames, MelodyStrategyNames, StrategyNames},
     interval::{base::Semitones, stack::Stack, stacktype::r#trait::StackType},
     msg::{ReceiveMsgRef, ToUi},
-    notename::{HasNoteNames, NoteNameStyle},
+    notename::HasNoteNames,
 };
 
 use super::{common::CorrectionSystemChooser, toplevel::KeysAndTunings};
@@ -23,8 +23,12 @@ pub struct Notifications<T: StackType> {
     enable_chord_list: (Option<bool>, Instant),
     enable_reanchor: (Option<bool>, Instant),
     detuned_notes: VecDeque<(u8, Semitones, Semitones, &'static str, Instant)>,
+    /// Names of input ports that were disconnected because of a feedback loop.
+    feedback_loops: VecDeque<(String, Instant)>,
     correction_system_chooser: Rc<RefCell<CorrectionSystemChooser<T>>>,
     cleanup_time: Duration,
+    /// How long warnings about the MIDI connections are shown.
+    warning_time: Duration,
 }
 
 impl<T: StackType + HasNoteNames> Notifications<T> {
@@ -36,8 +40,10 @@ impl<T: StackType + HasNoteNames> Notifications<T> {
             enable_chord_list: (None {}, Instant::now()),
             enable_reanchor: (None {}, Instant::now()),
             detuned_notes: VecDeque::new(),
+            feedback_loops: VecDeque::new(),
             correction_system_chooser,
             cleanup_time: Duration::from_secs(2),
+            warning_time: Duration::from_secs(10),
         }
     }
 
@@ -81,6 +87,14 @@ impl<T: StackType + HasNoteNames> Notifications<T> {
                 break;
             }
         }
+
+        while let Some((_, old)) = self.feedback_loops.front() {
+            if time.duration_since(*old) > self.warning_time {
+                let _ = self.feedback_loops.pop_front();
+            } else {
+                break;
+            }
+        }
     }
 
     pub fn is_nonempty(&self) -> bool {
@@ -90,6 +104,7 @@ impl<T: StackType + HasNoteNames> Notifications<T> {
             || self.enable_chord_list.0.is_some()
             || self.enable_reanchor.0.is_some()
             || !self.detuned_notes.is_empty()
+            || !self.feedback_loops.is_empty()
     }
 
     pub fn show(
@@ -98,11 +113,27 @@ impl<T: StackType + HasNoteNames> Notifications<T> {
         state: &KeysAndTunings<T>,
         info: Option<&StrategyNames<T>>,
     ) {
+        let notenamestyle = self
+            .correction_system_chooser
+            .borrow()
+            .notenamestyle
+            .with_octave(true);
+
+        for (portname, _) in &self.feedback_loops {
+            ui.label(
+                egui::RichText::new(format!(
+                    "disconnected input \"{portname}\": it received adaptuner's own output \
+                    (MIDI feedback loop)"
+                ))
+                .color(ui.style().visuals.warn_fg_color),
+            );
+        }
+
         if let (Some(neighbourhood_index), _) = self.neighbourhood_index {
             ui.horizontal(|ui| {
                 ui.spacing_mut().item_spacing.x = 0.0;
                 ui.label("switched to neighbourhood ");
-                ui.strong(match info {
+                ui.strong(match info.map(|x| x.innermost()) {
                     Some(StrategyNames::TwoStep {
                         melody:
                             MelodyStrategyNames::Neighbourhoods {
@@ -115,6 +146,14 @@ impl<T: StackType + HasNoteNames> Notifications<T> {
                         neighbourhood_names,
                         ..
                     }) => &neighbourhood_names[neighbourhood_index % neighbourhood_names.len()],
+                    Some(StrategyNames::Walking {
+                        neighbourhood_names,
+                        ..
+                    }) => &neighbourhood_names[neighbourhood_index % neighbourhood_names.len()],
+                    Some(StrategyNames::Sketch {
+                        neighbourhood_names,
+                        ..
+                    }) => &neighbourhood_names[neighbourhood_index % neighbourhood_names.len()],
                     _ => "<no name>",
                 });
             });
@@ -139,16 +178,22 @@ impl<T: StackType + HasNoteNames> Notifications<T> {
         if let (Some((pattern_index, reference)), _) = &self.chord {
             ui.horizontal(|ui| {
                 ui.spacing_mut().item_spacing.x = 0.0;
-                ui.strong(match info {
+                ui.strong(match info.map(|x| x.innermost()) {
                     Some(StrategyNames::TwoStep {
                         harmony: HarmonyStrategyNames::ChordList { patterns },
                         ..
                     }) => &patterns[*pattern_index % patterns.len()].name,
+                    Some(StrategyNames::Walking { patterns, .. }) => {
+                        &patterns[*pattern_index % patterns.len()].name
+                    }
+                    Some(StrategyNames::Sketch { key_shapes, .. }) => {
+                        &key_shapes[*pattern_index % key_shapes.len()].name
+                    }
                     _ => "<no name>",
                 });
                 ui.label(" on ");
                 ui.strong(reference.corrected_notename(
-                    &NoteNameStyle::Full,
+                    &notenamestyle,
                     self.correction_system_chooser.borrow().preference_order(),
                     self.correction_system_chooser.borrow().use_cent_values,
                 ));
@@ -160,7 +205,7 @@ impl<T: StackType + HasNoteNames> Notifications<T> {
                 ui.spacing_mut().item_spacing.x = 0.0;
                 ui.label("reference ");
                 ui.strong(reference.corrected_notename(
-                    &NoteNameStyle::Full,
+                    &notenamestyle,
                     self.correction_system_chooser.borrow().preference_order(),
                     self.correction_system_chooser.borrow().use_cent_values,
                 ));
@@ -172,7 +217,7 @@ impl<T: StackType + HasNoteNames> Notifications<T> {
                 "note {} not tuned correctly: should be \
                 {should_be:.02}, but is {actual:.02}: {explanation}",
                 state.tunings[*note as usize].corrected_notename(
-                    &NoteNameStyle::Full,
+                    &notenamestyle,
                     self.correction_system_chooser.borrow().preference_order(),
                     self.correction_system_chooser.borrow().use_cent_values,
                 ),
@@ -222,6 +267,10 @@ impl<T: StackType> ReceiveMsgRef<ToUi<T>> for Notifications<T> {
             ToUi::ReanchorOnMatch { reanchor } => {
                 self.enable_reanchor = (Some(*reanchor), Instant::now());
             }
+            ToUi::InputFeedbackLoop { portname } => {
+                self.feedback_loops
+                    .push_back((portname.clone(), Instant::now()));
+            }
 
             ToUi::CurrentStrategyIndex(_) => {}
             ToUi::Notify { .. } => {} // this will only contain MIDI parse errors (which shouldn't happen?)
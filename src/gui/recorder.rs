@@ -0,0 +1,68 @@
+use std::{path::PathBuf, sync::mpsc, time::Instant};
+
+use eframe::egui;
+
+use crate::{
+    gui::r#trait::GuiShow,
+    interval::stacktype::r#trait::StackType,
+    msg::{FromUi, ReceiveMsgRef, ToUi},
+};
+
+#[derive(Default)]
+pub struct RecorderControls {
+    /// Where the running recording will be saved, if there is one.
+    recording: Option<PathBuf>,
+    last_saved: Option<PathBuf>,
+    error: Option<String>,
+}
+
+impl RecorderControls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T: StackType> ReceiveMsgRef<ToUi<T>> for RecorderControls {
+    fn receive_msg_ref(&mut self, msg: &ToUi<T>) {
+        match msg {
+            ToUi::RecordingStarted { path } => {
+                self.recording = Some(path.clone());
+                self.error = None {};
+            }
+            ToUi::RecordingStopped { path } => {
+                self.recording = None {};
+                self.last_saved = Some(path.clone());
+            }
+            ToUi::RecordingError { reason } => {
+                self.recording = None {};
+                self.error = Some(reason.clone());
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<T: StackType> GuiShow<T> for RecorderControls {
+    fn show(&mut self, ui: &mut egui::Ui, forward: &mpsc::Sender<FromUi<T>>) {
+        if let Some(path) = &self.recording {
+            if ui.button("stop recording").clicked() {
+                let _ = forward.send(FromUi::StopRecording {
+                    time: Instant::now(),
+                });
+            }
+            ui.label(format!("recording to '{}'", path.display()));
+        } else {
+            if ui.button("start recording").clicked() {
+                let _ = forward.send(FromUi::StartRecording {
+                    time: Instant::now(),
+                });
+            }
+            if let Some(path) = &self.last_saved {
+                ui.label(format!("saved the last recording to '{}'", path.display()));
+            }
+        }
+        if let Some(reason) = &self.error {
+            ui.label(egui::RichText::new(reason).color(ui.style().visuals.warn_fg_color));
+        }
+    }
+}
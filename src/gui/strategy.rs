@@ -18,10 +18,15 @@ use super::{
     editor::{
         binding::BindingEditor,
         chordlist::ChordListEditor,
+        drift::DriftEditor,
+        hysteresis::HysteresisEditor,
         neighbourhood::NeighbourhoodEditor,
         reference::{ReferenceEditor, ReferenceEditorConfig},
+        sketch::SketchEditor,
+        springs::SpringsEditor,
         tuning::{TuningEditor, TuningEditorConfig},
         twostep::TwoStepEditor,
+        walking::WalkingEditor,
     },
     r#trait::GuiShow,
     toplevel::KeysAndTunings,
@@ -37,6 +42,11 @@ pub struct StrategyWindows<T: StackType + 'static> {
     binding_editor: BindingEditor,
     chord_list_editor: ChordListEditor<T>,
     twostep_editor: TwoStepEditor,
+    springs_editor: SpringsEditor,
+    walking_editor: WalkingEditor,
+    sketch_editor: SketchEditor,
+    hysteresis_editor: HysteresisEditor,
+    drift_editor: DriftEditor,
 }
 
 /// [OctavePeriodicStackType] is needed for the [ChordListEditor]
@@ -70,6 +80,11 @@ impl<T: OctavePeriodicStackType + HasNoteNames> StrategyWindows<T> {
             binding_editor: BindingEditor::new(),
             chord_list_editor: ChordListEditor::new(correction_system_chooser),
             twostep_editor: TwoStepEditor::new(),
+            springs_editor: SpringsEditor::new(),
+            walking_editor: WalkingEditor::new(),
+            sketch_editor: SketchEditor::new(),
+            hysteresis_editor: HysteresisEditor::new(),
+            drift_editor: DriftEditor::new(),
         }
     }
 
@@ -101,15 +116,17 @@ impl<T: StackType> ReceiveMsgRef<ToUi<T>> for StrategyWindows<T> {
                 }
             }
             ToUi::ReanchorOnMatch { reanchor } => {
-                if let Some((
-                    StrategyNames::TwoStep {
+                match self
+                    .strategies
+                    .current_selected_mut()
+                    .map(|(names, _)| names.innermost_mut())
+                {
+                    Some(StrategyNames::TwoStep {
                         melody: MelodyStrategyNames::Neighbourhoods { fixed, .. },
                         ..
-                    },
-                    _,
-                )) = self.strategies.current_selected_mut()
-                {
-                    *fixed = !reanchor;
+                    }) => *fixed = !reanchor,
+                    Some(StrategyNames::Walking { walk, .. }) => *walk = *reanchor,
+                    _ => {}
                 }
             }
             _ => {}
@@ -118,6 +135,8 @@ impl<T: StackType> ReceiveMsgRef<ToUi<T>> for StrategyWindows<T> {
         self.tuning_editor.receive_msg_ref(msg);
         self.neighbourhood_editor.receive_msg_ref(msg);
         self.chord_list_editor.receive_msg_ref(msg);
+        self.drift_editor.receive_msg_ref(msg);
+        self.binding_editor.receive_msg_ref(msg);
 
         // twostep_editor doesn't need to handle any messages, we handle ReanchorOnMatch here:
         // self.twostep_editor.handle_msg_ref(msg, forward);
@@ -165,7 +184,45 @@ impl<'a, T: OctavePeriodicStackType + HasNoteNames> AsStrategyPicker<'a, T> {
                     .show(ui, strn.0.strategy_kind(), &mut strn.1, forward)
             });
 
-            match &mut strn.0 {
+            let mut names = &mut strn.0;
+            let names = loop {
+                match names {
+                    StrategyNames::Hysteresis {
+                        inner,
+                        stable_ms,
+                        max_retune_cents,
+                        freeze,
+                    } => {
+                        ui.collapsing("hysteresis", |ui| {
+                            x.hysteresis_editor
+                                .show(ui, stable_ms, max_retune_cents, freeze, forward)
+                        });
+                        names = inner;
+                    }
+                    StrategyNames::Drift {
+                        inner,
+                        comma,
+                        max_drift_cents,
+                        compensate_at_rests,
+                        compensate_at_chord_changes,
+                    } => {
+                        ui.collapsing("drift", |ui| {
+                            x.drift_editor.show(
+                                ui,
+                                comma,
+                                max_drift_cents,
+                                compensate_at_rests,
+                                compensate_at_chord_changes,
+                                forward,
+                            )
+                        });
+                        names = inner;
+                    }
+                    other => break other,
+                }
+            };
+
+            match names {
                 StrategyNames::StaticTuning {
                     neighbourhood_names,
                     ..
@@ -200,6 +257,48 @@ impl<'a, T: OctavePeriodicStackType + HasNoteNames> AsStrategyPicker<'a, T> {
                         x.twostep_editor.show(ui, harmony, melody, forward)
                     });
                 }
+                StrategyNames::Springs {
+                    intervals,
+                    anchor_stiffness,
+                    ..
+                } => {
+                    ui.collapsing("springs", |ui| {
+                        x.springs_editor
+                            .show(ui, intervals, anchor_stiffness, forward)
+                    });
+                }
+                StrategyNames::Walking {
+                    neighbourhood_names,
+                    patterns,
+                    walk,
+                    temper_patterns,
+                    ..
+                } => {
+                    ui.collapsing("neighbourhoods", |ui| {
+                        x.neighbourhood_editor
+                            .show(ui, neighbourhood_names, forward)
+                    });
+                    ui.collapsing("chord list", |ui| {
+                        x.chord_list_editor.show(ui, state, patterns, forward);
+                    });
+                    ui.collapsing("walking", |ui| {
+                        x.walking_editor.show(ui, walk, temper_patterns, forward)
+                    });
+                }
+                StrategyNames::Sketch {
+                    neighbourhood_names,
+                    minimum_age_ms,
+                    ..
+                } => {
+                    ui.collapsing("neighbourhoods", |ui| {
+                        x.neighbourhood_editor
+                            .show(ui, neighbourhood_names, forward)
+                    });
+                    ui.collapsing("sketch", |ui| {
+                        x.sketch_editor.show(ui, minimum_age_ms, forward)
+                    });
+                }
+                StrategyNames::Hysteresis { .. } | StrategyNames::Drift { .. } => unreachable!(),
             }
         }
     }
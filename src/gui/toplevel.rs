@@ -26,6 +26,7 @@ use super::{
     latticecontrol::{AsBigControls, AsKeyboardControls},
     notifications::Notifications,
     r#trait::GuiShow,
+    recorder::RecorderControls,
     strategy::{AsStrategyPicker, AsWindows, StrategyWindows},
 };
 
@@ -70,6 +71,8 @@ pub struct Toplevel<T: StackType> {
 
     latency: LatencyWindow,
 
+    recorder: RecorderControls,
+
     tx: mpsc::Sender<FromUi<T>>,
 
     // notes: NoteWindow<T>,
@@ -95,6 +98,7 @@ impl<T: OctavePeriodicStackType + HasNoteNames + Hash + Serialize> Toplevel<T> {
         let correction_system_chooser = Rc::new(RefCell::new(CorrectionSystemChooser::new(
             "correction_system_chooser",
             config.use_cent_values,
+            config.lattice_window.notenamestyle,
         )));
 
         Self {
@@ -123,6 +127,7 @@ impl<T: OctavePeriodicStackType + HasNoteNames + Hash + Serialize> Toplevel<T> {
             connection_window: SmallFloatingWindow::new(egui::Id::new("connection_window"), true),
             backend: BackendWindow::new(config.backend_window.clone()),
             latency: LatencyWindow::new(config.latency_mean_over),
+            recorder: RecorderControls::new(),
             // notes: NoteWindow::new(ctx),
             // note_window: SmallFloatingWindow::new(egui::Id::new("note_window")),
             tx,
@@ -148,6 +153,7 @@ impl<T: OctavePeriodicStackType + HasNoteNames + Hash + Serialize> Toplevel<T> {
         let correction_system_chooser = Rc::new(RefCell::new(CorrectionSystemChooser::new(
             "correction_system_chooser",
             config.use_cent_values,
+            config.lattice_window.notenamestyle,
         )));
 
         self.state = KeysAndTunings::new(time);
@@ -162,7 +168,7 @@ impl<T: OctavePeriodicStackType + HasNoteNames + Hash + Serialize> Toplevel<T> {
             correction_system_chooser.clone(),
             time,
         );
-        // input, output, latency, config_file_dialog don't need a restart
+        // input, output, latency, recorder, config_file_dialog don't need a restart
 
         self.backend
             .restart_from_config(config.backend_window, time);
@@ -184,6 +190,7 @@ impl<T: StackType + Serialize> ReceiveMsg<ToUi<T>> for Toplevel<T> {
         self.input_connection.receive_msg_ref(&msg);
         self.output_connection.receive_msg_ref(&msg);
         self.latency.receive_msg_ref(&msg);
+        self.recorder.receive_msg_ref(&msg);
         self.notifications.receive_msg_ref(&msg);
 
         match msg {
@@ -294,16 +301,17 @@ where
                     self.current_config = self.extract_config();
                     self.temperament_editor = TemperamentEditor::new();
                 }
-                if self
-                    .comma_editor_window
-                    .show_hide_button(ui, "commas")
-                {
+                if self.comma_editor_window.show_hide_button(ui, "commas") {
                     self.current_config = self.extract_config();
                     self.comma_editor = CommaEditor::new();
                 }
 
                 ui.separator();
 
+                self.recorder.show(ui, &self.tx);
+
+                ui.separator();
+
                 if ui.button("save configuration").clicked() {
                     self.current_config = self.extract_config();
                     self.current_process_config = None {};
@@ -0,0 +1,739 @@
+//! A deterministic harness for tests of the whole message pipeline.
+//!
+//! The threads of [RunState] are started without the GUI, with in-memory stand-ins for the MIDI
+//! input and output, and all events are stamped with the time of a virtual clock. After every
+//! scripted event, the harness waits until everything that was caused by the event has passed
+//! through all threads, and returns the MIDI that was sent and the messages that reached the UI.
+//!
+//! Waiting works by sending requests down the same paths the event took, and waiting for their
+//! answers: A [FromMidiIn::Connected] after the incoming MIDI, then requests for the current
+//! configuration of the process and the backend, and finally a [ToMidiOut::ListPorts]. The
+//! answers to these are not returned.
+//!
+//! The timer is driven by the virtual clock, too: Ticks asked for by the process or the backend
+//! are only sent by [Harness::advance], when the clock passes them.
+//!
+//! Within one step, MIDI sent directly by the process (like controllers the process doesn't
+//! handle) and MIDI sent by the backend may arrive in any order.
+
+use std::{
+    fmt,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use midi_msg::MidiMsg;
+
+use crate::{
+    backend::SomeBackend,
+    config::{BackendConfig, ExtractConfig, MidiInputConfig, MidiOutputConfig, ProcessConfig},
+    interval::stacktype::r#trait::StackType,
+    msg::{
+        FromMidiIn, FromMidiOut, FromUi, HandleMsg, ToBackend, ToMidiIn, ToMidiOut, ToProcess,
+        ToTimer, ToUi,
+    },
+    notename::HasNoteNames,
+    process::zones::ZonedProcess,
+    run::{RunState, StartupActions, WakeUps},
+};
+
+/// How long to wait for an answer before deciding that something's stuck.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct VirtualMidiInput {}
+
+impl HandleMsg<ToMidiIn, FromMidiIn> for VirtualMidiInput {
+    fn handle_msg(&mut self, _msg: ToMidiIn, _forward: &mpsc::Sender<FromMidiIn>) {}
+}
+
+impl ExtractConfig<MidiInputConfig> for VirtualMidiInput {
+    fn extract_config(&self) -> MidiInputConfig {
+        MidiInputConfig {}
+    }
+}
+
+struct VirtualMidiOutput {
+    sent: mpsc::Sender<Vec<u8>>,
+}
+
+impl HandleMsg<ToMidiOut, FromMidiOut> for VirtualMidiOutput {
+    fn handle_msg(&mut self, msg: ToMidiOut, forward: &mpsc::Sender<FromMidiOut>) {
+        match msg {
+            ToMidiOut::OutgoingMidi { bytes, .. } => {
+                let _ = self.sent.send(bytes);
+            }
+            ToMidiOut::ListPorts => {
+                let _ = forward.send(FromMidiOut::Ports {
+                    available_ports: vec![],
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+impl ExtractConfig<MidiOutputConfig> for VirtualMidiOutput {
+    fn extract_config(&self) -> MidiOutputConfig {
+        MidiOutputConfig {}
+    }
+}
+
+/// Everything that came out of the pipeline in response to one event.
+pub struct Step<T: StackType> {
+    /// The bytes of the sent MIDI, one entry per [ToMidiOut::OutgoingMidi].
+    pub midi: Vec<Vec<u8>>,
+    pub ui: Vec<ToUi<T>>,
+}
+
+impl<T: StackType> Step<T> {
+    /// The sent MIDI, parsed. Panics if something unparseable was sent.
+    pub fn midi_msgs(&self) -> Vec<MidiMsg> {
+        let mut res = vec![];
+        for bytes in &self.midi {
+            let mut rest = &bytes[..];
+            while !rest.is_empty() {
+                let (msg, len) = MidiMsg::from_midi(rest).unwrap();
+                res.push(msg);
+                rest = &rest[len..];
+            }
+        }
+        res
+    }
+}
+
+pub struct Harness<T: StackType> {
+    run_state: RunState<T>,
+    now: Instant,
+    midi_in: mpsc::Sender<FromMidiIn>,
+    midi_out: mpsc::Receiver<Vec<u8>>,
+    to_ui: mpsc::Receiver<ToUi<T>>,
+    from_ui: mpsc::Sender<FromUi<T>>,
+    to_timer: mpsc::Receiver<ToTimer>,
+    to_process: mpsc::Sender<ToProcess<T>>,
+    to_backend: mpsc::Sender<ToBackend>,
+    wake_ups: WakeUps,
+}
+
+impl<T> Harness<T>
+where
+    T: StackType + HasNoteNames + fmt::Debug + Send + 'static,
+{
+    /// Start with the first strategy. Whatever is sent on startup is returned by the first call
+    /// to [Harness::settle].
+    pub fn new(process_config: ProcessConfig<T>, backend_config: BackendConfig) -> Self {
+        let now = Instant::now();
+        let (midi_out_tx, midi_out) = mpsc::channel();
+        let mut midi_in = None {};
+        let mut timer = None {};
+        let (run_state, to_ui, from_ui) =
+            RunState::start_without_gui::<ZonedProcess<T>, SomeBackend, _, _, _, _>(
+                |tx| {
+                    midi_in = Some(tx);
+                    VirtualMidiInput {}
+                },
+                VirtualMidiOutput { sent: midi_out_tx },
+                process_config,
+                backend_config,
+                StartupActions::default(),
+                |rx, to_process, to_backend| timer = Some((rx, to_process, to_backend)),
+                now,
+            );
+        let (to_timer, to_process, to_backend) = timer.unwrap();
+        Self {
+            run_state,
+            now,
+            midi_in: midi_in.unwrap(),
+            midi_out,
+            to_ui,
+            from_ui,
+            to_timer,
+            to_process,
+            to_backend,
+            wake_ups: WakeUps::default(),
+        }
+    }
+
+    /// The current time of the virtual clock.
+    pub fn now(&self) -> Instant {
+        self.now
+    }
+
+    /// Move the virtual clock forward, and send the ticks that become due on the way, in order.
+    /// Everything that comes out in response to them is returned.
+    pub fn advance(&mut self, by: Duration) -> Step<T> {
+        let until = self.now + by;
+        let mut res = Step {
+            midi: vec![],
+            ui: vec![],
+        };
+        loop {
+            for msg in self.to_timer.try_iter() {
+                self.wake_ups.insert(msg);
+            }
+            if !self
+                .wake_ups
+                .send_next(until, &self.to_process, &self.to_backend)
+            {
+                break;
+            }
+            let step = self.settle();
+            res.midi.extend(step.midi);
+            res.ui.extend(step.ui);
+        }
+        self.now = until;
+        res
+    }
+
+    /// Feed `bytes` to the MIDI input, at the current time.
+    pub fn midi(&mut self, bytes: &[u8]) -> Step<T> {
+        let _ = self.midi_in.send(FromMidiIn::IncomingMidi {
+            time: self.now,
+            bytes: bytes.to_vec(),
+        });
+        self.settle()
+    }
+
+    /// Send `msg` as if it came from the GUI.
+    pub fn ui(&mut self, msg: FromUi<T>) -> Step<T> {
+        let _ = self.from_ui.send(msg);
+        self.settle()
+    }
+
+    /// Wait until all threads are done with everything sent so far, and collect what came out.
+    pub fn settle(&mut self) -> Step<T> {
+        let mut ui = vec![];
+
+        let _ = self.midi_in.send(FromMidiIn::Connected {
+            portname: "virtual input".into(),
+        });
+        self.wait_for(&mut ui, |msg| matches!(msg, ToUi::InputConnected { .. }));
+
+        let _ = self.from_ui.send(FromUi::GetCurrentProcessConfig);
+        self.wait_for(&mut ui, |msg| matches!(msg, ToUi::CurrentProcessConfig(_)));
+
+        let _ = self.from_ui.send(FromUi::GetCurrentBackendConfig);
+        self.wait_for(&mut ui, |msg| matches!(msg, ToUi::CurrentBackendConfig(_)));
+
+        let _ = self.from_ui.send(FromUi::ListOutputPorts);
+        self.wait_for(&mut ui, |msg| matches!(msg, ToUi::OutputPorts { .. }));
+
+        Step {
+            midi: self.midi_out.try_iter().collect(),
+            ui,
+        }
+    }
+
+    fn wait_for<F: Fn(&ToUi<T>) -> bool>(&self, ui: &mut Vec<ToUi<T>>, done: F) {
+        loop {
+            match self.to_ui.recv_timeout(TIMEOUT) {
+                Ok(msg) if done(&msg) => return,
+                Ok(msg) => ui.push(msg),
+                Err(e) => panic!("the pipeline didn't settle: {e}"),
+            }
+        }
+    }
+
+    pub fn stop(self) -> (ProcessConfig<T>, BackendConfig) {
+        let (process_config, backend_config, _, _) = self.run_state.stop_without_gui().unwrap();
+        (process_config, backend_config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use midi_msg::{Channel, ChannelVoiceMsg, ControlChange};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{
+        backend::glide::{GlideConfig, GlideCurve},
+        backend::{
+            mpe::{MpeConfig, MpeZone},
+            pitchbend12::Pitchbend12Config,
+        },
+        bindable::{Bindings, ControllerMapping, MappedParameter, MidiBindable},
+        config::StrategyConfig,
+        interval::{
+            stack::Stack,
+            stacktype::fivelimit::mock::{
+                static_tuning_config, MockFiveLimitStackType, TWELVE_NOTES,
+            },
+        },
+        process::zones::ZoneConfig,
+        strategy::{hysteresis::HysteresisConfig, r#trait::StrategyAction},
+        util::list_action::ListAction,
+    };
+
+    /// A static five-limit tuning around C. The major third is pure if `pythagorean_third` is
+    /// false, and four fifths minus two octaves otherwise.
+    fn static_tuning(pythagorean_third: bool) -> StrategyConfig<MockFiveLimitStackType> {
+        let mut notes = TWELVE_NOTES;
+        if pythagorean_third {
+            notes[4] = [-2, 4, 0];
+        }
+        StrategyConfig::StaticTuning(static_tuning_config(&[notes]))
+    }
+
+    fn harness() -> Harness<MockFiveLimitStackType> {
+        let mut harness = Harness::new(
+            ProcessConfig {
+                strategies: vec![
+                    (static_tuning(false), Bindings::empty()),
+                    (static_tuning(true), Bindings::empty()),
+                ],
+                zones: vec![],
+            },
+            BackendConfig::Pitchbend12(Pitchbend12Config {
+                bend_range: 2.0,
+                channels: core::array::from_fn(|i| Channel::from_u8(i as u8).into()),
+                glide: None {},
+            }),
+        );
+        harness.settle();
+        harness
+    }
+
+    fn voice(channel: Channel, msg: ChannelVoiceMsg) -> MidiMsg {
+        MidiMsg::ChannelVoice { channel, msg }
+    }
+
+    fn hold(channel: Channel, value: u8) -> MidiMsg {
+        voice(
+            channel,
+            ChannelVoiceMsg::ControlChange {
+                control: ControlChange::Hold(value),
+            },
+        )
+    }
+
+    /// The bend for `semitones` with the bend range of two semitones used in these tests.
+    fn bend(semitones: f64) -> u16 {
+        (8191.0 * semitones / 2.0 + 8192.0) as u16
+    }
+
+    const PURE_THIRD: f64 = -0.13686286135165;
+    const PYTHAGOREAN_THIRD: f64 = 0.0782000346155;
+    const SYNTONIC_COMMA: f64 = 0.215062825329;
+
+    #[test]
+    fn test_held_notes_and_pedal() {
+        let mut harness = harness();
+
+        let step = harness.midi(&[0x90, 60, 100]);
+        assert_eq!(
+            step.midi_msgs(),
+            vec![voice(
+                Channel::Ch1,
+                ChannelVoiceMsg::NoteOn {
+                    note: 60,
+                    velocity: 100
+                }
+            )]
+        );
+        assert!(step.ui.iter().any(|msg| matches!(
+            msg,
+            ToUi::TunedNoteOn {
+                note: 60,
+                channel: Channel::Ch1,
+                ..
+            }
+        )));
+
+        harness.advance(Duration::from_millis(100));
+        let step = harness.midi(&[0x90, 64, 100]);
+        assert_eq!(
+            step.midi_msgs(),
+            vec![
+                voice(
+                    Channel::Ch5,
+                    ChannelVoiceMsg::NoteOn {
+                        note: 64,
+                        velocity: 100
+                    }
+                ),
+                voice(
+                    Channel::Ch5,
+                    ChannelVoiceMsg::PitchBend {
+                        bend: bend(PURE_THIRD)
+                    }
+                ),
+            ]
+        );
+
+        // the pedal goes to all channels used by the backend
+        harness.advance(Duration::from_millis(100));
+        let step = harness.midi(&[0xB0, 64, 127]);
+        assert_eq!(
+            step.midi_msgs(),
+            (0..12)
+                .map(|i| hold(Channel::from_u8(i), 127))
+                .collect::<Vec<_>>()
+        );
+
+        // releasing a key while the pedal is held sends the note off, the synthesizer will keep
+        // the note sounding
+        harness.advance(Duration::from_millis(100));
+        let step = harness.midi(&[0x80, 64, 0]);
+        assert_eq!(
+            step.midi_msgs(),
+            vec![voice(
+                Channel::Ch5,
+                ChannelVoiceMsg::NoteOff {
+                    note: 64,
+                    velocity: 0
+                }
+            )]
+        );
+        assert!(step.ui.iter().any(|msg| matches!(
+            msg,
+            ToUi::NoteOff {
+                note: 64,
+                channel: Channel::Ch1,
+                ..
+            }
+        )));
+
+        harness.advance(Duration::from_millis(100));
+        let step = harness.midi(&[0xB0, 64, 0]);
+        assert_eq!(
+            step.midi_msgs(),
+            (0..12)
+                .map(|i| hold(Channel::from_u8(i), 0))
+                .collect::<Vec<_>>()
+        );
+
+        // nothing was left over
+        assert!(harness.settle().midi.is_empty());
+
+        harness.stop();
+    }
+
+    #[test]
+    fn test_strategy_switching() {
+        let mut harness = harness();
+
+        harness.midi(&[0x90, 60, 100]);
+        let step = harness.midi(&[0x90, 64, 100]);
+        assert!(step.midi_msgs().contains(&voice(
+            Channel::Ch5,
+            ChannelVoiceMsg::PitchBend {
+                bend: bend(PURE_THIRD)
+            }
+        )));
+
+        // the held E is retuned to the Pythagorean third
+        harness.advance(Duration::from_millis(100));
+        let time = harness.now();
+        let step = harness.ui(FromUi::StrategyListAction {
+            action: ListAction::Select(1),
+            time,
+        });
+        assert!(step.midi_msgs().contains(&voice(
+            Channel::Ch5,
+            ChannelVoiceMsg::PitchBend {
+                bend: bend(PYTHAGOREAN_THIRD)
+            }
+        )));
+        assert!(step
+            .ui
+            .iter()
+            .any(|msg| matches!(msg, ToUi::CurrentStrategyIndex(Some(1)))));
+
+        // new notes use the new strategy, too
+        harness.midi(&[0x80, 64, 0]);
+        let step = harness.midi(&[0x90, 76, 100]);
+        assert!(step.midi_msgs().contains(&voice(
+            Channel::Ch5,
+            ChannelVoiceMsg::NoteOn {
+                note: 76,
+                velocity: 100
+            }
+        )));
+
+        let (process_config, _) = harness.stop();
+        assert_eq!(process_config.strategies.len(), 2);
+    }
+
+    #[test]
+    fn test_unused_controller_mapping() {
+        // static tunings have no group duration, the mapped controller must be ignored
+        let bindings = Bindings::empty().with_controllers(vec![ControllerMapping {
+            channel: None {},
+            controller: 20,
+            high_resolution: false,
+            parameter: MappedParameter::GroupMs,
+            min: 0.0,
+            max: 1000.0,
+        }]);
+        let mut harness = Harness::new(
+            ProcessConfig {
+                strategies: vec![(static_tuning(false), bindings)],
+                zones: vec![],
+            },
+            BackendConfig::Pitchbend12(Pitchbend12Config {
+                bend_range: 2.0,
+                channels: core::array::from_fn(|i| Channel::from_u8(i as u8).into()),
+                glide: None {},
+            }),
+        );
+        harness.settle();
+
+        harness.midi(&[0xB0, 20, 64]);
+        harness.advance(Duration::from_millis(100));
+        let step = harness.midi(&[0x90, 64, 100]);
+        assert!(step.midi_msgs().contains(&voice(
+            Channel::Ch5,
+            ChannelVoiceMsg::PitchBend {
+                bend: bend(PURE_THIRD)
+            }
+        )));
+
+        harness.stop();
+    }
+
+    /// The pitch bends in `step` on `channel`.
+    fn bends(step: &Step<MockFiveLimitStackType>, channel: Channel) -> Vec<u16> {
+        step.midi_msgs()
+            .iter()
+            .filter_map(|msg| match msg {
+                MidiMsg::ChannelVoice {
+                    channel: c,
+                    msg: ChannelVoiceMsg::PitchBend { bend },
+                } if *c == channel => Some(*bend),
+                _ => None {},
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_strategy_ticks() {
+        // The program change switches to a neighbourhood where C is a syntonic comma higher.
+        // Re-tuning the sounding C is held back for 100ms.
+        let mut raised = TWELVE_NOTES;
+        raised[0] = [-2, 4, -1];
+        let strategy = StrategyConfig::Hysteresis(HysteresisConfig {
+            inner: Box::new(StrategyConfig::StaticTuning(static_tuning_config(&[
+                TWELVE_NOTES,
+                raised,
+            ]))),
+            stable_ms: 100,
+            max_retune_cents: None {},
+            freeze: false,
+        });
+        let mut bindings = Bindings::empty();
+        bindings.insert(
+            MidiBindable::ProgramChange {
+                channel: None {},
+                program: 1,
+            },
+            StrategyAction::SwitchToNeighbourhood(1),
+        );
+        let mut harness = Harness::new(
+            ProcessConfig {
+                strategies: vec![(strategy, bindings)],
+                zones: vec![],
+            },
+            BackendConfig::Pitchbend12(Pitchbend12Config {
+                bend_range: 2.0,
+                channels: core::array::from_fn(|i| Channel::from_u8(i as u8).into()),
+                glide: None {},
+            }),
+        );
+        harness.settle();
+
+        harness.midi(&[0x90, 60, 100]);
+        harness.advance(Duration::from_millis(100));
+        let step = harness.midi(&[0xC0, 1]);
+        assert_eq!(bends(&step, Channel::Ch1), vec![]);
+
+        // no matter how long the test takes, the tick only comes when the clock passes it
+        std::thread::sleep(Duration::from_millis(150));
+        assert_eq!(bends(&harness.settle(), Channel::Ch1), vec![]);
+        let step = harness.advance(Duration::from_millis(99));
+        assert_eq!(bends(&step, Channel::Ch1), vec![]);
+        let step = harness.advance(Duration::from_millis(2));
+        assert_eq!(bends(&step, Channel::Ch1), vec![bend(SYNTONIC_COMMA)]);
+
+        harness.stop();
+    }
+
+    #[test]
+    fn test_glide() {
+        let mut harness = Harness::new(
+            ProcessConfig {
+                strategies: vec![
+                    (static_tuning(false), Bindings::empty()),
+                    (static_tuning(true), Bindings::empty()),
+                ],
+                zones: vec![],
+            },
+            BackendConfig::Pitchbend12(Pitchbend12Config {
+                bend_range: 2.0,
+                channels: core::array::from_fn(|i| Channel::from_u8(i as u8).into()),
+                glide: Some(GlideConfig {
+                    duration_ms: 20,
+                    curve: GlideCurve::Linear,
+                }),
+            }),
+        );
+        harness.settle();
+
+        harness.midi(&[0x90, 64, 100]);
+        harness.advance(Duration::from_millis(100));
+        let time = harness.now();
+        let step = harness.ui(FromUi::StrategyListAction {
+            action: ListAction::Select(1),
+            time,
+        });
+        assert_eq!(bends(&step, Channel::Ch5), vec![]);
+
+        // the E glides to the Pythagorean third in steps of 5ms
+        let step = harness.advance(Duration::from_millis(12));
+        let first = bends(&step, Channel::Ch5);
+        assert_eq!(first.len(), 2);
+        let step = harness.advance(Duration::from_millis(100));
+        let rest = bends(&step, Channel::Ch5);
+        assert_eq!(rest.len(), 2);
+        assert!(first
+            .iter()
+            .chain(&rest)
+            .collect::<Vec<_>>()
+            .windows(2)
+            .all(|w| w[0] < w[1]));
+        assert_eq!(rest.last(), Some(&bend(PYTHAGOREAN_THIRD)));
+
+        assert!(harness.advance(Duration::from_millis(100)).midi.is_empty());
+
+        harness.stop();
+    }
+
+    /// The left zone plays on the first twelve channels, the right zone on an MPE zone with the
+    /// master channel 16 and the member channels 15, 14, and 13.
+    fn zoned_harness(share_reference: bool) -> Harness<MockFiveLimitStackType> {
+        let zone = |name: &str, lowest, highest, strategy| ZoneConfig {
+            name: name.into(),
+            lowest,
+            highest,
+            channels: vec![],
+            strategy,
+            share_reference,
+        };
+        let mut harness = Harness::new(
+            ProcessConfig {
+                strategies: vec![
+                    (static_tuning(false), Bindings::empty()),
+                    (static_tuning(true), Bindings::empty()),
+                ],
+                zones: vec![zone("left", 0, 59, 1), zone("right", 60, 127, 0)],
+            },
+            BackendConfig::Zoned(vec![
+                BackendConfig::Pitchbend12(Pitchbend12Config {
+                    bend_range: 2.0,
+                    channels: core::array::from_fn(|i| Channel::from_u8(i as u8).into()),
+                    glide: None {},
+                }),
+                BackendConfig::Mpe(MpeConfig {
+                    zone: MpeZone::Upper,
+                    member_channels: 3,
+                    bend_range: 2,
+                }),
+            ]),
+        );
+        harness.settle();
+        harness
+    }
+
+    #[test]
+    fn test_zones() {
+        let mut harness = zoned_harness(false);
+
+        // the left zone uses the Pythagorean third and the first twelve channels
+        let step = harness.midi(&[0x90, 52, 100]);
+        assert_eq!(
+            step.midi_msgs(),
+            vec![
+                voice(
+                    Channel::Ch5,
+                    ChannelVoiceMsg::NoteOn {
+                        note: 52,
+                        velocity: 100
+                    }
+                ),
+                voice(
+                    Channel::Ch5,
+                    ChannelVoiceMsg::PitchBend {
+                        bend: bend(PYTHAGOREAN_THIRD)
+                    }
+                ),
+            ]
+        );
+
+        // the right zone uses the pure third and the first member channel of the MPE zone
+        harness.advance(Duration::from_millis(100));
+        let step = harness.midi(&[0x90, 64, 100]);
+        assert_eq!(
+            step.midi_msgs(),
+            vec![
+                voice(
+                    Channel::Ch15,
+                    ChannelVoiceMsg::PitchBend {
+                        bend: bend(PURE_THIRD)
+                    }
+                ),
+                voice(
+                    Channel::Ch15,
+                    ChannelVoiceMsg::NoteOn {
+                        note: 64,
+                        velocity: 100
+                    }
+                ),
+            ]
+        );
+
+        // the pedal goes to the channels of both zones
+        harness.advance(Duration::from_millis(100));
+        let step = harness.midi(&[0xB0, 64, 127]);
+        let mut channels: Vec<_> = step
+            .midi_msgs()
+            .iter()
+            .map(|msg| match msg {
+                MidiMsg::ChannelVoice { channel, .. } => *channel as u8,
+                _ => panic!("unexpected {msg:?}"),
+            })
+            .collect();
+        channels.sort();
+        assert_eq!(channels, (0..12).chain([15]).collect::<Vec<u8>>());
+
+        let (process_config, backend_config) = harness.stop();
+        assert_eq!(process_config.zones.len(), 2);
+        assert!(matches!(backend_config, BackendConfig::Zoned(ref b) if b.len() == 2));
+    }
+
+    #[test]
+    fn test_zones_sharing_reference() {
+        for share_reference in [false, true] {
+            let mut harness = zoned_harness(share_reference);
+
+            // setting the reference of the first zone to D makes E a Pythagorean major third
+            // above C in the zones that share it
+            let time = harness.now();
+            harness.ui(FromUi::SetReference {
+                reference: Stack::from_target(vec![-1, 2, 0]),
+                time,
+            });
+            harness.advance(Duration::from_millis(100));
+            let step = harness.midi(&[0x90, 64, 100]);
+            let third = if share_reference {
+                PYTHAGOREAN_THIRD
+            } else {
+                PURE_THIRD
+            };
+            assert!(step.midi_msgs().contains(&voice(
+                Channel::Ch15,
+                ChannelVoiceMsg::PitchBend { bend: bend(third) }
+            )));
+
+            harness.stop();
+        }
+    }
+}
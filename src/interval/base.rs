@@ -1,5 +1,11 @@
+use num_rational::Ratio;
 use serde_derive::{Deserialize, Serialize};
 
+use crate::{
+    custom_serde::common::{deserialize_ratio, serialize_ratio},
+    interval::stacktype::r#trait::StackCoeff,
+};
+
 /// The type of interval sizes measured in equally tempered semitones
 pub type Semitones = f64;
 
@@ -13,4 +19,45 @@ pub struct Interval {
     pub semitones: Semitones,
     /// The difference of the MIDI key numbers of the upper and lower note in the interval
     pub key_distance: u8,
+    /// The frequency ratio of the interval.
+    #[serde(
+        serialize_with = "serialize_ratio",
+        deserialize_with = "deserialize_ratio"
+    )]
+    pub ratio: Ratio<StackCoeff>,
+}
+
+/// The definition of a "base" [Interval] in the `intervals` section of a configuration file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct IntervalDefinition {
+    pub name: String,
+    /// The frequency ratio of the interval, like `3/2` for the fifth.
+    #[serde(
+        serialize_with = "serialize_ratio",
+        deserialize_with = "deserialize_ratio"
+    )]
+    pub ratio: Ratio<StackCoeff>,
+    pub key_distance: u8,
+    /// Is this the "period" interval? See
+    /// [IntervalBasis::try_period][crate::interval::stacktype::r#trait::IntervalBasis::try_period].
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub period: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+impl IntervalDefinition {
+    pub fn realize(&self) -> Interval {
+        Interval {
+            name: self.name.clone(),
+            semitones: 12.0
+                * (*self.ratio.numer() as Semitones / *self.ratio.denom() as Semitones).log2(),
+            key_distance: self.key_distance,
+            ratio: self.ratio,
+        }
+    }
 }
@@ -260,6 +260,21 @@ impl<T: StackType> Stack<T> {
         let temperament = &T::temperaments()[temperament_index];
         temperament.add_adjustment(self.target.view(), self.actual.view_mut());
     }
+
+    /// Like [Self::apply_temperament], but only add `amount` times the adjustment.
+    pub fn apply_temperament_partially(
+        &mut self,
+        temperament_index: usize,
+        amount: Ratio<StackCoeff>,
+    ) {
+        let mut adjustment = Array1::from_elem(self.target.len(), Ratio::zero());
+        let temperament = &T::temperaments()[temperament_index];
+        temperament.add_adjustment(self.target.view(), adjustment.view_mut());
+        self.actual.zip_mut_with(&self.target, |l, r| {
+            *l = Ratio::from_integer(*r);
+        });
+        self.actual.scaled_add(amount, &adjustment);
+    }
 }
 
 #[cfg(test)]
@@ -345,6 +360,43 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_apply_temperament_partially() {
+        let fifth = 12.0 * (3.0 / 2.0 as Semitones).log2();
+        let third = 12.0 * (5.0 / 4.0 as Semitones).log2();
+        let quarter_comma_down = 12.0 * (80.0 / 81.0 as Semitones).log2() / 4.0;
+        let eps = 0.00000000001;
+
+        // the 12-EDO adjustment of the fifth is replaced by half of the meantone one
+        let mut s =
+            Stack::<MockStackType>::from_temperaments_and_target(&[true, false], vec![0, 1, 0]);
+        s.apply_temperament_partially(1, Ratio::new(1, 2));
+        assert_relative_eq!(
+            s.semitones(),
+            fifth + quarter_comma_down / 2.0,
+            max_relative = eps
+        );
+        assert_eq!(s.target, Array1::from(vec![0, 1, 0]));
+
+        s.apply_temperament_partially(1, Ratio::zero());
+        assert!(s.is_target());
+        assert_relative_eq!(s.semitones(), fifth, max_relative = eps);
+
+        let mut full = s.clone();
+        full.apply_temperament(1);
+        s.apply_temperament_partially(1, Ratio::from_integer(1));
+        assert_eq!(s, full);
+
+        let mut s =
+            Stack::<MockStackType>::from_temperaments_and_target(&[false, false], vec![0, 0, 1]);
+        s.apply_temperament_partially(0, Ratio::new(1, 4));
+        assert_relative_eq!(
+            s.semitones(),
+            third + (4.0 - third) / 4.0,
+            max_relative = eps
+        );
+    }
+
     #[test]
     fn test_rollovers() {
         let octave = 12.0;
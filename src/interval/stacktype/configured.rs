@@ -0,0 +1,348 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Deref,
+    sync::{OnceLock, RwLock},
+};
+
+use ndarray::Array2;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    interval::{
+        base::{Interval, IntervalDefinition},
+        stacktype::r#trait::{
+            IntervalBasis, OctavePeriodicIntervalBasis, PeriodicIntervalBasis, StackCoeff,
+            StackType,
+        },
+        temperament::{Temperament, TemperamentDefinition},
+    },
+    notename::johnston::extended::{JohnstonCoordinates, PRIME_COORDINATES},
+    util::subsequences::Subsequences,
+};
+
+use super::r#trait::{
+    CoordinateSystem, NamedInterval, OctavePeriodicStackType, PeriodicStackType, Reloadable,
+    StackTypeInitialisationErr,
+};
+
+/// A [StackType] whose [IntervalBasis::intervals] are read from the `intervals` section of the
+/// configuration file, using [Reloadable::initialise_intervals].
+///
+/// The intervals can only be set once: Changing them at runtime would invalidate every [Stack]
+/// in use. Since this type is [OctavePeriodicStackType], the period must be an octave. Note names
+/// use Johnston's notation, so the frequency ratios must not have prime factors greater than 13.
+///
+/// [Stack]: crate::interval::stack::Stack
+#[derive(Hash, PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct TheConfiguredStackType {}
+
+struct Basis {
+    definitions: Vec<IntervalDefinition>,
+    intervals: Vec<Interval>,
+    positions: HashMap<String, usize>,
+    period_index: usize,
+    johnston_coordinates: Vec<JohnstonCoordinates>,
+}
+
+impl Basis {
+    fn new(definitions: &[IntervalDefinition]) -> Result<Self, String> {
+        if definitions.is_empty() {
+            return Err("there must be at least one interval".into());
+        }
+
+        let mut positions = HashMap::with_capacity(definitions.len());
+        let mut period_index = None {};
+        let mut johnston_coordinates = Vec::with_capacity(definitions.len());
+        for (i, def) in definitions.iter().enumerate() {
+            if positions.insert(def.name.clone(), i).is_some() {
+                return Err(format!("duplicate interval name '{}'", def.name));
+            }
+            if *def.ratio.numer() <= 0 || *def.ratio.denom() <= 0 {
+                return Err(format!("the ratio of '{}' must be positive", def.name));
+            }
+            if def.period {
+                if period_index.is_some() {
+                    return Err("only one interval can be the period".into());
+                }
+                if def.ratio != 2.into() || def.key_distance != 12 {
+                    return Err(format!(
+                        "the period '{}' must be an octave, with ratio 2 and key distance 12",
+                        def.name
+                    ));
+                }
+                period_index = Some(i);
+            }
+            johnston_coordinates.push(johnston_coordinates_of_ratio(def).ok_or(format!(
+                "the ratio of '{}' has prime factors greater than 13",
+                def.name
+            ))?);
+        }
+
+        Ok(Self {
+            definitions: definitions.to_vec(),
+            intervals: definitions.iter().map(|def| def.realize()).collect(),
+            positions,
+            period_index: period_index.ok_or("one interval must be marked as the period")?,
+            johnston_coordinates,
+        })
+    }
+}
+
+/// Returns `None` if the ratio has prime factors greater than 13.
+fn johnston_coordinates_of_ratio(def: &IntervalDefinition) -> Option<JohnstonCoordinates> {
+    let mut res = [0; 6];
+    for (n, sign) in [(*def.ratio.numer(), 1), (*def.ratio.denom(), -1)] {
+        let mut n = n;
+        for (p, coordinates) in PRIME_COORDINATES {
+            while n % p == 0 {
+                n /= p;
+                for (r, c) in res.iter_mut().zip(coordinates) {
+                    *r += sign * c;
+                }
+            }
+        }
+        if n != 1 {
+            return None {};
+        }
+    }
+    Some(res)
+}
+
+static BASIS: OnceLock<Basis> = OnceLock::new();
+
+static NAMED_INTERVALS: RwLock<Vec<NamedInterval<TheConfiguredStackType>>> = RwLock::new(vec![]);
+
+static COORDINATE_SYSTEMS: RwLock<BTreeMap<usize, (Vec<usize>, CoordinateSystem)>> =
+    RwLock::new(BTreeMap::new());
+
+static TEMPERAMENTS: RwLock<Vec<Temperament<StackCoeff>>> = RwLock::new(vec![]);
+
+static TEMPERAMENT_DEFINITIONS: RwLock<Vec<TemperamentDefinition<TheConfiguredStackType>>> =
+    RwLock::new(vec![]);
+
+fn basis() -> &'static Basis {
+    BASIS
+        .get()
+        .expect("the intervals of TheConfiguredStackType have not been initialised")
+}
+
+/// The key of a set of indices into the named intervals in the [COORDINATE_SYSTEMS].
+fn coordinate_system_key(sorted_indices: &[usize], n: usize) -> usize {
+    sorted_indices.iter().rev().fold(0, |acc, i| acc * n + i)
+}
+
+impl TheConfiguredStackType {
+    /// Sum up the [JohnstonCoordinates] of the intervals, with the given multiplicities.
+    pub fn johnston_coordinates(coeffs: impl Iterator<Item = StackCoeff>) -> JohnstonCoordinates {
+        let mut res = [0; 6];
+        for (c, coordinates) in coeffs.zip(basis().johnston_coordinates.iter()) {
+            for (r, x) in res.iter_mut().zip(coordinates) {
+                *r += c * x;
+            }
+        }
+        res
+    }
+}
+
+impl Reloadable for TheConfiguredStackType {
+    fn initialise_intervals(
+        definitions: Option<&[IntervalDefinition]>,
+    ) -> Result<(), StackTypeInitialisationErr> {
+        let definitions = definitions.ok_or(StackTypeInitialisationErr::MissingIntervalBasis)?;
+        if let Some(basis) = BASIS.get() {
+            return if basis.definitions == definitions {
+                Ok(())
+            } else {
+                Err(StackTypeInitialisationErr::IntervalBasisChanged)
+            };
+        }
+        let new_basis =
+            Basis::new(definitions).map_err(StackTypeInitialisationErr::InvalidIntervalBasis)?;
+        let _ = BASIS.set(new_basis);
+        Ok(())
+    }
+
+    fn interval_definitions() -> Option<Vec<IntervalDefinition>> {
+        BASIS.get().map(|basis| basis.definitions.clone())
+    }
+
+    fn initialise(
+        temperament_definitions: Vec<TemperamentDefinition<TheConfiguredStackType>>,
+        named_intervals: Vec<NamedInterval<TheConfiguredStackType>>,
+    ) -> Result<(), StackTypeInitialisationErr> {
+        {
+            let mut t = TEMPERAMENTS.write().unwrap();
+            t.clear();
+            for def in temperament_definitions.iter() {
+                t.push(
+                    def.realize()
+                        .map_err(StackTypeInitialisationErr::FromTemperamentErr)?,
+                );
+            }
+        }
+
+        {
+            let systems = &mut *COORDINATE_SYSTEMS.write().unwrap();
+            systems.clear();
+            let d = Self::num_intervals();
+            let n = named_intervals.len();
+            let indices: Vec<usize> = (0..n).collect();
+            let mut subsequences = Subsequences::new(&indices, d);
+            while let Some(basis_indices) = subsequences.next() {
+                let mut basis_columnwise = Array2::zeros((d, d));
+                for (col, i) in basis_indices.iter().enumerate() {
+                    basis_columnwise
+                        .column_mut(col)
+                        .assign(&named_intervals[*i].coeffs);
+                }
+                let _ = CoordinateSystem::new(basis_columnwise).map(|x| {
+                    systems.insert(
+                        coordinate_system_key(basis_indices, n),
+                        (basis_indices.to_vec(), x),
+                    );
+                });
+            }
+        }
+
+        *TEMPERAMENT_DEFINITIONS.write().unwrap() = temperament_definitions;
+        *NAMED_INTERVALS.write().unwrap() = named_intervals;
+
+        Ok(())
+    }
+}
+
+impl IntervalBasis for TheConfiguredStackType {
+    fn intervals() -> &'static [Interval] {
+        &basis().intervals
+    }
+
+    fn try_period_index() -> Option<usize> {
+        Some(basis().period_index)
+    }
+
+    fn interval_positions() -> &'static HashMap<String, usize> {
+        &basis().positions
+    }
+}
+
+impl StackType for TheConfiguredStackType {
+    fn temperaments() -> impl Deref<Target = Vec<Temperament<StackCoeff>>> {
+        TEMPERAMENTS.read().unwrap()
+    }
+
+    fn temperament_definitions() -> impl Deref<Target = Vec<TemperamentDefinition<Self>>> {
+        TEMPERAMENT_DEFINITIONS.read().unwrap()
+    }
+
+    fn named_intervals() -> impl Deref<Target = Vec<NamedInterval<Self>>> {
+        NAMED_INTERVALS.read().unwrap()
+    }
+
+    fn with_coordinate_system<R>(
+        basis_indices: &[usize],
+        mut f: impl FnMut(Option<&(Vec<usize>, CoordinateSystem)>) -> R,
+    ) -> R {
+        let mut sorted = basis_indices.to_vec();
+        sorted.sort_unstable();
+        let n = Self::named_intervals().len();
+
+        let cs = &*COORDINATE_SYSTEMS.read().unwrap();
+        f(cs.get(&coordinate_system_key(&sorted, n)))
+    }
+}
+
+impl PeriodicIntervalBasis for TheConfiguredStackType {}
+
+impl PeriodicStackType for TheConfiguredStackType {}
+
+impl OctavePeriodicIntervalBasis for TheConfiguredStackType {}
+
+impl OctavePeriodicStackType for TheConfiguredStackType {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn definitions(yaml: &str) -> Vec<IntervalDefinition> {
+        serde_yml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_basis() {
+        let basis = Basis::new(&definitions(
+            r#"
+- name: octave
+  ratio: 2
+  key-distance: 12
+  period: true
+- name: fifth
+  ratio: 3/2
+  key-distance: 7
+- name: eleventh harmonic
+  ratio: 11/8
+  key-distance: 6
+"#,
+        ))
+        .unwrap();
+
+        assert_eq!(basis.period_index, 0);
+        assert_eq!(basis.positions.get("eleventh harmonic"), Some(&2));
+        assert_eq!(
+            basis.johnston_coordinates,
+            vec![[1, 0, 0, 0, 0, 0], [0, 1, 0, 0, 0, 0], [1, -1, 0, 0, 1, 0]]
+        );
+        approx::assert_relative_eq!(basis.intervals[2].semitones, 5.513179423647566);
+    }
+
+    #[test]
+    fn test_basis_errors() {
+        let test_error = |yaml, expected: &str| match Basis::new(&definitions(yaml)) {
+            Ok(_) => panic!("expected the error '{expected}'"),
+            Err(e) => assert_eq!(e, expected),
+        };
+
+        test_error("[]", "there must be at least one interval");
+        test_error(
+            r#"
+- name: fifth
+  ratio: 3/2
+  key-distance: 7
+"#,
+            "one interval must be marked as the period",
+        );
+        test_error(
+            r#"
+- name: tritave
+  ratio: 3
+  key-distance: 19
+  period: true
+"#,
+            "the period 'tritave' must be an octave, with ratio 2 and key distance 12",
+        );
+        test_error(
+            r#"
+- name: octave
+  ratio: 2
+  key-distance: 12
+  period: true
+- name: octave
+  ratio: 3/2
+  key-distance: 7
+"#,
+            "duplicate interval name 'octave'",
+        );
+        test_error(
+            r#"
+- name: octave
+  ratio: 2
+  key-distance: 12
+  period: true
+- name: seventeenth harmonic
+  ratio: 17/16
+  key-distance: 1
+"#,
+            "the ratio of 'seventeenth harmonic' has prime factors greater than 13",
+        );
+    }
+}
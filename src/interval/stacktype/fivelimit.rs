@@ -5,6 +5,7 @@ use std::{
 };
 
 use ndarray::Array2;
+use num_rational::Ratio;
 use serde_derive::{Deserialize, Serialize};
 
 use crate::interval::{
@@ -30,16 +31,19 @@ static INTERVALS: LazyLock<[Interval; 3]> = LazyLock::new(|| {
             name: "octave".into(),
             semitones: 12.0,
             key_distance: 12,
+            ratio: Ratio::from_integer(2),
         },
         Interval {
             name: "fifth".into(),
             semitones: 12.0 * (3.0 / 2.0 as Semitones).log2(),
             key_distance: 7,
+            ratio: Ratio::new(3, 2),
         },
         Interval {
             name: "third".into(),
             semitones: 12.0 * (5.0 / 4.0 as Semitones).log2(),
             key_distance: 4,
+            ratio: Ratio::new(5, 4),
         },
     ]
 });
@@ -194,6 +198,17 @@ pub mod mock {
         },
         temperament::Temperament,
     };
+    use crate::{
+        neighbourhood::{
+            Neighbourhood, PeriodicComplete, PeriodicPartial, SomeCompleteNeighbourhood,
+            SomeNeighbourhood,
+        },
+        reference::Reference,
+        strategy::{
+            r#static::StaticTuningConfig,
+            twostep::harmony::chordlist::{keyshape::KeyShape, PatternConfig},
+        },
+    };
 
     use super::*;
 
@@ -360,6 +375,69 @@ pub mod mock {
             b.increment_at_index_pure(Self::third_index(), exponents[2]);
         }
     }
+
+    /// The tunings of the twelve pitch classes from C upwards used in the tests of the
+    /// strategies: C# D Eb E F F# G Ab A Bb B are 25:24, 9:8, 6:5, 5:4, 4:3, 45:32, 3:2, 8:5, 5:3,
+    /// 16:9, and 15:8 above C.
+    pub const TWELVE_NOTES: [[StackCoeff; 3]; 12] = [
+        [0, 0, 0],
+        [0, -1, 2],
+        [-1, 2, 0],
+        [0, 1, -1],
+        [0, 0, 1],
+        [1, -1, 0],
+        [-1, 2, 1],
+        [0, 1, 0],
+        [1, 0, -1],
+        [1, -1, 1],
+        [2, -2, 0],
+        [0, 1, 1],
+    ];
+
+    /// A complete neighbourhood with the given tunings of the twelve pitch classes.
+    pub fn neighbourhood(
+        notes: [[StackCoeff; 3]; 12],
+    ) -> SomeCompleteNeighbourhood<MockFiveLimitStackType> {
+        PeriodicComplete::new_periodic(
+            notes
+                .iter()
+                .map(|s| Stack::from_target(s.to_vec()))
+                .collect(),
+        )
+        .into()
+    }
+
+    /// A static tuning that can switch between neighbourhoods with the given tunings, and starts
+    /// with the first one. C is the reference, and middle C sounds at 60 semitones.
+    pub fn static_tuning_config(
+        neighbourhoods: &[[[StackCoeff; 3]; 12]],
+    ) -> StaticTuningConfig<MockFiveLimitStackType> {
+        StaticTuningConfig {
+            neighbourhoods: neighbourhoods.iter().map(|n| neighbourhood(*n)).collect(),
+            tuning_reference: Reference::from_semitones(Stack::new_zero(), 60.0),
+            reference: Stack::new_zero(),
+        }
+    }
+
+    /// The chord pattern of a triad with the given third above the root and a fifth.
+    pub fn triad(third: [StackCoeff; 3]) -> PatternConfig<MockFiveLimitStackType> {
+        let mut neighbourhood = PeriodicPartial::new_from_period_index(0);
+        for stack in [[0, 0, 0], third, [0, 1, 0]] {
+            neighbourhood.insert(&Stack::from_target(stack.to_vec()));
+        }
+        PatternConfig {
+            key_shape: KeyShape::ClassesRelative {
+                classes: vec![
+                    0,
+                    Stack::<MockFiveLimitStackType>::from_target(third.to_vec()).key_distance()
+                        as u8,
+                    7,
+                ],
+            },
+            neighbourhood: SomeNeighbourhood::PeriodicPartial(neighbourhood),
+            allow_extra_high_notes: false,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1,2 +1,4 @@
 pub mod r#trait;
 pub mod fivelimit;
+pub mod configured;
+pub mod sevenlimit;
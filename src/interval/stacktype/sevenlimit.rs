@@ -0,0 +1,247 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Deref,
+    sync::{LazyLock, RwLock},
+};
+
+use ndarray::Array2;
+use num_rational::Ratio;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::interval::{
+    base::{Interval, Semitones},
+    stacktype::r#trait::{
+        FiveLimitIntervalBasis, IntervalBasis, OctavePeriodicIntervalBasis, PeriodicIntervalBasis,
+        StackCoeff, StackType,
+    },
+    temperament::{Temperament, TemperamentDefinition},
+};
+
+use super::r#trait::{
+    CoordinateSystem, FiveLimitStackType, IntervalBasisName, NamedInterval,
+    OctavePeriodicStackType, PeriodicStackType, Reloadable, StackTypeInitialisationErr,
+};
+
+/// Like [TheFiveLimitStackType][super::fivelimit::TheFiveLimitStackType], but with the septimal
+/// seventh (7:4) as a fourth basis interval.
+#[derive(Hash, PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct TheSevenLimitStackType {}
+
+impl TheSevenLimitStackType {
+    pub fn seventh_index() -> usize {
+        3
+    }
+}
+
+static INTERVALS: LazyLock<[Interval; 4]> = LazyLock::new(|| {
+    [
+        Interval {
+            name: "octave".into(),
+            semitones: 12.0,
+            key_distance: 12,
+            ratio: Ratio::from_integer(2),
+        },
+        Interval {
+            name: "fifth".into(),
+            semitones: 12.0 * (3.0 / 2.0 as Semitones).log2(),
+            key_distance: 7,
+            ratio: Ratio::new(3, 2),
+        },
+        Interval {
+            name: "third".into(),
+            semitones: 12.0 * (5.0 / 4.0 as Semitones).log2(),
+            key_distance: 4,
+            ratio: Ratio::new(5, 4),
+        },
+        Interval {
+            name: "seventh".into(),
+            semitones: 12.0 * (7.0 / 4.0 as Semitones).log2(),
+            key_distance: 10,
+            ratio: Ratio::new(7, 4),
+        },
+    ]
+});
+
+static INTERVAL_POSITIONS: LazyLock<HashMap<String, usize>> = LazyLock::new(|| {
+    let mut m = HashMap::with_capacity(4);
+    m.insert("octave".into(), 0);
+    m.insert("fifth".into(), 1);
+    m.insert("third".into(), 2);
+    m.insert("seventh".into(), 3);
+    m
+});
+
+static NAMED_INTERVALS: RwLock<Vec<NamedInterval<TheSevenLimitStackType>>> = RwLock::new(vec![]);
+
+static COORDINATE_SYSTEMS: RwLock<BTreeMap<usize, (Vec<usize>, CoordinateSystem)>> =
+    RwLock::new(BTreeMap::new());
+
+static TEMPERAMENTS: RwLock<Vec<Temperament<StackCoeff>>> = RwLock::new(vec![]);
+
+static TEMPERAMENT_DEFINITIONS: RwLock<Vec<TemperamentDefinition<TheSevenLimitStackType>>> =
+    RwLock::new(vec![]);
+
+impl Reloadable for TheSevenLimitStackType {
+    fn interval_basis_name() -> Option<IntervalBasisName> {
+        Some(IntervalBasisName::SevenLimit)
+    }
+
+    fn initialise(
+        temperament_definitions: Vec<TemperamentDefinition<TheSevenLimitStackType>>,
+        named_intervals: Vec<NamedInterval<TheSevenLimitStackType>>,
+    ) -> Result<(), StackTypeInitialisationErr> {
+        {
+            let mut t = TEMPERAMENTS.write().unwrap();
+            t.clear();
+            for def in temperament_definitions.iter() {
+                t.push(
+                    def.realize()
+                        .map_err(StackTypeInitialisationErr::FromTemperamentErr)?,
+                );
+            }
+        }
+
+        {
+            let systems = &mut *COORDINATE_SYSTEMS.write().unwrap();
+            systems.clear();
+            let n = named_intervals.len();
+            for i in 0..n {
+                for j in (i + 1)..n {
+                    for k in (j + 1)..n {
+                        for l in (k + 1)..n {
+                            let mut basis_columnwise = Array2::zeros((4, 4));
+                            for (col, ix) in [i, j, k, l].iter().enumerate() {
+                                basis_columnwise
+                                    .column_mut(col)
+                                    .assign(&named_intervals[*ix].coeffs);
+                            }
+                            let _ = CoordinateSystem::new(basis_columnwise).map(|x| {
+                                systems.insert(
+                                    i + j * n + k * n * n + l * n * n * n,
+                                    (vec![i, j, k, l], x),
+                                );
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        *TEMPERAMENT_DEFINITIONS.write().unwrap() = temperament_definitions;
+        *NAMED_INTERVALS.write().unwrap() = named_intervals;
+
+        Ok(())
+    }
+}
+
+impl IntervalBasis for TheSevenLimitStackType {
+    fn intervals() -> &'static [Interval] {
+        &*INTERVALS
+    }
+
+    fn try_period_index() -> Option<usize> {
+        Some(0)
+    }
+
+    fn interval_positions() -> &'static HashMap<String, usize> {
+        &INTERVAL_POSITIONS
+    }
+}
+
+impl StackType for TheSevenLimitStackType {
+    fn temperaments() -> impl Deref<Target = Vec<Temperament<StackCoeff>>> {
+        TEMPERAMENTS.read().unwrap()
+    }
+
+    fn temperament_definitions() -> impl Deref<Target = Vec<TemperamentDefinition<Self>>> {
+        TEMPERAMENT_DEFINITIONS.read().unwrap()
+    }
+
+    fn named_intervals() -> impl Deref<Target = Vec<NamedInterval<Self>>> {
+        NAMED_INTERVALS.read().unwrap()
+    }
+
+    fn with_coordinate_system<R>(
+        basis_indices: &[usize],
+        mut f: impl FnMut(Option<&(Vec<usize>, CoordinateSystem)>) -> R,
+    ) -> R {
+        let mut sorted = [
+            basis_indices[0],
+            basis_indices[1],
+            basis_indices[2],
+            basis_indices[3],
+        ];
+        sorted.sort_unstable();
+        let [i, j, k, l] = sorted;
+        let n = Self::named_intervals().len();
+
+        let cs = &*COORDINATE_SYSTEMS.read().unwrap();
+        f(cs.get(&(i + j * n + k * n * n + l * n * n * n)))
+    }
+}
+
+impl FiveLimitIntervalBasis for TheSevenLimitStackType {
+    fn octave_index() -> usize {
+        0
+    }
+
+    fn fifth_index() -> usize {
+        1
+    }
+
+    fn third_index() -> usize {
+        2
+    }
+}
+
+impl FiveLimitStackType for TheSevenLimitStackType {}
+
+impl PeriodicIntervalBasis for TheSevenLimitStackType {
+    fn period_index() -> usize {
+        0
+    }
+}
+
+impl PeriodicStackType for TheSevenLimitStackType {}
+
+impl OctavePeriodicIntervalBasis for TheSevenLimitStackType {}
+
+impl OctavePeriodicStackType for TheSevenLimitStackType {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interval::stack::Stack;
+    use approx::assert_relative_eq;
+    use ndarray::arr1;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_seventh_semitones() {
+        let s = Stack::<TheSevenLimitStackType>::from_pure_interval(
+            TheSevenLimitStackType::seventh_index(),
+            1,
+        );
+        assert_eq!(s.key_distance(), 10);
+        assert_relative_eq!(s.semitones(), 9.688259064691249);
+    }
+
+    #[test]
+    fn test_stack_serde_yml_roundtrip() {
+        assert_eq!(
+            serde_yml::from_str::<Stack<TheSevenLimitStackType>>(
+                r#"octave: 1
+seventh: -1
+"#
+            )
+            .unwrap(),
+            Stack::from_target(arr1(&[1, 0, 0, -1])),
+        );
+
+        let stack = Stack::<TheSevenLimitStackType>::from_target(vec![-1, 2, 0, 1]);
+        assert_eq!(
+            stack,
+            serde_yml::from_str(&serde_yml::to_string(&stack).unwrap()).unwrap()
+        );
+    }
+}
@@ -2,10 +2,11 @@ use std::{collections::HashMap, marker::PhantomData, ops::Deref};
 
 use ndarray::{linalg::general_mat_vec_mul, Array1, Array2, ArrayView1, ArrayViewMut1};
 use num_rational::Ratio;
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
     interval::{
-        base::Interval,
+        base::{Interval, IntervalDefinition},
         temperament::{Temperament, TemperamentDefinition, TemperamentErr},
     },
     util::lu::{lu_rational, LUErr},
@@ -148,6 +149,10 @@ pub trait StackType: IntervalBasis + Eq + 'static {
 #[derive(Debug)]
 pub enum StackTypeInitialisationErr {
     FromTemperamentErr(TemperamentErr),
+    FixedIntervalBasis,
+    MissingIntervalBasis,
+    IntervalBasisChanged,
+    InvalidIntervalBasis(String),
 }
 
 impl std::fmt::Display for StackTypeInitialisationErr {
@@ -156,13 +161,67 @@ impl std::fmt::Display for StackTypeInitialisationErr {
             StackTypeInitialisationErr::FromTemperamentErr(temperament_err) => {
                 temperament_err.fmt(f)
             }
+            StackTypeInitialisationErr::FixedIntervalBasis => write!(
+                f,
+                "the intervals of this stack type are fixed, there must be no 'intervals' section"
+            ),
+            StackTypeInitialisationErr::MissingIntervalBasis => {
+                write!(f, "the 'intervals' section is missing")
+            }
+            StackTypeInitialisationErr::IntervalBasisChanged => write!(
+                f,
+                "the 'intervals' or 'interval-basis' differ from the ones currently in use. \
+                 Please restart adaptuner to change them."
+            ),
+            StackTypeInitialisationErr::InvalidIntervalBasis(reason) => {
+                write!(f, "invalid 'intervals' section: {reason}")
+            }
         }
     }
 }
 
 impl std::error::Error for StackTypeInitialisationErr {}
 
+/// The fixed interval bases that a configuration file can choose with its `interval-basis` field.
+/// Without that field (and without an `intervals` section), the basis is five-limit.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IntervalBasisName {
+    FiveLimit,
+    SevenLimit,
+}
+
 pub trait Reloadable: StackType {
+    /// Set the [IntervalBasis::intervals] from the `intervals` section of a configuration file.
+    /// This must happen before anything that refers to intervals by name (like
+    /// [Stack][crate::interval::stack::Stack]s, temperament definitions, or named intervals) is
+    /// deserialised.
+    ///
+    /// The default implementation is for stack types with a fixed basis, which only accept
+    /// `None`.
+    fn initialise_intervals(
+        definitions: Option<&[IntervalDefinition]>,
+    ) -> Result<(), StackTypeInitialisationErr> {
+        if definitions.is_some() {
+            Err(StackTypeInitialisationErr::FixedIntervalBasis)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The definitions passed to [Reloadable::initialise_intervals], if the basis is
+    /// configurable.
+    fn interval_definitions() -> Option<Vec<IntervalDefinition>> {
+        None {}
+    }
+
+    /// The `interval-basis` that configuration files for this stack type must name. The default
+    /// implementation is for the five-limit basis, which needs no name, and for configurable
+    /// bases.
+    fn interval_basis_name() -> Option<IntervalBasisName> {
+        None {}
+    }
+
     fn initialise(
         temperament_definitions: Vec<TemperamentDefinition<Self>>,
         named_intervals: Vec<NamedInterval<Self>>,
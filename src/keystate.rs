@@ -21,6 +21,11 @@ impl KeyState {
         (self.on_channels != 0) | (self.held_channels != 0)
     }
 
+    /// is the key pressed on any channel (as opposed to only being held by the pedal)?
+    pub fn is_pressed(&self) -> bool {
+        self.on_channels != 0
+    }
+
     /// returns true iff the note state changed between "sounding on no channel" and "sounding on
     /// any channel"
     pub fn note_on(&mut self, channel: Channel, time: Instant) -> bool {
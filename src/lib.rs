@@ -1,16 +1,23 @@
 pub mod backend;
 pub mod bindable;
+pub mod cli;
 pub mod config;
 pub mod custom_serde;
 pub mod gui;
+#[cfg(test)]
+pub mod headless;
 pub mod interval;
 pub mod keystate;
 pub mod maybeconnected;
 pub mod msg;
 pub mod neighbourhood;
 pub mod notename;
+pub mod offline;
 pub mod process;
+pub mod recorder;
 pub mod reference;
 pub mod run;
+pub mod scala;
+pub mod smf;
 pub mod strategy;
 pub mod util;
@@ -1,13 +1,28 @@
-use std::error::Error;
+use std::{
+    error::Error,
+    hash::Hash,
+    path::{Path, PathBuf},
+};
 
 use adaptuner::{
-    backend::pitchbend12::Pitchbend12,
-    config::Config,
+    backend::SomeBackend,
+    cli::{Args, USAGE},
+    config::{BackendConfig, Config, FromConfigAndState, IntervalsSection, ProcessConfig},
+    custom_serde::migration::upgrade,
     gui::toplevel::Toplevel,
-    interval::stacktype::{fivelimit::TheFiveLimitStackType, r#trait::Reloadable},
-    process::fromstrategy::ProcessFromStrategy,
-    run::RunState,
+    interval::stacktype::{
+        configured::TheConfiguredStackType,
+        fivelimit::TheFiveLimitStackType,
+        r#trait::{IntervalBasisName, OctavePeriodicStackType, Reloadable},
+        sevenlimit::TheSevenLimitStackType,
+    },
+    notename::HasNoteNames,
+    offline::{retune, Retuned},
+    process::zones::ZonedProcess,
+    run::{RunState, StartupActions},
 };
+use midir::{MidiIO, MidiInput, MidiOutput};
+use serde::{Deserialize, Serialize};
 
 fn main() {
     if let Err(e) = run() {
@@ -19,18 +34,176 @@ fn main() {
 const TEMPLATE_CONFIG: &'static str = include_str!("../configs/template.yaml");
 
 fn run() -> Result<(), Box<dyn Error>> {
-    let config: Config<TheFiveLimitStackType> = serde_yml::from_str(TEMPLATE_CONFIG)?;
+    let args = Args::parse(std::env::args().skip(1))?;
+
+    if args.help {
+        println!("{USAGE}");
+        return Ok(());
+    }
+
+    if args.list_ports {
+        let midi_in = MidiInput::new("adaptuner input")?;
+        let midi_out = MidiOutput::new("adaptuner output")?;
+        println!("MIDI inputs:");
+        for name in port_names(&midi_in) {
+            println!("  {name}");
+        }
+        println!("MIDI outputs:");
+        for name in port_names(&midi_out) {
+            println!("  {name}");
+        }
+        return Ok(());
+    }
+
+    let mut config_str = if let Some(path) = &args.config {
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read '{}': {e}", path.display()))?
+    } else {
+        TEMPLATE_CONFIG.into()
+    };
+
+    if let Some(upgrade) = upgrade(&config_str)? {
+        eprintln!(
+            "The configuration file is for adaptuner version {}. It was upgraded in memory:",
+            upgrade.from_version
+        );
+        for change in &upgrade.changes {
+            eprintln!("  - {change}");
+        }
+        config_str = upgrade.after;
+    }
+
+    // Configurations with an `intervals` section use the interval basis defined there, all others
+    // use the one named by their `interval-basis` (five-limit, if there is none).
+    let section: IntervalsSection = serde_yml::from_str(&config_str)?;
+    match section.fixed_basis()? {
+        None => run_with::<TheConfiguredStackType>(&config_str, &args),
+        Some(IntervalBasisName::FiveLimit) => run_with::<TheFiveLimitStackType>(&config_str, &args),
+        Some(IntervalBasisName::SevenLimit) => {
+            run_with::<TheSevenLimitStackType>(&config_str, &args)
+        }
+    }
+}
+
+fn port_names<IO: MidiIO>(io: &IO) -> Vec<String> {
+    io.ports()
+        .iter()
+        .map(|p| io.port_name(p).unwrap_or("<no name>".into()))
+        .filter(|name| !name.contains("adaptuner"))
+        .collect()
+}
+
+/// The first port whose name contains `substring`.
+fn find_port<IO: MidiIO>(io: &IO, substring: &str) -> Result<(IO::Port, String), String> {
+    io.ports()
+        .into_iter()
+        .filter_map(|p| io.port_name(&p).ok().map(|name| (p, name)))
+        .filter(|(_, name)| !name.contains("adaptuner"))
+        .find(|(_, name)| name.contains(substring))
+        .ok_or_else(|| {
+            format!(
+                "no MIDI port name contains '{substring}'. Available ports:\n  {}",
+                port_names(io).join("\n  ")
+            )
+        })
+}
+
+/// Retune the Standard MIDI File at `path` without any MIDI ports, see [adaptuner::offline].
+fn retune_file<T>(
+    path: &Path,
+    retune_to: Option<&Path>,
+    process_config: ProcessConfig<T>,
+    backend_config: BackendConfig,
+    select_strategy: Option<usize>,
+) -> Result<(), Box<dyn Error>>
+where
+    T: OctavePeriodicStackType + std::fmt::Debug + 'static,
+{
+    let input =
+        std::fs::read(path).map_err(|e| format!("could not read '{}': {e}", path.display()))?;
+    let mut process = ZonedProcess::initialise(process_config, ());
+    let mut backend = SomeBackend::initialise(backend_config.without_glide(), ());
+    let Retuned { file, warnings } = retune(&input, &mut process, &mut backend, select_strategy)?;
+    for warning in &warnings {
+        eprintln!("warning: {warning}");
+    }
+
+    let output = retune_to.map_or_else(|| path.with_extension("retuned.mid"), PathBuf::from);
+    std::fs::write(&output, file)
+        .map_err(|e| format!("could not write '{}': {e}", output.display()))?;
+    eprintln!("The retuned file was written to '{}'.", output.display());
+    Ok(())
+}
+
+fn run_with<T>(config_str: &str, args: &Args) -> Result<(), Box<dyn Error>>
+where
+    T: OctavePeriodicStackType
+        + HasNoteNames
+        + Hash
+        + Serialize
+        + for<'a> Deserialize<'a>
+        + Reloadable
+        + Send,
+{
+    let config: Config<T> = Config::from_yaml_str(config_str)?;
     let (process_config, gui_config, backend_config) = config.split();
-    TheFiveLimitStackType::initialise(config.temperaments, config.named_intervals)?;
+    T::initialise(config.temperaments, config.named_intervals)?;
+
+    let select_strategy = args
+        .strategy
+        .as_deref()
+        .map(|name| {
+            gui_config
+                .strategies
+                .iter()
+                .position(|(names, _)| names.name() == name)
+                .ok_or_else(|| {
+                    let available: Vec<&str> = gui_config
+                        .strategies
+                        .iter()
+                        .map(|(names, _)| names.name())
+                        .collect();
+                    format!(
+                        "there is no strategy called '{name}'. Available strategies:\n  {}",
+                        available.join("\n  ")
+                    )
+                })
+        })
+        .transpose()?;
+
+    if let Some(path) = &args.retune {
+        return retune_file(
+            path,
+            args.retune_to.as_deref(),
+            process_config,
+            backend_config,
+            select_strategy,
+        );
+    }
+
+    let midi_in = MidiInput::new("adaptuner input")?;
+    let midi_out = MidiOutput::new("adaptuner output")?;
 
-    let midi_in = midir::MidiInput::new("adaptuner input")?;
-    let midi_out = midir::MidiOutput::new("adaptuner output")?;
+    let startup_actions = StartupActions {
+        connect_inputs: args
+            .inputs
+            .iter()
+            .map(|substring| find_port(&midi_in, substring))
+            .collect::<Result<_, _>>()?,
+        connect_outputs: args
+            .outputs
+            .iter()
+            .map(|substring| find_port(&midi_out, substring))
+            .collect::<Result<_, _>>()?,
+        select_strategy,
+    };
 
-    let _runstate = RunState::new::<ProcessFromStrategy<TheFiveLimitStackType>, Pitchbend12, _, _>(
+    let _runstate = RunState::new::<ZonedProcess<T>, SomeBackend, _, _>(
         midi_in,
         midi_out,
         process_config,
         backend_config,
+        startup_actions,
         move |ctx, tx| Toplevel::new(gui_config, ctx, tx),
     )?;
 
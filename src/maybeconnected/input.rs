@@ -1,148 +1,156 @@
-use std::{sync::mpsc, time::Instant};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    time::Instant,
+};
 
-use midir::{MidiInput, MidiInputConnection, MidiInputPort};
+use midi_msg::Channel;
+use midir::{MidiInput, MidiInputConnection};
 
 use crate::{
     config::{ExtractConfig, MidiInputConfig},
-    maybeconnected::common::MaybeConnected,
+    maybeconnected::loopprobe::LoopProbe,
     msg::{FromMidiIn, HandleMsg, ToMidiIn},
-    util::update_cell::UpdateCell,
 };
 
-enum MidiInputOrConnectionInternal {
-    Unconnected {
-        midi_input: MidiInput,
-        tx: mpsc::Sender<FromMidiIn>,
-    },
-    Connected {
-        connection: MidiInputConnection<mpsc::Sender<FromMidiIn>>,
-        portname: String,
-    },
-}
-
-impl MidiInputOrConnectionInternal {
-    fn new(midi_input: MidiInput, tx: mpsc::Sender<FromMidiIn>) -> Self {
-        Self::Unconnected { midi_input, tx }
-    }
-
-    fn connect_internal(self, port: MidiInputPort, portname: &str) -> Result<Self, (String, Self)> {
-        match self {
-            Self::Unconnected { midi_input, tx } => {
-                match midi_input.connect(
-                    &port,
-                    portname,
-                    move |_, bytes, tx| {
-                        let time = Instant::now();
-                        let _ = tx.send(FromMidiIn::IncomingMidi {
-                            time,
-                            bytes: bytes.to_vec(),
-                        });
-                    },
-                    tx.clone(),
-                ) {
-                    Ok(connection) => Ok(Self::Connected {
-                        connection,
-                        portname: portname.to_string(),
-                    }),
-                    Err(err) => {
-                        let err_string = err.to_string();
-                        Err((
-                            err_string,
-                            Self::Unconnected {
-                                midi_input: err.into_inner(),
-                                tx,
-                            },
-                        ))
-                    }
-                }
-            }
-            Self::Connected { .. } => unreachable!(),
+/// Move the channel message at the start of `bytes` to `channel`. Other messages are left alone.
+fn remap_channel(bytes: &mut [u8], channel: Channel) {
+    if let Some(status) = bytes.first_mut() {
+        if (0x80..0xF0).contains(status) {
+            *status = (*status & 0xF0) | channel as u8;
         }
     }
 }
 
-impl MaybeConnected<MidiInput> for MidiInputOrConnectionInternal {
-    fn unconnected(&self) -> Option<&MidiInput> {
-        match self {
-            Self::Unconnected { midi_input, .. } => Some(midi_input),
-            Self::Connected { .. } => None {},
-        }
-    }
-
-    fn connected_port_name(&self) -> Option<&str> {
-        match self {
-            Self::Unconnected { .. } => None {},
-            Self::Connected { portname, .. } => Some(portname),
-        }
-    }
-
-    fn connect(self, port: MidiInputPort, portname: &str) -> Result<Self, (String, Self)> {
-        match self {
-            Self::Unconnected { .. } => self.connect_internal(port, portname),
-            Self::Connected { .. } => {
-                let disconnected = self.disconnect();
-                disconnected.connect_internal(port, portname)
-            }
-        }
-    }
-
-    fn disconnect(self) -> Self {
-        match self {
-            Self::Connected { connection, .. } => {
-                let (midi_input, tx) = connection.close();
-                Self::Unconnected { midi_input, tx }
-            }
-            Self::Unconnected { .. } => self,
-        }
-    }
+struct Connection {
+    connection: MidiInputConnection<()>,
+    portname: String,
+    remap: Arc<Mutex<Option<Channel>>>,
 }
 
+/// The input ports that are currently connected. Their MIDI is merged into one stream of
+/// [FromMidiIn::IncomingMidi] messages.
+///
+/// A port that receives the `probe` sends [FromMidiIn::FeedbackLoop], and nothing else from then
+/// on.
 pub struct MidiInputOrConnection {
-    internal: UpdateCell<MidiInputOrConnectionInternal>,
+    /// Only used to list the available ports, every connection has its own [MidiInput].
+    midi_input: MidiInput,
+    client_name: String,
+    probe: LoopProbe,
+    tx: mpsc::Sender<FromMidiIn>,
+    connections: Vec<Connection>,
 }
 
 impl MidiInputOrConnection {
-    pub fn new(midi_input: MidiInput, tx: mpsc::Sender<FromMidiIn>) -> Self {
+    /// The `client_name` is used for the [MidiInput]s of the connections.
+    pub fn new(
+        midi_input: MidiInput,
+        client_name: &str,
+        probe: LoopProbe,
+        tx: mpsc::Sender<FromMidiIn>,
+    ) -> Self {
         Self {
-            internal: UpdateCell::new(MidiInputOrConnectionInternal::new(midi_input, tx)),
+            midi_input,
+            client_name: client_name.into(),
+            probe,
+            tx,
+            connections: vec![],
+        }
+    }
+
+    fn connect(&mut self, port: &midir::MidiInputPort, portname: &str) -> Result<(), String> {
+        if self.connections.iter().any(|c| c.portname == portname) {
+            return Err(format!("\"{portname}\" is already connected"));
         }
+        let midi_input = MidiInput::new(&self.client_name).map_err(|e| e.to_string())?;
+        let remap = Arc::new(Mutex::new(None {}));
+        let tx = self.tx.clone();
+        let callback_remap = remap.clone();
+        let probe = self.probe.clone();
+        let looped = AtomicBool::new(false);
+        let callback_portname = portname.to_string();
+        let connection = midi_input
+            .connect(
+                port,
+                portname,
+                move |_, bytes, _| {
+                    if looped.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    if probe.is_probe(bytes) {
+                        looped.store(true, Ordering::Relaxed);
+                        let _ = tx.send(FromMidiIn::FeedbackLoop {
+                            portname: callback_portname.clone(),
+                        });
+                        return;
+                    }
+                    let time = Instant::now();
+                    let mut bytes = bytes.to_vec();
+                    if let Some(channel) = *callback_remap.lock().unwrap() {
+                        remap_channel(&mut bytes, channel);
+                    }
+                    let _ = tx.send(FromMidiIn::IncomingMidi { time, bytes });
+                },
+                (),
+            )
+            .map_err(|e| e.to_string())?;
+        self.connections.push(Connection {
+            connection,
+            portname: portname.into(),
+            remap,
+        });
+        Ok(())
+    }
+
+    fn send_available_ports(&self, forward: &mpsc::Sender<FromMidiIn>) {
+        let ports = self
+            .midi_input
+            .ports()
+            .drain(..)
+            .map(|p| {
+                let name = self.midi_input.port_name(&p).unwrap_or("<no name>".into());
+                (p, name)
+            })
+            .filter(|(_, name)| !name.contains("adaptuner output"))
+            .filter(|(_, name)| !self.connections.iter().any(|c| &c.portname == name))
+            .collect();
+        let _ = forward.send(FromMidiIn::Ports {
+            available_ports: ports,
+        });
     }
 }
 
 impl HandleMsg<ToMidiIn, FromMidiIn> for MidiInputOrConnection {
     fn handle_msg(&mut self, msg: ToMidiIn, forward: &mpsc::Sender<FromMidiIn>) {
         match msg {
-            ToMidiIn::Connect { port, portname } => {
-                self.internal
-                    .update(|old| match old.connect(port, &portname) {
-                        Ok(new) => {
-                            let _ = forward.send(FromMidiIn::Connected { portname });
-                            new
-                        }
-                        Err((reason, new)) => {
-                            let _ = forward.send(FromMidiIn::ConnectionError { reason });
-                            new
-                        }
-                    })
+            ToMidiIn::Connect { port, portname } => match self.connect(&port, &portname) {
+                Ok(()) => {
+                    let _ = forward.send(FromMidiIn::Connected { portname });
+                }
+                Err(reason) => {
+                    let _ = forward.send(FromMidiIn::ConnectionError { reason });
+                }
+            },
+            ToMidiIn::Disconnect { portname } => {
+                if let Some(i) = self.connections.iter().position(|c| c.portname == portname) {
+                    self.connections.swap_remove(i).connection.close();
+                    let _ = forward.send(FromMidiIn::Disconnected { portname });
+                }
+            }
+            ToMidiIn::Remap { portname, channel } => {
+                if let Some(c) = self.connections.iter().find(|c| c.portname == portname) {
+                    *c.remap.lock().unwrap() = channel;
+                }
+            }
+            ToMidiIn::Start | ToMidiIn::ListPorts => self.send_available_ports(forward),
+            ToMidiIn::Stop => {
+                for c in self.connections.drain(..) {
+                    c.connection.close();
+                }
             }
-            ToMidiIn::Start | ToMidiIn::Disconnect => self.internal.update(|old| {
-                let new = old.disconnect();
-                let input = new.unconnected().unwrap(); // this is ok, we just disconnected
-                let ports = input
-                    .ports()
-                    .drain(..)
-                    .map(|p| {
-                        let name = input.port_name(&p).unwrap_or("<no name>".into());
-                        (p, name)
-                    })
-                    .filter(|(_, name)| !name.contains("adaptuner output"))
-                    .collect();
-                let _ = forward.send(FromMidiIn::Disconnected {
-                    available_ports: ports,
-                });
-                new
-            }),
-            ToMidiIn::Stop => {}
         }
     }
 }
@@ -152,3 +160,20 @@ impl ExtractConfig<MidiInputConfig> for MidiInputOrConnection {
         MidiInputConfig {}
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_remap() {
+        let mut note_on = [0x92, 60, 100];
+        remap_channel(&mut note_on, Channel::Ch6);
+        assert_eq!(note_on, [0x95, 60, 100]);
+
+        let mut clock = [0xF8];
+        remap_channel(&mut clock, Channel::Ch6);
+        assert_eq!(clock, [0xF8]);
+    }
+}
@@ -0,0 +1,60 @@
+//! Noticing MIDI feedback loops, where something (like a DAW) routes the output of adaptuner back
+//! into one of its inputs.
+//!
+//! The outputs send a [LoopProbe] when they're connected, when an input is connected, and
+//! regularly while MIDI is sent. An input that receives the probe is part of a loop, and is
+//! disconnected. Loops that don't pass on System Exclusive messages are not noticed.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How often the probe is sent while there's outgoing MIDI.
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A System Exclusive message with the manufacturer ID for non-commercial use, which devices
+/// ignore. It contains a number that's different for every running adaptuner, so that chaining
+/// two of them isn't mistaken for a loop.
+#[derive(Clone)]
+pub struct LoopProbe {
+    bytes: Vec<u8>,
+}
+
+impl LoopProbe {
+    /// A probe that no other running adaptuner uses.
+    pub fn for_this_process() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        Self::with_nonce(std::process::id() ^ nanos)
+    }
+
+    fn with_nonce(nonce: u32) -> Self {
+        let mut bytes = vec![0xF0, 0x7D];
+        bytes.extend_from_slice(b"adaptuner");
+        bytes.extend((0..5).map(|i| ((nonce >> (7 * i)) & 0x7F) as u8));
+        bytes.push(0xF7);
+        Self { bytes }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn is_probe(&self, bytes: &[u8]) -> bool {
+        bytes == &self.bytes[..]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_probe() {
+        let probe = LoopProbe::with_nonce(0xFFFF_FFFF);
+        let bytes = probe.bytes();
+        assert!(bytes[1..bytes.len() - 1].iter().all(|b| *b < 0x80));
+        assert!(probe.is_probe(probe.bytes()));
+        assert!(!probe.is_probe(LoopProbe::with_nonce(0xFFFF_FFFE).bytes()));
+        assert!(!probe.is_probe(&[0x90, 60, 100]));
+    }
+}
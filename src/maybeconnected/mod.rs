@@ -1,5 +1,3 @@
-pub mod common;
 pub mod input;
+pub mod loopprobe;
 pub mod output;
-
-pub use common::MaybeConnected;
@@ -4,146 +4,138 @@ use midir::*;
 
 use crate::{
     config::{ExtractConfig, MidiOutputConfig},
-    maybeconnected::common::MaybeConnected,
+    maybeconnected::loopprobe::{LoopProbe, PROBE_INTERVAL},
     msg::{FromMidiOut, HandleMsg, ToMidiOut},
-    util::update_cell::UpdateCell,
 };
 
-enum MidiOutputOrConnectionInternal {
-    Unconnected {
-        midi_output: MidiOutput,
-    },
-    Connected {
-        connection: MidiOutputConnection,
-        portname: String,
-    },
+/// Should `bytes` go to a port with the given routing? Channel messages go there iff their
+/// channel is routed to the port, all other messages always do.
+fn is_routed(bytes: &[u8], channels: &[bool; 16]) -> bool {
+    match bytes.first() {
+        Some(status @ 0x80..0xF0) => channels[(status & 0x0F) as usize],
+        _ => true,
+    }
 }
 
-impl MidiOutputOrConnectionInternal {
-    fn new(midi_output: MidiOutput) -> Self {
-        Self::Unconnected { midi_output }
-    }
+struct Connection {
+    connection: MidiOutputConnection,
+    portname: String,
+    channels: [bool; 16],
+}
 
-    fn connect_internal(
-        self,
-        port: MidiOutputPort,
-        portname: &str,
-    ) -> Result<Self, (String, Self)> {
-        match self {
-            Self::Unconnected { midi_output } => match midi_output.connect(&port, portname) {
-                Ok(connection) => Ok(Self::Connected {
-                    connection,
-                    portname: portname.into(),
-                }),
-                Err(err) => {
-                    let err_string = err.to_string();
-                    Err((
-                        err_string,
-                        Self::Unconnected {
-                            midi_output: err.into_inner(),
-                        },
-                    ))
-                }
-            },
-            Self::Connected { .. } => unreachable!(),
-        }
-    }
+/// The output ports that are currently connected. Each of them gets the outgoing MIDI on the
+/// channels that are routed to it, and the `probe` that detects feedback loops, see
+/// [crate::maybeconnected::loopprobe].
+pub struct MidiOutputOrConnection {
+    /// Only used to list the available ports, every connection has its own [MidiOutput].
+    midi_output: MidiOutput,
+    client_name: String,
+    probe: LoopProbe,
+    last_probe: Instant,
+    connections: Vec<Connection>,
 }
 
-impl MaybeConnected<MidiOutput> for MidiOutputOrConnectionInternal {
-    fn connected_port_name(&self) -> Option<&str> {
-        match self {
-            Self::Unconnected { .. } => None {},
-            Self::Connected { portname, .. } => Some(portname),
+impl MidiOutputOrConnection {
+    /// The `client_name` is used for the [MidiOutput]s of the connections.
+    pub fn new(midi_output: MidiOutput, client_name: &str, probe: LoopProbe) -> Self {
+        Self {
+            midi_output,
+            client_name: client_name.into(),
+            probe,
+            last_probe: Instant::now(),
+            connections: vec![],
         }
     }
 
-    fn unconnected(&self) -> Option<&MidiOutput> {
-        match self {
-            Self::Unconnected { midi_output, .. } => Some(midi_output),
-            Self::Connected { .. } => None {},
+    fn send_probe(&mut self) {
+        for c in &mut self.connections {
+            let _ = c.connection.send(self.probe.bytes());
         }
+        self.last_probe = Instant::now();
     }
 
-    fn connect(self, port: MidiOutputPort, portname: &str) -> Result<Self, (String, Self)> {
-        match self {
-            Self::Unconnected { .. } => self.connect_internal(port, portname),
-            Self::Connected { .. } => {
-                let disconnected = self.disconnect();
-                disconnected.connect_internal(port, portname)
-            }
+    fn connect(&mut self, port: &MidiOutputPort, portname: &str) -> Result<(), String> {
+        if self.connections.iter().any(|c| c.portname == portname) {
+            return Err(format!("\"{portname}\" is already connected"));
         }
+        let midi_output = MidiOutput::new(&self.client_name).map_err(|e| e.to_string())?;
+        let mut connection = midi_output
+            .connect(port, portname)
+            .map_err(|e| e.to_string())?;
+        let _ = connection.send(self.probe.bytes());
+        self.connections.push(Connection {
+            connection,
+            portname: portname.into(),
+            channels: [true; 16],
+        });
+        Ok(())
     }
 
-    fn disconnect(self) -> Self {
-        match self {
-            Self::Connected { connection, .. } => Self::Unconnected {
-                midi_output: connection.close(),
-            },
-            Self::Unconnected { .. } => self,
-        }
-    }
-}
-
-pub struct MidiOutputOrConnection {
-    internal: UpdateCell<MidiOutputOrConnectionInternal>,
-}
-
-impl MidiOutputOrConnection {
-    pub fn new(midi_output: MidiOutput) -> Self {
-        Self {
-            internal: UpdateCell::new(MidiOutputOrConnectionInternal::new(midi_output)),
-        }
+    fn send_available_ports(&self, forward: &mpsc::Sender<FromMidiOut>) {
+        let ports = self
+            .midi_output
+            .ports()
+            .drain(..)
+            .map(|p| {
+                let name = self.midi_output.port_name(&p).unwrap_or("<no name>".into());
+                (p, name)
+            })
+            .filter(|(_, name)| !name.contains("adaptuner input"))
+            .filter(|(_, name)| !self.connections.iter().any(|c| &c.portname == name))
+            .collect();
+        let _ = forward.send(FromMidiOut::Ports {
+            available_ports: ports,
+        });
     }
 }
 
 impl HandleMsg<ToMidiOut, FromMidiOut> for MidiOutputOrConnection {
     fn handle_msg(&mut self, msg: ToMidiOut, forward: &mpsc::Sender<FromMidiOut>) {
         match msg {
-            ToMidiOut::OutgoingMidi { time, bytes } => match &mut *self.internal.borrow_mut() {
-                MidiOutputOrConnectionInternal::Connected { connection, .. } => {
-                    let _ = connection.send(&bytes);
+            ToMidiOut::OutgoingMidi { time, bytes } => {
+                let mut sent = false;
+                for c in &mut self.connections {
+                    if is_routed(&bytes, &c.channels) {
+                        let _ = c.connection.send(&bytes);
+                        sent = true;
+                    }
+                }
+                if sent {
                     let now = Instant::now();
                     let _ = forward.send(FromMidiOut::EventLatency {
                         since_input: now.duration_since(time),
                     });
+                    if now.duration_since(self.last_probe) > PROBE_INTERVAL {
+                        self.send_probe();
+                    }
+                }
+            }
+            ToMidiOut::Connect { port, portname } => match self.connect(&port, &portname) {
+                Ok(()) => {
+                    let _ = forward.send(FromMidiOut::Connected { portname });
+                }
+                Err(reason) => {
+                    let _ = forward.send(FromMidiOut::ConnectionError { reason });
                 }
-                MidiOutputOrConnectionInternal::Unconnected { .. } => {}
             },
-            ToMidiOut::Connect { port, portname } => {
-                self.internal
-                    .update(|old| match old.connect(port, &portname) {
-                        Ok(new) => {
-                            let _ = forward.send(FromMidiOut::Connected { portname });
-                            new
-                        }
-                        Err((reason, new)) => {
-                            let _ = forward.send(FromMidiOut::ConnectionError { reason });
-                            new
-                        }
-                    });
+            ToMidiOut::Disconnect { portname } => {
+                if let Some(i) = self.connections.iter().position(|c| c.portname == portname) {
+                    self.connections.swap_remove(i).connection.close();
+                    let _ = forward.send(FromMidiOut::Disconnected { portname });
+                }
             }
-            ToMidiOut::Start | ToMidiOut::Disconnect => {
-                self.internal.update(|old| {
-                    let new = old.disconnect();
-                    let input = new.unconnected().unwrap(); // this is ok, we just disconnected
-                    let ports = input
-                        .ports()
-                        .drain(..)
-                        .map(|p| {
-                            let name = input.port_name(&p).unwrap_or("<no name>".into());
-                            (p, name)
-                        })
-                        .filter(|(_, name)| !name.contains("adaptuner input"))
-                        .collect();
-                    let _ = forward.send(FromMidiOut::Disconnected {
-                        available_ports: ports,
-                    });
-
-                    new
-                });
+            ToMidiOut::Route { portname, channels } => {
+                if let Some(c) = self.connections.iter_mut().find(|c| c.portname == portname) {
+                    c.channels = channels;
+                }
+            }
+            ToMidiOut::SendProbe => self.send_probe(),
+            ToMidiOut::Start | ToMidiOut::ListPorts => self.send_available_ports(forward),
+            ToMidiOut::Stop => {
+                for c in self.connections.drain(..) {
+                    c.connection.close();
+                }
             }
-            ToMidiOut::Stop => {}
         }
     }
 }
@@ -153,3 +145,17 @@ impl ExtractConfig<MidiOutputConfig> for MidiOutputOrConnection {
         MidiOutputConfig {}
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_routed() {
+        let mut channels = [false; 16];
+        channels[4] = true;
+        assert!(is_routed(&[0x94, 60, 100], &channels));
+        assert!(!is_routed(&[0x95, 60, 100], &channels));
+        assert!(is_routed(&[0xF0, 0x7F, 0x7F, 0x08, 0x02, 0xF7], &channels));
+    }
+}
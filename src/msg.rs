@@ -1,15 +1,23 @@
 use std::{
+    path::PathBuf,
     sync::mpsc,
     time::{Duration, Instant},
 };
 
 use midi_msg::Channel;
 use midir::{MidiInputPort, MidiOutputPort};
+use num_rational::Ratio;
 
 use crate::{
+    backend::glide::GlideConfig,
     bindable::MidiBindable,
     config::{BackendConfig, ProcessConfig},
-    interval::{base::Semitones, stack::Stack, stacktype::r#trait::StackType},
+    interval::{
+        base::Semitones,
+        stack::Stack,
+        stacktype::r#trait::{StackCoeff, StackType},
+    },
+    neighbourhood::SomeCompleteNeighbourhood,
     reference::Reference,
     strategy::{r#trait::StrategyAction, twostep::harmony::chordlist::PatternConfig},
     util::list_action::ListAction,
@@ -48,6 +56,13 @@ pub trait MessageTranslate4<B, C, D, E> {
     fn translate4(self) -> (Option<B>, Option<C>, Option<D>, Option<E>);
 }
 
+/// For messages that should be copied to somewhere else, in addition to being delivered. Copies of
+/// MIDI messages are only made if `recording` is true, so that the MIDI isn't cloned for nothing
+/// most of the time.
+pub trait MessageTap<B> {
+    fn tap(&self, recording: bool) -> Option<B>;
+}
+
 pub enum ToProcess<T: StackType> {
     Stop,
     GetCurrentConfig,
@@ -90,10 +105,19 @@ pub enum ToProcess<T: StackType> {
         action: Option<StrategyAction>,
         bindable: MidiBindable,
     },
+    /// Turn the next incoming MIDI event into a [FromProcess::LearnedBindable].
+    LearnBindable {
+        learn: bool,
+    },
     StrategyListAction {
         action: ListAction,
         time: Instant,
     },
+    /// Export the current tuning of all keys, or the neighbourhood with the given index of the
+    /// current strategy, see [crate::scala::TuningFiles].
+    ExportTuning {
+        neighbourhood: Option<usize>,
+    },
 }
 
 pub enum FromProcess<T: StackType> {
@@ -135,6 +159,26 @@ pub enum FromProcess<T: StackType> {
     },
     CurrentStrategyIndex(Option<usize>),
     CurrentConfig(ProcessConfig<T>),
+    ToggleRecording {
+        time: Instant,
+    },
+    LearnedBindable {
+        bindable: MidiBindable,
+    },
+    BendRange {
+        range: Semitones,
+        time: Instant,
+    },
+    /// The paths of the written files.
+    TuningExported {
+        result: Result<Vec<PathBuf>, String>,
+    },
+    /// A message from the process of one of the [crate::process::zones::ZoneConfig]s. What it
+    /// sends to the backend goes to the backend of the zone.
+    InZone {
+        zone: usize,
+        msg: Box<FromProcess<T>>,
+    },
 }
 
 pub enum ToHarmonyStrategy<T: StackType> {
@@ -149,9 +193,12 @@ pub enum ToStrategy<T: StackType> {
         stack: Stack<T>,
         time: Instant,
     },
+    /// An `amount` of one means to apply the temperament fully, zero means to make the
+    /// neighbourhood pure.
     ApplyTemperamentToNeighbourhood {
         neighbourhood: usize,
         temperament: usize,
+        amount: Ratio<StackCoeff>,
         time: Instant,
     },
     MakeNeighbourhoodPure {
@@ -162,6 +209,11 @@ pub enum ToStrategy<T: StackType> {
         action: ListAction,
         time: Instant,
     },
+    /// Append the neighbourhood to the list of neighbourhoods, and switch to it.
+    AddNeighbourhood {
+        neighbourhood: SomeCompleteNeighbourhood<T>,
+        time: Instant,
+    },
     SetTuningReference {
         reference: Reference<T>,
         time: Instant,
@@ -181,6 +233,57 @@ pub enum ToStrategy<T: StackType> {
     SetGroupMs {
         group_ms: u64,
     },
+    SetSpringStiffness {
+        index: usize,
+        stiffness: Ratio<StackCoeff>,
+        time: Instant,
+    },
+    SetAnchorStiffness {
+        stiffness: Ratio<StackCoeff>,
+        time: Instant,
+    },
+    TemperPatterns {
+        temper: bool,
+    },
+    SetHysteresis {
+        stable_ms: u64,
+        max_retune_cents: Option<f64>,
+        freeze: bool,
+    },
+    SetDriftCompensation {
+        max_drift_cents: f64,
+        at_rests: bool,
+        at_chord_changes: bool,
+    },
+    /// The time a strategy asked for with [FromStrategy::WakeUp] has come.
+    Tick {
+        time: Instant,
+    },
+}
+
+impl<T: StackType> ToStrategy<T> {
+    /// The time of the message, if it has one.
+    pub fn time(&self) -> Option<Instant> {
+        match self {
+            ToStrategy::Consider { time, .. }
+            | ToStrategy::ApplyTemperamentToNeighbourhood { time, .. }
+            | ToStrategy::MakeNeighbourhoodPure { time, .. }
+            | ToStrategy::NeighbourhoodListAction { time, .. }
+            | ToStrategy::AddNeighbourhood { time, .. }
+            | ToStrategy::SetTuningReference { time, .. }
+            | ToStrategy::SetReference { time, .. }
+            | ToStrategy::Action { time, .. }
+            | ToStrategy::ToHarmonyStrategy(_, time)
+            | ToStrategy::SetSpringStiffness { time, .. }
+            | ToStrategy::SetAnchorStiffness { time, .. }
+            | ToStrategy::Tick { time } => Some(*time),
+            ToStrategy::ReanchorOnMatch { .. }
+            | ToStrategy::SetGroupMs { .. }
+            | ToStrategy::TemperPatterns { .. }
+            | ToStrategy::SetHysteresis { .. }
+            | ToStrategy::SetDriftCompensation { .. } => None {},
+        }
+    }
 }
 
 pub enum FromStrategy<T: StackType> {
@@ -212,8 +315,17 @@ pub enum FromStrategy<T: StackType> {
     ReanchorOnMatch {
         reanchor: bool,
     },
+    Drift {
+        cents: f64,
+        time: Instant,
+    },
+    /// Ask for a [ToStrategy::Tick] at the given time.
+    WakeUp {
+        time: Instant,
+    },
 }
 
+#[derive(Clone)]
 pub enum ToBackend {
     GetCurrentConfig,
     RestartWithConfig {
@@ -272,6 +384,20 @@ pub enum ToBackend {
         channels: [bool; 16],
         time: Instant,
     },
+    Glide {
+        glide: Option<GlideConfig>,
+        time: Instant,
+    },
+    /// The time a backend asked for with [FromBackend::WakeUp] has come.
+    Tick {
+        time: Instant,
+    },
+    /// For the backend of the zone, if there are separate backends for the zones, see
+    /// [BackendConfig::Zoned].
+    InZone {
+        zone: usize,
+        msg: Box<ToBackend>,
+    },
 }
 
 pub enum FromBackend {
@@ -286,6 +412,10 @@ pub enum FromBackend {
         explanation: &'static str,
     },
     CurrentConfig(BackendConfig),
+    /// Ask for a [ToBackend::Tick] at the given time.
+    WakeUp {
+        time: Instant,
+    },
 }
 
 pub enum ToUi<T: StackType> {
@@ -327,8 +457,14 @@ pub enum ToUi<T: StackType> {
         portname: String,
     },
     InputDisconnected {
+        portname: String,
+    },
+    InputPorts {
         available_ports: Vec<(MidiInputPort, String)>,
     },
+    InputFeedbackLoop {
+        portname: String,
+    },
     OutputConnectionError {
         reason: String,
     },
@@ -336,6 +472,9 @@ pub enum ToUi<T: StackType> {
         portname: String,
     },
     OutputDisconnected {
+        portname: String,
+    },
+    OutputPorts {
         available_ports: Vec<(MidiOutputPort, String)>,
     },
     SetReference {
@@ -369,6 +508,26 @@ pub enum ToUi<T: StackType> {
     ReanchorOnMatch {
         reanchor: bool,
     },
+    RecordingStarted {
+        path: PathBuf,
+    },
+    RecordingStopped {
+        path: PathBuf,
+    },
+    RecordingError {
+        reason: String,
+    },
+    Drift {
+        cents: f64,
+        time: Instant,
+    },
+    LearnedBindable {
+        bindable: MidiBindable,
+    },
+    /// The paths of the written files.
+    TuningExported {
+        result: Result<Vec<PathBuf>, String>,
+    },
 }
 
 pub enum FromUi<T: StackType> {
@@ -380,6 +539,15 @@ pub enum FromUi<T: StackType> {
         action: ListAction,
         time: Instant,
     },
+    /// Append the neighbourhood to the list of neighbourhoods, and switch to it.
+    AddNeighbourhood {
+        neighbourhood: SomeCompleteNeighbourhood<T>,
+        time: Instant,
+    },
+    /// Export the current tuning of all keys, or the neighbourhood with the given index.
+    ExportTuning {
+        neighbourhood: Option<usize>,
+    },
     ApplyTemperamentToNeighbourhood {
         neighbourhood: usize,
         temperament: usize,
@@ -389,18 +557,34 @@ pub enum FromUi<T: StackType> {
         neighbourhood: usize,
         time: Instant,
     },
-    DisconnectInput,
+    ListInputPorts,
+    DisconnectInput {
+        portname: String,
+    },
     ConnectInput {
         port: MidiInputPort,
         portname: String,
         time: Instant,
     },
-    DisconnectOutput,
+    /// See [ToMidiIn::Remap].
+    RemapInput {
+        portname: String,
+        channel: Option<Channel>,
+    },
+    ListOutputPorts,
+    DisconnectOutput {
+        portname: String,
+    },
     ConnectOutput {
         port: MidiOutputPort,
         portname: String,
         time: Instant,
     },
+    /// See [ToMidiOut::Route].
+    RouteOutput {
+        portname: String,
+        channels: [bool; 16],
+    },
     SetTuningReference {
         reference: Reference<T>,
         time: Instant,
@@ -434,6 +618,10 @@ pub enum FromUi<T: StackType> {
         channels: [bool; 16],
         time: Instant,
     },
+    Glide {
+        glide: Option<GlideConfig>,
+        time: Instant,
+    },
     StrategyListAction {
         action: ListAction,
         time: Instant,
@@ -446,6 +634,9 @@ pub enum FromUi<T: StackType> {
         action: Option<StrategyAction>,
         bindable: MidiBindable,
     },
+    LearnBindable {
+        learn: bool,
+    },
     GetCurrentProcessConfig,
     GetCurrentBackendConfig,
     RestartProcessWithConfig {
@@ -485,14 +676,54 @@ pub enum FromUi<T: StackType> {
     SetGroupMs {
         group_ms: u64,
     },
+    SetSpringStiffness {
+        index: usize,
+        stiffness: Ratio<StackCoeff>,
+        time: Instant,
+    },
+    SetAnchorStiffness {
+        stiffness: Ratio<StackCoeff>,
+        time: Instant,
+    },
+    TemperPatterns {
+        temper: bool,
+    },
+    SetHysteresis {
+        stable_ms: u64,
+        max_retune_cents: Option<f64>,
+        freeze: bool,
+    },
+    SetDriftCompensation {
+        max_drift_cents: f64,
+        at_rests: bool,
+        at_chord_changes: bool,
+    },
+    StartRecording {
+        time: Instant,
+    },
+    StopRecording {
+        time: Instant,
+    },
 }
 
+/// Several input ports can be connected at the same time. Their MIDI is merged.
 pub enum ToMidiIn {
+    /// Connect to the port, in addition to the ports that are already connected.
     Connect {
         port: MidiInputPort,
         portname: String,
     },
-    Disconnect,
+    Disconnect {
+        portname: String,
+    },
+    /// Move all channel messages from the port to `channel`, or leave them on their channels if
+    /// it's `None`.
+    Remap {
+        portname: String,
+        channel: Option<Channel>,
+    },
+    /// Answered with [FromMidiIn::Ports].
+    ListPorts,
     Start,
     Stop,
 }
@@ -509,20 +740,44 @@ pub enum FromMidiIn {
         portname: String,
     },
     Disconnected {
+        portname: String,
+    },
+    /// The ports that could be connected.
+    Ports {
         available_ports: Vec<(MidiInputPort, String)>,
     },
+    /// The port received what adaptuner sent, see [crate::maybeconnected::loopprobe]. It is
+    /// muted, and should be disconnected.
+    FeedbackLoop {
+        portname: String,
+    },
 }
 
+/// Several output ports can be connected at the same time. All of them get the outgoing MIDI on
+/// the channels routed to them, and all messages that have no channel.
 pub enum ToMidiOut {
     OutgoingMidi {
         time: Instant,
         bytes: Vec<u8>,
     },
+    /// Connect to the port, in addition to the ports that are already connected. Initially, all
+    /// channels are routed to the new port.
     Connect {
         port: MidiOutputPort,
         portname: String,
     },
-    Disconnect,
+    Disconnect {
+        portname: String,
+    },
+    /// Send the messages on channel `i` to the port iff `channels[i]`.
+    Route {
+        portname: String,
+        channels: [bool; 16],
+    },
+    /// Answered with [FromMidiOut::Ports].
+    ListPorts,
+    /// Send the probe for feedback loops to all ports, see [crate::maybeconnected::loopprobe].
+    SendProbe,
     Start,
     Stop,
 }
@@ -538,10 +793,65 @@ pub enum FromMidiOut {
         portname: String,
     },
     Disconnected {
+        portname: String,
+    },
+    /// The ports that could be connected.
+    Ports {
         available_ports: Vec<(MidiOutputPort, String)>,
     },
 }
 
+pub enum ToRecorder<T: StackType> {
+    Stop,
+    StartRecording {
+        time: Instant,
+    },
+    StopRecording {
+        time: Instant,
+    },
+    ToggleRecording {
+        time: Instant,
+    },
+    IncomingMidi {
+        time: Instant,
+        bytes: Vec<u8>,
+    },
+    OutgoingMidi {
+        time: Instant,
+        bytes: Vec<u8>,
+    },
+    CurrentStrategyIndex {
+        index: Option<usize>,
+        time: Instant,
+    },
+    SetReference {
+        stack: Stack<T>,
+        time: Instant,
+    },
+    SetTuningReference {
+        reference: Reference<T>,
+        time: Instant,
+    },
+    CurrentHarmony {
+        pattern_index: Option<usize>,
+        reference: Option<Stack<T>>,
+        time: Instant,
+    },
+}
+
+/// The timer sends a [ToStrategy::Tick] to the process, or a [ToBackend::Tick] to the backend, at
+/// the requested times.
+pub enum ToTimer {
+    WakeUp { time: Instant },
+    BackendWakeUp { time: Instant },
+}
+
+pub enum FromRecorder {
+    Started { path: PathBuf },
+    Stopped { path: PathBuf },
+    Error { reason: String },
+}
+
 impl<T: StackType> MessageTranslate3<ToBackend, ToMidiOut, ToUi<T>> for FromProcess<T> {
     fn translate3(self) -> (Option<ToBackend>, Option<ToMidiOut>, Option<ToUi<T>>) {
         match self {
@@ -658,6 +968,56 @@ impl<T: StackType> MessageTranslate3<ToBackend, ToMidiOut, ToUi<T>> for FromProc
             FromProcess::CurrentConfig(config) => {
                 (None {}, None {}, Some(ToUi::CurrentProcessConfig(config)))
             }
+            FromProcess::ToggleRecording { .. } => (None {}, None {}, None {}),
+            FromProcess::LearnedBindable { bindable } => {
+                (None {}, None {}, Some(ToUi::LearnedBindable { bindable }))
+            }
+            FromProcess::BendRange { range, time } => {
+                (Some(ToBackend::BendRange { range, time }), None {}, None {})
+            }
+            FromProcess::TuningExported { result } => {
+                (None {}, None {}, Some(ToUi::TuningExported { result }))
+            }
+            FromProcess::InZone { zone, msg } => {
+                let (to_backend, to_midi_out, to_ui) = msg.translate3();
+                (
+                    to_backend.map(|msg| ToBackend::InZone {
+                        zone,
+                        msg: Box::new(msg),
+                    }),
+                    to_midi_out,
+                    to_ui,
+                )
+            }
+        }
+    }
+}
+
+/// Only wake ups go to the timer, everything else is translated like in
+/// [MessageTranslate3::translate3].
+impl<T: StackType> MessageTranslate4<ToBackend, ToMidiOut, ToUi<T>, ToTimer> for FromProcess<T> {
+    fn translate4(
+        self,
+    ) -> (
+        Option<ToBackend>,
+        Option<ToMidiOut>,
+        Option<ToUi<T>>,
+        Option<ToTimer>,
+    ) {
+        let wake_up = match &self {
+            FromProcess::FromStrategy(FromStrategy::WakeUp { time }) => Some(*time),
+            FromProcess::InZone { msg, .. } => match &**msg {
+                FromProcess::FromStrategy(FromStrategy::WakeUp { time }) => Some(*time),
+                _ => None {},
+            },
+            _ => None {},
+        };
+        match wake_up {
+            Some(time) => (None {}, None {}, None {}, Some(ToTimer::WakeUp { time })),
+            None {} => {
+                let (to_backend, to_midi_out, to_ui) = self.translate3();
+                (to_backend, to_midi_out, to_ui, None {})
+            }
         }
     }
 }
@@ -698,6 +1058,8 @@ impl<T: StackType> MessageTranslate2<ToBackend, ToUi<T>> for FromStrategy<T> {
             FromStrategy::ReanchorOnMatch { reanchor } => {
                 (None {}, Some(ToUi::ReanchorOnMatch { reanchor }))
             }
+            FromStrategy::Drift { cents, time } => (None {}, Some(ToUi::Drift { cents, time })),
+            FromStrategy::WakeUp { .. } => (None {}, None {}),
         }
     }
 }
@@ -727,6 +1089,7 @@ impl<T: StackType> MessageTranslate4<ToProcess<T>, ToBackend, ToMidiIn, ToMidiOu
                     ToStrategy::ApplyTemperamentToNeighbourhood {
                         temperament,
                         neighbourhood,
+                        amount: Ratio::from_integer(1),
                         time,
                     },
                 )),
@@ -734,7 +1097,13 @@ impl<T: StackType> MessageTranslate4<ToProcess<T>, ToBackend, ToMidiIn, ToMidiOu
                 None {},
                 None {},
             ),
-            FromUi::DisconnectInput => (None {}, None {}, Some(ToMidiIn::Disconnect), None {}),
+            FromUi::ListInputPorts => (None {}, None {}, Some(ToMidiIn::ListPorts), None {}),
+            FromUi::DisconnectInput { portname } => (
+                None {},
+                None {},
+                Some(ToMidiIn::Disconnect { portname }),
+                None {},
+            ),
             FromUi::ConnectInput {
                 port,
                 portname,
@@ -745,7 +1114,19 @@ impl<T: StackType> MessageTranslate4<ToProcess<T>, ToBackend, ToMidiIn, ToMidiOu
                 Some(ToMidiIn::Connect { port, portname }),
                 None {},
             ),
-            FromUi::DisconnectOutput => (None {}, None {}, None {}, Some(ToMidiOut::Disconnect)),
+            FromUi::RemapInput { portname, channel } => (
+                None {},
+                None {},
+                Some(ToMidiIn::Remap { portname, channel }),
+                None {},
+            ),
+            FromUi::ListOutputPorts => (None {}, None {}, None {}, Some(ToMidiOut::ListPorts)),
+            FromUi::DisconnectOutput { portname } => (
+                None {},
+                None {},
+                None {},
+                Some(ToMidiOut::Disconnect { portname }),
+            ),
             FromUi::ConnectOutput {
                 port,
                 portname,
@@ -756,6 +1137,12 @@ impl<T: StackType> MessageTranslate4<ToProcess<T>, ToBackend, ToMidiIn, ToMidiOu
                 None {},
                 Some(ToMidiOut::Connect { port, portname }),
             ),
+            FromUi::RouteOutput { portname, channels } => (
+                None {},
+                None {},
+                None {},
+                Some(ToMidiOut::Route { portname, channels }),
+            ),
             FromUi::SetTuningReference { reference, time } => (
                 Some(ToProcess::ToStrategy(ToStrategy::SetTuningReference {
                     reference,
@@ -832,6 +1219,12 @@ impl<T: StackType> MessageTranslate4<ToProcess<T>, ToBackend, ToMidiIn, ToMidiOu
                 None {},
                 None {},
             ),
+            FromUi::Glide { glide, time } => (
+                None {},
+                Some(ToBackend::Glide { glide, time }),
+                None {},
+                None {},
+            ),
             FromUi::Action { action, time } => (
                 Some(ToProcess::ToStrategy(ToStrategy::Action { action, time })),
                 None {},
@@ -856,6 +1249,12 @@ impl<T: StackType> MessageTranslate4<ToProcess<T>, ToBackend, ToMidiIn, ToMidiOu
                 None {},
                 None {},
             ),
+            FromUi::LearnBindable { learn } => (
+                Some(ToProcess::LearnBindable { learn }),
+                None {},
+                None {},
+                None {},
+            ),
             FromUi::StrategyListAction { action, time } => (
                 Some(ToProcess::StrategyListAction { action, time }),
                 None {},
@@ -871,6 +1270,24 @@ impl<T: StackType> MessageTranslate4<ToProcess<T>, ToBackend, ToMidiIn, ToMidiOu
                 None {},
                 None {},
             ),
+            FromUi::AddNeighbourhood {
+                neighbourhood,
+                time,
+            } => (
+                Some(ToProcess::ToStrategy(ToStrategy::AddNeighbourhood {
+                    neighbourhood,
+                    time,
+                })),
+                None {},
+                None {},
+                None {},
+            ),
+            FromUi::ExportTuning { neighbourhood } => (
+                Some(ToProcess::ExportTuning { neighbourhood }),
+                None {},
+                None {},
+                None {},
+            ),
             FromUi::GetCurrentProcessConfig => {
                 (Some(ToProcess::GetCurrentConfig), None {}, None {}, None {})
             }
@@ -958,25 +1375,120 @@ impl<T: StackType> MessageTranslate4<ToProcess<T>, ToBackend, ToMidiIn, ToMidiOu
                 None {},
                 None {},
             ),
+            FromUi::SetHysteresis {
+                stable_ms,
+                max_retune_cents,
+                freeze,
+            } => (
+                Some(ToProcess::ToStrategy(ToStrategy::SetHysteresis {
+                    stable_ms,
+                    max_retune_cents,
+                    freeze,
+                })),
+                None {},
+                None {},
+                None {},
+            ),
+            FromUi::SetDriftCompensation {
+                max_drift_cents,
+                at_rests,
+                at_chord_changes,
+            } => (
+                Some(ToProcess::ToStrategy(ToStrategy::SetDriftCompensation {
+                    max_drift_cents,
+                    at_rests,
+                    at_chord_changes,
+                })),
+                None {},
+                None {},
+                None {},
+            ),
+            FromUi::SetSpringStiffness {
+                index,
+                stiffness,
+                time,
+            } => (
+                Some(ToProcess::ToStrategy(ToStrategy::SetSpringStiffness {
+                    index,
+                    stiffness,
+                    time,
+                })),
+                None {},
+                None {},
+                None {},
+            ),
+            FromUi::SetAnchorStiffness { stiffness, time } => (
+                Some(ToProcess::ToStrategy(ToStrategy::SetAnchorStiffness {
+                    stiffness,
+                    time,
+                })),
+                None {},
+                None {},
+                None {},
+            ),
+            FromUi::TemperPatterns { temper } => (
+                Some(ToProcess::ToStrategy(ToStrategy::TemperPatterns { temper })),
+                None {},
+                None {},
+                None {},
+            ),
+            FromUi::StartRecording { .. } | FromUi::StopRecording { .. } => {
+                (None {}, None {}, None {}, None {})
+            }
         }
     }
 }
 
-impl<T: StackType> MessageTranslate2<ToProcess<T>, ToUi<T>> for FromMidiIn {
-    fn translate2(self) -> (Option<ToProcess<T>>, Option<ToUi<T>>) {
+/// A connected input triggers a probe for feedback loops, and an input that's part of a loop is
+/// disconnected.
+impl<T: StackType> MessageTranslate4<ToProcess<T>, ToUi<T>, ToMidiIn, ToMidiOut> for FromMidiIn {
+    fn translate4(
+        self,
+    ) -> (
+        Option<ToProcess<T>>,
+        Option<ToUi<T>>,
+        Option<ToMidiIn>,
+        Option<ToMidiOut>,
+    ) {
         match self {
-            FromMidiIn::IncomingMidi { time, bytes } => {
-                (Some(ToProcess::IncomingMidi { time, bytes }), None {})
-            }
-            FromMidiIn::ConnectionError { reason } => {
-                (None {}, Some(ToUi::InputConnectionError { reason }))
-            }
-            FromMidiIn::Connected { portname } => {
-                (None {}, Some(ToUi::InputConnected { portname }))
-            }
-            FromMidiIn::Disconnected { available_ports } => {
-                (None {}, Some(ToUi::InputDisconnected { available_ports }))
-            }
+            FromMidiIn::IncomingMidi { time, bytes } => (
+                Some(ToProcess::IncomingMidi { time, bytes }),
+                None {},
+                None {},
+                None {},
+            ),
+            FromMidiIn::ConnectionError { reason } => (
+                None {},
+                Some(ToUi::InputConnectionError { reason }),
+                None {},
+                None {},
+            ),
+            FromMidiIn::Connected { portname } => (
+                None {},
+                Some(ToUi::InputConnected { portname }),
+                None {},
+                Some(ToMidiOut::SendProbe),
+            ),
+            FromMidiIn::Disconnected { portname } => (
+                None {},
+                Some(ToUi::InputDisconnected { portname }),
+                None {},
+                None {},
+            ),
+            FromMidiIn::Ports { available_ports } => (
+                None {},
+                Some(ToUi::InputPorts { available_ports }),
+                None {},
+                None {},
+            ),
+            FromMidiIn::FeedbackLoop { portname } => (
+                None {},
+                Some(ToUi::InputFeedbackLoop {
+                    portname: portname.clone(),
+                }),
+                Some(ToMidiIn::Disconnect { portname }),
+                None {},
+            ),
         }
     }
 }
@@ -987,9 +1499,8 @@ impl<T: StackType> MessageTranslate<ToUi<T>> for FromMidiOut {
             FromMidiOut::EventLatency { since_input } => Some(ToUi::EventLatency { since_input }),
             FromMidiOut::ConnectionError { reason } => Some(ToUi::OutputConnectionError { reason }),
             FromMidiOut::Connected { portname } => Some(ToUi::OutputConnected { portname }),
-            FromMidiOut::Disconnected { available_ports } => {
-                Some(ToUi::OutputDisconnected { available_ports })
-            }
+            FromMidiOut::Disconnected { portname } => Some(ToUi::OutputDisconnected { portname }),
+            FromMidiOut::Ports { available_ports } => Some(ToUi::OutputPorts { available_ports }),
         }
     }
 }
@@ -1024,6 +1535,114 @@ impl<T: StackType> MessageTranslate2<ToMidiOut, ToUi<T>> for FromBackend {
             FromBackend::CurrentConfig(config) => {
                 (None {}, Some(ToUi::CurrentBackendConfig(config)))
             }
+            FromBackend::WakeUp { .. } => (None {}, None {}),
+        }
+    }
+}
+
+/// Only wake ups go to the timer, everything else is translated like in
+/// [MessageTranslate2::translate2].
+impl<T: StackType> MessageTranslate3<ToMidiOut, ToUi<T>, ToTimer> for FromBackend {
+    fn translate3(self) -> (Option<ToMidiOut>, Option<ToUi<T>>, Option<ToTimer>) {
+        match self {
+            FromBackend::WakeUp { time } => {
+                (None {}, None {}, Some(ToTimer::BackendWakeUp { time }))
+            }
+            _ => {
+                let (to_midi_out, to_ui) = self.translate2();
+                (to_midi_out, to_ui, None {})
+            }
+        }
+    }
+}
+
+impl<T: StackType> MessageTranslate<ToUi<T>> for FromRecorder {
+    fn translate(self) -> Option<ToUi<T>> {
+        match self {
+            FromRecorder::Started { path } => Some(ToUi::RecordingStarted { path }),
+            FromRecorder::Stopped { path } => Some(ToUi::RecordingStopped { path }),
+            FromRecorder::Error { reason } => Some(ToUi::RecordingError { reason }),
+        }
+    }
+}
+
+impl<T: StackType> MessageTap<ToRecorder<T>> for FromMidiIn {
+    fn tap(&self, recording: bool) -> Option<ToRecorder<T>> {
+        match self {
+            FromMidiIn::IncomingMidi { time, bytes } if recording => {
+                Some(ToRecorder::IncomingMidi {
+                    time: *time,
+                    bytes: bytes.clone(),
+                })
+            }
+            _ => None {},
+        }
+    }
+}
+
+impl<T: StackType> MessageTap<ToRecorder<T>> for FromBackend {
+    fn tap(&self, recording: bool) -> Option<ToRecorder<T>> {
+        match self {
+            FromBackend::OutgoingMidi { time, bytes } if recording => {
+                Some(ToRecorder::OutgoingMidi {
+                    time: *time,
+                    bytes: bytes.clone(),
+                })
+            }
+            _ => None {},
+        }
+    }
+}
+
+/// Messages without a time stamp are recorded at the time they pass the tap.
+impl<T: StackType> MessageTap<ToRecorder<T>> for FromProcess<T> {
+    fn tap(&self, recording: bool) -> Option<ToRecorder<T>> {
+        match self {
+            FromProcess::OutgoingMidi { time, bytes } if recording => {
+                Some(ToRecorder::OutgoingMidi {
+                    time: *time,
+                    bytes: bytes.clone(),
+                })
+            }
+            FromProcess::ToggleRecording { time } => {
+                Some(ToRecorder::ToggleRecording { time: *time })
+            }
+            FromProcess::CurrentStrategyIndex(index) => Some(ToRecorder::CurrentStrategyIndex {
+                index: *index,
+                time: Instant::now(),
+            }),
+            FromProcess::FromStrategy(FromStrategy::SetReference { stack }) => {
+                Some(ToRecorder::SetReference {
+                    stack: stack.clone(),
+                    time: Instant::now(),
+                })
+            }
+            FromProcess::FromStrategy(FromStrategy::SetTuningReference { reference }) => {
+                Some(ToRecorder::SetTuningReference {
+                    reference: reference.clone(),
+                    time: Instant::now(),
+                })
+            }
+            FromProcess::FromStrategy(FromStrategy::CurrentHarmony {
+                pattern_index,
+                reference,
+            }) => Some(ToRecorder::CurrentHarmony {
+                pattern_index: *pattern_index,
+                reference: reference.clone(),
+                time: Instant::now(),
+            }),
+            FromProcess::InZone { msg, .. } => msg.tap(recording),
+            _ => None {},
+        }
+    }
+}
+
+impl<T: StackType> MessageTap<ToRecorder<T>> for FromUi<T> {
+    fn tap(&self, _recording: bool) -> Option<ToRecorder<T>> {
+        match self {
+            FromUi::StartRecording { time } => Some(ToRecorder::StartRecording { time: *time }),
+            FromUi::StopRecording { time } => Some(ToRecorder::StopRecording { time: *time }),
+            _ => None {},
         }
     }
 }
@@ -1063,3 +1682,9 @@ impl HasStop for ToMidiOut {
         }
     }
 }
+
+impl<T: StackType> HasStop for ToRecorder<T> {
+    fn is_stop(&self) -> bool {
+        matches!(self, Self::Stop)
+    }
+}
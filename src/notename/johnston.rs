@@ -1,15 +1,19 @@
 pub mod fivelimit {
-    use std::fmt;
+    use std::{fmt, str::FromStr};
 
     use crate::interval::{
         stack::Stack,
         stacktype::{
             fivelimit::TheFiveLimitStackType,
-            r#trait::{IntervalBasis, StackCoeff},
+            r#trait::{FiveLimitIntervalBasis, IntervalBasis, StackCoeff},
         },
     };
 
-    use crate::notename::BaseName::{self, *};
+    use crate::notename::{
+        spelling::Spelling,
+        BaseName::{self, *},
+        Notation,
+    };
 
     #[derive(Clone)]
     pub struct Accidental {
@@ -48,10 +52,14 @@ pub mod fivelimit {
             f: &mut W,
             style: &crate::notename::NoteNameStyle,
         ) -> fmt::Result {
-            match style {
-                crate::notename::NoteNameStyle::Full => self.write_full(f),
-                crate::notename::NoteNameStyle::Class => self.write_class(f),
+            match style.notation {
+                Notation::Johnston => self.write_class(f)?,
+                notation => self.spelling().write_class(f, notation)?,
             }
+            if style.octave {
+                write!(f, " {}", self.octave)?;
+            }
+            Ok(())
         }
 
         fn base_name(&self) -> BaseName {
@@ -111,7 +119,11 @@ pub mod fivelimit {
             Self::new_from_values(octaves, fifths, thirds)
         }
 
-        fn new_from_values(octaves: StackCoeff, fifths: StackCoeff, thirds: StackCoeff) -> Self {
+        pub fn new_from_values(
+            octaves: StackCoeff,
+            fifths: StackCoeff,
+            thirds: StackCoeff,
+        ) -> Self {
             let ix = 2 + 2 * fifths + thirds;
             NoteName {
                 basename: JOHNSTON_BASE_ROW[ix.rem_euclid(7) as usize],
@@ -123,41 +135,60 @@ pub mod fivelimit {
             }
         }
 
-        /// Write the pitch class (i.e. the note name without the octave number)
-        fn write_class<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
-            write!(f, "{}", self.basename)?;
+        /// The octaves, fifths, and thirds of the interval from middle C to the note. This is
+        /// the inverse of [NoteName::new_from_values].
+        pub fn values(&self) -> (StackCoeff, StackCoeff, StackCoeff) {
+            let (fifths, thirds) = fifths_and_thirds(
+                self.basename,
+                self.accidental.sharpflat,
+                self.accidental.plusminus,
+            );
+            let octaves = self.octave - 4 - (4 * fifths + 2 * thirds).div_euclid(7);
+            (octaves, fifths, thirds)
+        }
 
-            let sf = self.accidental.sharpflat;
-            if sf > 0 {
-                for _ in 0..(sf / 2) {
-                    write!(f, "\u{1D12A}")?; // double sharp
-                } 
-                if sf % 2 == 1 {   
-                    write!(f, "\u{266F}")?; // sharp
-                }
-            }
-            if sf < 0 {
-                for _ in 0..(-sf / 2) {
-                    write!(f, "\u{1D12B}")?; // double flat
-                }
-                if -sf % 2 == 1 {
-                    write!(f, "\u{266D}")?; // flat
-                }
-            }
+        fn spelling(&self) -> Spelling {
+            let (_, fifths, thirds) = self.values();
+            Spelling::from_johnston(self.basename, fifths, thirds, 0, 0, 0)
+        }
 
-            let pm = self.accidental.plusminus;
-            if pm > 0 {
-                for _ in 0..pm {
-                    write!(f, "\u{EE5C}")?; // plus
+        /// The stack of the interval from middle C to the note.
+        pub fn stack<T: FiveLimitIntervalBasis>(&self) -> Stack<T> {
+            let (octaves, fifths, thirds) = self.values();
+            let mut coeffs = vec![0; T::num_intervals()];
+            coeffs[T::octave_index()] = octaves;
+            coeffs[T::fifth_index()] = fifths;
+            coeffs[T::third_index()] = thirds;
+            Stack::from_target(coeffs)
+        }
+
+        /// Write the note name in a form that only uses ASCII characters, and that
+        /// [NoteName::from_str] reads: `#`, `b`, `+`, and `-` for sharps, flats, pluses and
+        /// minuses, and the octave number directly after them (or after a space, if it's
+        /// negative), like `Bb-3` or `C -1`.
+        pub fn write_ascii<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
+            write!(f, "{}", self.basename)?;
+            let write_repeated = |f: &mut W, n: StackCoeff, up: char, down: char| -> fmt::Result {
+                for _ in 0..n {
+                    write!(f, "{up}")?;
                 }
-            }
-            if pm < 0 {
-                for _ in 0..-pm {
-                    write!(f, "\u{EE5D}")?; // minus
+                for _ in n..0 {
+                    write!(f, "{down}")?;
                 }
+                Ok(())
+            };
+            write_repeated(f, self.accidental.sharpflat, '#', 'b')?;
+            write_repeated(f, self.accidental.plusminus, '+', '-')?;
+            if self.octave < 0 {
+                write!(f, " ")?;
             }
+            write!(f, "{}", self.octave)
+        }
 
-            Ok(())
+        /// Write the pitch class (i.e. the note name without the octave number)
+        fn write_class<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
+            write!(f, "{}", self.basename)?;
+            write_accidental(f, self.accidental.sharpflat, self.accidental.plusminus)
         }
 
         /// Write the full note name.
@@ -167,12 +198,133 @@ pub mod fivelimit {
         }
     }
 
+    /// The fifths and thirds (up to octaves) of the note with the given base name and accidental.
+    pub(super) fn fifths_and_thirds(
+        basename: BaseName,
+        sharpflat: StackCoeff,
+        plusminus: StackCoeff,
+    ) -> (StackCoeff, StackCoeff) {
+        let position = JOHNSTON_BASE_ROW
+            .iter()
+            .position(|&b| b == basename)
+            .unwrap() as StackCoeff;
+        let a = 7 * plusminus + position - 2; // = 2 * fifths + thirds
+        let fifths = (1 + 4 * a).div_euclid(7) - sharpflat;
+        (fifths, a - 2 * fifths)
+    }
+
+    /// Write the sharps, flats, pluses and minuses of an accidental.
+    pub(in crate::notename) fn write_accidental<W: fmt::Write>(
+        f: &mut W,
+        sharpflat: StackCoeff,
+        plusminus: StackCoeff,
+    ) -> fmt::Result {
+        let sf = sharpflat;
+        if sf > 0 {
+            for _ in 0..(sf / 2) {
+                write!(f, "\u{1D12A}")?; // double sharp
+            }
+            if sf % 2 == 1 {
+                write!(f, "\u{266F}")?; // sharp
+            }
+        }
+        if sf < 0 {
+            for _ in 0..(-sf / 2) {
+                write!(f, "\u{1D12B}")?; // double flat
+            }
+            if -sf % 2 == 1 {
+                write!(f, "\u{266D}")?; // flat
+            }
+        }
+
+        let pm = plusminus;
+        if pm > 0 {
+            for _ in 0..pm {
+                write!(f, "\u{EE5C}")?; // plus
+            }
+        }
+        if pm < 0 {
+            for _ in 0..-pm {
+                write!(f, "\u{EE5D}")?; // minus
+            }
+        }
+
+        Ok(())
+    }
+
     impl fmt::Display for NoteName {
         fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
             self.write_full(f)
         }
     }
 
+    /// Reads note names like `E+`, `Bb-`, `F#4`, or `A♭ 3`: A base name, followed by
+    /// accidentals, and optionally the octave number. Sharps and flats can be written as `#`,
+    /// `x` (double sharp), and `b`, or with the musical symbols; pluses and minuses as `+` and
+    /// `-`, or with the symbols that [NoteName::write] uses. Without an octave number, the note
+    /// is in octave 4, which starts at middle C. Negative octave numbers must be separated by a
+    /// space, because `C-1` is C minus in octave 1.
+    impl FromStr for NoteName {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let s = s.trim();
+            let (name, octave) = match s.split_once(char::is_whitespace) {
+                Some((name, octave)) => (name, Some(octave.trim())),
+                None {} => match s.find(|c: char| c.is_ascii_digit()) {
+                    Some(i) => (&s[..i], Some(&s[i..])),
+                    None {} => (s, None {}),
+                },
+            };
+
+            let mut chars = name.chars();
+            let basename = match chars.next() {
+                Some('C') => C,
+                Some('D') => D,
+                Some('E') => E,
+                Some('F') => F,
+                Some('G') => G,
+                Some('A') => A,
+                Some('B') => B,
+                _ => {
+                    return Err(format!(
+                        "'{s}' doesn't start with one of the letters A to G"
+                    ))
+                }
+            };
+
+            let mut sharpflat = 0;
+            let mut plusminus = 0;
+            for c in chars {
+                match c {
+                    '#' | '\u{266F}' => sharpflat += 1,
+                    'x' | '\u{1D12A}' => sharpflat += 2,
+                    'b' | '\u{266D}' => sharpflat -= 1,
+                    '\u{1D12B}' => sharpflat -= 2,
+                    '+' | '\u{EE5C}' => plusminus += 1,
+                    '-' | '\u{EE5D}' => plusminus -= 1,
+                    _ => return Err(format!("'{c}' in '{s}' is not an accidental")),
+                }
+            }
+
+            let octave = match octave {
+                None {} => 4,
+                Some(o) => o
+                    .parse()
+                    .map_err(|_| format!("'{o}' in '{s}' is not an octave number"))?,
+            };
+
+            Ok(NoteName {
+                basename,
+                octave,
+                accidental: Accidental {
+                    sharpflat,
+                    plusminus,
+                },
+            })
+        }
+    }
+
     #[cfg(test)]
     mod test {
         use super::*;
@@ -224,10 +376,361 @@ pub mod fivelimit {
             for (coeffs, name) in examples.iter() {
                 assert_eq!(
                     Stack::<MockFiveLimitStackType>::from_target(coeffs.to_vec())
-                        .notename(&NoteNameStyle::Full),
+                        .notename(&NoteNameStyle::FULL),
                     String::from(*name)
                 );
+                assert_eq!(
+                    name.parse::<NoteName>().unwrap().stack(),
+                    Stack::<MockFiveLimitStackType>::from_target(coeffs.to_vec()),
+                );
+            }
+        }
+
+        #[test]
+        fn test_parse() {
+            let examples = [
+                ("C", [0, 0, 0], "C4"),
+                ("E+", [-2, 4, 0], "E+4"),
+                ("E", [0, 0, 1], "E4"),
+                ("Bb-", [2, -2, 0], "Bb-4"),
+                ("Bb- 2", [0, -2, 0], "Bb-2"),
+                ("F#4", [1, -2, 2], "F#4"),
+                ("Gx5", [1, -1, 4], "G##5"),
+                ("C-1", [-1, -4, 1], "C-1"),
+                ("C -1", [-5, 0, 0], "C -1"),
+                ("  A♭ 3 ", [0, 0, -1], "Ab3"),
+            ];
+
+            for (name, coeffs, ascii) in examples.iter() {
+                let parsed = name.parse::<NoteName>().unwrap();
+                assert_eq!(
+                    parsed.stack(),
+                    Stack::<MockFiveLimitStackType>::from_target(coeffs.to_vec()),
+                    "{name}"
+                );
+                let mut written = String::new();
+                parsed.write_ascii(&mut written).unwrap();
+                assert_eq!(&written, ascii);
+                assert_eq!(
+                    written.parse::<NoteName>().unwrap().values(),
+                    parsed.values()
+                );
             }
+
+            assert_eq!(
+                "H".parse::<NoteName>().err().unwrap(),
+                "'H' doesn't start with one of the letters A to G"
+            );
+            assert_eq!(
+                "Cq".parse::<NoteName>().err().unwrap(),
+                "'q' in 'Cq' is not an accidental"
+            );
+            assert_eq!(
+                "C four".parse::<NoteName>().err().unwrap(),
+                "'four' in 'C four' is not an octave number"
+            );
+        }
+    }
+}
+
+/// Johnston's notation for intervals with prime factors up to 13: The five-limit names are
+/// extended by the accidentals 7 and L (lowering and raising by 35:36, L stands in for Johnston's
+/// inverted 7), ↑ and ↓ (raising and lowering by 33:32), and 13 and ƐƖ (raising and lowering by
+/// 65:64). All of them are written as plain text.
+pub mod extended {
+    use std::fmt;
+
+    use crate::interval::{
+        stack::Stack,
+        stacktype::{
+            configured::TheConfiguredStackType,
+            r#trait::{FiveLimitIntervalBasis, StackCoeff},
+            sevenlimit::TheSevenLimitStackType,
+        },
+    };
+
+    use crate::notename::{
+        spelling::Spelling,
+        Accidental as _,
+        BaseName::{self, *},
+        Notation, NoteName as _,
+    };
+
+    use super::fivelimit;
+
+    /// Coordinates of an interval, for the purposes of Johnston's notation. The entries are:
+    /// octaves, fifths, thirds, septimal, undecimal, and tridecimal commas. The last three count
+    /// the number of L, ↑, and 13 accidentals, respectively.
+    pub type JohnstonCoordinates = [StackCoeff; 6];
+
+    /// The [JohnstonCoordinates] of the primes 2, 3, 5, 7, 11, and 13.
+    pub const PRIME_COORDINATES: [(StackCoeff, JohnstonCoordinates); 6] = [
+        (2, [1, 0, 0, 0, 0, 0]),
+        (3, [1, 1, 0, 0, 0, 0]),
+        (5, [2, 0, 1, 0, 0, 0]),
+        (7, [2, 2, -1, -1, 0, 0]), // 7:4 is B♭7, i.e. 9:5 lowered by 35:36
+        (11, [4, -1, 0, 0, 1, 0]), // 11:8 is F↑, i.e. 4:3 raised by 33:32
+        (13, [4, 0, -1, 0, 0, 1]), // 13:8 is A♭13, i.e. 8:5 raised by 65:64
+    ];
+
+    #[derive(Clone)]
+    pub struct Accidental {
+        sharpflat: StackCoeff,
+        plusminus: StackCoeff,
+        septimal: StackCoeff,
+        undecimal: StackCoeff,
+        tridecimal: StackCoeff,
+    }
+
+    impl Accidental {
+        /// The number of septimal commas 36:35 by which the note is raised. Negative values mean
+        /// that the note is lowered.
+        pub fn septimal(&self) -> StackCoeff {
+            self.septimal
+        }
+
+        /// The number of undecimal commas 33:32 by which the note is raised. Negative values
+        /// mean that the note is lowered.
+        pub fn undecimal(&self) -> StackCoeff {
+            self.undecimal
+        }
+
+        /// The number of tridecimal commas 65:64 by which the note is raised. Negative values
+        /// mean that the note is lowered.
+        pub fn tridecimal(&self) -> StackCoeff {
+            self.tridecimal
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct NoteName {
+        basename: BaseName,
+        octave: StackCoeff,
+        accidental: Accidental,
+    }
+
+    impl crate::notename::Accidental for Accidental {
+        fn is_natural(&self) -> bool {
+            self.sharpflat == 0
+                && self.plusminus == 0
+                && self.septimal == 0
+                && self.undecimal == 0
+                && self.tridecimal == 0
+        }
+
+        fn sharpflat(&self) -> StackCoeff {
+            self.sharpflat
+        }
+
+        fn plusminus(&self) -> StackCoeff {
+            self.plusminus
+        }
+    }
+
+    impl crate::notename::NoteName for NoteName {
+        type Accidental = Accidental;
+
+        fn write<W: fmt::Write>(
+            &self,
+            f: &mut W,
+            style: &crate::notename::NoteNameStyle,
+        ) -> fmt::Result {
+            match style.notation {
+                Notation::Johnston => self.write_class(f)?,
+                notation => self.spelling().write_class(f, notation)?,
+            }
+            if style.octave {
+                write!(f, " {}", self.octave)?;
+            }
+            Ok(())
+        }
+
+        fn base_name(&self) -> BaseName {
+            self.basename
+        }
+
+        fn octave(&self) -> StackCoeff {
+            self.octave
+        }
+
+        fn accidental(&self) -> &Self::Accidental {
+            &self.accidental
+        }
+
+        fn middle_c() -> Self {
+            NoteName {
+                basename: C,
+                octave: 4,
+                accidental: Accidental {
+                    sharpflat: 0,
+                    plusminus: 0,
+                    septimal: 0,
+                    undecimal: 0,
+                    tridecimal: 0,
+                },
+            }
+        }
+    }
+
+    impl crate::notename::NoteNameFor<TheSevenLimitStackType> for NoteName {
+        fn new_from_stack(stack: &Stack<TheSevenLimitStackType>) -> Self {
+            Self::new_from_sevenlimit_values(
+                stack.target[TheSevenLimitStackType::octave_index()],
+                stack.target[TheSevenLimitStackType::fifth_index()],
+                stack.target[TheSevenLimitStackType::third_index()],
+                stack.target[TheSevenLimitStackType::seventh_index()],
+            )
+        }
+
+        fn new_from_stack_actual(stack: &Stack<TheSevenLimitStackType>) -> Self {
+            Self::new_from_sevenlimit_values(
+                stack.actual[TheSevenLimitStackType::octave_index()].to_integer(),
+                stack.actual[TheSevenLimitStackType::fifth_index()].to_integer(),
+                stack.actual[TheSevenLimitStackType::third_index()].to_integer(),
+                stack.actual[TheSevenLimitStackType::seventh_index()].to_integer(),
+            )
+        }
+    }
+
+    impl crate::notename::NoteNameFor<TheConfiguredStackType> for NoteName {
+        fn new_from_stack(stack: &Stack<TheConfiguredStackType>) -> Self {
+            Self::new_from_coordinates(&TheConfiguredStackType::johnston_coordinates(
+                stack.target.iter().copied(),
+            ))
+        }
+
+        fn new_from_stack_actual(stack: &Stack<TheConfiguredStackType>) -> Self {
+            Self::new_from_coordinates(&TheConfiguredStackType::johnston_coordinates(
+                stack.actual.iter().map(|x| x.to_integer()),
+            ))
+        }
+    }
+
+    impl NoteName {
+        fn new_from_sevenlimit_values(
+            octaves: StackCoeff,
+            fifths: StackCoeff,
+            thirds: StackCoeff,
+            sevenths: StackCoeff,
+        ) -> Self {
+            let mut coordinates = [octaves, fifths, thirds, 0, 0, 0];
+            for (c, s) in coordinates.iter_mut().zip(PRIME_COORDINATES[3].1) {
+                *c += sevenths * s;
+            }
+            coordinates[0] -= 2 * sevenths; // the seventh is 7:4, not 7:1
+            Self::new_from_coordinates(&coordinates)
+        }
+
+        pub fn new_from_coordinates(coordinates: &JohnstonCoordinates) -> Self {
+            let [octaves, fifths, thirds, septimal, undecimal, tridecimal] = *coordinates;
+            let five_limit = fivelimit::NoteName::new_from_values(octaves, fifths, thirds);
+            NoteName {
+                basename: five_limit.base_name(),
+                octave: five_limit.octave(),
+                accidental: Accidental {
+                    sharpflat: five_limit.accidental().sharpflat(),
+                    plusminus: five_limit.accidental().plusminus(),
+                    septimal,
+                    undecimal,
+                    tridecimal,
+                },
+            }
+        }
+
+        fn spelling(&self) -> Spelling {
+            let (fifths, thirds) = fivelimit::fifths_and_thirds(
+                self.basename,
+                self.accidental.sharpflat,
+                self.accidental.plusminus,
+            );
+            Spelling::from_johnston(
+                self.basename,
+                fifths,
+                thirds,
+                self.accidental.septimal,
+                self.accidental.undecimal,
+                self.accidental.tridecimal,
+            )
+        }
+
+        /// Write the pitch class (i.e. the note name without the octave number)
+        fn write_class<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
+            write!(f, "{}", self.basename)?;
+            fivelimit::write_accidental(f, self.accidental.sharpflat, self.accidental.plusminus)?;
+
+            let write_repeated = |f: &mut W, n: StackCoeff, up: &str, down: &str| {
+                for _ in 0..n {
+                    write!(f, "{up}")?;
+                }
+                for _ in n..0 {
+                    write!(f, "{down}")?;
+                }
+                Ok(())
+            };
+
+            write_repeated(f, self.accidental.septimal, "L", "7")?;
+            write_repeated(f, self.accidental.undecimal, "\u{2191}", "\u{2193}")?;
+            write_repeated(f, self.accidental.tridecimal, "13", "\u{190}\u{196}")
+        }
+
+        /// Write the full note name.
+        fn write_full<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
+            self.write_class(f)?;
+            write!(f, " {}", self.octave)
+        }
+    }
+
+    impl fmt::Display for NoteName {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            self.write_full(f)
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use crate::notename::NoteNameStyle;
+
+        #[test]
+        fn test_str_name() {
+            let examples = [
+                ([0, 0, 0, 0], "C 4"),
+                ([0, 1, 1, 0], "B 4"),
+                ([0, 0, 0, 1], "B♭7 4"),
+                ([0, 0, 0, -1], "D\u{ee5d}L 3"),
+                ([0, 0, 1, 1], "D7 5"),
+                ([-1, 1, 0, 1], "F\u{ee5c}7 4"),
+                ([0, 0, 0, 2], "A♭\u{ee5c}77 5"),
+            ];
+
+            for (coeffs, name) in examples.iter() {
+                assert_eq!(
+                    Stack::<TheSevenLimitStackType>::from_target(coeffs.to_vec())
+                        .notename(&NoteNameStyle::FULL),
+                    String::from(*name)
+                );
+            }
+        }
+
+        #[test]
+        fn test_str_name_from_coordinates() {
+            let name = |prime_index: usize, octaves: StackCoeff| {
+                let mut coordinates = PRIME_COORDINATES[prime_index].1;
+                coordinates[0] += octaves;
+                NoteName::new_from_coordinates(&coordinates).to_string()
+            };
+
+            assert_eq!(name(3, -2), "B♭7 4");
+            assert_eq!(name(4, -3), "F\u{2191} 4");
+            assert_eq!(name(5, -3), "A♭13 4");
+
+            let mut coordinates = PRIME_COORDINATES[4].1;
+            coordinates.iter_mut().for_each(|c| *c *= -1);
+            coordinates[0] += 4;
+            assert_eq!(
+                NoteName::new_from_coordinates(&coordinates).to_string(),
+                "G\u{2193} 4"
+            );
         }
     }
 }
@@ -1,26 +1,132 @@
 use std::fmt;
 
-use serde_derive::{Deserialize, Serialize};
 use crate::interval::{
     stack::Stack,
     stacktype::{
+        configured::TheConfiguredStackType,
         fivelimit::TheFiveLimitStackType,
         r#trait::{IntervalBasis, StackCoeff, StackType},
+        sevenlimit::TheSevenLimitStackType,
     },
 };
+use serde_derive::{Deserialize, Serialize};
 
 pub mod correction;
 pub mod johnston;
+pub mod spelling;
+
+/// The notations in which note names can be written. Johnston's notation starts from the notes of
+/// a just C major scale, and the others from the Pythagorean notes, see [spelling].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Notation {
+    Johnston,
+    Heji,
+    SagittalAscii,
+    SagittalSmufl,
+    UpsAndDowns,
+}
+
+impl Notation {
+    pub const ALL: [Notation; 5] = [
+        Notation::Johnston,
+        Notation::Heji,
+        Notation::SagittalAscii,
+        Notation::SagittalSmufl,
+        Notation::UpsAndDowns,
+    ];
+
+    /// The name used in [NoteNameStyle]s in configuration files.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Notation::Johnston => "johnston",
+            Notation::Heji => "heji",
+            Notation::SagittalAscii => "sagittal-ascii",
+            Notation::SagittalSmufl => "sagittal-smufl",
+            Notation::UpsAndDowns => "ups-and-downs",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Notation::Johnston => "Johnston",
+            Notation::Heji => "Helmholtz-Ellis (HEJI)",
+            Notation::SagittalAscii => "Sagittal (ASCII)",
+            Notation::SagittalSmufl => "Sagittal (SMuFL symbols)",
+            Notation::UpsAndDowns => "ups and downs (53-EDO)",
+        }
+    }
+}
+
+/// How to write note names: in which [Notation], and with or without the octave number.
+///
+/// In configuration files, this is written as `full` or `class` (with and without octave number)
+/// for Johnston's notation, and with the [Notation::key] in front for the others, like
+/// `heji-class`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub struct NoteNameStyle {
+    pub notation: Notation,
+    pub octave: bool,
+}
+
+impl NoteNameStyle {
+    /// Johnston's notation, with octave numbers.
+    pub const FULL: Self = NoteNameStyle {
+        notation: Notation::Johnston,
+        octave: true,
+    };
+
+    /// Johnston's notation, without octave numbers.
+    pub const CLASS: Self = NoteNameStyle {
+        notation: Notation::Johnston,
+        octave: false,
+    };
+
+    /// The same notation, with or without octave numbers.
+    pub fn with_octave(self, octave: bool) -> Self {
+        NoteNameStyle { octave, ..self }
+    }
+}
+
+impl From<NoteNameStyle> for String {
+    fn from(style: NoteNameStyle) -> String {
+        let octave = if style.octave { "full" } else { "class" };
+        match style.notation {
+            Notation::Johnston => octave.into(),
+            notation => format!("{}-{octave}", notation.key()),
+        }
+    }
+}
+
+impl TryFrom<String> for NoteNameStyle {
+    type Error = String;
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
-#[derive(Clone, Copy)]
-pub enum NoteNameStyle {
-    Full,
-    Class,
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let (notation, octave) = match s.rsplit_once('-') {
+            Some((notation, octave)) => (Some(notation), octave),
+            None {} => (None {}, s.as_str()),
+        };
+        let octave = match octave {
+            "full" => true,
+            "class" => false,
+            _ => {
+                return Err(format!(
+                    "'{s}' is not a note name style: it should end with 'full' or 'class'"
+                ))
+            }
+        };
+        let notation = match notation {
+            None {} => Notation::Johnston,
+            Some(key) => *Notation::ALL
+                .iter()
+                .find(|n| n.key() == key)
+                .ok_or_else(|| format!("'{key}' in '{s}' is not the name of a notation"))?,
+        };
+        Ok(NoteNameStyle { notation, octave })
+    }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum BaseName {
     C,
     D,
@@ -143,6 +249,14 @@ impl HasNoteNames for TheFiveLimitStackType {
     type NoteName = johnston::fivelimit::NoteName;
 }
 
+impl HasNoteNames for TheSevenLimitStackType {
+    type NoteName = johnston::extended::NoteName;
+}
+
+impl HasNoteNames for TheConfiguredStackType {
+    type NoteName = johnston::extended::NoteName;
+}
+
 impl<T: StackType + HasNoteNames> Stack<T> {
     pub fn write_notename<W: fmt::Write>(&self, f: &mut W, style: &NoteNameStyle) -> fmt::Result {
         T::write_notename(self, f, style)
@@ -190,3 +304,47 @@ impl<T: StackType + HasNoteNames> Stack<T> {
         res
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_notenamestyle_serde() {
+        for (style, yaml) in [
+            (NoteNameStyle::FULL, "full"),
+            (NoteNameStyle::CLASS, "class"),
+            (
+                NoteNameStyle {
+                    notation: Notation::Heji,
+                    octave: false,
+                },
+                "heji-class",
+            ),
+            (
+                NoteNameStyle {
+                    notation: Notation::UpsAndDowns,
+                    octave: true,
+                },
+                "ups-and-downs-full",
+            ),
+        ] {
+            assert_eq!(serde_yml::to_string(&style).unwrap(), format!("{yaml}\n"));
+            assert_eq!(serde_yml::from_str::<NoteNameStyle>(yaml).unwrap(), style);
+        }
+
+        assert_eq!(
+            serde_yml::from_str::<NoteNameStyle>("johnston-full").unwrap(),
+            NoteNameStyle::FULL
+        );
+        assert!(serde_yml::from_str::<NoteNameStyle>("heji")
+            .unwrap_err()
+            .to_string()
+            .contains("'heji' is not a note name style"));
+        assert!(serde_yml::from_str::<NoteNameStyle>("helmholtz-full")
+            .unwrap_err()
+            .to_string()
+            .contains("'helmholtz' in 'helmholtz-full' is not the name of a notation"));
+    }
+}
@@ -0,0 +1,261 @@
+//! Note names in the notations that start from the Pythagorean notes, i.e. the chain of fifths
+//! F, C, G, D, A, E, B, with sharps and flats. The notes of just intonation are then written as
+//! Pythagorean notes that are raised or lowered by small commas, one for every prime:
+//!
+//! - 5: the syntonic comma 81:80, so that 5:4 is E lowered by one comma,
+//! - 7: the septimal comma 64:63, so that 7:4 is B♭ lowered by one comma,
+//! - 11: the undecimal quartertone 33:32, so that 11:8 is F raised by one quartertone,
+//! - 13: the tridecimal quartertone 1053:1024, so that 13:8 is A♭ raised by one quartertone.
+//!
+//! The notations only differ in how they write these commas. The base names and octave numbers
+//! are the same as in Johnston's notation, because the commas are too small to change them.
+
+use std::fmt;
+
+use crate::interval::stacktype::r#trait::StackCoeff;
+
+use super::{johnston::fivelimit::write_accidental, BaseName, Notation};
+
+/// A Pythagorean note, and the commas by which it is raised (or, if negative, lowered).
+#[derive(Clone)]
+pub struct Spelling {
+    basename: BaseName,
+    /// Positive for sharps, negative for flats.
+    sharpflat: StackCoeff,
+    /// The commas for the primes 5, 7, 11, and 13, in this order.
+    commas: [StackCoeff; 4],
+}
+
+impl Spelling {
+    /// The spelling of the note with the given base name in Johnston's notation, that is `fifths`
+    /// and `thirds` (and octaves) away from C, and raised by the given numbers of Johnston's
+    /// septimal (36:35), undecimal (33:32), and tridecimal (65:64) commas.
+    pub fn from_johnston(
+        basename: BaseName,
+        fifths: StackCoeff,
+        thirds: StackCoeff,
+        septimal: StackCoeff,
+        undecimal: StackCoeff,
+        tridecimal: StackCoeff,
+    ) -> Self {
+        // the position of the Pythagorean note on the chain of fifths, with C at 0
+        let pythagorean = fifths + 4 * thirds;
+        Spelling {
+            basename,
+            sharpflat: (pythagorean + 1).div_euclid(7),
+            commas: [
+                septimal - thirds - tridecimal,
+                septimal,
+                undecimal,
+                tridecimal,
+            ],
+        }
+    }
+
+    /// Write the pitch class (i.e. the note name without the octave number). Johnston's
+    /// notation is not possible, it must be written from the [johnston][super::johnston] note
+    /// names.
+    pub fn write_class<W: fmt::Write>(&self, f: &mut W, notation: Notation) -> fmt::Result {
+        match notation {
+            Notation::Johnston => Err(fmt::Error),
+            Notation::Heji => self.write_heji(f),
+            Notation::SagittalAscii => self.write_sagittal(f, true),
+            Notation::SagittalSmufl => self.write_sagittal(f, false),
+            Notation::UpsAndDowns => self.write_ups_and_downs(f),
+        }
+    }
+
+    /// Helmholtz-Ellis notation, with the SMuFL symbols. The syntonic commas are arrows on the
+    /// sharp, flat, or natural sign, and the other commas follow it.
+    fn write_heji<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
+        write!(f, "{}", self.basename)?;
+        let [five, seven, eleven, thirteen] = self.commas;
+
+        let mut sf = self.sharpflat;
+        if five == 0 {
+            write_accidental(f, sf, 0)?;
+        } else {
+            // the symbols with arrows go up to double sharps and flats, and three arrows.
+            while sf > 2 {
+                write!(f, "\u{1D12A}")?; // double sharp
+                sf -= 2;
+            }
+            while sf < -2 {
+                write!(f, "\u{1D12B}")?; // double flat
+                sf += 2;
+            }
+            let mut arrows = five.abs();
+            while arrows > 0 {
+                let n = arrows.min(3);
+                let up = if five > 0 { 5 } else { 0 };
+                let symbol = 0xE2C0 + 10 * (n - 1) + up + (sf + 2);
+                write!(f, "{}", char::from_u32(symbol as u32).ok_or(fmt::Error)?)?;
+                arrows -= n;
+                sf = 0;
+            }
+        }
+
+        write_repeated(f, seven / 2, "\u{E2E1}", "\u{E2E0}")?;
+        write_repeated(f, seven % 2, "\u{E2DF}", "\u{E2DE}")?;
+        write_repeated(f, eleven, "\u{E2E3}", "\u{E2E2}")?;
+        write_repeated(f, thirteen, "\u{E2E5}", "\u{E2E4}")
+    }
+
+    /// "Mixed" Sagittal notation: sharps and flats, followed by one symbol for every comma. The
+    /// tridecimal quartertone uses the symbol of the 35-medium diesis, which stands for both.
+    fn write_sagittal<W: fmt::Write>(&self, f: &mut W, ascii: bool) -> fmt::Result {
+        write!(f, "{}", self.basename)?;
+        let [five, seven, eleven, thirteen] = self.commas;
+        if ascii {
+            write_repeated(f, self.sharpflat, "#", "b")?;
+            write_repeated(f, five, "/|", "\\!")?;
+            write_repeated(f, seven, "|)", "!)")?;
+            write_repeated(f, eleven, "/|\\", "\\!/")?;
+            write_repeated(f, thirteen, "/|)", "\\!)")
+        } else {
+            write_accidental(f, self.sharpflat, 0)?;
+            write_repeated(f, five, "\u{E302}", "\u{E303}")?;
+            write_repeated(f, seven, "\u{E304}", "\u{E305}")?;
+            write_repeated(f, eleven, "\u{E30A}", "\u{E30B}")?;
+            write_repeated(f, thirteen, "\u{E308}", "\u{E309}")
+        }
+    }
+
+    /// Ups and downs, which count steps of 53-EDO in front of the base name. The syntonic and
+    /// septimal commas are one step each, the quartertones two steps, so that 5:4 is vE, 7:4 is
+    /// vB♭, 11:8 is ^^F, and 13:8 is ^^A♭.
+    fn write_ups_and_downs<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
+        let [five, seven, eleven, thirteen] = self.commas;
+        write_repeated(f, five + seven + 2 * eleven + 2 * thirteen, "^", "v")?;
+        write!(f, "{}", self.basename)?;
+        write_accidental(f, self.sharpflat, 0)
+    }
+}
+
+/// Write `up` `n` times, or `down` `-n` times.
+fn write_repeated<W: fmt::Write>(f: &mut W, n: StackCoeff, up: &str, down: &str) -> fmt::Result {
+    for _ in 0..n {
+        write!(f, "{up}")?;
+    }
+    for _ in n..0 {
+        write!(f, "{down}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        interval::{stack::Stack, stacktype::fivelimit::mock::MockFiveLimitStackType},
+        notename::{
+            johnston::extended::{self, PRIME_COORDINATES},
+            NoteNameStyle,
+        },
+    };
+    use pretty_assertions::assert_eq;
+
+    fn class(notation: Notation) -> NoteNameStyle {
+        NoteNameStyle {
+            notation,
+            octave: false,
+        }
+    }
+
+    #[test]
+    fn test_five_limit() {
+        let examples = [
+            ([0, 0, 0], "C", "C", "C", "C"),
+            ([0, 0, 1], "E\u{E2C2}", "E\\!", "E\u{E303}", "vE"),
+            (
+                [0, 1, -1],
+                "E\u{E2C6}",
+                "Eb/|",
+                "E\u{266D}\u{E302}",
+                "^E\u{266D}",
+            ),
+            ([0, -2, 0], "B\u{266D}", "Bb", "B\u{266D}", "B\u{266D}"),
+            (
+                [0, 0, 2],
+                "G\u{E2CD}",
+                "G#\\!\\!",
+                "G\u{266F}\u{E303}\u{E303}",
+                "vvG\u{266F}",
+            ),
+            (
+                [0, 2, 1],
+                "F\u{E2C3}",
+                "F#\\!",
+                "F\u{266F}\u{E303}",
+                "vF\u{266F}",
+            ),
+            (
+                [0, 0, -4],
+                "B\u{1D12B}\u{E2DA}\u{E2C7}",
+                "Bbbb/|/|/|/|",
+                "B\u{1D12B}\u{266D}\u{E302}\u{E302}\u{E302}\u{E302}",
+                "^^^^B\u{1D12B}\u{266D}",
+            ),
+            (
+                [0, 4, 4],
+                "F\u{1D12A}\u{E2D7}\u{E2C2}",
+                "F###\\!\\!\\!\\!",
+                "F\u{1D12A}\u{266F}\u{E303}\u{E303}\u{E303}\u{E303}",
+                "vvvvF\u{1D12A}\u{266F}",
+            ),
+        ];
+
+        for (coeffs, heji, sagittal_ascii, sagittal_smufl, ups_and_downs) in examples {
+            let stack = Stack::<MockFiveLimitStackType>::from_target(coeffs.to_vec());
+            assert_eq!(stack.notename(&class(Notation::Heji)), heji);
+            assert_eq!(
+                stack.notename(&class(Notation::SagittalAscii)),
+                sagittal_ascii
+            );
+            assert_eq!(
+                stack.notename(&class(Notation::SagittalSmufl)),
+                sagittal_smufl
+            );
+            assert_eq!(stack.notename(&class(Notation::UpsAndDowns)), ups_and_downs);
+        }
+
+        let below_middle_c = Stack::<MockFiveLimitStackType>::from_target(vec![-1, 0, 1]);
+        assert_eq!(
+            below_middle_c.notename(&NoteNameStyle {
+                notation: Notation::UpsAndDowns,
+                octave: true,
+            }),
+            "vE 3"
+        );
+    }
+
+    #[test]
+    fn test_higher_primes() {
+        let name = |prime_index: usize, octaves: StackCoeff, notation: Notation| {
+            let mut coordinates = PRIME_COORDINATES[prime_index].1;
+            coordinates[0] += octaves;
+            let mut res = String::new();
+            crate::notename::NoteName::write(
+                &extended::NoteName::new_from_coordinates(&coordinates),
+                &mut res,
+                &class(notation),
+            )
+            .unwrap();
+            res
+        };
+
+        assert_eq!(name(3, -2, Notation::Heji), "B\u{266D}\u{E2DE}");
+        assert_eq!(name(4, -3, Notation::Heji), "F\u{E2E3}");
+        assert_eq!(name(5, -3, Notation::Heji), "A\u{266D}\u{E2E5}");
+
+        assert_eq!(name(3, -2, Notation::SagittalAscii), "Bb!)");
+        assert_eq!(name(4, -3, Notation::SagittalAscii), "F/|\\");
+        assert_eq!(name(5, -3, Notation::SagittalAscii), "Ab/|)");
+
+        assert_eq!(name(3, -2, Notation::SagittalSmufl), "B\u{266D}\u{E305}");
+
+        assert_eq!(name(3, -2, Notation::UpsAndDowns), "vB\u{266D}");
+        assert_eq!(name(4, -3, Notation::UpsAndDowns), "^^F");
+        assert_eq!(name(5, -3, Notation::UpsAndDowns), "^^A\u{266D}");
+    }
+}
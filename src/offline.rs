@@ -0,0 +1,678 @@
+//! Retuning Standard MIDI Files without any MIDI ports.
+//!
+//! The events of all tracks of the input file are merged and fed through the process and the
+//! backend, with [Instant]s computed from their original ticks and the tempo map of the file.
+//! Everything that comes out is written to a new Type 1 file at the tick of the event that caused
+//! it: The first track contains the meta events (tempo, time signatures, markers...) of the input,
+//! the second one the retuned performance.
+
+use std::{
+    collections::BTreeSet,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use midi_msg::{Division, Meta, MidiFile, MidiFileParseError, MidiMsg, TimeCodeType, Track};
+
+use crate::{
+    interval::stacktype::r#trait::StackType,
+    msg::{
+        FromBackend, FromProcess, HandleMsg, MessageTranslate2, MessageTranslate4, ToBackend,
+        ToMidiOut, ToProcess, ToStrategy, ToTimer, ToUi,
+    },
+    smf::{self, meta_event, split_messages, track_event, write_file},
+    util::list_action::ListAction,
+};
+
+/// Converts ticks to seconds. Ticks must be given in ascending order.
+struct Clock {
+    division: Division,
+    /// microseconds per quarter note
+    tempo: u32,
+    last_tick: u32,
+    last_seconds: f64,
+}
+
+impl Clock {
+    fn new(division: Division) -> Self {
+        Self {
+            division,
+            tempo: smf::DEFAULT_TEMPO,
+            last_tick: 0,
+            last_seconds: 0.0,
+        }
+    }
+
+    fn seconds_per_tick(&self) -> f64 {
+        match self.division {
+            Division::TicksPerQuarterNote(tpqn) => self.tempo as f64 / 1_000_000.0 / tpqn as f64,
+            Division::TimeCode {
+                frames_per_second,
+                ticks_per_frame,
+            } => {
+                let fps = match frames_per_second {
+                    TimeCodeType::FPS24 => 24.0,
+                    TimeCodeType::FPS25 => 25.0,
+                    TimeCodeType::DF30 => 29.97,
+                    TimeCodeType::NDF30 => 30.0,
+                };
+                1.0 / fps / ticks_per_frame as f64
+            }
+        }
+    }
+
+    /// Like [Clock::seconds], but doesn't advance the clock.
+    fn peek_seconds(&self, tick: u32) -> f64 {
+        let ticks = tick.saturating_sub(self.last_tick) as f64;
+        self.last_seconds + ticks * self.seconds_per_tick()
+    }
+
+    fn seconds(&mut self, tick: u32) -> f64 {
+        self.last_seconds = self.peek_seconds(tick);
+        self.last_tick = self.last_tick.max(tick);
+        self.last_seconds
+    }
+
+    /// The inverse of [Clock::peek_seconds]. `seconds` must not lie before the last tick that was
+    /// given.
+    fn tick_at(&self, seconds: f64) -> u32 {
+        let ticks = (seconds - self.last_seconds) / self.seconds_per_tick();
+        self.last_tick.saturating_add(ticks.max(0.0).round() as u32)
+    }
+
+    /// Only has an effect for [Division::TicksPerQuarterNote].
+    fn set_tempo(&mut self, tick: u32, tempo: u32) {
+        self.seconds(tick);
+        self.tempo = tempo;
+    }
+}
+
+/// The events of all tracks, with their absolute ticks, ordered by tick. Events at the same tick
+/// keep the order of the tracks.
+fn merged_events(file: &MidiFile) -> Vec<(u32, &MidiMsg)> {
+    let mut res = vec![];
+    for track in &file.tracks {
+        if let Track::Midi(events) = track {
+            let mut tick = 0u32;
+            for e in events {
+                tick = tick.saturating_add(e.delta_time);
+                res.push((tick, &e.event));
+            }
+        }
+    }
+    res.sort_by_key(|(tick, _)| *tick);
+    res
+}
+
+/// Runs the process and the backend synchronously, and collects the MIDI they send.
+struct Pipeline<'a, T: StackType, P, B> {
+    process: &'a mut P,
+    backend: &'a mut B,
+    process_tx: mpsc::Sender<FromProcess<T>>,
+    process_rx: mpsc::Receiver<FromProcess<T>>,
+    backend_tx: mpsc::Sender<FromBackend>,
+    backend_rx: mpsc::Receiver<FromBackend>,
+    output: Vec<(u32, Vec<u8>)>,
+    warnings: Vec<String>,
+    /// The times at which the process asked for a [ToStrategy::Tick].
+    wake_ups: BTreeSet<Instant>,
+}
+
+impl<'a, T, P, B> Pipeline<'a, T, P, B>
+where
+    T: StackType,
+    P: HandleMsg<ToProcess<T>, FromProcess<T>>,
+    B: HandleMsg<ToBackend, FromBackend>,
+{
+    fn new(process: &'a mut P, backend: &'a mut B) -> Self {
+        let (process_tx, process_rx) = mpsc::channel();
+        let (backend_tx, backend_rx) = mpsc::channel();
+        Self {
+            process,
+            backend,
+            process_tx,
+            process_rx,
+            backend_tx,
+            backend_rx,
+            output: vec![],
+            warnings: vec![],
+            wake_ups: BTreeSet::new(),
+        }
+    }
+
+    fn handle_to_midi_out_and_ui(
+        &mut self,
+        tick: u32,
+        midi: Option<ToMidiOut>,
+        ui: Option<ToUi<T>>,
+    ) {
+        if let Some(ToMidiOut::OutgoingMidi { bytes, .. }) = midi {
+            self.output.extend(
+                split_messages(&bytes)
+                    .iter()
+                    .map(|msg| (tick, track_event(msg))),
+            );
+        }
+        match ui {
+            Some(ToUi::Notify { line }) => self.warnings.push(line),
+            Some(ToUi::DetunedNote {
+                note,
+                should_be,
+                actual,
+                explanation,
+            }) => self.warnings.push(format!(
+                "note {note} should sound at {should_be:.2} semitones, but sounds at \
+                {actual:.2} ({explanation})"
+            )),
+            _ => {}
+        }
+    }
+
+    fn send_to_backend(&mut self, tick: u32, msg: ToBackend) {
+        self.backend.handle_msg(msg, &self.backend_tx);
+        while let Ok(msg) = self.backend_rx.try_recv() {
+            let (midi, ui) = msg.translate2();
+            self.handle_to_midi_out_and_ui(tick, midi, ui);
+        }
+    }
+
+    fn send_to_process(&mut self, tick: u32, msg: ToProcess<T>) {
+        self.process.handle_msg(msg, &self.process_tx);
+        while let Ok(msg) = self.process_rx.try_recv() {
+            let (to_backend, midi, ui, timer) = msg.translate4();
+            if let Some(msg) = to_backend {
+                self.send_to_backend(tick, msg);
+            }
+            self.handle_to_midi_out_and_ui(tick, midi, ui);
+            if let Some(ToTimer::WakeUp { time }) = timer {
+                self.wake_ups.insert(time);
+            }
+        }
+    }
+
+    /// Send the ticks the process asked for up to (and including) `until`. This may ask for
+    /// more ticks, which are also sent if they're due.
+    fn send_ticks(&mut self, clock: &Clock, start: Instant, until: Option<Instant>) {
+        while let Some(&time) = self.wake_ups.first() {
+            if until.is_some_and(|until| time > until) {
+                break;
+            }
+            self.wake_ups.pop_first();
+            let tick = clock.tick_at(time.saturating_duration_since(start).as_secs_f64());
+            self.send_to_process(tick, ToProcess::ToStrategy(ToStrategy::Tick { time }));
+        }
+    }
+}
+
+/// The result of [retune].
+pub struct Retuned {
+    /// The bytes of the retuned Standard MIDI File.
+    pub file: Vec<u8>,
+    /// Problems that would have been shown in the GUI, like notes that couldn't be tuned
+    /// correctly by the backend.
+    pub warnings: Vec<String>,
+}
+
+/// Retune the Standard MIDI File given by `input`, using `process` and `backend`, which should be
+/// freshly initialised. If `select_strategy` is given, that strategy is used instead of the
+/// first one. The backend must not glide (see [BackendConfig::without_glide]), because only the
+/// ticks asked for by the process are sent, and glides would stop at their first bend.
+///
+/// [BackendConfig::without_glide]: crate::config::BackendConfig::without_glide
+pub fn retune<T, P, B>(
+    input: &[u8],
+    process: &mut P,
+    backend: &mut B,
+    select_strategy: Option<usize>,
+) -> Result<Retuned, MidiFileParseError>
+where
+    T: StackType,
+    P: HandleMsg<ToProcess<T>, FromProcess<T>>,
+    B: HandleMsg<ToBackend, FromBackend>,
+{
+    let file = MidiFile::from_midi(input)?;
+    let mut clock = Clock::new(file.header.division);
+    let start = Instant::now();
+    let time_at = |clock: &mut Clock, tick| start + Duration::from_secs_f64(clock.seconds(tick));
+
+    let mut pipeline = Pipeline::new(process, backend);
+    let mut meta_events = vec![];
+
+    pipeline.send_to_backend(0, ToBackend::Start { time: start });
+    // Like [RunState::start_without_gui][crate::run::RunState::start_without_gui]: all zones
+    // are started, and the selection of a strategy only concerns the first one.
+    pipeline.send_to_process(0, ToProcess::Start { time: start });
+    if let Some(index) = select_strategy {
+        pipeline.send_to_process(
+            0,
+            ToProcess::StrategyListAction {
+                action: ListAction::Select(index),
+                time: start,
+            },
+        );
+    }
+
+    for (tick, event) in merged_events(&file) {
+        // Ticks that are due before the event are sent before the clock passes them.
+        let until = start + Duration::from_secs_f64(clock.peek_seconds(tick));
+        pipeline.send_ticks(&clock, start, Some(until));
+        match event {
+            MidiMsg::Meta {
+                msg: Meta::EndOfTrack,
+            } => {}
+            MidiMsg::Meta { msg } => {
+                if let Meta::SetTempo(tempo) = msg {
+                    clock.set_tempo(tick, *tempo);
+                }
+                meta_events.push((tick, meta_event(msg)));
+            }
+            _ => {
+                let time = time_at(&mut clock, tick);
+                for bytes in split_messages(&event.to_midi()) {
+                    pipeline.send_to_process(tick, ToProcess::IncomingMidi { time, bytes });
+                }
+            }
+        }
+    }
+
+    pipeline.send_ticks(&clock, start, None {});
+
+    Ok(Retuned {
+        file: write_file(file.header.division, &[meta_events, pipeline.output]),
+        warnings: pipeline.warnings,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use approx::assert_relative_eq;
+    use midi_msg::{Channel, ChannelVoiceMsg, ControlChange, TrackEvent};
+    use num_rational::Ratio;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{
+        backend::{
+            mpe::{MpeConfig, MpeZone},
+            pitchbend12::{Pitchbend12, Pitchbend12Config},
+            SomeBackend,
+        },
+        bindable::{Bindings, ControllerMapping, MappedParameter, MidiBindable},
+        config::{BackendConfig, FromConfigAndState, ProcessConfig, StrategyConfig},
+        interval::{
+            stack::Stack,
+            stacktype::fivelimit::mock::{
+                static_tuning_config, MockFiveLimitStackType, TWELVE_NOTES,
+            },
+        },
+        process::{
+            fromstrategy::ProcessFromStrategy,
+            zones::{ZoneConfig, ZonedProcess},
+        },
+        reference::Reference,
+        strategy::{
+            hysteresis::HysteresisConfig,
+            r#trait::StrategyAction,
+            springs::{SpringInterval, SpringsConfig},
+        },
+    };
+
+    #[test]
+    fn test_clock() {
+        let mut clock = Clock::new(Division::TicksPerQuarterNote(100));
+        assert_relative_eq!(clock.seconds(0), 0.0);
+        assert_relative_eq!(clock.seconds(200), 1.0);
+        clock.set_tempo(200, 1_000_000);
+        assert_relative_eq!(clock.seconds(300), 2.0);
+
+        let mut clock = Clock::new(Division::TimeCode {
+            frames_per_second: TimeCodeType::FPS25,
+            ticks_per_frame: 40,
+        });
+        assert_relative_eq!(clock.seconds(1000), 1.0);
+    }
+
+    fn note_event(delta_time: u32, msg: ChannelVoiceMsg) -> TrackEvent {
+        TrackEvent {
+            delta_time,
+            event: MidiMsg::ChannelVoice {
+                channel: Channel::Ch1,
+                msg,
+            },
+            beat_or_frame: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_retune() {
+        let input = MidiFile {
+            header: midi_msg::Header {
+                format: midi_msg::SMFFormat::MultiTrack,
+                num_tracks: 2,
+                division: Division::TicksPerQuarterNote(96),
+            },
+            tracks: vec![
+                Track::Midi(vec![TrackEvent {
+                    delta_time: 0,
+                    event: MidiMsg::Meta {
+                        msg: Meta::SetTempo(600_000),
+                    },
+                    beat_or_frame: 0.0,
+                }]),
+                Track::Midi(vec![
+                    note_event(
+                        96,
+                        ChannelVoiceMsg::NoteOn {
+                            note: 60,
+                            velocity: 100,
+                        },
+                    ),
+                    note_event(
+                        0,
+                        ChannelVoiceMsg::NoteOn {
+                            note: 64,
+                            velocity: 100,
+                        },
+                    ),
+                    note_event(
+                        96,
+                        ChannelVoiceMsg::NoteOff {
+                            note: 64,
+                            velocity: 0,
+                        },
+                    ),
+                ]),
+            ],
+        };
+
+        let strategy = StrategyConfig::<MockFiveLimitStackType>::Springs(SpringsConfig {
+            intervals: vec![SpringInterval {
+                interval: Stack::from_target(vec![0, 0, 1]),
+                stiffness: Ratio::new(1, 5),
+            }],
+            anchor_stiffness: Ratio::new(1, 100),
+            tuning_reference: Reference::from_semitones(Stack::new_zero(), 60.0),
+            reference: Stack::new_zero(),
+        });
+        let mut process = ProcessFromStrategy::new(vec![(strategy.realize(), Bindings::empty())]);
+        let mut backend = Pitchbend12::new(Pitchbend12Config {
+            bend_range: 2.0,
+            channels: core::array::from_fn(|i| Channel::from_u8(i as u8).into()),
+            glide: None {},
+        });
+
+        let Retuned { file, warnings } =
+            retune(&input.to_midi(), &mut process, &mut backend, None {}).unwrap();
+        assert_eq!(warnings, Vec::<String>::new());
+
+        let output = MidiFile::from_midi(&file).unwrap();
+        assert_eq!(output.header.division, Division::TicksPerQuarterNote(96));
+        let events = |i: usize| match &output.tracks[i] {
+            Track::Midi(events) => {
+                let mut tick = 0;
+                events
+                    .iter()
+                    .map(|e| {
+                        tick += e.delta_time;
+                        (tick, e.event.clone())
+                    })
+                    .collect::<Vec<_>>()
+            }
+            Track::AlienChunk(_) => panic!(),
+        };
+
+        assert_eq!(
+            events(0),
+            vec![
+                (
+                    0,
+                    MidiMsg::Meta {
+                        msg: Meta::SetTempo(600_000)
+                    }
+                ),
+                (
+                    0,
+                    MidiMsg::Meta {
+                        msg: Meta::EndOfTrack
+                    }
+                ),
+            ]
+        );
+
+        // the pure major third is lower than the equally tempered one, and E is played on its
+        // own channel
+        let performance: Vec<_> = events(1)
+            .into_iter()
+            .filter(|(tick, _)| *tick > 0)
+            .collect();
+        let bend_e = performance.iter().find_map(|(tick, e)| match e {
+            MidiMsg::ChannelVoice {
+                channel: Channel::Ch5,
+                msg: ChannelVoiceMsg::PitchBend { bend },
+            } => Some((*tick, *bend)),
+            _ => None {},
+        });
+        assert!(matches!(bend_e, Some((96, bend)) if bend < 8192));
+        assert!(performance.contains(&(
+            96,
+            MidiMsg::ChannelVoice {
+                channel: Channel::Ch5,
+                msg: ChannelVoiceMsg::NoteOn {
+                    note: 64,
+                    velocity: 100
+                }
+            }
+        )));
+        assert!(performance.contains(&(
+            192,
+            MidiMsg::ChannelVoice {
+                channel: Channel::Ch5,
+                msg: ChannelVoiceMsg::NoteOff {
+                    note: 64,
+                    velocity: 0
+                }
+            }
+        )));
+    }
+
+    #[test]
+    fn test_ticks() {
+        // one tick is 6.25ms
+        let input = MidiFile {
+            header: midi_msg::Header {
+                format: midi_msg::SMFFormat::MultiTrack,
+                num_tracks: 1,
+                division: Division::TicksPerQuarterNote(96),
+            },
+            tracks: vec![Track::Midi(vec![
+                TrackEvent {
+                    delta_time: 0,
+                    event: MidiMsg::Meta {
+                        msg: Meta::SetTempo(600_000),
+                    },
+                    beat_or_frame: 0.0,
+                },
+                note_event(
+                    96,
+                    ChannelVoiceMsg::NoteOn {
+                        note: 60,
+                        velocity: 100,
+                    },
+                ),
+                note_event(96, ChannelVoiceMsg::ProgramChange { program: 1 }),
+                note_event(
+                    288,
+                    ChannelVoiceMsg::NoteOff {
+                        note: 60,
+                        velocity: 0,
+                    },
+                ),
+            ])],
+        };
+
+        // The program change switches to a neighbourhood where C is a syntonic comma higher.
+        // Re-tuning the sounding C is held back for 100ms.
+        let mut raised = TWELVE_NOTES;
+        raised[0] = [-2, 4, -1];
+        let strategy = StrategyConfig::<MockFiveLimitStackType>::Hysteresis(HysteresisConfig {
+            inner: Box::new(StrategyConfig::StaticTuning(static_tuning_config(&[
+                TWELVE_NOTES,
+                raised,
+            ]))),
+            stable_ms: 100,
+            max_retune_cents: None {},
+            freeze: false,
+        });
+        let mut bindings = Bindings::empty();
+        bindings.insert(
+            MidiBindable::ProgramChange {
+                channel: None {},
+                program: 1,
+            },
+            StrategyAction::SwitchToNeighbourhood(1),
+        );
+        let mut process = ProcessFromStrategy::new(vec![(strategy.realize(), bindings)]);
+        let mut backend = Pitchbend12::new(Pitchbend12Config {
+            bend_range: 2.0,
+            channels: core::array::from_fn(|i| Channel::from_u8(i as u8).into()),
+            glide: None {},
+        });
+
+        let Retuned { file, .. } =
+            retune(&input.to_midi(), &mut process, &mut backend, None {}).unwrap();
+        let output = MidiFile::from_midi(&file).unwrap();
+        let Track::Midi(events) = &output.tracks[1] else {
+            panic!();
+        };
+        let mut tick = 0;
+        let bends_c: Vec<_> = events
+            .iter()
+            .filter_map(|e| {
+                tick += e.delta_time;
+                match e.event {
+                    MidiMsg::ChannelVoice {
+                        channel: Channel::Ch1,
+                        msg: ChannelVoiceMsg::PitchBend { .. },
+                    } if tick > 0 => Some(tick),
+                    _ => None {},
+                }
+            })
+            .collect();
+        assert_eq!(bends_c, vec![208]);
+    }
+
+    #[test]
+    fn test_zones_with_selected_strategy() {
+        let input = MidiFile {
+            header: midi_msg::Header {
+                format: midi_msg::SMFFormat::SingleTrack,
+                num_tracks: 1,
+                division: Division::TicksPerQuarterNote(96),
+            },
+            tracks: vec![Track::Midi(vec![
+                note_event(
+                    96,
+                    ChannelVoiceMsg::NoteOn {
+                        note: 52,
+                        velocity: 100,
+                    },
+                ),
+                note_event(
+                    0,
+                    ChannelVoiceMsg::NoteOn {
+                        note: 64,
+                        velocity: 100,
+                    },
+                ),
+                note_event(
+                    96,
+                    ChannelVoiceMsg::ControlChange {
+                        control: ControlChange::Undefined {
+                            control: 20,
+                            value: 127,
+                        },
+                    },
+                ),
+            ])],
+        };
+
+        // The selected Pythagorean tuning applies only to the left zone. The right zone keeps the
+        // first strategy, whose tuning reference can only be moved once it has been started.
+        let mut pythagorean = TWELVE_NOTES;
+        pythagorean[4] = [-2, 4, 0];
+        let zone = |name: &str, lowest, highest| ZoneConfig {
+            name: name.into(),
+            lowest,
+            highest,
+            channels: vec![],
+            strategy: 0,
+            share_reference: false,
+        };
+        let mut process = ZonedProcess::initialise(
+            ProcessConfig {
+                strategies: vec![
+                    (
+                        StrategyConfig::StaticTuning(static_tuning_config(&[TWELVE_NOTES])),
+                        Bindings::empty().with_controllers(vec![ControllerMapping {
+                            channel: None {},
+                            controller: 20,
+                            high_resolution: false,
+                            parameter: MappedParameter::TuningReferenceFrequency,
+                            min: 250.0,
+                            max: 270.0,
+                        }]),
+                    ),
+                    (
+                        StrategyConfig::StaticTuning(static_tuning_config(&[pythagorean])),
+                        Bindings::empty(),
+                    ),
+                ],
+                zones: vec![zone("left", 0, 59), zone("right", 60, 127)],
+            },
+            (),
+        );
+        let mut backend = SomeBackend::initialise(
+            BackendConfig::Zoned(vec![
+                BackendConfig::Pitchbend12(Pitchbend12Config {
+                    bend_range: 2.0,
+                    channels: core::array::from_fn(|i| Channel::from_u8(i as u8).into()),
+                    glide: None {},
+                }),
+                BackendConfig::Mpe(MpeConfig {
+                    zone: MpeZone::Upper,
+                    member_channels: 3,
+                    bend_range: 2,
+                }),
+            ]),
+            (),
+        );
+
+        let Retuned { file, warnings } =
+            retune(&input.to_midi(), &mut process, &mut backend, Some(1)).unwrap();
+        assert_eq!(warnings, Vec::<String>::new());
+        let output = MidiFile::from_midi(&file).unwrap();
+        let Track::Midi(events) = &output.tracks[1] else {
+            panic!();
+        };
+        let bends: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e.event {
+                MidiMsg::ChannelVoice {
+                    channel,
+                    msg: ChannelVoiceMsg::PitchBend { bend },
+                } => Some((channel, bend)),
+                _ => None {},
+            })
+            .collect();
+        // After the initial bends: the Pythagorean third in the left zone, the pure third in the
+        // right zone, and the latter again after its tuning reference is moved to 270Hz.
+        assert_eq!(
+            bends[15..],
+            [
+                (Channel::Ch5, 8512),
+                (Channel::Ch15, 7631),
+                (Channel::Ch15, 9865)
+            ]
+        );
+    }
+}
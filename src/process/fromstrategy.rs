@@ -1,4 +1,10 @@
-use std::{collections::VecDeque, fmt, sync::mpsc, time::Instant};
+use std::{
+    collections::VecDeque,
+    fmt,
+    path::PathBuf,
+    sync::mpsc,
+    time::{Instant, SystemTime},
+};
 
 use midi_msg::{
     Channel,
@@ -6,16 +12,26 @@ use midi_msg::{
     ControlChange::{Hold, SoftPedal, Sostenuto},
     MidiMsg,
 };
+use num_rational::Ratio;
 
 use crate::{
-    bindable::{Bindings, MidiBindable},
+    bindable::{Bindings, MappedParameter, MidiBindable, MidiEvent},
     config::{ExtractConfig, FromConfigAndState, ProcessConfig},
-    interval::{stack::Stack, stacktype::r#trait::StackType},
+    interval::{
+        stack::Stack,
+        stacktype::r#trait::{StackCoeff, StackType},
+    },
     keystate::KeyState,
     msg::{FromProcess, FromStrategy, HandleMsg, ToProcess, ToStrategy},
-    strategy::r#trait::Strategy,
+    reference::Reference,
+    scala::TuningFiles,
+    strategy::r#trait::{Strategy, StrategyAction},
 };
 
+/// Partially applied temperaments are rounded to multiples of one over this number, to keep the
+/// rational coefficients of the tempered stacks small.
+const TEMPERAMENT_AMOUNT_STEPS: StackCoeff = 120;
+
 pub struct ProcessFromStrategy<T: StackType> {
     strategies: Vec<(Box<dyn Strategy<T>>, Bindings<MidiBindable>)>,
     curr_strategy_index: Option<usize>,
@@ -24,6 +40,13 @@ pub struct ProcessFromStrategy<T: StackType> {
     pedal_hold: [bool; 16],
     sostenuto_hold: [bool; 16],
     soft_hold: [bool; 16],
+    /// The last value of every controller on every channel, to detect threshold crossings.
+    controller_values: [[u8; 128]; 16],
+    learning: bool,
+    /// The last tuning reference and neighbourhood index the current strategy sent, needed to
+    /// apply [MappedParameter]s.
+    tuning_reference: Option<Reference<T>>,
+    curr_neighbourhood_index: Option<usize>,
     queue: VecDeque<FromStrategy<T>>,
 }
 
@@ -42,9 +65,21 @@ impl<T: StackType> ProcessFromStrategy<T> {
             pedal_hold: [false; 16],
             sostenuto_hold: [false; 16],
             soft_hold: [false; 16],
+            controller_values: [[0; 128]; 16],
+            learning: false,
+            tuning_reference: None {},
+            curr_neighbourhood_index: None {},
             queue: VecDeque::new(),
         }
     }
+
+    /// Start with the strategy with the given index instead of the first one.
+    pub fn starting_with(mut self, index: usize) -> Self {
+        if index < self.strategies.len() {
+            self.curr_strategy_index = Some(index);
+        }
+        self
+    }
 }
 
 impl<T: StackType> ProcessFromStrategy<T> {
@@ -80,7 +115,7 @@ impl<T: StackType> ProcessFromStrategy<T> {
                     },
             } => {
                 if let Some(csi) = self.curr_strategy_index {
-                    let (ref mut strategy, ref bindings) = self.strategies[csi];
+                    let (_, ref bindings) = self.strategies[csi];
                     let was_down = self.sostenuto_hold.iter().any(|b| *b);
                     self.sostenuto_hold[channel as usize] = value > 0;
                     let is_down = self.sostenuto_hold.iter().any(|b| *b);
@@ -90,15 +125,7 @@ impl<T: StackType> ProcessFromStrategy<T> {
                         _ => None {},
                     };
                     if let Some(&action) = action {
-                        let _ = strategy.handle_msg(
-                            &self.key_states,
-                            &mut self.tunings,
-                            ToStrategy::Action { action, time },
-                            &mut self.queue,
-                        );
-                        self.queue.drain(..).for_each(|msg| {
-                            let _ = forward.send(FromProcess::FromStrategy(msg));
-                        });
+                        self.handle_action(csi, action, time, forward);
                     } else {
                         forward_untouched();
                     }
@@ -113,7 +140,7 @@ impl<T: StackType> ProcessFromStrategy<T> {
                     },
             } => {
                 if let Some(csi) = self.curr_strategy_index {
-                    let (ref mut strategy, ref bindings) = self.strategies[csi];
+                    let (_, ref bindings) = self.strategies[csi];
                     let was_down = self.soft_hold.iter().any(|b| *b);
                     self.soft_hold[channel as usize] = value > 0;
                     let is_down = self.soft_hold.iter().any(|b| *b);
@@ -123,15 +150,7 @@ impl<T: StackType> ProcessFromStrategy<T> {
                         _ => None {},
                     };
                     if let Some(&action) = action {
-                        let _ = strategy.handle_msg(
-                            &self.key_states,
-                            &mut self.tunings,
-                            ToStrategy::Action { action, time },
-                            &mut self.queue,
-                        );
-                        self.queue.drain(..).for_each(|msg| {
-                            let _ = forward.send(FromProcess::FromStrategy(msg));
-                        });
+                        self.handle_action(csi, action, time, forward);
                     } else {
                         forward_untouched();
                     }
@@ -153,6 +172,152 @@ impl<T: StackType> ProcessFromStrategy<T> {
         }
     }
 
+    /// Learn a new bindable, or trigger the actions bound to the event. Returns true iff the
+    /// event must not be processed any further.
+    ///
+    /// The pedal bindables are not handled here, but in [Self::handle_midi].
+    fn handle_midi_bindings(
+        &mut self,
+        time: Instant,
+        bytes: &[u8],
+        forward: &mpsc::Sender<FromProcess<T>>,
+    ) -> bool {
+        let Some(event) = MidiEvent::from_bytes(bytes) else {
+            return false;
+        };
+
+        if self.learning {
+            if let Some(bindable) = MidiBindable::learn(&event) {
+                self.learning = false;
+                let _ = forward.send(FromProcess::LearnedBindable { bindable });
+                return true;
+            }
+        }
+
+        let previous = match event {
+            MidiEvent::Controller {
+                channel,
+                controller,
+                value,
+            } => std::mem::replace(
+                &mut self.controller_values[channel as usize][controller as usize],
+                value,
+            ),
+            _ => 0,
+        };
+
+        let Some(csi) = self.curr_strategy_index else {
+            return false;
+        };
+        let mut swallow = false;
+        let mut actions = vec![];
+        for (bindable, action) in self.strategies[csi].1.iter() {
+            swallow |= bindable.swallows(&event);
+            if bindable.triggered_by(&event, previous) {
+                actions.push(*action);
+            }
+        }
+        for action in actions {
+            self.handle_action(csi, action, time, forward);
+        }
+
+        if let MidiEvent::Controller {
+            channel,
+            controller,
+            ..
+        } = event
+        {
+            let mut parameters = vec![];
+            for mapping in &self.strategies[csi].1.controllers {
+                if !mapping.listens_to(channel, controller) {
+                    continue;
+                }
+                swallow = true;
+                let values = &mut self.controller_values[channel as usize];
+                if mapping.high_resolution && controller == mapping.controller {
+                    // A new MSB resets the LSB.
+                    if let Some(lsb) = values.get_mut(controller as usize + 32) {
+                        *lsb = 0;
+                    }
+                }
+                let msb = values[mapping.controller as usize];
+                let lsb = values.get(mapping.controller as usize + 32).map_or(0, |x| *x);
+                parameters.push((mapping.parameter, mapping.value(msb, lsb)));
+            }
+            for (parameter, value) in parameters {
+                self.set_mapped_parameter(csi, parameter, value, time, forward);
+            }
+        }
+
+        swallow
+    }
+
+    fn set_mapped_parameter(
+        &mut self,
+        csi: usize,
+        parameter: MappedParameter,
+        value: f64,
+        time: Instant,
+        forward: &mpsc::Sender<FromProcess<T>>,
+    ) {
+        let msg = match parameter {
+            MappedParameter::GroupMs => ToStrategy::SetGroupMs {
+                group_ms: value.max(0.0).round() as u64,
+            },
+            MappedParameter::TuningReferenceFrequency => match &self.tuning_reference {
+                Some(reference) => ToStrategy::SetTuningReference {
+                    reference: Reference::from_frequency(reference.stack.clone(), value),
+                    time,
+                },
+                None {} => return,
+            },
+            MappedParameter::BendRange => {
+                let _ = forward.send(FromProcess::BendRange { range: value, time });
+                return;
+            }
+            MappedParameter::TemperamentAmount { temperament } => {
+                match self.curr_neighbourhood_index {
+                    Some(neighbourhood) if temperament < T::temperaments().len() => {
+                        ToStrategy::ApplyTemperamentToNeighbourhood {
+                            neighbourhood,
+                            temperament,
+                            amount: Ratio::new(
+                                (value * TEMPERAMENT_AMOUNT_STEPS as f64).round() as StackCoeff,
+                                TEMPERAMENT_AMOUNT_STEPS,
+                            ),
+                            time,
+                        }
+                    }
+                    _ => return,
+                }
+            }
+        };
+        let _ = self.strategies[csi].0.handle_msg(
+            &self.key_states,
+            &mut self.tunings,
+            msg,
+            &mut self.queue,
+        );
+        self.flush_queue(forward);
+    }
+
+    /// Forward all messages from the strategy, and remember the ones needed for
+    /// [Self::set_mapped_parameter].
+    fn flush_queue(&mut self, forward: &mpsc::Sender<FromProcess<T>>) {
+        for msg in self.queue.drain(..) {
+            match &msg {
+                FromStrategy::SetTuningReference { reference } => {
+                    self.tuning_reference = Some(reference.clone());
+                }
+                FromStrategy::CurrentNeighbourhoodIndex { index } => {
+                    self.curr_neighbourhood_index = Some(*index);
+                }
+                _ => {}
+            }
+            let _ = forward.send(FromProcess::FromStrategy(msg));
+        }
+    }
+
     fn handle_note_on(
         &mut self,
         time: Instant,
@@ -188,9 +353,7 @@ impl<T: StackType> ProcessFromStrategy<T> {
                             tuning_stack: tuning_stack.clone(),
                             time,
                         });
-                        self.queue.drain(..).for_each(|msg| {
-                            let _ = forward.send(FromProcess::FromStrategy(msg));
-                        });
+                        self.flush_queue(forward);
                     }
                     None {} => {
                         send_simple_note_on();
@@ -224,9 +387,7 @@ impl<T: StackType> ProcessFromStrategy<T> {
                     time,
                     &mut self.queue,
                 );
-                self.queue.drain(..).for_each(|msg| {
-                    let _ = forward.send(FromProcess::FromStrategy(msg));
-                });
+                self.flush_queue(forward);
             }
             let _ = forward.send(FromProcess::NoteOff {
                 channel,
@@ -259,9 +420,7 @@ impl<T: StackType> ProcessFromStrategy<T> {
                             time,
                             &mut self.queue,
                         );
-                        self.queue.drain(..).for_each(|msg| {
-                            let _ = forward.send(FromProcess::FromStrategy(msg));
-                        });
+                        self.flush_queue(forward);
                     }
                 }
             }
@@ -273,6 +432,91 @@ impl<T: StackType> ProcessFromStrategy<T> {
         }
     }
 
+    /// Write the current tuning of all keys, or the neighbourhood with the given index of the
+    /// current strategy, to files in the working directory.
+    fn export_tuning(&self, neighbourhood: Option<usize>) -> Result<Vec<PathBuf>, String> {
+        let seconds = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let (name, files) = match neighbourhood {
+            None {} => {
+                let c4_semitones = self
+                    .tuning_reference
+                    .as_ref()
+                    .ok_or("the current strategy has not set a tuning reference")?
+                    .c4_semitones();
+                let semitones =
+                    core::array::from_fn(|i| self.tunings[i].absolute_semitones(c4_semitones));
+                let name = format!("adaptuner-tuning-{seconds}");
+                let files = TuningFiles::of_tuning(&name, &semitones);
+                (name, files)
+            }
+            Some(index) => {
+                let csi = self
+                    .curr_strategy_index
+                    .ok_or("there is no current strategy")?;
+                let config = self.strategies[csi].0.extract_config();
+                let config = config
+                    .static_tuning()
+                    .ok_or("the current strategy has no neighbourhoods")?;
+                let neighbourhood = config
+                    .neighbourhoods
+                    .get(index)
+                    .ok_or(format!("there is no neighbourhood {index}"))?;
+                let name = format!("adaptuner-neighbourhood-{index}-{seconds}");
+                let files = TuningFiles::of_neighbourhood(
+                    &name,
+                    neighbourhood,
+                    &config.reference,
+                    config.tuning_reference.c4_semitones(),
+                )?;
+                (name, files)
+            }
+        };
+        let directory = std::env::current_dir().unwrap_or(PathBuf::from("."));
+        files
+            .write(&directory.join(name))
+            .map_err(|e| format!("couldn't write the tuning files: {e}"))
+    }
+
+    /// [StrategyAction::ToggleRecording] is not for the strategy, but for the recorder, and
+    /// [StrategyAction::SwitchToStrategy] and [StrategyAction::ExportTuning] are handled here.
+    fn handle_action(
+        &mut self,
+        csi: usize,
+        action: StrategyAction,
+        time: Instant,
+        forward: &mpsc::Sender<FromProcess<T>>,
+    ) {
+        match action {
+            StrategyAction::ToggleRecording => {
+                let _ = forward.send(FromProcess::ToggleRecording { time });
+                return;
+            }
+            StrategyAction::ExportTuning => {
+                let _ = forward.send(FromProcess::TuningExported {
+                    result: self.export_tuning(None {}),
+                });
+                return;
+            }
+            StrategyAction::SwitchToStrategy(index) => {
+                if index < self.strategies.len() && Some(index) != self.curr_strategy_index {
+                    self.curr_strategy_index = Some(index);
+                    self.start(time, forward);
+                }
+                return;
+            }
+            _ => {}
+        }
+        let _ = self.strategies[csi].0.handle_msg(
+            &self.key_states,
+            &mut self.tunings,
+            ToStrategy::Action { action, time },
+            &mut self.queue,
+        );
+        self.flush_queue(forward);
+    }
+
     fn start(&mut self, time: Instant, forward: &mpsc::Sender<FromProcess<T>>) {
         if let Some(csi) = self.curr_strategy_index {
             self.strategies[csi].0.start(
@@ -281,9 +525,7 @@ impl<T: StackType> ProcessFromStrategy<T> {
                 time,
                 &mut self.queue,
             );
-            self.queue.drain(..).for_each(|msg| {
-                let _ = forward.send(FromProcess::FromStrategy(msg));
-            });
+            self.flush_queue(forward);
         }
         let _ = forward.send(FromProcess::CurrentStrategyIndex(self.curr_strategy_index));
     }
@@ -297,12 +539,17 @@ impl<T: StackType + fmt::Debug + 'static> HandleMsg<ToProcess<T>, FromProcess<T>
             ToProcess::Stop => {}
             ToProcess::Reset { time } => self.start(time, forward),
             ToProcess::Start { time } => self.start(time, forward),
-            ToProcess::IncomingMidi { time, bytes } => match MidiMsg::from_midi(&bytes) {
-                Ok((msg, _)) => self.handle_midi(time, msg, forward), // TODO: multi-part messages?
-                Err(e) => {
-                    let _ = forward.send(FromProcess::MidiParseErr(e.to_string()));
+            ToProcess::IncomingMidi { time, bytes } => {
+                if self.handle_midi_bindings(time, &bytes, forward) {
+                    return;
                 }
-            },
+                match MidiMsg::from_midi(&bytes) {
+                    Ok((msg, _)) => self.handle_midi(time, msg, forward), // TODO: multi-part messages?
+                    Err(e) => {
+                        let _ = forward.send(FromProcess::MidiParseErr(e.to_string()));
+                    }
+                }
+            }
             ToProcess::NoteOn {
                 channel,
                 note,
@@ -320,6 +567,11 @@ impl<T: StackType + fmt::Debug + 'static> HandleMsg<ToProcess<T>, FromProcess<T>
                 value,
                 time,
             } => self.handle_pedal_hold(time, channel, value, forward),
+            ToProcess::ToStrategy(ToStrategy::Action { action, time }) => {
+                if let Some(csi) = self.curr_strategy_index {
+                    self.handle_action(csi, action, time, forward);
+                }
+            }
             ToProcess::ToStrategy(msg) => {
                 if let Some(csi) = self.curr_strategy_index {
                     let _success = self.strategies[csi].0.handle_msg(
@@ -328,9 +580,7 @@ impl<T: StackType + fmt::Debug + 'static> HandleMsg<ToProcess<T>, FromProcess<T>
                         msg,
                         &mut self.queue,
                     );
-                    self.queue.drain(..).for_each(|msg| {
-                        let _ = forward.send(FromProcess::FromStrategy(msg));
-                    });
+                    self.flush_queue(forward);
                 }
             }
             ToProcess::StrategyListAction { action, time } => {
@@ -351,6 +601,12 @@ impl<T: StackType + fmt::Debug + 'static> HandleMsg<ToProcess<T>, FromProcess<T>
                     }
                 }
             }
+            ToProcess::LearnBindable { learn } => self.learning = learn,
+            ToProcess::ExportTuning { neighbourhood } => {
+                let _ = forward.send(FromProcess::TuningExported {
+                    result: self.export_tuning(neighbourhood),
+                });
+            }
             ToProcess::GetCurrentConfig => {
                 let _ = forward.send(FromProcess::CurrentConfig(self.extract_config()));
             }
@@ -374,13 +630,14 @@ impl<T: StackType> ExtractConfig<ProcessConfig<T>> for ProcessFromStrategy<T> {
                 .iter()
                 .map(|(s, b)| (s.extract_config(), b.clone()))
                 .collect(),
+            zones: vec![],
         }
     }
 }
 
 impl<T: StackType, S> FromConfigAndState<ProcessConfig<T>, S> for ProcessFromStrategy<T> {
     fn initialise(config: ProcessConfig<T>, _state: S) -> Self {
-        let ProcessConfig { mut strategies } = config;
+        let ProcessConfig { mut strategies, .. } = config;
         Self::new(
             strategies
                 .drain(..)
@@ -1,5 +1,4 @@
 pub mod fromstrategy;
 pub mod onlyforward;
-pub mod sketch;
 pub mod springs;
-pub mod walking;
+pub mod zones;
@@ -1,224 +0,0 @@
-// use std::{fmt, sync::mpsc, time::{Duration, Instant}};
-//
-// use midi_msg::{ChannelVoiceMsg, ControlChange, MidiMsg};
-//
-// use crate::{
-//     interval::{Stack, StackCoeff},
-//     msg,
-//     neighbourhood::Neighbourhood,
-//     pattern::{Fit, Pattern},
-//     process::r#trait::ProcessState,
-//     util::dimension::{AtLeast, Bounded, Dimension, Vector},
-// };
-//
-// #[derive(Clone, Debug)]
-// pub struct TuningFrame<D: Dimension, T: Dimension> {
-//     pub reference_stack: Stack<D, T>,
-//     pub reference_key: u8,
-//     pub neighbourhood: Neighbourhood<D>,
-//     pub active_temperaments: Vector<T, bool>,
-// }
-//
-// #[derive(Debug)]
-// pub struct Config<'a> {
-//     pub patterns: &'a [Pattern],
-//     pub minimum_age: Duration, // microseconds
-// }
-//
-// #[derive(Debug)]
-// pub struct State<'a, D: Dimension, T: Dimension> {
-//     pub current: TuningFrame<D, T>,
-//     pub old: TuningFrame<D, T>,
-//     pub birthday: Instant, // microseconds
-//     pub active_notes: [bool; 128],
-//
-//     pub sustain: u8,
-//
-//     pub config: Config<'a>,
-// }
-//
-// impl<'a, D, T> State<'a, D, T>
-// where
-//     D: AtLeast<1> + Copy + fmt::Debug,
-//     T: Dimension + Copy,
-// {
-//     /// Go through all currently active notes and send [Retune][msg::ToBackend::Retune] messages to
-//     /// to the backend, describing their current tunings.
-//     fn send_retunes(&self, to_backend: &mpsc::Sender<(Instant, msg::ToBackend)>, time: Instant) {
-//         for i in 0..128 {
-//             if self.active_notes[i] {
-//                 let tuning = stack_from_tuning_frame(&self.current, i as u8).semitones();
-//                 to_backend
-//                     .send((
-//                         time,
-//                         msg::ToBackend::Retune {
-//                             tuning,
-//                             note: i as u8,
-//                         },
-//                     ))
-//                     .unwrap_or(())
-//             }
-//         }
-//     }
-// }
-//
-// impl<'a, D, T> ProcessState<D, T> for State<'a, D, T>
-// where
-//     D: AtLeast<1> + Clone + Copy + fmt::Debug + PartialEq,
-//     T: Dimension + Clone + Copy + PartialEq,
-// {
-//     fn handle_msg(
-//         &mut self,
-//         time: Instant,
-//         msg: msg::ToProcess<D, T>,
-//         to_backend: &mpsc::Sender<(Instant, msg::ToBackend)>,
-//         to_ui: &mpsc::Sender<(Instant, msg::ToUI<D, T>)>,
-//     ) {
-//         match msg {
-//             msg::ToProcess::Start => {}
-//             msg::ToProcess::Stop => {}
-//             msg::ToProcess::SetNeighboughood { neighbourhood: n } => {
-//                 self.current.neighbourhood = n;
-//                 self.send_retunes(&to_backend, time);
-//             }
-//             msg::ToProcess::ToggleTemperament { index: t } => {
-//                 self.current.active_temperaments[t] = !self.current.active_temperaments[t];
-//                 self.send_retunes(&to_backend, time);
-//             }
-//             msg::ToProcess::IncomingMidi { bytes } => {
-//                 self.handle_midi_msg(time, &bytes, to_backend, to_ui);
-//             }
-//         }
-//     }
-// }
-//
-// impl<'a, D, T> State<'a, D, T>
-// where
-//     D: Dimension + AtLeast<1> + fmt::Debug + Copy + PartialEq,
-//     T: Dimension + Copy + PartialEq,
-// {
-//     fn handle_midi_msg(
-//         &mut self,
-//         time: Instant,
-//         bytes: &Vec<u8>,
-//         to_backend: &mpsc::Sender<(Instant, msg::ToBackend)>,
-//         to_ui: &mpsc::Sender<(Instant, msg::ToUI<D, T>)>,
-//     ) {
-//         let send_to_backend =
-//             |msg: msg::ToBackend, time: Instant| to_backend.send((time, msg)).unwrap_or(());
-//
-//         let send_to_ui =
-//             |msg: msg::ToUI<D, T>, time: Instant| to_ui.send((time, msg)).unwrap_or(());
-//
-//         if time - self.birthday >= self.config.minimum_age {
-//             self.old.clone_from(&self.current); // TODO: clone-free option?
-//         }
-//
-//         match MidiMsg::from_midi(&bytes) {
-//             Err(e) => send_to_ui(msg::ToUI::MidiParseErr(e), time),
-//             Ok((msg, _number_of_bytes_parsed)) => match msg {
-//                 MidiMsg::ChannelVoice {
-//                     channel,
-//                     msg: ChannelVoiceMsg::NoteOn { note, velocity },
-//                 } => {
-//                     send_to_backend(
-//                         msg::ToBackend::TunedNoteOn {
-//                             channel,
-//                             note,
-//                             velocity,
-//                             tuning: stack_from_tuning_frame(&self.current, note).semitones(),
-//                         },
-//                         time,
-//                     );
-//                     send_to_ui(msg::ToUI::NoteOn { note }, time);
-//
-//                     if !self.active_notes[note as usize] {
-//                         self.active_notes[note as usize] = true;
-//                         for p in self.config.patterns {
-//                             match p.fit(&self.active_notes, 0) {
-//                                 Fit { reference, next } => {
-//                                     if next == 128 && self.current.reference_key != reference as u8
-//                                     {
-//                                         self.birthday = time;
-//                                         self.current.reference_key = reference as u8;
-//                                         self.current.reference_stack =
-//                                             stack_from_tuning_frame(&self.old, reference as u8);
-//                                     }
-//                                     break;
-//                                 }
-//                             }
-//                         }
-//                         self.send_retunes(to_backend, time);
-//                     }
-//                     send_to_ui(
-//                         msg::ToUI::SetReference {
-//                             key: self.current.reference_key,
-//                             stack: self.current.reference_stack.clone(),
-//                         },
-//                         time,
-//                     );
-//                 }
-//
-//                 MidiMsg::ChannelVoice {
-//                     channel,
-//                     msg: ChannelVoiceMsg::NoteOff { note, velocity },
-//                 } => {
-//                     if self.sustain == 0 {
-//                         self.active_notes[note as usize] = false;
-//                         send_to_ui(msg::ToUI::NoteOff { note }, time);
-//                     }
-//                     send_to_backend(
-//                         msg::ToBackend::NoteOff {
-//                             channel,
-//                             note,
-//                             velocity,
-//                         },
-//                         time,
-//                     );
-//                 }
-//
-//                 MidiMsg::ChannelVoice {
-//                     channel,
-//                     msg:
-//                         ChannelVoiceMsg::ControlChange {
-//                             control: ControlChange::Hold(value),
-//                         },
-//                 } => {
-//                     self.sustain = value;
-//                     send_to_backend(msg::ToBackend::Sustain { channel, value }, time);
-//                     for i in 0..128 {
-//                         if !self.active_notes[i as usize] {
-//                             send_to_ui(msg::ToUI::NoteOff { note: i }, time);
-//                         }
-//                     }
-//                 }
-//
-//                 MidiMsg::ChannelVoice {
-//                     channel,
-//                     msg: ChannelVoiceMsg::ProgramChange { program },
-//                 } => send_to_backend(msg::ToBackend::ProgramChange { channel, program }, time),
-//
-//                 _ => to_backend
-//                     .send((time, msg::ToBackend::ForwardMidi { msg }))
-//                     .unwrap_or(()),
-//             },
-//         }
-//     }
-// }
-//
-// fn stack_from_tuning_frame<D, T>(frame: &TuningFrame<D, T>, key: u8) -> Stack<D, T>
-// where
-//     D: AtLeast<1> + Copy + fmt::Debug,
-//     T: Dimension + Copy,
-// {
-//     let d = key as StackCoeff - frame.reference_key as StackCoeff;
-//     let (q, r) = (d.div_euclid(12), d.rem_euclid(12));
-//     let mut res = frame.reference_stack.clone();
-//     res.increment(
-//         &frame.active_temperaments,
-//         &frame.neighbourhood.coefficients[r as usize],
-//     );
-//     res.increment_at_index(&frame.active_temperaments, Bounded::new(0).unwrap(), q);
-//
-//     res
-// }
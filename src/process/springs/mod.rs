@@ -1,3 +1,2 @@
-pub mod fixed;
 pub mod solver;
 pub mod util;
@@ -359,28 +359,67 @@ impl<T: StackType + Eq + std::fmt::Debug> Workspace<T> {
         let mut solution = Array2::zeros((keys.len(), T::num_intervals()));
         let mut energy = Energy::MAX;
         let mut relaxed = false;
+        let mut best_candidates = vec![];
 
         while !relaxed {
-            match solutions.next()? {
+            let improved = match solutions.next()? {
                 None {} => break,
                 Some((new_solution, new_relaxed, new_energy)) => {
-                    if new_relaxed {
-                        relaxed = true;
+                    if new_relaxed | (new_energy < energy) {
+                        relaxed = new_relaxed;
                         energy = new_energy;
                         solution.assign(&new_solution);
+                        true
                     } else {
-                        if new_energy < energy {
-                            energy = new_energy;
-                            solution.assign(&new_solution);
-                        }
+                        false
                     }
                 }
+            };
+            if improved {
+                best_candidates = solutions.workspace.candidate_indices();
             }
         }
 
+        // Leave the candidates of the best solution selected, so that
+        // [Self::current_interval_targets] and [Self::current_anchor_targets] describe it.
+        self.set_candidate_indices(&best_candidates);
+
         Ok((solution, relaxed, energy))
     }
 
+    /// The currently selected candidates of all anchors and springs.
+    fn candidate_indices(&self) -> Vec<usize> {
+        self.current_anchors
+            .values()
+            .map(|v| v.current_candidate_index)
+            .chain(
+                self.current_springs
+                    .values()
+                    .map(|v| v.current_candidate_index),
+            )
+            .collect()
+    }
+
+    /// Inverse of [Self::candidate_indices]. Does nothing if `indices` is empty.
+    fn set_candidate_indices(&mut self, indices: &[usize]) {
+        if indices.is_empty() {
+            return;
+        }
+        for (v, i) in self
+            .current_anchors
+            .values_mut()
+            .map(|v| &mut v.current_candidate_index)
+            .chain(
+                self.current_springs
+                    .values_mut()
+                    .map(|v| &mut v.current_candidate_index),
+            )
+            .zip(indices)
+        {
+            *v = *i;
+        }
+    }
+
     /// This function anchors the position of the first key to the zero [Stack], and then tries to
     /// find the optimal intervals, given the connectors specified by the other arguments, which
     /// have the same meaning as for [Self::compute_best_solution].
@@ -1519,31 +1558,32 @@ mod test {
         assert!(energy > epsilon);
         assert!(!relaxed);
 
+        // the candidates of the best solution stay selected: all whole tones are minor
         let interval_targets = ws.current_interval_targets();
         assert_eq!(
             interval_targets,
             vec![
                 // intervals from C
-                arr1(&[-1, 2, 0]),
+                arr1(&[1, -2, 1]),
                 arr1(&[0, 0, 1]),
                 arr1(&[0, 1, 0]),
                 arr1(&[1, -1, 1]),
                 // intervals from D
-                arr1(&[-1, 2, 0]),
+                arr1(&[1, -2, 1]),
                 arr1(&[1, -1, 0]),
                 arr1(&[0, 1, 0]),
                 //intervals from E
                 arr1(&[0, 1, -1]),
                 arr1(&[1, -1, 0]),
                 //intervals from G
-                arr1(&[-1, 2, 0]),
+                arr1(&[1, -2, 1]),
             ]
         );
         assert_eq!(
             ws.current_anchor_targets(&interval_targets),
             vec![
                 arr1(&[0, 0, 0]),
-                arr1(&[-1, 2, 0]),
+                arr1(&[1, -2, 1]),
                 arr1(&[0, 0, 1]),
                 arr1(&[0, 1, 0]),
                 arr1(&[1, -1, 1]),
@@ -1603,6 +1643,7 @@ mod test {
         assert!(energy > epsilon);
         assert!(!relaxed);
 
+        // A is a fifth above D, because of the rod
         let interval_targets = ws.current_interval_targets();
         assert_eq!(
             interval_targets,
@@ -1611,9 +1652,9 @@ mod test {
                 arr1(&[-1, 2, 0]),
                 arr1(&[0, 0, 1]),
                 arr1(&[0, 1, 0]),
-                arr1(&[1, -1, 1]),
+                arr1(&[-1, 3, 0]),
                 // intervals from D
-                arr1(&[-1, 2, 0]),
+                arr1(&[1, -2, 1]),
                 arr1(&[1, -1, 0]),
                 arr1(&[0, 1, 0]),
                 //intervals from E
@@ -1630,7 +1671,7 @@ mod test {
                 arr1(&[-1, 2, 0]),
                 arr1(&[0, 0, 1]),
                 arr1(&[0, 1, 0]),
-                arr1(&[1, -1, 1]),
+                arr1(&[-1, 3, 0]),
             ]
         );
 
@@ -0,0 +1,390 @@
+//! Splitting the keyboard into zones that are tuned by separate strategies.
+//!
+//! Every [ZoneConfig] gets its own [ProcessFromStrategy], which starts with the strategy given by
+//! [ZoneConfig::strategy]. Notes go to the zones whose key range and input channels contain them,
+//! all other channel messages go to the zones that listen to their channel. Notes outside of all
+//! zones are ignored. Messages from the GUI that concern a strategy go to the first zone, and only
+//! the first zone tells the GUI about its strategy.
+//!
+//! Everything a zone sends is wrapped in [FromProcess::InZone], so that it reaches the backend of
+//! the zone, if there are separate ones (see [BackendConfig::Zoned][crate::config::BackendConfig]).
+//!
+//! Zones with [ZoneConfig::share_reference] follow each other's reference and tuning reference.
+
+use std::{sync::mpsc, time::Instant};
+
+use midi_msg::{Channel, ChannelVoiceMsg, MidiMsg};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    backend::pitchbend12::WrappedChannel,
+    config::{ExtractConfig, FromConfigAndState, ProcessConfig},
+    interval::{stack::Stack, stacktype::r#trait::StackType},
+    msg::{FromProcess, FromStrategy, HandleMsg, ToProcess, ToStrategy},
+    process::fromstrategy::ProcessFromStrategy,
+    reference::Reference,
+};
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct ZoneConfig {
+    pub name: String,
+    /// The lowest key (MIDI note number) in the zone.
+    pub lowest: u8,
+    /// The highest key (MIDI note number) in the zone.
+    pub highest: u8,
+    /// The input channels the zone listens to. If this is empty, it listens to all channels.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channels: Vec<WrappedChannel>,
+    /// The index (in [ProcessConfig::strategies]) of the strategy the zone starts with. In the
+    /// configuration file, this may also be the name of the strategy, see
+    /// [crate::custom_serde::action_names].
+    pub strategy: usize,
+    #[serde(default)]
+    pub share_reference: bool,
+}
+
+impl ZoneConfig {
+    fn listens_to(&self, channel: Channel) -> bool {
+        self.channels.is_empty()
+            || self
+                .channels
+                .iter()
+                .any(|&c| Into::<Channel>::into(c) == channel)
+    }
+
+    fn contains(&self, channel: Channel, note: u8) -> bool {
+        self.listens_to(channel) && (self.lowest..=self.highest).contains(&note)
+    }
+}
+
+enum SharedReference<T: StackType> {
+    Reference(Stack<T>),
+    TuningReference(Reference<T>),
+}
+
+pub struct ZonedProcess<T: StackType> {
+    zones: Vec<ZoneConfig>,
+    /// One for every zone. If there are no zones, there's one process for the whole keyboard,
+    /// whose messages are forwarded unchanged.
+    processes: Vec<ProcessFromStrategy<T>>,
+    /// The processes of the zones send here, so that their messages can be wrapped in
+    /// [FromProcess::InZone].
+    zone_tx: mpsc::Sender<FromProcess<T>>,
+    zone_rx: mpsc::Receiver<FromProcess<T>>,
+}
+
+impl<T: StackType + std::fmt::Debug + 'static> ZonedProcess<T> {
+    /// The zones that should see the MIDI message.
+    fn zones_for_midi(&self, bytes: &[u8]) -> Vec<usize> {
+        let all = || (0..self.zones.len()).collect();
+        let Ok((msg, _)) = MidiMsg::from_midi(bytes) else {
+            // The first zone will report the error.
+            return vec![0];
+        };
+        let (channel, note) = match msg {
+            MidiMsg::ChannelVoice {
+                channel,
+                msg:
+                    ChannelVoiceMsg::NoteOn { note, .. }
+                    | ChannelVoiceMsg::NoteOff { note, .. }
+                    | ChannelVoiceMsg::PolyPressure { note, .. },
+            } => (channel, Some(note)),
+            MidiMsg::ChannelVoice { channel, .. } | MidiMsg::ChannelMode { channel, .. } => {
+                (channel, None {})
+            }
+            _ => return all(),
+        };
+        self.zones_for(channel, note)
+    }
+
+    fn zones_for(&self, channel: Channel, note: Option<u8>) -> Vec<usize> {
+        self.zones
+            .iter()
+            .enumerate()
+            .filter(|(_, zone)| match note {
+                Some(note) => zone.contains(channel, note),
+                None {} => zone.listens_to(channel),
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Send `msg` to the process of the zone, and forward everything it sends. If the zone shares
+    /// its reference, the other zones that do are told about new references.
+    ///
+    /// MIDI that the process sends out unchanged is only forwarded if it isn't in
+    /// `passed_through` yet, so that messages that went to several zones are sent only once.
+    fn send_to_zone(
+        &mut self,
+        zone: usize,
+        msg: ToProcess<T>,
+        time: Instant,
+        passed_through: &mut Vec<Vec<u8>>,
+        forward: &mpsc::Sender<FromProcess<T>>,
+    ) {
+        self.processes[zone].handle_msg(msg, &self.zone_tx);
+        let mut shared = vec![];
+        for msg in self.zone_rx.try_iter().collect::<Vec<_>>() {
+            if self.zones[zone].share_reference {
+                match &msg {
+                    FromProcess::FromStrategy(FromStrategy::SetReference { stack }) => {
+                        shared.push(SharedReference::Reference(stack.clone()));
+                    }
+                    FromProcess::FromStrategy(FromStrategy::SetTuningReference { reference }) => {
+                        shared.push(SharedReference::TuningReference(reference.clone()));
+                    }
+                    _ => {}
+                }
+            }
+            self.forward_from_zone(zone, msg, passed_through, forward);
+        }
+
+        if shared.is_empty() {
+            return;
+        }
+        for other in 0..self.zones.len() {
+            if other == zone || !self.zones[other].share_reference {
+                continue;
+            }
+            for reference in &shared {
+                let msg = match reference {
+                    SharedReference::Reference(stack) => ToStrategy::SetReference {
+                        reference: stack.clone(),
+                        time,
+                    },
+                    SharedReference::TuningReference(reference) => ToStrategy::SetTuningReference {
+                        reference: reference.clone(),
+                        time,
+                    },
+                };
+                self.processes[other].handle_msg(ToProcess::ToStrategy(msg), &self.zone_tx);
+            }
+            // The references the other zones send back are not shared again.
+            for msg in self.zone_rx.try_iter().collect::<Vec<_>>() {
+                self.forward_from_zone(other, msg, passed_through, forward);
+            }
+        }
+    }
+
+    fn forward_from_zone(
+        &self,
+        zone: usize,
+        msg: FromProcess<T>,
+        passed_through: &mut Vec<Vec<u8>>,
+        forward: &mpsc::Sender<FromProcess<T>>,
+    ) {
+        let keep = match &msg {
+            FromProcess::OutgoingMidi { bytes, .. } => {
+                if passed_through.contains(bytes) {
+                    false
+                } else {
+                    passed_through.push(bytes.clone());
+                    true
+                }
+            }
+            FromProcess::CurrentStrategyIndex(_)
+            | FromProcess::CurrentConfig(_)
+            | FromProcess::LearnedBindable { .. }
+            | FromProcess::TuningExported { .. } => zone == 0,
+            FromProcess::FromStrategy(FromStrategy::Retune { .. } | FromStrategy::WakeUp { .. }) => {
+                true
+            }
+            FromProcess::FromStrategy(_) => zone == 0,
+            _ => true,
+        };
+        if keep {
+            let _ = forward.send(FromProcess::InZone {
+                zone,
+                msg: Box::new(msg),
+            });
+        }
+    }
+
+    fn restart(
+        &mut self,
+        config: ProcessConfig<T>,
+        time: Instant,
+        forward: &mpsc::Sender<FromProcess<T>>,
+    ) {
+        *self = <Self as FromConfigAndState<_, _>>::initialise(config, ());
+        self.handle_msg(ToProcess::Start { time }, forward);
+    }
+}
+
+impl<T: StackType + std::fmt::Debug + 'static> HandleMsg<ToProcess<T>, FromProcess<T>>
+    for ZonedProcess<T>
+{
+    fn handle_msg(&mut self, msg: ToProcess<T>, forward: &mpsc::Sender<FromProcess<T>>) {
+        if self.zones.is_empty() {
+            self.processes[0].handle_msg(msg, forward);
+            return;
+        }
+
+        let mut passed_through = vec![];
+        match msg {
+            ToProcess::Stop => {}
+            ToProcess::Start { time } => {
+                for zone in 0..self.zones.len() {
+                    self.send_to_zone(
+                        zone,
+                        ToProcess::Start { time },
+                        time,
+                        &mut passed_through,
+                        forward,
+                    );
+                }
+            }
+            ToProcess::Reset { time } => {
+                for zone in 0..self.zones.len() {
+                    self.send_to_zone(
+                        zone,
+                        ToProcess::Reset { time },
+                        time,
+                        &mut passed_through,
+                        forward,
+                    );
+                }
+            }
+            ToProcess::IncomingMidi { time, bytes } => {
+                for zone in self.zones_for_midi(&bytes) {
+                    self.send_to_zone(
+                        zone,
+                        ToProcess::IncomingMidi {
+                            time,
+                            bytes: bytes.clone(),
+                        },
+                        time,
+                        &mut passed_through,
+                        forward,
+                    );
+                }
+            }
+            ToProcess::NoteOn {
+                channel,
+                note,
+                velocity,
+                time,
+            } => {
+                for zone in self.zones_for(channel, Some(note)) {
+                    self.send_to_zone(
+                        zone,
+                        ToProcess::NoteOn {
+                            channel,
+                            note,
+                            velocity,
+                            time,
+                        },
+                        time,
+                        &mut passed_through,
+                        forward,
+                    );
+                }
+            }
+            ToProcess::NoteOff {
+                channel,
+                note,
+                velocity,
+                time,
+            } => {
+                for zone in self.zones_for(channel, Some(note)) {
+                    self.send_to_zone(
+                        zone,
+                        ToProcess::NoteOff {
+                            channel,
+                            note,
+                            velocity,
+                            time,
+                        },
+                        time,
+                        &mut passed_through,
+                        forward,
+                    );
+                }
+            }
+            ToProcess::PedalHold {
+                channel,
+                value,
+                time,
+            } => {
+                for zone in self.zones_for(channel, None {}) {
+                    self.send_to_zone(
+                        zone,
+                        ToProcess::PedalHold {
+                            channel,
+                            value,
+                            time,
+                        },
+                        time,
+                        &mut passed_through,
+                        forward,
+                    );
+                }
+            }
+            ToProcess::GetCurrentConfig => {
+                let _ = forward.send(FromProcess::CurrentConfig(self.extract_config()));
+            }
+            ToProcess::RestartWithConfig { time, config } => self.restart(config, time, forward),
+            ToProcess::RestartWithCurrentConfig { time } => {
+                self.restart(self.extract_config(), time, forward)
+            }
+            ToProcess::ToStrategy(ToStrategy::Tick { time }) => {
+                // Any of the zones may have asked for it.
+                for zone in 0..self.zones.len() {
+                    self.send_to_zone(
+                        zone,
+                        ToProcess::ToStrategy(ToStrategy::Tick { time }),
+                        time,
+                        &mut passed_through,
+                        forward,
+                    );
+                }
+            }
+            msg @ (ToProcess::ToStrategy(_)
+            | ToProcess::BindAction { .. }
+            | ToProcess::LearnBindable { .. }
+            | ToProcess::StrategyListAction { .. }
+            | ToProcess::ExportTuning { .. }) => {
+                let time = match &msg {
+                    ToProcess::StrategyListAction { time, .. } => *time,
+                    _ => Instant::now(),
+                };
+                self.send_to_zone(0, msg, time, &mut passed_through, forward);
+            }
+        }
+    }
+}
+
+/// The strategies are those of the first zone.
+impl<T: StackType> ExtractConfig<ProcessConfig<T>> for ZonedProcess<T> {
+    fn extract_config(&self) -> ProcessConfig<T> {
+        ProcessConfig {
+            zones: self.zones.clone(),
+            ..self.processes[0].extract_config()
+        }
+    }
+}
+
+impl<T: StackType, S> FromConfigAndState<ProcessConfig<T>, S> for ZonedProcess<T> {
+    fn initialise(config: ProcessConfig<T>, _state: S) -> Self {
+        let (zone_tx, zone_rx) = mpsc::channel();
+        let zones = config.zones.clone();
+        let processes = if zones.is_empty() {
+            vec![ProcessFromStrategy::initialise(config, ())]
+        } else {
+            zones
+                .iter()
+                .map(|zone| {
+                    ProcessFromStrategy::initialise(config.clone(), ()).starting_with(zone.strategy)
+                })
+                .collect()
+        };
+        Self {
+            zones,
+            processes,
+            zone_tx,
+            zone_rx,
+        }
+    }
+}
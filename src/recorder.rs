@@ -0,0 +1,393 @@
+//! Recording sessions to Standard MIDI Files.
+//!
+//! The recorder gets copies of some messages from the process (see the
+//! [MessageTap](crate::msg::MessageTap) implementations in [crate::msg]) and, while a recording
+//! is running, of the incoming and outgoing MIDI. During a recording, these are collected, and
+//! when it stops, they're written to a Type 1 file: The first track contains markers for strategy
+//! switches and text events for changes of the reference and the current harmony, the second one
+//! the incoming, and the third one the outgoing MIDI.
+
+use std::{
+    marker::PhantomData,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    time::{Instant, SystemTime},
+};
+
+use midi_msg::{Division, Meta};
+
+use crate::{
+    config::ExtractConfig,
+    interval::{stack::Stack, stacktype::r#trait::StackType},
+    msg::{FromRecorder, HandleMsg, ToRecorder},
+    notename::{HasNoteNames, NoteNameStyle},
+    smf::{self, meta_event, split_messages, track_event, write_file},
+};
+
+/// With the default tempo, this makes one tick last a bit more than half a millisecond.
+const TICKS_PER_QUARTER_NOTE: u16 = 960;
+
+/// The events of a running recording, with their ticks.
+struct Recording {
+    path: PathBuf,
+    start: Instant,
+    meta: Vec<(u32, Vec<u8>)>,
+    incoming: Vec<(u32, Vec<u8>)>,
+    outgoing: Vec<(u32, Vec<u8>)>,
+}
+
+impl Recording {
+    fn new(path: PathBuf, start: Instant) -> Self {
+        Self {
+            path,
+            start,
+            meta: vec![
+                (0, meta_event(&Meta::TrackName("adaptuner session".into()))),
+                (0, meta_event(&Meta::SetTempo(smf::DEFAULT_TEMPO))),
+            ],
+            incoming: vec![(0, meta_event(&Meta::TrackName("incoming MIDI".into())))],
+            outgoing: vec![(0, meta_event(&Meta::TrackName("outgoing MIDI".into())))],
+        }
+    }
+
+    /// Events that happened before the start of the recording are put at the beginning.
+    fn tick(&self, time: Instant) -> u32 {
+        let micros = time.saturating_duration_since(self.start).as_micros();
+        let ticks = micros * TICKS_PER_QUARTER_NOTE as u128 / smf::DEFAULT_TEMPO as u128;
+        ticks.try_into().unwrap_or(u32::MAX)
+    }
+
+    fn push_meta(&mut self, time: Instant, meta: &Meta) {
+        self.meta.push((self.tick(time), meta_event(meta)));
+    }
+
+    fn push_incoming(&mut self, time: Instant, bytes: &[u8]) {
+        let tick = self.tick(time);
+        self.incoming.extend(
+            split_messages(bytes)
+                .iter()
+                .map(|msg| (tick, track_event(msg))),
+        );
+    }
+
+    fn push_outgoing(&mut self, time: Instant, bytes: &[u8]) {
+        let tick = self.tick(time);
+        self.outgoing.extend(
+            split_messages(bytes)
+                .iter()
+                .map(|msg| (tick, track_event(msg))),
+        );
+    }
+
+    /// The messages come from different threads, so they may arrive slightly out of order. This
+    /// sorts them before writing the file.
+    fn file(mut self) -> (PathBuf, Vec<u8>) {
+        self.meta.sort_by_key(|(tick, _)| *tick);
+        self.incoming.sort_by_key(|(tick, _)| *tick);
+        self.outgoing.sort_by_key(|(tick, _)| *tick);
+        (
+            self.path,
+            write_file(
+                Division::TicksPerQuarterNote(TICKS_PER_QUARTER_NOTE),
+                &[self.meta, self.incoming, self.outgoing],
+            ),
+        )
+    }
+}
+
+pub struct Recorder<T: StackType> {
+    /// Where recordings are saved.
+    directory: PathBuf,
+    recording: Option<Recording>,
+    /// Shared with the threads that send copies of the MIDI, which they only do while this is
+    /// true.
+    is_recording: Arc<AtomicBool>,
+    /// The most recent meta events of each kind. They're written at the start of every recording,
+    /// so that it's clear what was going on at that point.
+    strategy: Option<Meta>,
+    reference: Option<Meta>,
+    tuning_reference: Option<Meta>,
+    harmony: Option<Meta>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: StackType + HasNoteNames> Recorder<T> {
+    pub fn new(directory: PathBuf, is_recording: Arc<AtomicBool>) -> Self {
+        Self {
+            directory,
+            recording: None {},
+            is_recording,
+            strategy: None {},
+            reference: None {},
+            tuning_reference: None {},
+            harmony: None {},
+            _phantom: PhantomData,
+        }
+    }
+
+    fn start_recording(&mut self, time: Instant, forward: &mpsc::Sender<FromRecorder>) {
+        if self.recording.is_some() {
+            return;
+        }
+        let seconds = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let path = self
+            .directory
+            .join(format!("adaptuner-session-{seconds}.mid"));
+        let mut recording = Recording::new(path.clone(), time);
+        for meta in [
+            &self.strategy,
+            &self.reference,
+            &self.tuning_reference,
+            &self.harmony,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            recording.push_meta(time, meta);
+        }
+        self.recording = Some(recording);
+        self.is_recording.store(true, Ordering::Relaxed);
+        let _ = forward.send(FromRecorder::Started { path });
+    }
+
+    fn stop_recording(&mut self, forward: &mpsc::Sender<FromRecorder>) {
+        if let Some(recording) = self.recording.take() {
+            self.is_recording.store(false, Ordering::Relaxed);
+            let (path, bytes) = recording.file();
+            let _ = forward.send(match std::fs::write(&path, bytes) {
+                Ok(()) => FromRecorder::Stopped { path },
+                Err(e) => FromRecorder::Error {
+                    reason: format!("couldn't save the recording to '{}': {e}", path.display()),
+                },
+            });
+        }
+    }
+
+    fn note_name(stack: &Stack<T>) -> String {
+        stack.corrected_notename(&NoteNameStyle::FULL, &[], true)
+    }
+}
+
+/// Remember `meta` in `current`, and record it, if there's a running recording.
+fn update_meta(
+    current: &mut Option<Meta>,
+    recording: &mut Option<Recording>,
+    time: Instant,
+    meta: Meta,
+) {
+    if let Some(recording) = recording {
+        recording.push_meta(time, &meta);
+    }
+    *current = Some(meta);
+}
+
+impl<T: StackType + HasNoteNames> HandleMsg<ToRecorder<T>, FromRecorder> for Recorder<T> {
+    fn handle_msg(&mut self, msg: ToRecorder<T>, forward: &mpsc::Sender<FromRecorder>) {
+        match msg {
+            ToRecorder::Stop => self.stop_recording(forward),
+            ToRecorder::StartRecording { time } => self.start_recording(time, forward),
+            ToRecorder::StopRecording { .. } => self.stop_recording(forward),
+            ToRecorder::ToggleRecording { time } => {
+                if self.recording.is_some() {
+                    self.stop_recording(forward);
+                } else {
+                    self.start_recording(time, forward);
+                }
+            }
+            ToRecorder::IncomingMidi { time, bytes } => {
+                if let Some(recording) = &mut self.recording {
+                    recording.push_incoming(time, &bytes);
+                }
+            }
+            ToRecorder::OutgoingMidi { time, bytes } => {
+                if let Some(recording) = &mut self.recording {
+                    recording.push_outgoing(time, &bytes);
+                }
+            }
+            ToRecorder::CurrentStrategyIndex { index, time } => update_meta(
+                &mut self.strategy,
+                &mut self.recording,
+                time,
+                Meta::Marker(match index {
+                    Some(i) => format!("strategy {}", i + 1),
+                    None => "no strategy".into(),
+                }),
+            ),
+            ToRecorder::SetReference { stack, time } => update_meta(
+                &mut self.reference,
+                &mut self.recording,
+                time,
+                Meta::Text(format!("reference: {}", Self::note_name(&stack))),
+            ),
+            ToRecorder::SetTuningReference { reference, time } => update_meta(
+                &mut self.tuning_reference,
+                &mut self.recording,
+                time,
+                Meta::Text(format!(
+                    "tuning reference: {} at {:.2} semitones",
+                    Self::note_name(&reference.stack),
+                    reference.semitones
+                )),
+            ),
+            ToRecorder::CurrentHarmony {
+                pattern_index,
+                reference,
+                time,
+            } => update_meta(
+                &mut self.harmony,
+                &mut self.recording,
+                time,
+                Meta::Text(match (pattern_index, reference) {
+                    (Some(i), Some(reference)) => format!(
+                        "harmony: chord pattern {} on {}",
+                        i + 1,
+                        Self::note_name(&reference)
+                    ),
+                    (Some(i), None) => format!("harmony: chord pattern {}", i + 1),
+                    (None, Some(reference)) => {
+                        format!("harmony: on {}", Self::note_name(&reference))
+                    }
+                    (None, None) => "harmony: none".into(),
+                }),
+            ),
+        }
+    }
+}
+
+impl<T: StackType> ExtractConfig<()> for Recorder<T> {
+    fn extract_config(&self) {}
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use midi_msg::{Channel, ChannelVoiceMsg, MidiFile, MidiMsg, Track};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::interval::stacktype::fivelimit::mock::MockFiveLimitStackType;
+
+    #[test]
+    fn test_recording() {
+        let start = Instant::now();
+        let mut recording = Recording::new("test.mid".into(), start);
+        recording.push_meta(start, &Meta::Marker("strategy 1".into()));
+        // a quarter note later, in two messages, with running status:
+        recording.push_incoming(
+            start + Duration::from_millis(500),
+            &[0x90, 60, 100, 64, 100],
+        );
+        // out of order:
+        recording.push_outgoing(start + Duration::from_millis(1000), &[0x80, 60, 0]);
+        recording.push_outgoing(start + Duration::from_millis(500), &[0x91, 60, 100]);
+        // before the start:
+        recording.push_meta(
+            start - Duration::from_millis(10),
+            &Meta::Text("too early".into()),
+        );
+
+        let (path, bytes) = recording.file();
+        assert_eq!(path, PathBuf::from("test.mid"));
+
+        let file = MidiFile::from_midi(&bytes).unwrap();
+        assert_eq!(
+            file.header.division,
+            Division::TicksPerQuarterNote(TICKS_PER_QUARTER_NOTE)
+        );
+        let tracks: Vec<Vec<(u32, MidiMsg)>> = file
+            .tracks
+            .iter()
+            .map(|track| match track {
+                Track::Midi(events) => {
+                    let mut tick = 0;
+                    events
+                        .iter()
+                        .map(|e| {
+                            tick += e.delta_time;
+                            (tick, e.event.clone())
+                        })
+                        .collect()
+                }
+                Track::AlienChunk(_) => panic!("unexpected alien chunk"),
+            })
+            .collect();
+
+        let meta = |tick, msg| (tick, MidiMsg::Meta { msg });
+        let note_on = |tick, channel, note| {
+            (
+                tick,
+                MidiMsg::ChannelVoice {
+                    channel,
+                    msg: ChannelVoiceMsg::NoteOn {
+                        note,
+                        velocity: 100,
+                    },
+                },
+            )
+        };
+
+        assert_eq!(
+            tracks[0],
+            vec![
+                meta(0, Meta::TrackName("adaptuner session".into())),
+                meta(0, Meta::SetTempo(smf::DEFAULT_TEMPO)),
+                meta(0, Meta::Marker("strategy 1".into())),
+                meta(0, Meta::Text("too early".into())),
+                meta(0, Meta::EndOfTrack),
+            ]
+        );
+        assert_eq!(
+            tracks[1],
+            vec![
+                meta(0, Meta::TrackName("incoming MIDI".into())),
+                note_on(960, Channel::Ch1, 60),
+                note_on(960, Channel::Ch1, 64),
+                meta(960, Meta::EndOfTrack),
+            ]
+        );
+        assert_eq!(
+            tracks[2],
+            vec![
+                meta(0, Meta::TrackName("outgoing MIDI".into())),
+                note_on(960, Channel::Ch2, 60),
+                (
+                    1920,
+                    MidiMsg::ChannelVoice {
+                        channel: Channel::Ch1,
+                        msg: ChannelVoiceMsg::NoteOff {
+                            note: 60,
+                            velocity: 0
+                        },
+                    }
+                ),
+                meta(1920, Meta::EndOfTrack),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recording_flag() {
+        let is_recording = Arc::new(AtomicBool::new(false));
+        let mut recorder =
+            Recorder::<MockFiveLimitStackType>::new(std::env::temp_dir(), is_recording.clone());
+        let (tx, rx) = mpsc::channel();
+        let time = Instant::now();
+
+        recorder.handle_msg(ToRecorder::StartRecording { time }, &tx);
+        assert!(is_recording.load(Ordering::Relaxed));
+        let Ok(FromRecorder::Started { path }) = rx.try_recv() else {
+            panic!("the recording should have started");
+        };
+
+        recorder.handle_msg(ToRecorder::StopRecording { time }, &tx);
+        assert!(!is_recording.load(Ordering::Relaxed));
+        assert!(matches!(rx.try_recv(), Ok(FromRecorder::Stopped { .. })));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -1,11 +1,16 @@
 use std::{
-    sync::{mpsc, Arc, Mutex},
+    collections::BTreeSet,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
     thread,
     time::Instant,
 };
 
 use eframe::egui;
-use midir::{MidiInput, MidiOutput};
+use midir::{MidiInput, MidiInputPort, MidiOutput, MidiOutputPort};
 
 use crate::{
     config::{
@@ -13,12 +18,17 @@ use crate::{
         MidiOutputConfig, ProcessConfig,
     },
     interval::stacktype::r#trait::StackType,
-    maybeconnected::{input::MidiInputOrConnection, output::MidiOutputOrConnection},
+    maybeconnected::{
+        input::MidiInputOrConnection, loopprobe::LoopProbe, output::MidiOutputOrConnection,
+    },
     msg::{
-        FromBackend, FromMidiIn, FromMidiOut, FromProcess, FromUi, HandleMsg, HasStop,
-        MessageTranslate, MessageTranslate2, MessageTranslate3, MessageTranslate4, ReceiveMsg,
-        ToBackend, ToMidiIn, ToMidiOut, ToProcess, ToUi,
+        FromBackend, FromMidiIn, FromMidiOut, FromProcess, FromRecorder, FromUi, HandleMsg,
+        HasStop, MessageTap, MessageTranslate, MessageTranslate3, MessageTranslate4, ReceiveMsg,
+        ToBackend, ToMidiIn, ToMidiOut, ToProcess, ToRecorder, ToStrategy, ToTimer, ToUi,
     },
+    notename::HasNoteNames,
+    recorder::Recorder,
+    util::list_action::ListAction,
 };
 
 fn start_handler_thread<I, O, H, C, NH>(
@@ -114,7 +124,11 @@ fn setup_fonts(ctx: &egui::Context) {
         FontData::from_static(include_bytes!("../assets/InterMusic.ttf")).into(),
     );
 
-    fonts.families.get_mut(&FontFamily::Proportional).unwrap().insert(0, "inter_music".to_owned());
+    fonts
+        .families
+        .get_mut(&FontFamily::Proportional)
+        .unwrap()
+        .insert(0, "inter_music".to_owned());
 
     ctx.set_fonts(fonts);
 }
@@ -194,58 +208,28 @@ where
     })
 }
 
-fn start_translate_2_thread<B, C, A>(
-    rxa: mpsc::Receiver<A>,
-    txb: &mpsc::Sender<B>,
-    txc: &mpsc::Sender<C>,
-) -> thread::JoinHandle<()>
-where
-    B: Send + 'static,
-    C: Send + 'static,
-    A: MessageTranslate2<B, C> + Send + 'static,
-{
-    let txb_clone = txb.clone();
-    let txc_clone = txc.clone();
-    thread::spawn(move || loop {
-        match rxa.recv() {
-            Ok(msg) => {
-                let (tb, tc) = msg.translate2();
-                match tb {
-                    Some(tb) => {
-                        let _ = txb_clone.send(tb);
-                    }
-                    None {} => {}
-                }
-                match tc {
-                    Some(tc) => {
-                        let _ = txc_clone.send(tc);
-                    }
-                    None {} => {}
-                }
-            }
-            Err(_) => break,
-        }
-    })
-}
-
-fn start_translate_3_thread<B, C, D, A>(
+fn start_translate_3_thread<B, C, D, A, R>(
     rxa: mpsc::Receiver<A>,
     txb: &mpsc::Sender<B>,
     txc: &mpsc::Sender<C>,
     txd: &mpsc::Sender<D>,
+    tap: &Tap<R>,
 ) -> thread::JoinHandle<()>
 where
     B: Send + 'static,
     C: Send + 'static,
     D: Send + 'static,
-    A: MessageTranslate3<B, C, D> + Send + 'static,
+    R: Send + 'static,
+    A: MessageTranslate3<B, C, D> + MessageTap<R> + Send + 'static,
 {
     let txb_clone = txb.clone();
     let txc_clone = txc.clone();
     let txd_clone = txd.clone();
+    let tap = tap.clone();
     thread::spawn(move || loop {
         match rxa.recv() {
             Ok(msg) => {
+                tap.copy(&msg);
                 let (tb, tc, td) = msg.translate3();
                 match tb {
                     Some(tb) => {
@@ -270,27 +254,32 @@ where
         }
     })
 }
-fn start_translate_4_thread<B, C, D, E, A>(
+
+fn start_translate_4_thread<B, C, D, E, A, R>(
     rxa: mpsc::Receiver<A>,
     txb: &mpsc::Sender<B>,
     txc: &mpsc::Sender<C>,
     txd: &mpsc::Sender<D>,
     txe: &mpsc::Sender<E>,
+    tap: &Tap<R>,
 ) -> thread::JoinHandle<()>
 where
     B: Send + 'static,
     C: Send + 'static,
     D: Send + 'static,
     E: Send + 'static,
-    A: MessageTranslate4<B, C, D, E> + Send + 'static,
+    R: Send + 'static,
+    A: MessageTranslate4<B, C, D, E> + MessageTap<R> + Send + 'static,
 {
     let txb_clone = txb.clone();
     let txc_clone = txc.clone();
     let txd_clone = txd.clone();
     let txe_clone = txe.clone();
+    let tap = tap.clone();
     thread::spawn(move || loop {
         match rxa.recv() {
             Ok(msg) => {
+                tap.copy(&msg);
                 let (tb, tc, td, te) = msg.translate4();
                 match tb {
                     Some(tb) => {
@@ -322,6 +311,105 @@ where
     })
 }
 
+/// Where the forwarding threads send copies of messages (see [MessageTap]). The `recording` flag
+/// is kept up to date by the [Recorder].
+struct Tap<R> {
+    tx: mpsc::Sender<R>,
+    recording: Arc<AtomicBool>,
+}
+
+impl<R> Clone for Tap<R> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            recording: self.recording.clone(),
+        }
+    }
+}
+
+impl<R> Tap<R> {
+    fn copy<A: MessageTap<R>>(&self, msg: &A) {
+        if let Some(r) = msg.tap(self.recording.load(Ordering::Relaxed)) {
+            let _ = self.tx.send(r);
+        }
+    }
+}
+
+/// The times at which the process and the backend asked for ticks with [ToTimer] messages.
+/// Several wake ups for the same time result in only one tick.
+#[derive(Default)]
+pub struct WakeUps {
+    process: BTreeSet<Instant>,
+    backend: BTreeSet<Instant>,
+}
+
+impl WakeUps {
+    pub fn insert(&mut self, msg: ToTimer) {
+        match msg {
+            ToTimer::WakeUp { time } => self.process.insert(time),
+            ToTimer::BackendWakeUp { time } => self.backend.insert(time),
+        };
+    }
+
+    /// The time of the earliest tick that is still to be sent.
+    pub fn next(&self) -> Option<Instant> {
+        match (self.process.first(), self.backend.first()) {
+            (Some(&p), Some(&b)) => Some(p.min(b)),
+            (p, b) => p.or(b).copied(),
+        }
+    }
+
+    /// Send the earliest tick, if it is due at `now`. Ticks for the process come before ticks
+    /// for the backend at the same time. Returns whether a tick was sent.
+    pub fn send_next<T: StackType>(
+        &mut self,
+        now: Instant,
+        to_process_tx: &mpsc::Sender<ToProcess<T>>,
+        to_backend_tx: &mpsc::Sender<ToBackend>,
+    ) -> bool {
+        match self.next() {
+            Some(time) if time <= now => {
+                if self.process.first() == Some(&time) {
+                    self.process.pop_first();
+                    let _ = to_process_tx.send(ToProcess::ToStrategy(ToStrategy::Tick { time }));
+                } else {
+                    self.backend.pop_first();
+                    let _ = to_backend_tx.send(ToBackend::Tick { time });
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Sends the ticks asked for with [ToTimer] messages at the (wall-clock) times they're due.
+fn start_timer_thread<T>(
+    rx: mpsc::Receiver<ToTimer>,
+    to_process_tx: mpsc::Sender<ToProcess<T>>,
+    to_backend_tx: mpsc::Sender<ToBackend>,
+) where
+    T: StackType + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut wake_ups = WakeUps::default();
+        loop {
+            let msg = match wake_ups.next() {
+                Some(time) => rx.recv_timeout(time.saturating_duration_since(Instant::now())),
+                None {} => rx.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+            };
+            match msg {
+                Ok(msg) => wake_ups.insert(msg),
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    let now = Instant::now();
+                    while wake_ups.send_next(now, &to_process_tx, &to_backend_tx) {}
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+}
+
 pub struct RunState<T: StackType> {
     midi_input: thread::JoinHandle<(
         MidiInputConfig,
@@ -343,10 +431,16 @@ pub struct RunState<T: StackType> {
         mpsc::Receiver<ToBackend>,
         mpsc::Sender<FromBackend>,
     )>,
+    recorder: thread::JoinHandle<(
+        (),
+        mpsc::Receiver<ToRecorder<T>>,
+        mpsc::Sender<FromRecorder>,
+    )>,
     to_process_tx: mpsc::Sender<ToProcess<T>>,
     to_backend_tx: mpsc::Sender<ToBackend>,
     to_midi_input_tx: mpsc::Sender<ToMidiIn>,
     to_midi_output_tx: mpsc::Sender<ToMidiOut>,
+    to_recorder_tx: mpsc::Sender<ToRecorder<T>>,
     gui_config_return: Arc<Mutex<Option<GuiConfig<T>>>>,
 }
 
@@ -357,6 +451,7 @@ pub enum JoinError {
     Gui,
     MidiInput,
     MidiOutput,
+    Recorder,
 }
 
 impl std::fmt::Display for JoinError {
@@ -367,22 +462,33 @@ impl std::fmt::Display for JoinError {
             JoinError::Gui => write!(f, "couldn't join the GUI thread"),
             JoinError::MidiInput => write!(f, "couldn't join the midi input thread"),
             JoinError::MidiOutput => write!(f, "couldn't join the midi output thread"),
+            JoinError::Recorder => write!(f, "couldn't join the recorder thread"),
         }
     }
 }
 
 impl std::error::Error for JoinError {}
 
+/// Things to do immediately after startup, which would otherwise need clicks in the GUI.
+#[derive(Default)]
+pub struct StartupActions {
+    pub connect_inputs: Vec<(MidiInputPort, String)>,
+    pub connect_outputs: Vec<(MidiOutputPort, String)>,
+    /// Index into [ProcessConfig::strategies].
+    pub select_strategy: Option<usize>,
+}
+
 impl<T: StackType> RunState<T> {
     pub fn new<P, B, U, NU>(
         midi_in: MidiInput,
         midi_out: MidiOutput,
         process_config: ProcessConfig<T>,
         backend_config: BackendConfig,
+        startup_actions: StartupActions,
         new_ui_state: NU,
     ) -> Result<Self, eframe::Error>
     where
-        T: Send + 'static,
+        T: HasNoteNames + Send + 'static,
         P: HandleMsg<ToProcess<T>, FromProcess<T>>
             + ExtractConfig<ProcessConfig<T>>
             + FromConfigAndState<ProcessConfig<T>, ()>,
@@ -391,14 +497,71 @@ impl<T: StackType> RunState<T> {
             + FromConfigAndState<BackendConfig, ()>,
         U: ReceiveMsg<ToUi<T>> + eframe::App + ExtractConfig<GuiConfig<T>>,
         NU: FnOnce(&egui::Context, mpsc::Sender<FromUi<T>>) -> U + Send + 'static,
+    {
+        let probe = LoopProbe::for_this_process();
+        let (res, to_ui_rx, from_ui_tx) = Self::start_without_gui::<P, B, _, _, _, _>(
+            |from_midi_input_tx| {
+                MidiInputOrConnection::new(
+                    midi_in,
+                    "adaptuner input",
+                    probe.clone(),
+                    from_midi_input_tx,
+                )
+            },
+            MidiOutputOrConnection::new(midi_out, "adaptuner output", probe.clone()),
+            process_config,
+            backend_config,
+            startup_actions,
+            start_timer_thread,
+            Instant::now(),
+        );
+
+        start_gui(
+            new_ui_state,
+            to_ui_rx,
+            from_ui_tx,
+            res.gui_config_return.clone(),
+        )?;
+
+        Ok(res)
+    }
+
+    /// Start all threads except for the GUI, and return the channels the GUI would use. The MIDI
+    /// input and output need not be real ports: `new_midi_input` gets the sender for incoming
+    /// MIDI, and `midi_output` receives everything that should be sent out.
+    ///
+    /// The timer need not run on wall-clock time either: `new_timer` gets the receiver for the
+    /// [ToTimer] messages, and the senders on which the ticks (see [WakeUps]) must be sent.
+    ///
+    /// `time` is used for the messages sent on startup.
+    pub fn start_without_gui<P, B, I, O, NI, NT>(
+        new_midi_input: NI,
+        midi_output: O,
+        process_config: ProcessConfig<T>,
+        backend_config: BackendConfig,
+        startup_actions: StartupActions,
+        new_timer: NT,
+        time: Instant,
+    ) -> (Self, mpsc::Receiver<ToUi<T>>, mpsc::Sender<FromUi<T>>)
+    where
+        T: HasNoteNames + Send + 'static,
+        P: HandleMsg<ToProcess<T>, FromProcess<T>>
+            + ExtractConfig<ProcessConfig<T>>
+            + FromConfigAndState<ProcessConfig<T>, ()>,
+        B: HandleMsg<ToBackend, FromBackend>
+            + ExtractConfig<BackendConfig>
+            + FromConfigAndState<BackendConfig, ()>,
+        I: HandleMsg<ToMidiIn, FromMidiIn> + ExtractConfig<MidiInputConfig> + Send + 'static,
+        O: HandleMsg<ToMidiOut, FromMidiOut> + ExtractConfig<MidiOutputConfig> + Send + 'static,
+        NI: FnOnce(mpsc::Sender<FromMidiIn>) -> I,
+        NT: FnOnce(mpsc::Receiver<ToTimer>, mpsc::Sender<ToProcess<T>>, mpsc::Sender<ToBackend>),
     {
         let (to_midi_input_tx, to_midi_input_rx) = mpsc::channel();
         let (from_midi_input_tx, from_midi_input_rx) = mpsc::channel();
-        let midi_input = MidiInputOrConnection::new(midi_in, from_midi_input_tx.clone());
+        let midi_input = new_midi_input(from_midi_input_tx.clone());
 
         let (to_midi_output_tx, to_midi_output_rx) = mpsc::channel();
         let (from_midi_output_tx, from_midi_output_rx) = mpsc::channel();
-        let midi_output = MidiOutputOrConnection::new(midi_out);
 
         let (to_process_tx, to_process_rx) = mpsc::channel();
         let (from_process_tx, from_process_rx) = mpsc::channel::<FromProcess<T>>();
@@ -409,25 +572,51 @@ impl<T: StackType> RunState<T> {
         let (to_ui_tx, to_ui_rx) = mpsc::channel();
         let (from_ui_tx, from_ui_rx) = mpsc::channel();
 
+        let (to_recorder_tx, to_recorder_rx) = mpsc::channel();
+        let (from_recorder_tx, from_recorder_rx) = mpsc::channel();
+
         let gui_config_return = Arc::new(Mutex::new(None {}));
 
+        let recording = Arc::new(AtomicBool::new(false));
+        let tap = Tap {
+            tx: to_recorder_tx.clone(),
+            recording: recording.clone(),
+        };
+
         let _midi_output_forward = start_translate_thread(from_midi_output_rx, &to_ui_tx);
-        let _midi_input_forward =
-            start_translate_2_thread(from_midi_input_rx, &to_process_tx, &to_ui_tx);
-        let _process_forward = start_translate_3_thread(
+        let _recorder_forward = start_translate_thread(from_recorder_rx, &to_ui_tx);
+        let _midi_input_forward = start_translate_4_thread(
+            from_midi_input_rx,
+            &to_process_tx,
+            &to_ui_tx,
+            &to_midi_input_tx,
+            &to_midi_output_tx,
+            &tap,
+        );
+        let (to_timer_tx, to_timer_rx) = mpsc::channel();
+        new_timer(to_timer_rx, to_process_tx.clone(), to_backend_tx.clone());
+        let _process_forward = start_translate_4_thread(
             from_process_rx,
             &to_backend_tx,
             &to_midi_output_tx,
             &to_ui_tx,
+            &to_timer_tx,
+            &tap,
+        );
+        let _backend_forward = start_translate_3_thread(
+            from_backend_rx,
+            &to_midi_output_tx,
+            &to_ui_tx,
+            &to_timer_tx,
+            &tap,
         );
-        let _backend_forward =
-            start_translate_2_thread(from_backend_rx, &to_midi_output_tx, &to_ui_tx);
         let _ui_forward = start_translate_4_thread(
             from_ui_rx,
             &to_process_tx,
             &to_backend_tx,
             &to_midi_input_tx,
             &to_midi_output_tx,
+            &tap,
         );
 
         let res = Self {
@@ -447,23 +636,48 @@ impl<T: StackType> RunState<T> {
                 to_backend_rx,
                 from_backend_tx,
             ),
+            recorder: start_handler_thread(
+                || {
+                    Recorder::new(
+                        std::env::current_dir().unwrap_or(PathBuf::from(".")),
+                        recording,
+                    )
+                },
+                to_recorder_rx,
+                from_recorder_tx,
+            ),
             to_process_tx: to_process_tx.clone(),
             to_backend_tx,
             to_midi_input_tx: to_midi_input_tx.clone(),
             to_midi_output_tx: to_midi_output_tx.clone(),
-            gui_config_return: gui_config_return.clone(),
+            to_recorder_tx,
+            gui_config_return,
         };
 
         let _ = to_midi_input_tx.send(ToMidiIn::Start);
         let _ = to_midi_output_tx.send(ToMidiOut::Start);
-        let _ = to_process_tx.send(ToProcess::Start {
-            time: Instant::now(),
-        });
+        let _ = to_process_tx.send(ToProcess::Start { time });
         // TODO: send more start messages?
 
-        start_gui(new_ui_state, to_ui_rx, from_ui_tx, gui_config_return)?;
+        let StartupActions {
+            connect_inputs,
+            connect_outputs,
+            select_strategy,
+        } = startup_actions;
+        for (port, portname) in connect_inputs {
+            let _ = to_midi_input_tx.send(ToMidiIn::Connect { port, portname });
+        }
+        for (port, portname) in connect_outputs {
+            let _ = to_midi_output_tx.send(ToMidiOut::Connect { port, portname });
+        }
+        if let Some(index) = select_strategy {
+            let _ = to_process_tx.send(ToProcess::StrategyListAction {
+                action: ListAction::Select(index),
+                time,
+            });
+        }
 
-        Ok(res)
+        (res, to_ui_rx, from_ui_tx)
     }
 
     pub fn stop(
@@ -477,6 +691,36 @@ impl<T: StackType> RunState<T> {
             MidiOutputConfig,
         ),
         JoinError,
+    > {
+        let gui_config_return = self.gui_config_return.clone();
+        let (process_config, backend_config, midi_input_config, midi_output_config) =
+            self.stop_without_gui()?;
+
+        let gui_config = gui_config_return.lock().unwrap().clone();
+        if let Some(gui_config) = gui_config {
+            Ok((
+                process_config,
+                backend_config,
+                gui_config,
+                midi_input_config,
+                midi_output_config,
+            ))
+        } else {
+            Err(JoinError::Gui)
+        }
+    }
+
+    /// Stop all threads except for the GUI, and return their configurations.
+    pub fn stop_without_gui(
+        self,
+    ) -> Result<
+        (
+            ProcessConfig<T>,
+            BackendConfig,
+            MidiInputConfig,
+            MidiOutputConfig,
+        ),
+        JoinError,
     > {
         let _ = self.to_process_tx.send(ToProcess::Stop);
         let Ok((process_config, _, _)) = self.process.join() else {
@@ -498,16 +742,17 @@ impl<T: StackType> RunState<T> {
             return Err(JoinError::MidiOutput);
         };
 
-        if let Some(gui_config) = self.gui_config_return.lock().unwrap().as_ref() {
-            Ok((
-                process_config,
-                backend_config,
-                gui_config.clone(),
-                midi_input_config,
-                midi_output_config,
-            ))
-        } else {
-            Err(JoinError::Gui)
-        }
+        // this saves a running recording
+        let _ = self.to_recorder_tx.send(ToRecorder::Stop);
+        let Ok(((), _, _)) = self.recorder.join() else {
+            return Err(JoinError::Recorder);
+        };
+
+        Ok((
+            process_config,
+            backend_config,
+            midi_input_config,
+            midi_output_config,
+        ))
     }
 }
@@ -0,0 +1,977 @@
+//! Importing tunings from Scala scale (`.scl`) and keyboard mapping (`.kbm`) files, and exporting
+//! them to these formats and to AnaMark tuning (`.tun`) files.
+//!
+//! The formats are described at <https://www.huygens-fokker.org/scala/scl_format.html>,
+//! <https://www.huygens-fokker.org/scala/help.htm#mappings>, and
+//! <https://www.mark-henning.de/eternity/tuningspecs.html>. A scale (together with the mapping)
+//! becomes a [PeriodicComplete] neighbourhood of the middle note of the mapping: Entries given as
+//! ratios become pure [Stack]s, if they can be written in the interval basis, and entries given in
+//! cents become tempered [Stack]s near the closest pure one. A neighbourhood applies to all keys
+//! and doesn't say how high it sounds, so the key range and the reference frequency of the
+//! mapping are not used; they're reported in [ScalaImport::ignored]. See [TuningFiles] for the
+//! export.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use ndarray::ArrayView1;
+use num_rational::Ratio;
+use num_traits::{One, Zero};
+
+use crate::{
+    interval::{
+        base::Semitones,
+        stack::{key_distance_from_coefficients, Stack},
+        stacktype::r#trait::{IntervalBasis, StackCoeff},
+    },
+    neighbourhood::{CompleteNeigbourhood, PeriodicComplete},
+    reference::frequency_from_semitones,
+};
+
+/// The search radius for the coefficients of the non-period intervals in the pure [Stack]s that
+/// are used as targets for tempered entries.
+const TARGET_SEARCH_RADIUS: StackCoeff = 2;
+
+/// The resolution of tempered entries, in parts of a cent.
+const CENT_RESOLUTION: Semitones = 100.0;
+
+/// A pitch in a Scala file: either a frequency ratio or a size in cents.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pitch {
+    Ratio(Ratio<StackCoeff>),
+    Cents(f64),
+}
+
+impl Pitch {
+    pub fn semitones(&self) -> Semitones {
+        match self {
+            Pitch::Ratio(r) => 12.0 * (*r.numer() as Semitones / *r.denom() as Semitones).log2(),
+            Pitch::Cents(c) => c / 100.0,
+        }
+    }
+
+    /// The pitch `n` times `period` above this one.
+    fn add_periods(self, n: StackCoeff, period: Pitch) -> Pitch {
+        match (self, period) {
+            (Pitch::Ratio(r), Pitch::Ratio(p)) => Pitch::Ratio(r * p.pow(n as i32)),
+            _ => Pitch::Cents(100.0 * (self.semitones() + n as Semitones * period.semitones())),
+        }
+    }
+
+    /// Equality of ratios, or approximate equality of cent values.
+    fn same_as(&self, other: &Pitch) -> bool {
+        match (self, other) {
+            (Pitch::Ratio(a), Pitch::Ratio(b)) => a == b,
+            _ => (self.semitones() - other.semitones()).abs() < 0.5 / CENT_RESOLUTION / 100.0,
+        }
+    }
+}
+
+impl std::str::FromStr for Pitch {
+    type Err = String;
+
+    /// Only the first word is read, the rest of the line is a comment.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.split_whitespace().next().ok_or("the entry is empty")?;
+        if s.contains('.') {
+            s.parse()
+                .map(Pitch::Cents)
+                .map_err(|_| format!("'{s}' is not a number of cents"))
+        } else {
+            let (n, d) = s.split_once('/').unwrap_or((s, "1"));
+            match (n.parse::<StackCoeff>(), d.parse::<StackCoeff>()) {
+                (Ok(n), Ok(d)) if n > 0 && d > 0 => Ok(Pitch::Ratio(Ratio::new(n, d))),
+                _ => Err(format!("'{s}' is not a positive ratio")),
+            }
+        }
+    }
+}
+
+/// The contents of a `.scl` file.
+#[derive(Debug, PartialEq)]
+pub struct Scale {
+    pub description: String,
+    /// The entries of the scale with their line numbers, without the implicit unison `1/1`. The
+    /// last entry is the interval at which the scale repeats. Entries that can't be read are
+    /// errors, which are reported per entry by [import].
+    pub entries: Vec<(usize, Result<Pitch, String>)>,
+}
+
+/// The contents of a `.kbm` file.
+#[derive(Debug, PartialEq)]
+pub struct KeyboardMapping {
+    /// The number of keys after which the mapping repeats. Zero means a linear mapping, where
+    /// consecutive keys get consecutive scale degrees.
+    pub size: usize,
+    pub first_note: u8,
+    pub last_note: u8,
+    /// The key that gets the first scale degree (the unison).
+    pub middle_note: u8,
+    pub reference_note: u8,
+    pub reference_frequency: f64,
+    /// The scale degree of the interval at which the mapping repeats.
+    pub octave_degree: usize,
+    /// The scale degrees of the keys starting from the `middle_note`, or `None` for unmapped keys.
+    /// This always has `size` entries.
+    pub mapping: Vec<Option<usize>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ScalaErr {
+    /// The `.scl` file is malformed.
+    Scl {
+        line: Option<usize>,
+        message: String,
+    },
+    /// The `.kbm` file is malformed.
+    Kbm {
+        line: Option<usize>,
+        message: String,
+    },
+    /// The interval basis has no period, so there are no periodic neighbourhoods.
+    NoPeriod,
+    /// The number of keys after which the mapping repeats is not the number of keys in the period
+    /// of the interval basis.
+    MappingSize { size: usize, period_keys: usize },
+    /// The mapping doesn't repeat at the period of the interval basis.
+    WrongPeriod {
+        semitones: Semitones,
+        period: String,
+    },
+    /// There's no [Stack] that can be used for the key this many keys above the middle note.
+    NoStackForKey(usize),
+}
+
+impl fmt::Display for ScalaErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let write_line = |f: &mut fmt::Formatter<'_>, line: &Option<usize>| match line {
+            Some(l) => write!(f, " in line {l}"),
+            None => Ok(()),
+        };
+        match self {
+            ScalaErr::Scl { line, message } => {
+                write!(f, "error in the scale file")?;
+                write_line(f, line)?;
+                write!(f, ": {message}")
+            }
+            ScalaErr::Kbm { line, message } => {
+                write!(f, "error in the keyboard mapping")?;
+                write_line(f, line)?;
+                write!(f, ": {message}")
+            }
+            ScalaErr::NoPeriod => write!(f, "the interval basis has no period"),
+            ScalaErr::MappingSize { size, period_keys } => write!(
+                f,
+                "the mapping repeats after {size} keys, but the period of the interval basis \
+                spans {period_keys} keys"
+            ),
+            ScalaErr::WrongPeriod { semitones, period } => write!(
+                f,
+                "the mapping repeats after {:.2} cents, which is not the {period}",
+                semitones * 100.0
+            ),
+            ScalaErr::NoStackForKey(i) => write!(
+                f,
+                "the interval basis has no simple interval spanning {i} keys"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ScalaErr {}
+
+/// A key whose tuning could not be imported exactly.
+#[derive(Debug, PartialEq)]
+pub struct EntryProblem {
+    /// The number of keys above the middle note of the mapping.
+    pub key: usize,
+    pub problem: String,
+}
+
+impl fmt::Display for EntryProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "key {}: {}", self.key, self.problem)
+    }
+}
+
+/// The result of [import].
+pub struct ScalaImport<T: IntervalBasis> {
+    pub neighbourhood: PeriodicComplete<T>,
+    /// Everything that was approximated or replaced, one entry per affected key.
+    pub problems: Vec<EntryProblem>,
+    /// The settings of the keyboard mapping that were not used.
+    pub ignored: Vec<String>,
+}
+
+/// Non-comment lines, trimmed, with their (one-based) line numbers.
+fn content_lines(s: &str) -> impl Iterator<Item = (usize, &str)> {
+    s.lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim()))
+        .filter(|(_, l)| !l.starts_with('!'))
+}
+
+fn first_word<X: std::str::FromStr>(line: &str) -> Option<X> {
+    line.split_whitespace().next()?.parse().ok()
+}
+
+pub fn parse_scl(s: &str) -> Result<Scale, ScalaErr> {
+    let err = |line, message: &str| ScalaErr::Scl {
+        line,
+        message: message.into(),
+    };
+    let mut lines = content_lines(s);
+    let (_, description) = lines
+        .next()
+        .ok_or_else(|| err(None {}, "the description is missing"))?;
+    let mut lines = lines.filter(|(_, l)| !l.is_empty());
+    let (line, count) = lines
+        .next()
+        .ok_or_else(|| err(None {}, "the number of notes is missing"))?;
+    let n: usize =
+        first_word(count).ok_or_else(|| err(Some(line), "expected the number of notes"))?;
+    if n == 0 {
+        return Err(err(Some(line), "the scale has no notes"));
+    }
+    let entries: Vec<_> = lines
+        .take(n)
+        .map(|(line, l)| (line, l.parse::<Pitch>()))
+        .collect();
+    if entries.len() < n {
+        return Err(err(
+            None {},
+            &format!("expected {n} notes, but found only {}", entries.len()),
+        ));
+    }
+    Ok(Scale {
+        description: description.into(),
+        entries,
+    })
+}
+
+pub fn parse_kbm(s: &str) -> Result<KeyboardMapping, ScalaErr> {
+    let mut lines = content_lines(s).filter(|(_, l)| !l.is_empty());
+    let mut field = |name: &str| {
+        let (line, l) = lines.next().ok_or_else(|| ScalaErr::Kbm {
+            line: None {},
+            message: format!("the {name} is missing"),
+        })?;
+        Ok::<_, ScalaErr>((line, l))
+    };
+    fn parse<X: std::str::FromStr>((line, l): (usize, &str), name: &str) -> Result<X, ScalaErr> {
+        first_word(l).ok_or_else(|| ScalaErr::Kbm {
+            line: Some(line),
+            message: format!("expected the {name}"),
+        })
+    }
+    let size = parse(field("map size")?, "map size")?;
+    let first_note = parse(field("first MIDI note")?, "first MIDI note")?;
+    let last_note = parse(field("last MIDI note")?, "last MIDI note")?;
+    let middle_note = parse(field("middle note")?, "middle note")?;
+    let reference_note = parse(field("reference note")?, "reference note")?;
+    let reference_frequency = parse(field("reference frequency")?, "reference frequency")?;
+    let octave_degree = parse(field("octave degree")?, "octave degree")?;
+
+    // Keys whose entries are missing at the end of the file are unmapped.
+    let mut mapping = vec![None {}; size];
+    for (entry, (line, l)) in mapping.iter_mut().zip(lines) {
+        if l.split_whitespace().next() != Some("x") {
+            *entry = Some(parse((line, l), "scale degree or 'x'")?);
+        }
+    }
+
+    Ok(KeyboardMapping {
+        size,
+        first_note,
+        last_note,
+        middle_note,
+        reference_note,
+        reference_frequency,
+        octave_degree,
+        mapping,
+    })
+}
+
+/// The exponents of the `primes` in the factorisation of `ratio`, or `None` if there are other
+/// prime factors.
+fn exponents(ratio: Ratio<StackCoeff>, primes: &[StackCoeff]) -> Option<Vec<StackCoeff>> {
+    let mut res = vec![0; primes.len()];
+    for (n, sign) in [(*ratio.numer(), 1), (*ratio.denom(), -1)] {
+        let mut n = n;
+        for (p, e) in primes.iter().zip(res.iter_mut()) {
+            while n % p == 0 {
+                n /= p;
+                *e += sign;
+            }
+        }
+        if n != 1 {
+            return None {};
+        }
+    }
+    Some(res)
+}
+
+fn add_prime_factors(mut n: StackCoeff, primes: &mut Vec<StackCoeff>) {
+    let mut p = 2;
+    while p * p <= n {
+        if n % p == 0 {
+            if !primes.contains(&p) {
+                primes.push(p);
+            }
+            n /= p;
+        } else {
+            p += 1;
+        }
+    }
+    if n > 1 && !primes.contains(&n) {
+        primes.push(n);
+    }
+}
+
+/// Find an integer solution `x` of `sum_j x[j] * columns[j] = rhs`, by Gaussian elimination over
+/// the rationals. If the columns are linearly dependent, the free coefficients are set to zero.
+fn solve_integer(columns: &[Vec<StackCoeff>], rhs: &[StackCoeff]) -> Option<Vec<StackCoeff>> {
+    let (m, k) = (rhs.len(), columns.len());
+    let mut a: Vec<Vec<Ratio<StackCoeff>>> = (0..m)
+        .map(|r| {
+            columns
+                .iter()
+                .map(|c| Ratio::from_integer(c[r]))
+                .chain([Ratio::from_integer(rhs[r])])
+                .collect()
+        })
+        .collect();
+
+    let mut pivots = vec![];
+    let mut row = 0;
+    for col in 0..k {
+        if row == m {
+            break;
+        }
+        let Some(p) = (row..m).find(|&r| !a[r][col].is_zero()) else {
+            continue;
+        };
+        a.swap(row, p);
+        let pivot = a[row][col];
+        for x in a[row].iter_mut() {
+            *x /= pivot;
+        }
+        let pivot_row = a[row].clone();
+        for (r, other) in a.iter_mut().enumerate() {
+            let factor = other[col];
+            if r != row && !factor.is_zero() {
+                for (x, &p) in other.iter_mut().zip(pivot_row.iter()) {
+                    *x -= factor * p;
+                }
+            }
+        }
+        pivots.push((row, col));
+        row += 1;
+    }
+
+    if a[row..].iter().any(|r| !r[k].is_zero()) {
+        return None {};
+    }
+    let mut res = vec![0; k];
+    for (r, c) in pivots {
+        if !a[r][k].is_integer() {
+            return None {};
+        }
+        res[c] = a[r][k].to_integer();
+    }
+    Some(res)
+}
+
+/// The coefficients of `ratio` in the interval basis, if it is a product of powers of the
+/// [IntervalBasis::intervals].
+pub fn factor<T: IntervalBasis>(ratio: Ratio<StackCoeff>) -> Option<Vec<StackCoeff>> {
+    let mut primes = vec![];
+    for interval in T::intervals() {
+        add_prime_factors(*interval.ratio.numer(), &mut primes);
+        add_prime_factors(*interval.ratio.denom(), &mut primes);
+    }
+    let columns: Vec<_> = T::intervals()
+        .iter()
+        .map(|i| exponents(i.ratio, &primes))
+        .collect::<Option<_>>()?;
+    solve_integer(&columns, &exponents(ratio, &primes)?)
+}
+
+/// Pure [Stack]s with small coefficients, grouped by their [Stack::key_distance] in the period,
+/// and sorted by complexity in each group.
+fn target_candidates<T: IntervalBasis>(
+    period_index: usize,
+    period_keys: usize,
+) -> Vec<Vec<Stack<T>>> {
+    let n = period_keys as StackCoeff;
+    let others: Vec<usize> = (0..T::num_intervals())
+        .filter(|&i| i != period_index)
+        .collect();
+    let mut res: Vec<Vec<(StackCoeff, Stack<T>)>> = vec![vec![]; period_keys];
+    let mut coeffs = vec![-TARGET_SEARCH_RADIUS; others.len()];
+    loop {
+        let mut target = vec![0; T::num_intervals()];
+        for (&i, &c) in others.iter().zip(coeffs.iter()) {
+            target[i] = c;
+        }
+        let key_distance = key_distance_from_coefficients::<T>(ArrayView1::from(&target));
+        target[period_index] = -key_distance.div_euclid(n);
+        let complexity = coeffs.iter().map(|c| c.abs()).sum();
+        res[key_distance.rem_euclid(n) as usize].push((complexity, Stack::from_target(target)));
+
+        // the next combination of coefficients, like an odometer
+        let Some(j) = coeffs.iter().position(|&c| c < TARGET_SEARCH_RADIUS) else {
+            break;
+        };
+        coeffs[j] += 1;
+        for c in coeffs[..j].iter_mut() {
+            *c = -TARGET_SEARCH_RADIUS;
+        }
+    }
+    res.into_iter()
+        .map(|mut v| {
+            v.sort_by_key(|(complexity, _)| *complexity);
+            v.into_iter().map(|(_, s)| s).collect()
+        })
+        .collect()
+}
+
+/// Make a neighbourhood from a scale and an optional keyboard mapping. Without a mapping, the
+/// scale must have as many notes as there are keys in the period of the interval basis, and
+/// repeat at that period.
+pub fn import<T: IntervalBasis>(scl: &str, kbm: Option<&str>) -> Result<ScalaImport<T>, ScalaErr> {
+    let scale = parse_scl(scl)?;
+    let mapping = kbm.map(parse_kbm).transpose()?;
+    let period_index = T::try_period_index().ok_or(ScalaErr::NoPeriod)?;
+    let period = &T::intervals()[period_index];
+    let period_keys = period.key_distance as usize;
+    let num_notes = scale.entries.len();
+
+    let mut ignored = vec![];
+    if let Some(m) = &mapping {
+        if m.first_note > 0 || m.last_note < 127 {
+            ignored.push(format!(
+                "the mapping is meant for the keys {} to {}, but the neighbourhood is used for \
+                all keys",
+                m.first_note, m.last_note
+            ));
+        }
+        // Mappings commonly give the frequency of standard concert pitch; only mention it if
+        // it's something else.
+        let standard = frequency_from_semitones(m.reference_note as Semitones);
+        if (m.reference_frequency - standard).abs() > 0.01 {
+            ignored.push(format!(
+                "the mapping tunes key {} to {} Hz, but the tuning reference is not changed",
+                m.reference_note, m.reference_frequency
+            ));
+        }
+    }
+
+    let (degrees, octave_degree) = match mapping {
+        Some(m) if m.size > 0 => (m.mapping, m.octave_degree),
+        _ => ((0..num_notes).map(Some).collect(), num_notes),
+    };
+    if degrees.len() != period_keys {
+        return Err(ScalaErr::MappingSize {
+            size: degrees.len(),
+            period_keys,
+        });
+    }
+
+    let entry = |i: usize| -> Result<Pitch, String> {
+        if i == 0 {
+            return Ok(Pitch::Ratio(Ratio::one()));
+        }
+        let (line, pitch) = &scale.entries[i - 1];
+        pitch.clone().map_err(|e| format!("line {line}: {e}"))
+    };
+    let pitch = |degree: usize| -> Result<Pitch, String> {
+        let base = entry(degree % num_notes)?;
+        let repeats = (degree / num_notes) as StackCoeff;
+        if repeats == 0 {
+            Ok(base)
+        } else {
+            Ok(base.add_periods(repeats, entry(num_notes)?))
+        }
+    };
+
+    let repeat = pitch(octave_degree).map_err(|message| ScalaErr::Scl {
+        line: None {},
+        message,
+    })?;
+    if !repeat.same_as(&Pitch::Ratio(period.ratio)) {
+        return Err(ScalaErr::WrongPeriod {
+            semitones: repeat.semitones(),
+            period: period.name.clone(),
+        });
+    }
+
+    let candidates = target_candidates::<T>(period_index, period_keys);
+    let nearest = |key: usize, semitones: Semitones| {
+        candidates[key]
+            .iter()
+            .min_by(|a, b| {
+                (a.semitones() - semitones)
+                    .abs()
+                    .total_cmp(&(b.semitones() - semitones).abs())
+            })
+            .ok_or(ScalaErr::NoStackForKey(key))
+    };
+    let tempered = |key: usize, semitones: Semitones| -> Result<Stack<T>, ScalaErr> {
+        let mut stack = nearest(key, semitones)?.clone();
+        let deviation = semitones - stack.semitones();
+        stack.actual[period_index] += Ratio::new(
+            (deviation * 100.0 * CENT_RESOLUTION).round() as StackCoeff,
+            (period.semitones * 100.0 * CENT_RESOLUTION).round() as StackCoeff,
+        );
+        Ok(stack)
+    };
+
+    let mut stacks = Vec::with_capacity(period_keys);
+    let mut problems = vec![];
+    let mut problem = |key, problem| problems.push(EntryProblem { key, problem });
+    for (key, degree) in degrees.iter().enumerate() {
+        let equal_temperament = key as Semitones * period.semitones / period_keys as Semitones;
+        let stack = match degree.map(pitch) {
+            None => {
+                problem(key, "the key is unmapped, it is tuned like the closest pure interval to equal temperament".into());
+                nearest(key, equal_temperament)?.clone()
+            }
+            Some(Err(e)) => {
+                problem(
+                    key,
+                    format!(
+                        "{e}, the key is tuned like the closest pure interval to equal temperament"
+                    ),
+                );
+                nearest(key, equal_temperament)?.clone()
+            }
+            Some(Ok(Pitch::Cents(c))) => tempered(key, c / 100.0)?,
+            Some(Ok(p @ Pitch::Ratio(r))) => match factor::<T>(r) {
+                Some(coeffs)
+                    if key_distance_from_coefficients::<T>(ArrayView1::from(&coeffs))
+                        == key as StackCoeff =>
+                {
+                    Stack::from_target(coeffs)
+                }
+                Some(_) => {
+                    problem(
+                        key,
+                        format!("{r} spans a different number of keys, so it is tempered"),
+                    );
+                    tempered(key, p.semitones())?
+                }
+                None => {
+                    problem(
+                        key,
+                        format!("{r} can't be written in the interval basis, so it is tempered"),
+                    );
+                    tempered(key, p.semitones())?
+                }
+            },
+        };
+        stacks.push(stack);
+    }
+
+    Ok(ScalaImport {
+        neighbourhood: PeriodicComplete {
+            stacks,
+            period: Stack::from_pure_interval(period_index, 1),
+            period_index: Some(period_index),
+        },
+        problems,
+        ignored,
+    })
+}
+
+/// The frequency ratio of a pure [Stack], if it fits into [StackCoeff]s.
+fn ratio_of<T: IntervalBasis>(stack: &Stack<T>) -> Option<Ratio<StackCoeff>> {
+    if !stack.is_pure() {
+        return None {};
+    }
+    let (mut numer, mut denom): (StackCoeff, StackCoeff) = (1, 1);
+    for (interval, c) in T::intervals().iter().zip(stack.actual.iter()) {
+        let c = c.to_integer();
+        let (n, d) = if c >= 0 {
+            (*interval.ratio.numer(), *interval.ratio.denom())
+        } else {
+            (*interval.ratio.denom(), *interval.ratio.numer())
+        };
+        let exponent = c.unsigned_abs().try_into().ok()?;
+        numer = numer.checked_mul(n.checked_pow(exponent)?)?;
+        denom = denom.checked_mul(d.checked_pow(exponent)?)?;
+    }
+    Some(Ratio::new(numer, denom))
+}
+
+/// A ratio for pure [Stack]s, cents otherwise.
+fn scl_entry<T: IntervalBasis>(stack: &Stack<T>) -> String {
+    match ratio_of(stack) {
+        Some(r) => r.to_string(),
+        None => format!("{:.6}", stack.semitones() * 100.0),
+    }
+}
+
+fn write_scl(description: &str, entries: &[String]) -> String {
+    let mut res = format!(
+        "! written by adaptuner\n!\n{}\n {}\n!\n",
+        description.replace('\n', " "),
+        entries.len()
+    );
+    for entry in entries {
+        res.push_str(&format!(" {entry}\n"));
+    }
+    res
+}
+
+/// A mapping that retunes all keys, and puts the first entry of the scale on the middle note,
+/// which is also the reference note.
+fn write_kbm(
+    size: usize,
+    middle_note: u8,
+    reference_frequency: f64,
+    octave_degree: usize,
+) -> String {
+    let mut res = format!(
+        "! written by adaptuner
+! Map size:
+{size}
+! First MIDI note number to retune:
+0
+! Last MIDI note number to retune:
+127
+! Middle note where the first entry of the mapping is mapped to:
+{middle_note}
+! Reference note for which frequency is given:
+{middle_note}
+! Frequency to tune the above note to:
+{reference_frequency:.6}
+! Scale degree to consider as formal octave:
+{octave_degree}
+! Mapping.
+"
+    );
+    for degree in 0..size {
+        res.push_str(&format!("{degree}\n"));
+    }
+    res
+}
+
+/// `semitones` are the fractional MIDI note numbers of all keys.
+fn write_tun(description: &str, semitones: &[Semitones; 128]) -> String {
+    let mut res = format!(
+        "; written by adaptuner
+[Scale Begin]
+Format= \"AnaMark-TUN\"
+FormatVersion= 200
+FormatSpecs= \"http://www.mark-henning.de/eternity/tuningspecs.html\"
+
+[Info]
+Name= \"{}\"
+
+[Tuning]
+",
+        description.replace(['\n', '"'], " ")
+    );
+    for (i, s) in semitones.iter().enumerate() {
+        res.push_str(&format!("note {i}= {}\n", (s * 100.0).round() as i64));
+    }
+    res.push_str(&format!(
+        "\n[Exact Tuning]\nBaseFreq= {:.10}\n",
+        frequency_from_semitones(0.0)
+    ));
+    for (i, s) in semitones.iter().enumerate() {
+        res.push_str(&format!("note {i}= {:.6}\n", s * 100.0));
+    }
+    res.push_str("\n[Scale End]\n");
+    res
+}
+
+/// The contents of the `.scl`, `.kbm`, and `.tun` files describing a tuning.
+pub struct TuningFiles {
+    pub scl: String,
+    pub kbm: String,
+    pub tun: String,
+}
+
+impl TuningFiles {
+    /// `semitones` are the fractional MIDI note numbers of all keys. The scale has an entry for
+    /// each key above the lowest one, and the mapping is linear.
+    pub fn of_tuning(description: &str, semitones: &[Semitones; 128]) -> Self {
+        let entries: Vec<_> = semitones[1..]
+            .iter()
+            .map(|s| format!("{:.6}", (s - semitones[0]) * 100.0))
+            .collect();
+        Self {
+            scl: write_scl(description, &entries),
+            kbm: write_kbm(0, 0, frequency_from_semitones(semitones[0]), entries.len()),
+            tun: write_tun(description, semitones),
+        }
+    }
+
+    /// The scale has one entry per key in the period of the neighbourhood, which is mapped to the
+    /// key of the `reference`.
+    pub fn of_neighbourhood<T: IntervalBasis, N: CompleteNeigbourhood<T>>(
+        description: &str,
+        neighbourhood: &N,
+        reference: &Stack<T>,
+        c4_semitones: Semitones,
+    ) -> Result<Self, String> {
+        let period = neighbourhood
+            .try_period()
+            .ok_or("the neighbourhood is not periodic")?;
+        let size = period.key_distance();
+        if size <= 0 {
+            return Err("the period of the neighbourhood spans no keys".into());
+        }
+        let middle_note = reference.key_number();
+        if !(0..128).contains(&middle_note) {
+            return Err("the reference is not on the keyboard".into());
+        }
+
+        let mut entries: Vec<_> = (1..size)
+            .map(|i| scl_entry(&neighbourhood.get_relative_stack(i)))
+            .collect();
+        entries.push(scl_entry(period));
+
+        let reference_semitones = reference.absolute_semitones(c4_semitones);
+        let semitones = std::array::from_fn(|key| {
+            reference_semitones
+                + neighbourhood
+                    .get_relative_stack(key as StackCoeff - middle_note)
+                    .semitones()
+        });
+
+        Ok(Self {
+            scl: write_scl(description, &entries),
+            kbm: write_kbm(
+                size as usize,
+                middle_note as u8,
+                frequency_from_semitones(reference_semitones),
+                size as usize,
+            ),
+            tun: write_tun(description, &semitones),
+        })
+    }
+
+    /// Write the files to `base` with the extensions `.scl`, `.kbm`, and `.tun`.
+    pub fn write(&self, base: &Path) -> std::io::Result<Vec<PathBuf>> {
+        let mut res = vec![];
+        for (extension, contents) in [("scl", &self.scl), ("kbm", &self.kbm), ("tun", &self.tun)] {
+            let path = base.with_extension(extension);
+            std::fs::write(&path, contents)?;
+            res.push(path);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use approx::assert_relative_eq;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::interval::stacktype::fivelimit::mock::MockFiveLimitStackType;
+
+    const JUST: &str = "! just.scl
+!
+5-limit just intonation
+ 12
+!
+ 16/15
+ 9/8
+ 6/5
+ 5/4
+ 4/3
+ 45/32
+ 3/2
+ 8/5
+ 5/3
+ 9/5
+ 15/8
+ 2/1
+";
+
+    #[test]
+    fn test_factor() {
+        assert_eq!(
+            factor::<MockFiveLimitStackType>(Ratio::new(9, 8)),
+            Some(vec![-1, 2, 0])
+        );
+        assert_eq!(
+            factor::<MockFiveLimitStackType>(Ratio::new(16, 15)),
+            Some(vec![1, -1, -1])
+        );
+        assert_eq!(factor::<MockFiveLimitStackType>(Ratio::new(7, 4)), None {});
+    }
+
+    #[test]
+    fn test_import_ratios() {
+        let import = import::<MockFiveLimitStackType>(JUST, None {}).unwrap();
+        assert_eq!(import.problems, vec![]);
+        assert_eq!(
+            import.neighbourhood.stacks[1],
+            Stack::from_target(vec![1, -1, -1])
+        );
+        assert_eq!(
+            import.neighbourhood.stacks[4],
+            Stack::from_target(vec![0, 0, 1])
+        );
+        assert_eq!(
+            import.neighbourhood.stacks[10],
+            Stack::from_target(vec![0, 2, -1])
+        );
+        for (i, s) in import.neighbourhood.stacks.iter().enumerate() {
+            assert_eq!(s.key_distance(), i as StackCoeff);
+        }
+    }
+
+    #[test]
+    fn test_import_cents_and_problems() {
+        let scl = "12-tone equal temperament, with a mistake
+12
+100.0
+200.
+300.0
+400.0
+500.0
+600.0
+700.0
+800.0
+900.0
+1000.0
+11/7
+1200.0
+";
+        let import = import::<MockFiveLimitStackType>(scl, None {}).unwrap();
+        for (i, s) in import.neighbourhood.stacks.iter().enumerate() {
+            assert_eq!(s.key_distance(), i as StackCoeff);
+            if i != 11 {
+                assert_relative_eq!(s.semitones(), i as Semitones, epsilon = 0.0001);
+            }
+        }
+        assert_eq!(
+            import.neighbourhood.stacks[7].target,
+            Stack::<MockFiveLimitStackType>::from_target(vec![0, 1, 0]).target
+        );
+        assert_eq!(
+            import.problems,
+            vec![EntryProblem {
+                key: 11,
+                problem: "11/7 can't be written in the interval basis, so it is tempered".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_import_with_mapping() {
+        let kbm = "! only the white keys
+12
+0
+127
+60
+69
+440.0
+12
+0
+x
+2
+x
+4
+5
+x
+7
+x
+9
+x
+11
+";
+        let import = import::<MockFiveLimitStackType>(JUST, Some(kbm)).unwrap();
+        assert_eq!(
+            import.problems.iter().map(|p| p.key).collect::<Vec<_>>(),
+            vec![1, 3, 6, 8, 10]
+        );
+        assert_eq!(
+            import.neighbourhood.stacks[2],
+            Stack::from_target(vec![-1, 2, 0])
+        );
+        assert_eq!(import.ignored, Vec::<String>::new());
+
+        let kbm = kbm
+            .replacen("0\n127\n", "36\n96\n", 1)
+            .replacen("440.0", "432.0", 1);
+        let import = super::import::<MockFiveLimitStackType>(JUST, Some(&kbm)).unwrap();
+        assert_eq!(
+            import.ignored,
+            vec![
+                "the mapping is meant for the keys 36 to 96, but the neighbourhood is used for all \
+                keys",
+                "the mapping tunes key 69 to 432 Hz, but the tuning reference is not changed",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_export_neighbourhood() {
+        let just = import::<MockFiveLimitStackType>(JUST, None {}).unwrap();
+        let reference = Stack::from_target(vec![0, 1, 0]);
+        let files =
+            TuningFiles::of_neighbourhood("just", &just.neighbourhood, &reference, 60.0).unwrap();
+
+        let again = import::<MockFiveLimitStackType>(&files.scl, Some(&files.kbm)).unwrap();
+        assert_eq!(again.problems, vec![]);
+        assert_eq!(again.neighbourhood, just.neighbourhood);
+
+        let kbm = parse_kbm(&files.kbm).unwrap();
+        assert_eq!(kbm.middle_note, 67);
+        assert_relative_eq!(kbm.reference_frequency, 392.438, epsilon = 0.001);
+
+        let exact_g = format!("note 67= {:.6}\n", reference.semitones() * 100.0 + 6000.0);
+        assert!(files.tun.contains(&exact_g));
+        let exact_e = format!(
+            "note 64= {:.6}\n",
+            (reference.semitones() + Pitch::Ratio(Ratio::new(5, 6)).semitones()) * 100.0 + 6000.0
+        );
+        assert!(files.tun.contains(&exact_e));
+    }
+
+    #[test]
+    fn test_export_tuning() {
+        let semitones = std::array::from_fn(|i| i as Semitones + 0.5);
+        let files = TuningFiles::of_tuning("quarter tones", &semitones);
+        let scale = parse_scl(&files.scl).unwrap();
+        assert_eq!(scale.entries.len(), 127);
+        assert_eq!(scale.entries[126].1, Ok(Pitch::Cents(12700.0)));
+        assert!(files.tun.contains("note 127= 12750\n"));
+        assert!(files.tun.contains("note 0= 50.000000\n"));
+    }
+
+    #[test]
+    fn test_import_errors() {
+        assert_eq!(
+            import::<MockFiveLimitStackType>("pentatonic\n5\n9/8\n5/4\n3/2\n5/3\n2/1\n", None {})
+                .err()
+                .unwrap(),
+            ScalaErr::MappingSize {
+                size: 5,
+                period_keys: 12
+            }
+        );
+        assert_eq!(
+            import::<MockFiveLimitStackType>("short\n3\n9/8\n5/4\n", None {})
+                .err()
+                .unwrap(),
+            ScalaErr::Scl {
+                line: None {},
+                message: "expected 3 notes, but found only 2".into()
+            }
+        );
+        assert!(matches!(
+            import::<MockFiveLimitStackType>(&JUST.replace("2/1", "3/1"), None {}),
+            Err(ScalaErr::WrongPeriod { .. })
+        ));
+    }
+}
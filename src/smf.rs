@@ -0,0 +1,161 @@
+//! Reading and writing the raw bytes of Standard MIDI Files.
+//!
+//! Files are written from raw bytes, and not via [MidiMsg], because [MidiMsg::to_midi] may combine
+//! several messages (like the MSB and LSB of a controller, or RPNs) into one, which can't be
+//! written as a single track event.
+
+use midi_msg::{Division, Meta, MidiMsg, TimeCodeType};
+
+/// The default tempo of Standard MIDI Files, in microseconds per quarter note.
+pub const DEFAULT_TEMPO: u32 = 500_000;
+
+/// Split `bytes` into single messages, each with its status byte. Running status is resolved.
+pub fn split_messages(bytes: &[u8]) -> Vec<Vec<u8>> {
+    let mut res = vec![];
+    let mut running_status = None {};
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == 0xF0 {
+            let end = bytes[i..]
+                .iter()
+                .position(|&b| b == 0xF7)
+                .map_or(bytes.len(), |j| i + j + 1);
+            res.push(bytes[i..end].to_vec());
+            running_status = None {};
+            i = end;
+            continue;
+        }
+        let (status, data_start) = if bytes[i] >= 0x80 {
+            (bytes[i], i + 1)
+        } else if let Some(status) = running_status {
+            (status, i)
+        } else {
+            // a data byte without a status: skip it
+            i += 1;
+            continue;
+        };
+        let n_data = match status {
+            0xC0..=0xDF => 1,
+            0x80..=0xEF => 2,
+            0xF1 | 0xF3 => 1,
+            0xF2 => 2,
+            _ => 0,
+        };
+        match status {
+            0x80..=0xEF => running_status = Some(status),
+            0xF0..=0xF7 => running_status = None {},
+            _ => {}
+        }
+        let end = (data_start + n_data).min(bytes.len());
+        let mut msg = vec![status];
+        msg.extend_from_slice(&bytes[data_start..end]);
+        res.push(msg);
+        i = end;
+    }
+    res
+}
+
+fn push_vlq(mut x: u32, v: &mut Vec<u8>) {
+    let mut bytes = vec![(x & 0x7F) as u8];
+    x >>= 7;
+    while x > 0 {
+        bytes.push((x & 0x7F) as u8 | 0x80);
+        x >>= 7;
+    }
+    v.extend(bytes.iter().rev());
+}
+
+/// The bytes of a track event (without the delta time) for a single message, as returned by
+/// [split_messages].
+pub fn track_event(msg: &[u8]) -> Vec<u8> {
+    match msg.first() {
+        Some(0xF0) => {
+            let mut v = vec![0xF0];
+            push_vlq(msg.len() as u32 - 1, &mut v);
+            v.extend_from_slice(&msg[1..]);
+            v
+        }
+        Some(&status) if status > 0xF0 => {
+            // escaped, because system common and real time messages can't appear in files
+            let mut v = vec![0xF7];
+            push_vlq(msg.len() as u32, &mut v);
+            v.extend_from_slice(msg);
+            v
+        }
+        _ => msg.to_vec(),
+    }
+}
+
+pub fn meta_event(meta: &Meta) -> Vec<u8> {
+    let mut v = vec![0xFF];
+    v.extend(MidiMsg::Meta { msg: meta.clone() }.to_midi());
+    v
+}
+
+/// Write a track chunk, given the absolute ticks and bytes of its events. The end of track event
+/// is added.
+fn write_track(events: &[(u32, Vec<u8>)], v: &mut Vec<u8>) {
+    let mut chunk = vec![];
+    let mut last_tick = 0;
+    for (tick, event) in events {
+        push_vlq(tick - last_tick, &mut chunk);
+        chunk.extend_from_slice(event);
+        last_tick = *tick;
+    }
+    push_vlq(0, &mut chunk);
+    chunk.extend(meta_event(&Meta::EndOfTrack));
+
+    v.extend_from_slice(b"MTrk");
+    v.extend((chunk.len() as u32).to_be_bytes());
+    v.extend(chunk);
+}
+
+pub fn write_file(division: Division, tracks: &[Vec<(u32, Vec<u8>)>]) -> Vec<u8> {
+    let mut v = vec![];
+    v.extend_from_slice(b"MThd");
+    v.extend(6u32.to_be_bytes());
+    v.extend(1u16.to_be_bytes());
+    v.extend((tracks.len() as u16).to_be_bytes());
+    match division {
+        Division::TicksPerQuarterNote(tpqn) => v.extend((tpqn & 0x7FFF).to_be_bytes()),
+        Division::TimeCode {
+            frames_per_second,
+            ticks_per_frame,
+        } => {
+            let fps: i8 = match frames_per_second {
+                TimeCodeType::FPS24 => 24,
+                TimeCodeType::FPS25 => 25,
+                TimeCodeType::DF30 => 29,
+                TimeCodeType::NDF30 => 30,
+            };
+            v.push((-fps) as u8);
+            v.push(ticks_per_frame);
+        }
+    }
+    for track in tracks {
+        write_track(track, &mut v);
+    }
+    v
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_split_messages() {
+        assert_eq!(
+            split_messages(&[0xB0, 100, 0, 101, 0, 6, 2, 0x90, 60, 64, 0xF0, 1, 2, 0xF7, 0xC1, 5]),
+            vec![
+                vec![0xB0, 100, 0],
+                vec![0xB0, 101, 0],
+                vec![0xB0, 6, 2],
+                vec![0x90, 60, 64],
+                vec![0xF0, 1, 2, 0xF7],
+                vec![0xC1, 5],
+            ]
+        );
+    }
+}
@@ -0,0 +1,415 @@
+//! A wrapper around any other strategy that keeps track of how far the reference has drifted,
+//! and optionally nudges it back.
+//!
+//! The drift is the number of cents by which the reference deviates from where it would be in
+//! equal temperament, relative to the note of the tuning reference. So, if the reference is a pure
+//! fifth above that note, the drift is about +2 cents; if it is a syntonic comma below, it's about
+//! -21.5 cents.
+//!
+//! If compensation is enabled, the reference is moved by the `comma` whenever the drift exceeds
+//! `max_drift_cents` and that reduces the drift. This happens only during rests (when no key is
+//! sounding), or at chord changes, or both.
+
+use std::{collections::VecDeque, time::Instant};
+
+use crate::{
+    config::{ExtractConfig, StrategyConfig},
+    interval::{
+        base::Semitones,
+        stack::{ScaledAdd, Stack},
+        stacktype::r#trait::{IntervalBasis, StackType},
+    },
+    keystate::KeyState,
+    msg::{FromStrategy, ToStrategy},
+    strategy::r#trait::Strategy,
+};
+
+#[derive(Clone)]
+pub struct DriftConfig<T: IntervalBasis> {
+    pub inner: Box<StrategyConfig<T>>,
+    pub comma: Stack<T>,
+    pub max_drift_cents: f64,
+    pub compensate_at_rests: bool,
+    pub compensate_at_chord_changes: bool,
+}
+
+pub struct Drift<T: StackType> {
+    inner: Box<dyn Strategy<T>>,
+    comma: Stack<T>,
+    max_drift_cents: f64,
+    compensate_at_rests: bool,
+    compensate_at_chord_changes: bool,
+    /// The note of the tuning reference, relative to which the drift is measured.
+    baseline: Stack<T>,
+    /// The last reference the inner strategy sent.
+    reference: Stack<T>,
+    /// The last chord the inner strategy sent.
+    harmony: Option<(usize, Stack<T>)>,
+    queue: VecDeque<FromStrategy<T>>,
+    /// The time of the latest event, for messages that have none.
+    time: Instant,
+}
+
+/// The drift of `reference` from `baseline`, in cents.
+pub fn drift_cents<T: IntervalBasis>(baseline: &Stack<T>, reference: &Stack<T>) -> f64 {
+    let mut difference = reference.clone();
+    difference.scaled_add(-1, baseline);
+    100.0 * (difference.semitones() - difference.key_distance() as Semitones)
+}
+
+impl<T: StackType> Drift<T> {
+    pub fn new(config: DriftConfig<T>) -> Self {
+        let baseline = config
+            .inner
+            .static_tuning()
+            .map_or(Stack::new_zero(), |c| c.tuning_reference.stack.clone());
+        Self {
+            inner: config.inner.realize(),
+            comma: config.comma,
+            max_drift_cents: config.max_drift_cents,
+            compensate_at_rests: config.compensate_at_rests,
+            compensate_at_chord_changes: config.compensate_at_chord_changes,
+            baseline,
+            reference: Stack::new_zero(),
+            harmony: None {},
+            queue: VecDeque::new(),
+            time: Instant::now(),
+        }
+    }
+
+    /// Forward everything the inner strategy sent, and send the drift if the reference changed.
+    /// Returns true iff the inner strategy sent a new chord.
+    fn forward_queue(&mut self, time: Instant, forward: &mut VecDeque<FromStrategy<T>>) -> bool {
+        let mut reference_changed = false;
+        let mut chord_changed = false;
+        while let Some(msg) = self.queue.pop_front() {
+            match &msg {
+                FromStrategy::SetReference { stack } => {
+                    self.reference.clone_from(stack);
+                    reference_changed = true;
+                }
+                FromStrategy::SetTuningReference { reference } => {
+                    self.baseline.clone_from(&reference.stack);
+                    reference_changed = true;
+                }
+                FromStrategy::CurrentHarmony {
+                    pattern_index,
+                    reference,
+                } => {
+                    let harmony = pattern_index.zip(reference.clone());
+                    if harmony.is_some() && harmony != self.harmony {
+                        chord_changed = true;
+                    }
+                    self.harmony = harmony;
+                }
+                _ => {}
+            }
+            forward.push_back(msg);
+        }
+        if reference_changed {
+            forward.push_back(FromStrategy::Drift {
+                cents: drift_cents(&self.baseline, &self.reference),
+                time,
+            });
+        }
+        chord_changed
+    }
+
+    /// Move the reference by the comma, if the drift is too large and that makes it smaller.
+    /// Returns true iff the reference was moved.
+    fn compensate(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        let drift = drift_cents(&self.baseline, &self.reference);
+        if drift.abs() <= self.max_drift_cents {
+            return false;
+        }
+        let comma = 100.0 * self.comma.semitones();
+        let direction = if (drift > 0.0) == (comma > 0.0) {
+            -1
+        } else {
+            1
+        };
+        if (drift + direction as f64 * comma).abs() >= drift.abs() {
+            return false;
+        }
+        let mut reference = self.reference.clone();
+        reference.scaled_add(direction, &self.comma);
+        let success = self.inner.handle_msg(
+            keys,
+            tunings,
+            ToStrategy::SetReference { reference, time },
+            &mut self.queue,
+        );
+        self.forward_queue(time, forward);
+        success
+    }
+}
+
+impl<T: StackType> Strategy<T> for Drift<T> {
+    fn note_on<'a>(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &'a mut [Stack<T>; 128],
+        note: u8,
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> Option<(Semitones, &'a Stack<T>)> {
+        self.time = time;
+        let mut tuning = self
+            .inner
+            .note_on(keys, tunings, note, time, &mut self.queue)?
+            .0;
+        let chord_changed = self.forward_queue(time, forward);
+        if chord_changed
+            && self.compensate_at_chord_changes
+            && self.compensate(keys, tunings, time, forward)
+        {
+            // The new note was re-tuned as well.
+            if let Some(t) = forward.iter().rev().find_map(|msg| match msg {
+                FromStrategy::Retune {
+                    note: n, tuning, ..
+                } if *n == note => Some(*tuning),
+                _ => None {},
+            }) {
+                tuning = t;
+            }
+        }
+        Some((tuning, &tunings[note as usize]))
+    }
+
+    fn note_off(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        note: u8,
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        self.time = time;
+        let success = self
+            .inner
+            .note_off(keys, tunings, note, time, &mut self.queue);
+        let chord_changed = self.forward_queue(time, forward);
+        let rest = !keys.iter().any(|k| k.is_sounding());
+        if (rest && self.compensate_at_rests) || (chord_changed && self.compensate_at_chord_changes)
+        {
+            self.compensate(keys, tunings, time, forward);
+        }
+        success
+    }
+
+    fn handle_msg(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        msg: ToStrategy<T>,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        match msg {
+            ToStrategy::SetDriftCompensation {
+                max_drift_cents,
+                at_rests,
+                at_chord_changes,
+            } => {
+                self.max_drift_cents = max_drift_cents;
+                self.compensate_at_rests = at_rests;
+                self.compensate_at_chord_changes = at_chord_changes;
+                true
+            }
+            _ => {
+                if let Some(time) = msg.time() {
+                    self.time = time;
+                }
+                let success = self.inner.handle_msg(keys, tunings, msg, &mut self.queue);
+                self.forward_queue(self.time, forward);
+                success
+            }
+        }
+    }
+
+    fn start(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) {
+        self.time = time;
+        self.inner.start(keys, tunings, time, &mut self.queue);
+        self.harmony = None {};
+        self.forward_queue(time, forward);
+    }
+}
+
+impl<T: StackType> ExtractConfig<StrategyConfig<T>> for Drift<T> {
+    fn extract_config(&self) -> StrategyConfig<T> {
+        StrategyConfig::Drift(DriftConfig {
+            inner: Box::new(self.inner.extract_config()),
+            comma: self.comma.clone(),
+            max_drift_cents: self.max_drift_cents,
+            compensate_at_rests: self.compensate_at_rests,
+            compensate_at_chord_changes: self.compensate_at_chord_changes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use midi_msg::Channel;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        interval::stacktype::fivelimit::mock::{
+            static_tuning_config, triad, MockFiveLimitStackType, TWELVE_NOTES,
+        },
+        reference::Reference,
+        strategy::{twostep::harmony::chordlist::ChordListConfig, walking::WalkingConfig},
+    };
+
+    fn drift(
+        compensate_at_rests: bool,
+        compensate_at_chord_changes: bool,
+    ) -> Drift<MockFiveLimitStackType> {
+        Drift::new(DriftConfig {
+            inner: Box::new(StrategyConfig::Walking(WalkingConfig {
+                patterns: ChordListConfig {
+                    enable: true,
+                    patterns: vec![triad([0, 0, 1]), triad([0, 1, -1])],
+                },
+                inner: static_tuning_config(&[TWELVE_NOTES]),
+                walk: true,
+                temper_patterns: false,
+            })),
+            comma: Stack::from_target(vec![-2, 4, -1]),
+            max_drift_cents: 10.0,
+            compensate_at_rests,
+            compensate_at_chord_changes,
+        })
+    }
+
+    /// Play A minor, D minor, G major and C major, which makes the reference drift down by a
+    /// syntonic comma. Returns the drift after each chord. If `legato` is set, the keys of each
+    /// chord are only released after the keys of the next one are pressed, so that there are no
+    /// rests.
+    fn play(s: &mut Drift<MockFiveLimitStackType>, legato: bool) -> Vec<i64> {
+        let start = Instant::now();
+        let mut keys: [KeyState; 128] = core::array::from_fn(|_| KeyState::new(start));
+        let mut tunings: [Stack<MockFiveLimitStackType>; 128] =
+            core::array::from_fn(|_| Stack::new_zero());
+        let mut forward = VecDeque::new();
+        s.start(&keys, &mut tunings, start, &mut forward);
+
+        let mut res = vec![];
+        let mut held: &[u8] = &[];
+        for (i, chord) in [[57, 60, 64], [62, 65, 69], [55, 59, 62], [60, 64, 67]]
+            .iter()
+            .enumerate()
+        {
+            let time = start + Duration::from_millis(100 * i as u64);
+            for &note in chord.iter().filter(|n| !held.contains(n)) {
+                keys[note as usize].note_on(Channel::Ch1, time);
+                assert!(s
+                    .note_on(&keys, &mut tunings, note, time, &mut forward)
+                    .is_some());
+            }
+            let mut release = held
+                .iter()
+                .filter(|n| !chord.contains(n))
+                .collect::<Vec<_>>();
+            if !legato {
+                release.extend(chord);
+            }
+            for &note in release {
+                keys[note as usize].note_off(Channel::Ch1, false, time);
+                assert!(s.note_off(&keys, &mut tunings, note, time, &mut forward));
+            }
+            held = if legato { chord } else { &[] };
+            res.push(drift_cents(&s.baseline, &s.reference).round() as i64);
+        }
+        res
+    }
+
+    #[test]
+    fn test_drift_from_tuning_reference() {
+        let mut s = drift(false, false);
+        let start = Instant::now();
+        let keys: [KeyState; 128] = core::array::from_fn(|_| KeyState::new(start));
+        let mut tunings: [Stack<MockFiveLimitStackType>; 128] =
+            core::array::from_fn(|_| Stack::new_zero());
+        let mut forward = VecDeque::new();
+        s.start(&keys, &mut tunings, start, &mut forward);
+
+        let mut drifts = |s: &mut Drift<MockFiveLimitStackType>, msg| {
+            forward.clear();
+            assert!(s.handle_msg(&keys, &mut tunings, msg, &mut forward));
+            forward
+                .iter()
+                .filter_map(|msg| match msg {
+                    FromStrategy::Drift { cents, time } => Some(((100.0 * cents).round(), *time)),
+                    _ => None {},
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // a pure fifth above the C of the tuning reference
+        let g = Stack::from_target(vec![0, 1, 0]);
+        let time = start + Duration::from_millis(100);
+        assert_eq!(
+            drifts(
+                &mut s,
+                ToStrategy::SetReference {
+                    reference: g.clone(),
+                    time
+                }
+            ),
+            vec![(196.0, time)]
+        );
+
+        let time = start + Duration::from_millis(200);
+        assert_eq!(
+            drifts(
+                &mut s,
+                ToStrategy::SetTuningReference {
+                    reference: Reference::from_semitones(g, 67.0),
+                    time
+                }
+            ),
+            vec![(0.0, time)]
+        );
+    }
+
+    #[test]
+    fn test_drift_tracking() {
+        // Every step of the progression lowers the key centre by about 2ct relative to its
+        // equally tempered position, until the final C is a syntonic comma low.
+        assert_eq!(
+            play(&mut drift(false, false), false),
+            vec![-16, -18, -20, -22]
+        );
+    }
+
+    #[test]
+    fn test_compensation_at_rests() {
+        // After the first chord, the drift exceeds the maximum and the key centre moves up by a
+        // comma. From then on, it stays within bounds.
+        assert_eq!(play(&mut drift(true, false), false), vec![6, 4, 2, 0]);
+    }
+
+    #[test]
+    fn test_compensation_at_chord_changes() {
+        // Without rests, only compensation at chord changes moves the key centre. It does so as
+        // soon as the first chord is recognised.
+        assert_eq!(
+            play(&mut drift(true, false), true),
+            vec![-16, -18, -20, -22]
+        );
+        assert_eq!(play(&mut drift(false, true), true), vec![6, 4, 2, 0]);
+    }
+}
@@ -0,0 +1,559 @@
+//! A wrapper around any other strategy that makes re-tunings of already sounding notes less
+//! jumpy.
+//!
+//! Newly pressed notes (and notes that aren't sounding) always get the tuning the inner strategy
+//! proposes. Re-tunings of sounding notes are held back until the inner strategy has proposed the
+//! same tuning for at least `stable_ms`. If `max_retune_cents` is set, held back re-tunings are
+//! applied in steps of at most that size, one step per `stable_ms`. If `freeze` is set, sounding
+//! notes are not re-tuned at all, and only pick up their new tuning once they're released.
+//!
+//! Held back re-tunings are applied at the next event (note on or off, or message to the
+//! strategy) after they've become stable. So that they're also applied if no such event comes,
+//! the strategy asks for a [ToStrategy::Tick] at that time (see [FromStrategy::WakeUp]).
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use ndarray::Array1;
+use num_rational::Ratio;
+
+use crate::{
+    config::{ExtractConfig, StrategyConfig},
+    interval::{
+        base::Semitones,
+        stack::Stack,
+        stacktype::r#trait::{IntervalBasis, StackCoeff, StackType},
+    },
+    keystate::KeyState,
+    msg::{FromStrategy, ToStrategy},
+    strategy::r#trait::Strategy,
+};
+
+#[derive(Clone)]
+pub struct HysteresisConfig<T: IntervalBasis> {
+    pub inner: Box<StrategyConfig<T>>,
+    pub stable_ms: u64,
+    pub max_retune_cents: Option<f64>,
+    pub freeze: bool,
+}
+
+/// A re-tuning of a sounding note that the inner strategy proposed, but that hasn't been (fully)
+/// applied yet.
+struct Pending<T: StackType> {
+    tuning: Semitones,
+    tuning_stack: Stack<T>,
+    since: Instant,
+}
+
+pub struct Hysteresis<T: StackType> {
+    inner: Box<dyn Strategy<T>>,
+    stable: Duration,
+    max_retune_cents: Option<f64>,
+    freeze: bool,
+    /// The tunings as proposed by the inner strategy.
+    inner_tunings: [Stack<T>; 128],
+    /// The tunings that were last sent out for each note.
+    current: [Semitones; 128],
+    pending: [Option<Pending<T>>; 128],
+    queue: VecDeque<FromStrategy<T>>,
+    /// The time of the last [FromStrategy::WakeUp] that was sent.
+    wake_up: Option<Instant>,
+}
+
+/// The stack `amount` of the way from `from` to `to`, with the target of `to`. The actual
+/// coefficients are rounded to millionths, so that their denominators don't grow with every
+/// step.
+fn intermediate<T: StackType>(from: &Stack<T>, to: &Stack<T>, amount: f64) -> Stack<T> {
+    let as_float = |c: Ratio<StackCoeff>| {
+        let (n, d) = c.into_raw();
+        n as f64 / d as f64
+    };
+    let (from, to_actual) = (from.actual_coefficients(), to.actual_coefficients());
+    Stack::from_target_and_actual(
+        to.target_coefficients().to_owned(),
+        Array1::from_shape_fn(from.len(), |i| {
+            let c = as_float(from[i]) + amount * (as_float(to_actual[i]) - as_float(from[i]));
+            Ratio::new((c * 1_000_000.0).round() as StackCoeff, 1_000_000)
+        }),
+    )
+}
+
+impl<T: StackType> Hysteresis<T> {
+    pub fn new(config: HysteresisConfig<T>) -> Self {
+        Self {
+            inner: config.inner.realize(),
+            stable: Duration::from_millis(config.stable_ms),
+            max_retune_cents: config.max_retune_cents,
+            freeze: config.freeze,
+            inner_tunings: core::array::from_fn(|_| Stack::new_zero()),
+            current: [0.0; 128],
+            pending: core::array::from_fn(|_| None {}),
+            queue: VecDeque::new(),
+            wake_up: None {},
+        }
+    }
+
+    fn apply(
+        &mut self,
+        tunings: &mut [Stack<T>; 128],
+        note: u8,
+        tuning: Semitones,
+        tuning_stack: Stack<T>,
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) {
+        self.current[note as usize] = tuning;
+        self.pending[note as usize] = None {};
+        tunings[note as usize].clone_from(&tuning_stack);
+        forward.push_back(FromStrategy::Retune {
+            note,
+            tuning,
+            tuning_stack,
+            time,
+        });
+    }
+
+    /// Forward everything the inner strategy sent, except for re-tunings of sounding notes other
+    /// than `new_note`, which are remembered as pending. Returns the time of the latest
+    /// re-tuning that was held back, if any.
+    fn filter_queue(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        new_note: Option<u8>,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> Option<Instant> {
+        let mut held_back = None {};
+        while let Some(msg) = self.queue.pop_front() {
+            match msg {
+                FromStrategy::Retune {
+                    note,
+                    tuning,
+                    tuning_stack,
+                    time,
+                } => {
+                    if Some(note) == new_note || !keys[note as usize].is_sounding() {
+                        self.apply(tunings, note, tuning, tuning_stack, time, forward);
+                    } else if tuning == self.current[note as usize] {
+                        self.pending[note as usize] = None {};
+                    } else {
+                        held_back = Some(time);
+                        match &mut self.pending[note as usize] {
+                            Some(pending) if pending.tuning == tuning => {}
+                            pending => {
+                                *pending = Some(Pending {
+                                    tuning,
+                                    tuning_stack,
+                                    since: time,
+                                })
+                            }
+                        }
+                    }
+                }
+                _ => forward.push_back(msg),
+            }
+        }
+        held_back
+    }
+
+    /// Apply the pending re-tunings that have been stable for long enough.
+    fn release(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) {
+        for note in 0..128 {
+            let Some(pending) = self.pending[note as usize].take() else {
+                continue;
+            };
+            if !keys[note as usize].is_sounding() {
+                self.apply(
+                    tunings,
+                    note,
+                    pending.tuning,
+                    pending.tuning_stack,
+                    time,
+                    forward,
+                );
+                continue;
+            }
+            if self.freeze || time.saturating_duration_since(pending.since) < self.stable {
+                self.pending[note as usize] = Some(pending);
+                continue;
+            }
+            let step = pending.tuning - self.current[note as usize];
+            match self.max_retune_cents {
+                Some(max) if step.abs() * 100.0 > max => {
+                    let amount = max / (step.abs() * 100.0);
+                    let tuning = self.current[note as usize] + amount * step;
+                    let tuning_stack =
+                        intermediate(&tunings[note as usize], &pending.tuning_stack, amount);
+                    self.current[note as usize] = tuning;
+                    tunings[note as usize].clone_from(&tuning_stack);
+                    forward.push_back(FromStrategy::Retune {
+                        note,
+                        tuning,
+                        tuning_stack,
+                        time,
+                    });
+                    self.pending[note as usize] = Some(Pending {
+                        since: time,
+                        ..pending
+                    });
+                }
+                _ => self.apply(
+                    tunings,
+                    note,
+                    pending.tuning,
+                    pending.tuning_stack,
+                    time,
+                    forward,
+                ),
+            }
+        }
+    }
+
+    /// Ask for a [ToStrategy::Tick] at the time the next held back re-tuning becomes stable,
+    /// unless that was already asked for.
+    fn request_wake_up(&mut self, forward: &mut VecDeque<FromStrategy<T>>) {
+        if self.freeze {
+            return;
+        }
+        let next = self
+            .pending
+            .iter()
+            .flatten()
+            .map(|pending| pending.since + self.stable)
+            .min();
+        if let Some(time) = next {
+            if self.wake_up != Some(time) {
+                self.wake_up = Some(time);
+                forward.push_back(FromStrategy::WakeUp { time });
+            }
+        }
+    }
+}
+
+impl<T: StackType> Strategy<T> for Hysteresis<T> {
+    fn note_on<'a>(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &'a mut [Stack<T>; 128],
+        note: u8,
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> Option<(Semitones, &'a Stack<T>)> {
+        let proposal = self
+            .inner
+            .note_on(keys, &mut self.inner_tunings, note, time, &mut self.queue)
+            .map(|(tuning, stack)| (tuning, stack.clone()));
+        if let Some((tuning, tuning_stack)) = &proposal {
+            self.current[note as usize] = *tuning;
+            self.pending[note as usize] = None {};
+            tunings[note as usize].clone_from(tuning_stack);
+        }
+        // Whatever else the inner strategy sent must be handled even if it couldn't tune the
+        // note.
+        self.filter_queue(keys, tunings, proposal.as_ref().map(|_| note), forward);
+        self.release(keys, tunings, time, forward);
+        self.request_wake_up(forward);
+        proposal.map(|(tuning, _)| (tuning, &tunings[note as usize]))
+    }
+
+    fn note_off(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        note: u8,
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        let success =
+            self.inner
+                .note_off(keys, &mut self.inner_tunings, note, time, &mut self.queue);
+        self.filter_queue(keys, tunings, None {}, forward);
+        self.release(keys, tunings, time, forward);
+        self.request_wake_up(forward);
+        success
+    }
+
+    fn handle_msg(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        msg: ToStrategy<T>,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        match msg {
+            ToStrategy::SetHysteresis {
+                stable_ms,
+                max_retune_cents,
+                freeze,
+            } => {
+                self.stable = Duration::from_millis(stable_ms);
+                self.max_retune_cents = max_retune_cents;
+                self.freeze = freeze;
+                self.request_wake_up(forward);
+                true
+            }
+            _ => {
+                let time = msg.time();
+                let success =
+                    self.inner
+                        .handle_msg(keys, &mut self.inner_tunings, msg, &mut self.queue);
+                let held_back = self.filter_queue(keys, tunings, None {}, forward);
+                // Messages without a time (like changes of settings) can only use the time of
+                // the re-tunings they caused.
+                if let Some(time) = time.or(held_back) {
+                    self.release(keys, tunings, time, forward);
+                }
+                self.request_wake_up(forward);
+                success
+            }
+        }
+    }
+
+    fn start(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) {
+        self.inner
+            .start(keys, &mut self.inner_tunings, time, &mut self.queue);
+        // When (re-)starting, there's nothing to smooth over.
+        self.pending.iter_mut().for_each(|p| *p = None {});
+        self.wake_up = None {};
+        while let Some(msg) = self.queue.pop_front() {
+            match msg {
+                FromStrategy::Retune {
+                    note,
+                    tuning,
+                    tuning_stack,
+                    time,
+                } => self.apply(tunings, note, tuning, tuning_stack, time, forward),
+                _ => forward.push_back(msg),
+            }
+        }
+    }
+}
+
+impl<T: StackType> ExtractConfig<StrategyConfig<T>> for Hysteresis<T> {
+    fn extract_config(&self) -> StrategyConfig<T> {
+        StrategyConfig::Hysteresis(HysteresisConfig {
+            inner: Box::new(self.inner.extract_config()),
+            stable_ms: self.stable.as_millis() as u64,
+            max_retune_cents: self.max_retune_cents,
+            freeze: self.freeze,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use midi_msg::Channel;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::interval::stacktype::fivelimit::mock::{
+        static_tuning_config, MockFiveLimitStackType, TWELVE_NOTES,
+    };
+
+    struct Setup {
+        strategy: Hysteresis<MockFiveLimitStackType>,
+        start: Instant,
+        keys: [KeyState; 128],
+        tunings: [Stack<MockFiveLimitStackType>; 128],
+        forward: VecDeque<FromStrategy<MockFiveLimitStackType>>,
+    }
+
+    impl Setup {
+        fn new(stable_ms: u64, max_retune_cents: Option<f64>, freeze: bool) -> Self {
+            let mut strategy = Hysteresis::new(HysteresisConfig {
+                inner: Box::new(StrategyConfig::StaticTuning(static_tuning_config(&[
+                    TWELVE_NOTES,
+                ]))),
+                stable_ms,
+                max_retune_cents,
+                freeze,
+            });
+            let start = Instant::now();
+            let keys = core::array::from_fn(|_| KeyState::new(start));
+            let mut tunings = core::array::from_fn(|_| Stack::new_zero());
+            let mut forward = VecDeque::new();
+            strategy.start(&keys, &mut tunings, start, &mut forward);
+            Self {
+                strategy,
+                start,
+                keys,
+                tunings,
+                forward,
+            }
+        }
+
+        fn at(&self, ms: u64) -> Instant {
+            self.start + Duration::from_millis(ms)
+        }
+
+        fn note_on(&mut self, note: u8, ms: u64) {
+            let time = self.at(ms);
+            self.keys[note as usize].note_on(Channel::Ch1, time);
+            assert!(self
+                .strategy
+                .note_on(&self.keys, &mut self.tunings, note, time, &mut self.forward)
+                .is_some());
+        }
+
+        fn note_off(&mut self, note: u8, ms: u64) {
+            let time = self.at(ms);
+            self.keys[note as usize].note_off(Channel::Ch1, false, time);
+            assert!(self.strategy.note_off(
+                &self.keys,
+                &mut self.tunings,
+                note,
+                time,
+                &mut self.forward
+            ));
+        }
+
+        /// Set the reference to a syntonic comma above C.
+        fn raise_reference(&mut self, ms: u64) {
+            let time = self.at(ms);
+            assert!(self.strategy.handle_msg(
+                &self.keys,
+                &mut self.tunings,
+                ToStrategy::SetReference {
+                    reference: Stack::from_target(vec![-2, 4, -1]),
+                    time,
+                },
+                &mut self.forward,
+            ));
+        }
+
+        fn tick(&mut self, ms: u64) {
+            let time = self.at(ms);
+            self.strategy.handle_msg(
+                &self.keys,
+                &mut self.tunings,
+                ToStrategy::Tick { time },
+                &mut self.forward,
+            );
+        }
+
+        /// The times (in ms after the start) of the wake ups asked for since the last call of
+        /// [Setup::retunes].
+        fn wake_ups(&self) -> Vec<u64> {
+            self.forward
+                .iter()
+                .filter_map(|msg| match msg {
+                    FromStrategy::WakeUp { time } => {
+                        Some(time.duration_since(self.start).as_millis() as u64)
+                    }
+                    _ => None {},
+                })
+                .collect()
+        }
+
+        /// The retunes sent since the last call, in cents relative to equal temperament. Their
+        /// stacks must match their tunings.
+        fn retunes(&mut self) -> Vec<(u8, i64)> {
+            self.forward
+                .drain(..)
+                .filter_map(|msg| match msg {
+                    FromStrategy::Retune {
+                        note,
+                        tuning,
+                        tuning_stack,
+                        ..
+                    } => {
+                        assert!((tuning - 60.0 - tuning_stack.semitones()).abs() < 1e-4);
+                        Some((note, ((tuning - note as Semitones) * 100.0).round() as i64))
+                    }
+                    _ => None {},
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_stable() {
+        let mut s = Setup::new(100, None {}, false);
+        s.note_on(60, 0);
+        assert_eq!(s.retunes(), vec![(60, 0)]);
+
+        s.raise_reference(10);
+        assert_eq!(s.retunes(), vec![]);
+
+        // too early
+        s.note_on(67, 50);
+        assert_eq!(s.retunes(), vec![(67, 23)]);
+
+        s.note_off(67, 150);
+        assert_eq!(s.retunes(), vec![(60, 22)]);
+    }
+
+    #[test]
+    fn test_tick() {
+        let mut s = Setup::new(100, None {}, false);
+        s.note_on(60, 0);
+        s.retunes();
+
+        s.raise_reference(10);
+        assert_eq!(s.wake_ups(), vec![110]);
+        assert_eq!(s.retunes(), vec![]);
+
+        s.tick(110);
+        assert_eq!(s.wake_ups(), vec![]);
+        assert_eq!(s.retunes(), vec![(60, 22)]);
+    }
+
+    #[test]
+    fn test_max_retune() {
+        let mut s = Setup::new(0, Some(10.0), false);
+        s.note_on(60, 0);
+        s.retunes();
+
+        s.raise_reference(10);
+        assert_eq!(s.retunes(), vec![(60, 10)]);
+        s.tick(10);
+        assert_eq!(s.retunes(), vec![(60, 20)]);
+        s.note_on(67, 20);
+        assert_eq!(s.retunes(), vec![(67, 23), (60, 22)]);
+        s.note_off(67, 30);
+        assert_eq!(s.retunes(), vec![]);
+    }
+
+    #[test]
+    fn test_max_retune_at_events() {
+        let mut s = Setup::new(0, Some(10.0), false);
+        s.note_on(60, 0);
+        s.retunes();
+
+        s.raise_reference(10);
+        assert_eq!(s.retunes(), vec![(60, 10)]);
+        s.note_on(67, 20);
+        assert_eq!(s.retunes(), vec![(67, 23), (60, 20)]);
+        s.note_off(67, 30);
+        assert_eq!(s.retunes(), vec![(60, 22)]);
+    }
+
+    #[test]
+    fn test_freeze() {
+        let mut s = Setup::new(0, None {}, true);
+        s.note_on(60, 0);
+        s.note_on(64, 0);
+        s.retunes();
+
+        s.raise_reference(10);
+        assert_eq!(s.retunes(), vec![]);
+        s.note_off(64, 1000);
+        assert_eq!(s.retunes(), vec![(64, 8)]);
+        s.note_off(60, 1000);
+        assert_eq!(s.retunes(), vec![(60, 22)]);
+    }
+}
@@ -1,3 +1,8 @@
+pub mod drift;
+pub mod hysteresis;
+pub mod sketch;
+pub mod springs;
 pub mod r#static;
 pub mod r#trait;
 pub mod twostep;
+pub mod walking;
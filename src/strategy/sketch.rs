@@ -0,0 +1,273 @@
+//! A strategy that keeps two tuning frames, i.e. references: the current one, which is used to
+//! tune all notes, and an older one, which has been in use for at least the `minimum_age`.
+//!
+//! Whenever a new note completes one of the chord shapes, the chord's reference becomes the new
+//! current reference. Its tuning is computed relative to the older reference. That way, grace
+//! notes and fast runs can't move the tuning around: The current frame only becomes the older
+//! one once it's been in use for the `minimum_age`.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::{ExtractConfig, StrategyConfig},
+    interval::{
+        base::Semitones,
+        stack::Stack,
+        stacktype::r#trait::{IntervalBasis, StackCoeff, StackType},
+    },
+    keystate::KeyState,
+    msg::{FromStrategy, ToStrategy},
+    strategy::{
+        r#static::{StaticTuning, StaticTuningConfig},
+        r#trait::Strategy,
+        twostep::harmony::chordlist::keyshape::{first_complete_fit_or_best, KeyShape},
+    },
+};
+
+#[derive(Clone)]
+pub struct SketchConfig<T: IntervalBasis> {
+    pub inner: StaticTuningConfig<T>,
+    pub key_shapes: Vec<KeyShape>,
+    pub minimum_age_ms: u64,
+}
+
+pub struct Sketch<T: StackType> {
+    /// The `reference` of this is the current frame.
+    inner: StaticTuning<T>,
+    key_shapes: Vec<KeyShape>,
+    minimum_age: Duration,
+    /// The reference of the older frame.
+    old_reference: Stack<T>,
+    /// When the current frame was started.
+    birthday: Instant,
+    current_fit: Option<usize>,
+}
+
+impl<T: StackType> Sketch<T> {
+    pub fn new(config: SketchConfig<T>) -> Self {
+        Self {
+            old_reference: config.inner.reference.clone(),
+            inner: StaticTuning::new(config.inner),
+            key_shapes: config.key_shapes,
+            minimum_age: Duration::from_millis(config.minimum_age_ms),
+            birthday: Instant::now(),
+            current_fit: None {},
+        }
+    }
+
+    fn commit_if_old_enough(&mut self, time: Instant) {
+        if time.saturating_duration_since(self.birthday) >= self.minimum_age {
+            self.old_reference.clone_from(&self.inner.reference);
+        }
+    }
+
+    /// Start a new frame if the sounding notes complete a chord shape whose reference isn't the
+    /// current one. Returns true iff that happened.
+    fn start_new_frame_on_fit(
+        &mut self,
+        keys: &[KeyState; 128],
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        let (index, fit) = first_complete_fit_or_best(keys, self.key_shapes.iter());
+        let mut new_frame = false;
+        if fit.is_complete() {
+            self.current_fit = Some(index);
+            let key = fit.reference() as StackCoeff;
+            if key != self.inner.reference.key_number() {
+                if let Some(reference) = self
+                    .inner
+                    .compute_tuning_relative_to(&self.old_reference, key)
+                {
+                    self.birthday = time;
+                    self.inner.set_reference_to(&reference, forward);
+                    new_frame = true;
+                }
+            }
+        } else {
+            self.current_fit = None {};
+        }
+        forward.push_back(FromStrategy::CurrentHarmony {
+            pattern_index: self.current_fit,
+            reference: self.current_fit.map(|_| self.inner.reference.clone()),
+        });
+        new_frame
+    }
+}
+
+impl<T: StackType> Strategy<T> for Sketch<T> {
+    fn note_on<'a>(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &'a mut [Stack<T>; 128],
+        note: u8,
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> Option<(Semitones, &'a Stack<T>)> {
+        self.commit_if_old_enough(time);
+        if self.start_new_frame_on_fit(keys, time, forward) {
+            if self
+                .inner
+                .update_all_tunings_and_send(keys, tunings, time, forward)
+                < 128
+            {
+                return None {};
+            }
+            let stack = &tunings[note as usize];
+            Some((
+                stack.absolute_semitones(self.inner.tuning_reference.c4_semitones()),
+                stack,
+            ))
+        } else {
+            self.inner.note_on(keys, tunings, note, time, forward)
+        }
+    }
+
+    fn note_off(
+        &mut self,
+        _keys: &[KeyState; 128],
+        _tunings: &mut [Stack<T>; 128],
+        _note: u8,
+        time: Instant,
+        _forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        self.commit_if_old_enough(time);
+        true
+    }
+
+    fn handle_msg(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        msg: ToStrategy<T>,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        match msg {
+            ToStrategy::SetGroupMs { group_ms } => {
+                self.minimum_age = Duration::from_millis(group_ms);
+                true
+            }
+            ToStrategy::ToHarmonyStrategy(_, _)
+            | ToStrategy::ReanchorOnMatch { .. }
+            | ToStrategy::SetSpringStiffness { .. }
+            | ToStrategy::SetAnchorStiffness { .. }
+            | ToStrategy::TemperPatterns { .. }
+            | ToStrategy::SetHysteresis { .. }
+            | ToStrategy::SetDriftCompensation { .. }
+            | ToStrategy::Tick { .. } => false,
+            _ => {
+                // References set explicitly by the user are used immediately, without waiting
+                // for the minimum age.
+                let commit = matches!(
+                    msg,
+                    ToStrategy::SetReference { .. } | ToStrategy::Action { .. }
+                );
+                if let Some(time) = self
+                    .inner
+                    .handle_msg_but_dont_retune(keys, tunings, msg, forward)
+                {
+                    self.inner
+                        .update_all_tunings_and_send(keys, tunings, time, forward);
+                }
+                if commit {
+                    self.old_reference.clone_from(&self.inner.reference);
+                }
+                true
+            }
+        }
+    }
+
+    fn start(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) {
+        self.old_reference.clone_from(&self.inner.reference);
+        self.birthday = time;
+        self.inner.start(keys, tunings, time, forward);
+    }
+}
+
+impl<T: StackType> ExtractConfig<StrategyConfig<T>> for Sketch<T> {
+    fn extract_config(&self) -> StrategyConfig<T> {
+        match self.inner.extract_config() {
+            StrategyConfig::StaticTuning(inner) => StrategyConfig::Sketch(SketchConfig {
+                inner,
+                key_shapes: self.key_shapes.clone(),
+                minimum_age_ms: self.minimum_age.as_millis() as u64,
+            }),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use midi_msg::Channel;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::interval::stacktype::fivelimit::mock::{
+        static_tuning_config, MockFiveLimitStackType, TWELVE_NOTES,
+    };
+
+    fn sketch(minimum_age_ms: u64) -> Sketch<MockFiveLimitStackType> {
+        Sketch::new(SketchConfig {
+            inner: static_tuning_config(&[TWELVE_NOTES]),
+            key_shapes: vec![
+                KeyShape::ClassesRelative {
+                    classes: vec![0, 4, 7],
+                },
+                KeyShape::ClassesRelative {
+                    classes: vec![0, 3, 7],
+                },
+            ],
+            minimum_age_ms,
+        })
+    }
+
+    /// Play an A minor chord at 0ms, a D minor chord at `d_minor_ms`, and return the tuning of
+    /// the D.
+    fn d_after_a_minor(minimum_age_ms: u64, d_minor_ms: u64) -> Vec<StackCoeff> {
+        let mut s = sketch(minimum_age_ms);
+        let start = Instant::now();
+        let mut keys: [KeyState; 128] = core::array::from_fn(|_| KeyState::new(start));
+        let mut tunings: [Stack<MockFiveLimitStackType>; 128] =
+            core::array::from_fn(|_| Stack::new_zero());
+        let mut forward = VecDeque::new();
+        s.start(&keys, &mut tunings, start, &mut forward);
+
+        let mut chord = |notes: [u8; 3], time: Instant| {
+            for note in notes {
+                keys[note as usize].note_on(Channel::Ch1, time);
+                assert!(s
+                    .note_on(&keys, &mut tunings, note, time, &mut forward)
+                    .is_some());
+            }
+            let res = tunings[notes[0] as usize].target_coefficients().to_vec();
+            for note in notes {
+                keys[note as usize].note_off(Channel::Ch1, false, time);
+                assert!(s.note_off(&keys, &mut tunings, note, time, &mut forward));
+            }
+            res
+        };
+
+        assert_eq!(chord([57, 60, 64], start), vec![0, -1, 1]);
+        chord([62, 65, 69], start + Duration::from_millis(d_minor_ms))
+    }
+
+    #[test]
+    fn test_minimum_age() {
+        // The A minor chord was in use long enough, so the D is a fourth above the A.
+        assert_eq!(d_after_a_minor(100, 150), vec![1, -2, 1]);
+        // The A minor chord was too short, so the D is still relative to the C.
+        assert_eq!(d_after_a_minor(100, 50), vec![-1, 2, 0]);
+        // Without a minimum age, every chord counts.
+        assert_eq!(d_after_a_minor(0, 0), vec![1, -2, 1]);
+    }
+}
@@ -0,0 +1,522 @@
+//! A strategy that tunes all sounding notes together, as the minimum-energy configuration of a
+//! system of springs (see `doc/springs.tex`):
+//!
+//! - Between two sounding notes, there's a spring whose relaxed length is one of the configured
+//!   intervals. If several intervals fit the key distance, the one that gives the lowest energy is
+//!   chosen. The stiffness of a spring says how much it resists being detuned.
+//! - Notes that are a whole number of periods (usually octaves) apart are connected by rods, i.e.
+//!   the period is never detuned.
+//! - Every note is attached by an anchor spring to its position relative to the reference, which
+//!   keeps the tuning from drifting away.
+
+use std::{collections::VecDeque, time::Instant};
+
+use num_rational::Ratio;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    config::{ExtractConfig, StrategyConfig},
+    custom_serde::common::{deserialize_ratio, serialize_ratio},
+    interval::{
+        base::Semitones,
+        stack::{ScaledAdd, Stack},
+        stacktype::r#trait::{IntervalBasis, StackCoeff, StackType},
+    },
+    keystate::KeyState,
+    msg::{FromStrategy, ToStrategy},
+    process::springs::{
+        solver::Solver,
+        util::{Connector, KeyDistance, KeyNumber, RodSpec, Workspace},
+    },
+    reference::Reference,
+    strategy::r#trait::{Strategy, StrategyAction},
+};
+
+/// If at most this many notes sound, there are springs between all of them. Otherwise, only
+/// neighbouring notes are connected, to keep the number of combinations of candidate intervals
+/// manageable.
+const MAX_FULLY_CONNECTED: usize = 5;
+
+pub struct Springs<T: StackType> {
+    intervals: Vec<SpringInterval<T>>,
+    anchor_stiffness: Ratio<StackCoeff>,
+    tuning_reference: Reference<T>,
+    reference: Stack<T>,
+    solver: Solver,
+    workspace: Workspace<T>,
+    /// sorted descendingly, so that lower notes are more "stable", see [Workspace::best_solution]
+    active_keys: Vec<KeyNumber>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct SpringInterval<T: IntervalBasis> {
+    pub interval: Stack<T>,
+    #[serde(
+        deserialize_with = "deserialize_stiffness",
+        serialize_with = "serialize_ratio"
+    )]
+    pub stiffness: Ratio<StackCoeff>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct SpringsConfig<T: IntervalBasis> {
+    pub intervals: Vec<SpringInterval<T>>,
+    #[serde(
+        deserialize_with = "deserialize_stiffness",
+        serialize_with = "serialize_ratio"
+    )]
+    pub anchor_stiffness: Ratio<StackCoeff>,
+    pub tuning_reference: Reference<T>,
+    pub reference: Stack<T>,
+}
+
+/// Like [deserialize_ratio], but only accepts positive numbers.
+pub fn deserialize_stiffness<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Ratio<StackCoeff>, D::Error> {
+    let x = deserialize_ratio(deserializer)?;
+    if x > Ratio::ZERO {
+        Ok(x)
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "the stiffness must be positive, but it is {x}"
+        )))
+    }
+}
+
+/// The intervals that can be used for the key distance `d`, with their stiffnesses, stiffest
+/// first. Intervals are also used downwards, and shifted by whole periods.
+fn candidate_springs<T: StackType>(
+    intervals: &[SpringInterval<T>],
+    d: StackCoeff,
+) -> Vec<(Stack<T>, Ratio<StackCoeff>)> {
+    let mut res: Vec<(Stack<T>, Ratio<StackCoeff>)> = vec![];
+    for SpringInterval {
+        interval,
+        stiffness,
+    } in intervals
+    {
+        for sign in [1, -1] {
+            let mut candidate = Stack::new_zero();
+            candidate.scaled_add(sign, interval);
+            let missing = d - candidate.key_distance();
+            match (T::try_period_index(), T::try_period_keys()) {
+                (Some(period_index), Some(period_keys)) => {
+                    let period_keys = period_keys as StackCoeff;
+                    if missing.rem_euclid(period_keys) != 0 {
+                        continue;
+                    }
+                    candidate.increment_at_index_pure(period_index, missing / period_keys);
+                }
+                _ => {
+                    if missing != 0 {
+                        continue;
+                    }
+                }
+            }
+            if let Some((_, s)) = res.iter_mut().find(|(x, _)| *x == candidate) {
+                *s = (*s).max(*stiffness);
+            } else {
+                res.push((candidate, *stiffness));
+            }
+        }
+    }
+    res.sort_by(|(_, a), (_, b)| b.cmp(a));
+    res
+}
+
+impl<T: StackType> Springs<T> {
+    pub fn new(config: SpringsConfig<T>) -> Self {
+        Self {
+            intervals: config.intervals,
+            anchor_stiffness: config.anchor_stiffness,
+            tuning_reference: config.tuning_reference,
+            reference: config.reference,
+            solver: Solver::new(1, 1, T::num_intervals()),
+            workspace: Workspace::new(1, true, false, true),
+            active_keys: vec![],
+        }
+    }
+
+    /// The anchor of `key`: its position relative to the [Self::reference]. That's a whole number
+    /// of periods, if possible, and otherwise uses the stiffest fitting interval. `None` if no
+    /// interval fits.
+    fn anchor(&self, key: KeyNumber) -> Option<Stack<T>> {
+        let d = key as StackCoeff - self.reference.key_number();
+        let mut res = match (T::try_period_index(), T::try_period_keys()) {
+            (Some(period_index), Some(period_keys))
+                if d.rem_euclid(period_keys as StackCoeff) == 0 =>
+            {
+                Stack::from_pure_interval(period_index, d / period_keys as StackCoeff)
+            }
+            _ if d == 0 => Stack::new_zero(),
+            _ => candidate_springs(&self.intervals, d).into_iter().next()?.0,
+        };
+        res.scaled_add(1, &self.reference);
+        Some(res)
+    }
+
+    /// Re-compute the tunings of all sounding notes, and send [FromStrategy::Retune] for the ones
+    /// that changed, or for all of them, if `retune_all` is set. Notes that can't be related to the
+    /// reference by any of the intervals are not tuned.
+    ///
+    /// Returns false iff the solver failed.
+    fn solve(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        retune_all: bool,
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        let anchors: Vec<(KeyNumber, Stack<T>)> = (0..128)
+            .rev()
+            .filter(|&k| keys[k as usize].is_sounding())
+            .filter_map(|k| self.anchor(k).map(|a| (k, a)))
+            .collect();
+        self.active_keys.clear();
+        self.active_keys.extend(anchors.iter().map(|(k, _)| *k));
+        if self.active_keys.is_empty() {
+            return true;
+        }
+
+        let Self {
+            intervals,
+            anchor_stiffness,
+            solver,
+            workspace,
+            active_keys,
+            ..
+        } = self;
+        let n = active_keys.len();
+        let period = T::try_period_index().zip(T::try_period_keys());
+        let solution = workspace.best_solution(
+            active_keys,
+            |_| true,
+            |i, j| {
+                let d = active_keys[j] as KeyDistance - active_keys[i] as KeyDistance;
+                if let Some((_, period_keys)) = period {
+                    let period_keys = period_keys as KeyDistance;
+                    if d % period_keys == 0 {
+                        return Connector::Rod(vec![(
+                            period_keys,
+                            (d / period_keys) as StackCoeff,
+                        )]);
+                    }
+                }
+                if (n > MAX_FULLY_CONNECTED) & (j != i + 1) {
+                    return Connector::None;
+                }
+                if candidate_springs(intervals, d as StackCoeff).is_empty() {
+                    Connector::None
+                } else {
+                    Connector::Spring
+                }
+            },
+            |d| candidate_springs(intervals, d as StackCoeff),
+            |k| {
+                let (_, anchor) = anchors.iter().find(|(x, _)| *x == k).unwrap();
+                vec![(anchor.clone(), *anchor_stiffness)]
+            },
+            |spec: &RodSpec| {
+                let (period_index, period_keys) = period.unwrap();
+                Stack::from_pure_interval(
+                    period_index,
+                    spec.iter()
+                        .map(|(d, c)| *d as StackCoeff / period_keys as StackCoeff * c)
+                        .sum(),
+                )
+            },
+            solver,
+        );
+
+        let Ok((solution, _relaxed, _energy)) = solution else {
+            return false;
+        };
+
+        let interval_targets = self.workspace.current_interval_targets();
+        let anchor_targets = self.workspace.current_anchor_targets(&interval_targets);
+        for (i, target) in anchor_targets.into_iter().enumerate() {
+            let note = self.active_keys[i];
+            let stack = Stack::from_target_and_actual(target, solution.row(i).to_owned());
+            if retune_all | (stack != tunings[note as usize]) {
+                forward.push_back(FromStrategy::Retune {
+                    note,
+                    tuning: stack.absolute_semitones(self.tuning_reference.c4_semitones()),
+                    tuning_stack: stack.clone(),
+                    time,
+                });
+                tunings[note as usize] = stack;
+            }
+        }
+        true
+    }
+
+    fn set_reference(&mut self, reference: Stack<T>, forward: &mut VecDeque<FromStrategy<T>>) {
+        forward.push_back(FromStrategy::SetReference {
+            stack: reference.clone(),
+        });
+        self.reference = reference;
+    }
+
+    /// returns true iff the action was handled, and a re-tune is necessary
+    fn handle_action(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &[Stack<T>; 128],
+        action: StrategyAction,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        match action {
+            StrategyAction::Reset => {
+                self.set_reference(Stack::new_zero(), forward);
+                true
+            }
+            StrategyAction::SetReferenceToLowest | StrategyAction::SetReferenceToHighest => {
+                let lowest = action == StrategyAction::SetReferenceToLowest;
+                let note = if lowest {
+                    self.active_keys.last()
+                } else {
+                    self.active_keys.first()
+                };
+                if let Some(&note) = note {
+                    if keys[note as usize].is_sounding() {
+                        self.set_reference(tunings[note as usize].clone(), forward);
+                        return true;
+                    }
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<T: StackType> Strategy<T> for Springs<T> {
+    fn note_on<'a>(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &'a mut [Stack<T>; 128],
+        note: u8,
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> Option<(Semitones, &'a Stack<T>)> {
+        if self.solve(keys, tunings, false, time, forward) & self.active_keys.contains(&note) {
+            let stack = &tunings[note as usize];
+            Some((
+                stack.absolute_semitones(self.tuning_reference.c4_semitones()),
+                stack,
+            ))
+        } else {
+            None {}
+        }
+    }
+
+    fn note_off(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        _note: u8,
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        self.solve(keys, tunings, false, time, forward)
+    }
+
+    fn handle_msg(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        msg: ToStrategy<T>,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        match msg {
+            ToStrategy::SetTuningReference { reference, time } => {
+                self.tuning_reference.clone_from(&reference);
+                forward.push_back(FromStrategy::SetTuningReference { reference });
+                self.solve(keys, tunings, true, time, forward)
+            }
+            ToStrategy::SetReference { reference, time } => {
+                self.set_reference(reference, forward);
+                self.solve(keys, tunings, false, time, forward)
+            }
+            ToStrategy::Action { action, time } => {
+                self.handle_action(keys, tunings, action, forward)
+                    && self.solve(keys, tunings, false, time, forward)
+            }
+            ToStrategy::SetSpringStiffness {
+                index,
+                stiffness,
+                time,
+            } => {
+                self.intervals[index].stiffness = stiffness;
+                // the stiffnesses are memoised in the workspace
+                self.workspace = Workspace::new(1, true, false, true);
+                self.solve(keys, tunings, false, time, forward)
+            }
+            ToStrategy::SetAnchorStiffness { stiffness, time } => {
+                self.anchor_stiffness = stiffness;
+                self.solve(keys, tunings, false, time, forward)
+            }
+            _ => false,
+        }
+    }
+
+    fn start(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) {
+        forward.push_back(FromStrategy::SetTuningReference {
+            reference: self.tuning_reference.clone(),
+        });
+        forward.push_back(FromStrategy::SetReference {
+            stack: self.reference.clone(),
+        });
+        self.solve(keys, tunings, true, time, forward);
+    }
+}
+
+impl<T: StackType> ExtractConfig<StrategyConfig<T>> for Springs<T> {
+    fn extract_config(&self) -> StrategyConfig<T> {
+        StrategyConfig::Springs(SpringsConfig {
+            intervals: self.intervals.clone(),
+            anchor_stiffness: self.anchor_stiffness,
+            tuning_reference: self.tuning_reference.clone(),
+            reference: self.reference.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use midi_msg::Channel;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::interval::stacktype::fivelimit::mock::MockFiveLimitStackType;
+
+    fn interval(
+        octaves: StackCoeff,
+        fifths: StackCoeff,
+        thirds: StackCoeff,
+        stiffness: Ratio<StackCoeff>,
+    ) -> SpringInterval<MockFiveLimitStackType> {
+        SpringInterval {
+            interval: Stack::from_target(vec![octaves, fifths, thirds]),
+            stiffness,
+        }
+    }
+
+    fn springs() -> Springs<MockFiveLimitStackType> {
+        Springs::new(SpringsConfig {
+            intervals: vec![
+                interval(0, 1, 0, Ratio::new(1, 3)),   // fifth
+                interval(0, 0, 1, Ratio::new(1, 5)),   // major third
+                interval(0, 1, -1, Ratio::new(1, 15)), // minor third
+                interval(-1, 2, 0, Ratio::new(1, 9)),  // major whole tone
+                interval(1, -2, 1, Ratio::new(1, 45)), // minor whole tone
+            ],
+            anchor_stiffness: Ratio::new(1, 100),
+            tuning_reference: Reference::from_semitones(Stack::new_zero(), 60.0),
+            reference: Stack::new_zero(),
+        })
+    }
+
+    #[test]
+    fn test_candidate_springs() {
+        let s = springs();
+        let candidates = |d| {
+            candidate_springs(&s.intervals, d)
+                .into_iter()
+                .map(|(stack, stiffness)| (stack.target_coefficients().to_vec(), stiffness))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(candidates(7), vec![(vec![0, 1, 0], Ratio::new(1, 3))]);
+        // a fifth down, and a fourth
+        assert_eq!(candidates(-7), vec![(vec![0, -1, 0], Ratio::new(1, 3))]);
+        assert_eq!(candidates(5), vec![(vec![1, -1, 0], Ratio::new(1, 3))]);
+        // whole tones, and minor sevenths as whole tones down
+        assert_eq!(
+            candidates(10),
+            vec![
+                (vec![2, -2, 0], Ratio::new(1, 9)),
+                (vec![0, 2, -1], Ratio::new(1, 45)),
+            ]
+        );
+        assert_eq!(candidates(6), vec![]);
+    }
+
+    #[test]
+    fn test_solve() {
+        let mut s = springs();
+        let start = Instant::now();
+        let t = |ms| start + Duration::from_millis(ms);
+        let mut keys: [KeyState; 128] = core::array::from_fn(|_| KeyState::new(start));
+        let mut tunings: [Stack<MockFiveLimitStackType>; 128] =
+            core::array::from_fn(|_| Stack::new_zero());
+        let mut forward = VecDeque::new();
+
+        let note_on = |keys: &mut [KeyState; 128], note, time| {
+            keys[note as usize].note_on(Channel::Ch1, time);
+        };
+        let actual = |tunings: &[Stack<MockFiveLimitStackType>; 128], note: usize| {
+            tunings[note].actual_coefficients().to_vec()
+        };
+
+        // a C major triad is pure
+        for (i, note) in [60, 64, 67].iter().enumerate() {
+            note_on(&mut keys, *note, t(i as u64));
+            assert!(s
+                .note_on(&keys, &mut tunings, *note, t(i as u64), &mut forward)
+                .is_some());
+        }
+        assert_eq!(actual(&tunings, 60), vec![0.into(), 0.into(), 0.into()]);
+        assert_eq!(actual(&tunings, 64), vec![0.into(), 0.into(), 1.into()]);
+        assert_eq!(actual(&tunings, 67), vec![0.into(), 1.into(), 0.into()]);
+
+        for (i, note) in [60, 64, 67].iter().enumerate() {
+            keys[*note as usize].note_off(Channel::Ch1, false, t(10 + i as u64));
+            assert!(s.note_off(&keys, &mut tunings, *note, t(10 + i as u64), &mut forward));
+        }
+        assert_eq!(s.active_keys, vec![]);
+
+        // a D minor triad can't be pure and have all notes at their positions relative to C: the
+        // anchors want a major whole tone C-D, a fourth C-F, and a major sixth C-A. The triad is
+        // almost pure, because the springs are much stiffer than the anchors, and the D ends up
+        // between the minor and the major whole tone.
+        forward.clear();
+        for (i, note) in [62, 65, 69].iter().enumerate() {
+            note_on(&mut keys, *note, t(20 + i as u64));
+            assert!(s
+                .note_on(&keys, &mut tunings, *note, t(20 + i as u64), &mut forward)
+                .is_some());
+        }
+        let detuning = |a: usize, b: usize, pure: Stack<MockFiveLimitStackType>| {
+            (tunings[b].semitones() - tunings[a].semitones() - pure.semitones()).abs()
+        };
+        assert!(detuning(62, 65, Stack::from_target(vec![0, 1, -1])) < 0.05);
+        assert!(detuning(62, 69, Stack::from_target(vec![0, 1, 0])) < 0.05);
+        let d = tunings[62].semitones();
+        assert!(d > Stack::<MockFiveLimitStackType>::from_target(vec![1, -2, 1]).semitones());
+        assert!(d < Stack::<MockFiveLimitStackType>::from_target(vec![-1, 2, 0]).semitones());
+        assert!(!forward.is_empty());
+
+        // no interval relates F# to the reference, so it isn't tuned, but the other notes still
+        // are
+        note_on(&mut keys, 66, t(30));
+        assert!(s
+            .note_on(&keys, &mut tunings, 66, t(30), &mut forward)
+            .is_none());
+        assert_eq!(s.active_keys, vec![69, 65, 62]);
+    }
+}
@@ -51,10 +51,20 @@ impl<T: StackType> StaticTuning<T> {
     /// Compute the tuning for a note (that may lie outside of the MIDI range). Returns `None` only
     /// in the case when there's no neighbourhood currently selected).
     pub fn compute_tuning_for(&self, note: StackCoeff) -> Option<Stack<T>> {
+        self.compute_tuning_relative_to(&self.reference, note)
+    }
+
+    /// Like [Self::compute_tuning_for], but relative to the given `reference` instead of the
+    /// current one.
+    pub fn compute_tuning_relative_to(
+        &self,
+        reference: &Stack<T>,
+        note: StackCoeff,
+    ) -> Option<Stack<T>> {
         if let Some(cni) = self.curr_neighbourhood_index {
             let mut res =
-                self.neighbourhoods[cni].get_relative_stack(note - self.reference.key_number());
-            res.scaled_add(1, &self.reference);
+                self.neighbourhoods[cni].get_relative_stack(note - reference.key_number());
+            res.scaled_add(1, reference);
             Some(res)
         } else {
             None {}
@@ -86,6 +96,10 @@ impl<T: StackType> StaticTuning<T> {
         }
     }
 
+    pub fn current_neighbourhood_index(&self) -> Option<usize> {
+        self.curr_neighbourhood_index
+    }
+
     pub fn mark_tuning_as_outdated(&mut self, note: u8) {
         self.tuning_up_to_date[note as usize] = false;
     }
@@ -168,9 +182,18 @@ impl<T: StackType> StaticTuning<T> {
         forward: &mut VecDeque<FromStrategy<T>>,
     ) -> Option<Instant> {
         if match action {
+            StrategyAction::SwitchToNeighbourhood(index) => {
+                self.switch_to_neighbourhood(index, forward)
+            }
             StrategyAction::IncrementNeighbourhoodIndex(inc) => {
                 self.increment_neighbourhood(inc, forward)
             }
+            StrategyAction::ApplyTemperament(temperament) => {
+                self.temper_current_neighbourhood(Some(temperament), forward)
+            }
+            StrategyAction::MakeNeighbourhoodPure => {
+                self.temper_current_neighbourhood(None {}, forward)
+            }
             StrategyAction::SetReferenceToLowest => {
                 self.set_reference(false, keys, tunings, forward)
             }
@@ -201,22 +224,53 @@ impl<T: StackType> StaticTuning<T> {
         forward: &mut VecDeque<FromStrategy<T>>,
     ) -> bool {
         if let Some(cni) = self.curr_neighbourhood_index {
-            let old_index = cni;
             let new_index =
                 (cni as isize + increment).rem_euclid(self.neighbourhoods.len() as isize) as usize;
-            if old_index != new_index {
-                self.curr_neighbourhood_index = Some(new_index);
-                self.tuning_up_to_date.iter_mut().for_each(|b| *b = false);
-                self.start_but_dont_retune(forward);
-                true
-            } else {
-                false
-            }
+            self.switch_to_neighbourhood(new_index, forward)
         } else {
             false
         }
     }
 
+    fn switch_to_neighbourhood(
+        &mut self,
+        index: usize,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        if index >= self.neighbourhoods.len() || Some(index) == self.curr_neighbourhood_index {
+            return false;
+        }
+        self.curr_neighbourhood_index = Some(index);
+        self.tuning_up_to_date.iter_mut().for_each(|b| *b = false);
+        self.start_but_dont_retune(forward);
+        true
+    }
+
+    /// Apply the temperament to the current neighbourhood, or make it pure if `temperament` is
+    /// `None`.
+    fn temper_current_neighbourhood(
+        &mut self,
+        temperament: Option<usize>,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        let Some(cni) = self.curr_neighbourhood_index else {
+            return false;
+        };
+        if temperament.is_some_and(|t| t >= T::num_temperaments()) {
+            return false;
+        }
+        self.neighbourhoods[cni].for_each_stack_mut(|_, stack| {
+            match temperament {
+                Some(t) => stack.apply_temperament(t),
+                None {} => stack.make_pure(),
+            }
+            forward.push_back(FromStrategy::Consider {
+                stack: stack.clone(),
+            });
+        });
+        true
+    }
+
     fn set_reference(
         &mut self,
         to_highest: bool,
@@ -276,11 +330,12 @@ impl<T: StackType> StaticTuning<T> {
             ToStrategy::ApplyTemperamentToNeighbourhood {
                 temperament,
                 neighbourhood,
+                amount,
                 time,
             } => {
                 if Some(neighbourhood) == self.curr_neighbourhood_index {
                     self.neighbourhoods[neighbourhood].for_each_stack_mut(|_, stack| {
-                        stack.apply_temperament(temperament);
+                        stack.apply_temperament_partially(temperament, amount);
                         forward.push_back(FromStrategy::Consider {
                             stack: stack.clone(),
                         });
@@ -288,7 +343,7 @@ impl<T: StackType> StaticTuning<T> {
                     Some(time)
                 } else {
                     self.neighbourhoods[neighbourhood].for_each_stack_mut(|_, stack| {
-                        stack.apply_temperament(temperament);
+                        stack.apply_temperament_partially(temperament, amount);
                     });
                     None {}
                 }
@@ -334,9 +389,26 @@ impl<T: StackType> StaticTuning<T> {
                 self.start_but_dont_retune(forward);
                 Some(time)
             }
-            ToStrategy::ToHarmonyStrategy(_, _)
+            ToStrategy::AddNeighbourhood {
+                neighbourhood,
+                time,
+            } => {
+                self.neighbourhoods.push(neighbourhood);
+                self.curr_neighbourhood_index = Some(self.neighbourhoods.len() - 1);
+                self.tuning_up_to_date.iter_mut().for_each(|b| *b = false);
+                self.start_but_dont_retune(forward);
+                Some(time)
+            }
+            // Controller mappings may send parameters of other strategies, these are ignored.
+            ToStrategy::Tick { .. }
+            | ToStrategy::ToHarmonyStrategy(_, _)
             | ToStrategy::ReanchorOnMatch { .. }
-            | ToStrategy::SetGroupMs { .. } => unreachable!(),
+            | ToStrategy::SetGroupMs { .. }
+            | ToStrategy::SetSpringStiffness { .. }
+            | ToStrategy::SetAnchorStiffness { .. }
+            | ToStrategy::TemperPatterns { .. }
+            | ToStrategy::SetHysteresis { .. }
+            | ToStrategy::SetDriftCompensation { .. } => None {},
         }
     }
 }
@@ -11,11 +11,14 @@ use crate::{
 
 /// Why these are not simply variants of [ToStrategy]: I want to expose them to users, to construct
 /// [crate::bindable::Bindings] in the configuration file, and [ToStrategy] doesn't belong there.
+///
+/// The indices of strategies, neighbourhoods, and temperaments may be given as names in the
+/// configuration file, see [crate::custom_serde::action_names].
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
 pub enum StrategyAction {
-    // SwitchToNeighbourhood(usize),
+    SwitchToNeighbourhood(usize),
     IncrementNeighbourhoodIndex(isize),
     SetReferenceToLowest,
     SetReferenceToHighest,
@@ -23,11 +26,22 @@ pub enum StrategyAction {
     ToggleChordMatching,
     ToggleReanchor,
     Reset,
+    /// Handled outside of the strategies, by the session recorder.
+    ToggleRecording,
+    /// Handled outside of the strategies, by [crate::process::fromstrategy::ProcessFromStrategy].
+    SwitchToStrategy(usize),
+    /// Apply the temperament to the current neighbourhood.
+    ApplyTemperament(usize),
+    MakeNeighbourhoodPure,
+    /// Handled outside of the strategies, by [crate::process::fromstrategy::ProcessFromStrategy]:
+    /// Write the current tuning of all keys to `.scl`, `.kbm`, and `.tun` files.
+    ExportTuning,
 }
 
 impl fmt::Display for StrategyAction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            StrategyAction::SwitchToNeighbourhood(i) => write!(f, "switch to neighbourhood {i}"),
             StrategyAction::IncrementNeighbourhoodIndex(i) => {
                 write!(f, "increment neighbourhood index by {i}")
             }
@@ -43,6 +57,13 @@ impl fmt::Display for StrategyAction {
             StrategyAction::ToggleChordMatching => write!(f, "toggle chord matching"),
             StrategyAction::ToggleReanchor => write!(f, "toggle re-setting of the reference on chord match"),
             StrategyAction::Reset => write!(f, "reset"),
+            StrategyAction::ToggleRecording => write!(f, "start or stop recording"),
+            StrategyAction::SwitchToStrategy(i) => write!(f, "switch to strategy {i}"),
+            StrategyAction::ApplyTemperament(i) => {
+                write!(f, "apply temperament {i} to current neighbourhood")
+            }
+            StrategyAction::MakeNeighbourhoodPure => write!(f, "make current neighbourhood pure"),
+            StrategyAction::ExportTuning => write!(f, "export current tuning"),
         }
     }
 }
@@ -279,6 +279,13 @@ impl<T: StackType> ChordList<T> {
             patterns: conf.patterns.drain(..).map(|c| Pattern::new(c)).collect(),
         }
     }
+
+    /// Apply `f` to all stacks in the neighbourhoods of all patterns.
+    pub fn for_each_pattern_stack_mut<F: FnMut(&mut Stack<T>)>(&mut self, mut f: F) {
+        for pattern in self.patterns.iter_mut() {
+            Rc::make_mut(&mut pattern.neighbourhood).for_each_stack_mut(|_, stack| f(stack));
+        }
+    }
 }
 
 impl<T: StackType> HarmonyStrategy<T> for ChordList<T> {
@@ -49,7 +49,11 @@ impl<T: StackType> Neighbourhoods<T> {
 }
 
 impl<T: StackType> StaticTuning<T> {
-    fn update_tunings_from_harmony(
+    /// Tune the sounding notes that are covered by the `harmony` relative to the tuning of its
+    /// reference (which is computed from the current neighbourhood and reference), and all other
+    /// sounding notes using the current neighbourhood. Returns a boolean signalling success and
+    /// the tuning of the `harmony.reference`, if there is a `harmony`.
+    pub fn update_tunings_from_harmony(
         &mut self,
         keys: &[KeyState; 128],
         tunings: &mut [Stack<T>; 128],
@@ -170,6 +170,8 @@ impl<T: StackType> Strategy<T> for TwoStep<T> {
                 success
             }
 
+            ToStrategy::Tick { .. } => false,
+
             _ => {
                 let (pattern_index, harmony) = self.harmony.solve(keys);
                 let (success, reference) =
@@ -0,0 +1,419 @@
+//! A strategy that moves a key centre through the lattice:
+//!
+//! - Notes are tuned relative to the key centre, using the current neighbourhood.
+//! - If the sounding notes fit one of the chord patterns, the notes of the chord are tuned
+//!   according to the pattern, relative to the tuning of the chord's reference (which is in turn
+//!   taken from the current neighbourhood).
+//! - If walking is enabled, the key centre moves to the reference of every newly fitting chord.
+//!   Otherwise, it only moves on [StrategyAction::SetReferenceToCurrent].
+//!
+//! Since every step is taken relative to the current key centre, chord progressions can make the
+//! key centre drift away from where it started.
+
+use std::{collections::VecDeque, time::Instant};
+
+use crate::{
+    config::{ExtractConfig, HarmonyStrategyConfig, StrategyConfig},
+    interval::{
+        base::Semitones,
+        stack::Stack,
+        stacktype::r#trait::{IntervalBasis, StackCoeff, StackType},
+    },
+    keystate::KeyState,
+    msg::{FromStrategy, ToStrategy},
+    strategy::{
+        r#static::{StaticTuning, StaticTuningConfig},
+        r#trait::{Strategy, StrategyAction},
+        twostep::{
+            harmony::chordlist::{ChordList, ChordListConfig},
+            HarmonyStrategy,
+        },
+    },
+};
+
+#[derive(Clone)]
+pub struct WalkingConfig<T: IntervalBasis> {
+    pub patterns: ChordListConfig<T>,
+    /// The `reference` of this is the initial key centre.
+    pub inner: StaticTuningConfig<T>,
+    pub walk: bool,
+    pub temper_patterns: bool,
+}
+
+pub struct Walking<T: StackType> {
+    patterns: ChordList<T>,
+    inner: StaticTuning<T>,
+    walk: bool,
+    /// If this is set, temperaments that are applied to (or removed from) the current
+    /// neighbourhood are also applied to (or removed from) the neighbourhoods of the patterns.
+    temper_patterns: bool,
+    /// The index of the currently fitting pattern, and the MIDI key number of its reference.
+    current_fit: Option<(usize, StackCoeff)>,
+}
+
+impl<T: StackType> Walking<T> {
+    pub fn new(config: WalkingConfig<T>) -> Self {
+        Self {
+            patterns: ChordList::new(config.patterns),
+            inner: StaticTuning::new(config.inner),
+            walk: config.walk,
+            temper_patterns: config.temper_patterns,
+            current_fit: None {},
+        }
+    }
+
+    /// Move the key centre to the tuning of `key` relative to the current key centre. Returns
+    /// false iff there's no neighbourhood selected.
+    fn walk_to(&mut self, key: StackCoeff, forward: &mut VecDeque<FromStrategy<T>>) -> bool {
+        if let Some(key_centre) = self.inner.compute_tuning_for(key) {
+            self.inner.set_reference_to(&key_centre, forward);
+            self.inner.mark_all_tunings_as_outdated();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn solve(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        let (pattern_index, harmony) = self.patterns.solve(keys);
+        let fit = pattern_index.zip(harmony.as_ref().map(|h| h.reference));
+        if let Some((_, reference)) = fit {
+            if self.walk & (fit != self.current_fit) {
+                self.walk_to(reference, forward);
+            }
+        }
+        self.current_fit = fit;
+
+        let (success, reference) = self
+            .inner
+            .update_tunings_from_harmony(keys, tunings, harmony, time, forward);
+        forward.push_back(FromStrategy::CurrentHarmony {
+            pattern_index,
+            reference,
+        });
+        success
+    }
+
+    /// If `msg` changes the temperament of the current neighbourhood, do the same to the
+    /// neighbourhoods of all patterns.
+    fn temper_patterns_like(&mut self, msg: &ToStrategy<T>) {
+        let current = self.inner.current_neighbourhood_index();
+        match msg {
+            ToStrategy::ApplyTemperamentToNeighbourhood {
+                neighbourhood,
+                temperament,
+                amount,
+                ..
+            } if Some(*neighbourhood) == current => {
+                self.patterns.for_each_pattern_stack_mut(|stack| {
+                    stack.apply_temperament_partially(*temperament, *amount)
+                });
+            }
+            ToStrategy::MakeNeighbourhoodPure { neighbourhood, .. }
+                if Some(*neighbourhood) == current =>
+            {
+                self.patterns
+                    .for_each_pattern_stack_mut(|stack| stack.make_pure());
+            }
+            ToStrategy::Action {
+                action: StrategyAction::ApplyTemperament(temperament),
+                ..
+            } if current.is_some() && *temperament < T::num_temperaments() => {
+                self.patterns
+                    .for_each_pattern_stack_mut(|stack| stack.apply_temperament(*temperament));
+            }
+            ToStrategy::Action {
+                action: StrategyAction::MakeNeighbourhoodPure,
+                ..
+            } if current.is_some() => {
+                self.patterns
+                    .for_each_pattern_stack_mut(|stack| stack.make_pure());
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_action(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        action: StrategyAction,
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        match action {
+            StrategyAction::ToggleChordMatching => self.patterns.handle_action(action, forward),
+            StrategyAction::ToggleReanchor => {
+                self.walk = !self.walk;
+                forward.push_back(FromStrategy::ReanchorOnMatch {
+                    reanchor: self.walk,
+                });
+            }
+            StrategyAction::SetReferenceToCurrent => {
+                if let Some((_, reference)) = self.current_fit {
+                    self.walk_to(reference, forward);
+                }
+            }
+            StrategyAction::Reset => {
+                self.current_fit = None {};
+                self.inner
+                    .handle_action(keys, tunings, action, time, forward);
+            }
+            _ => {
+                self.inner
+                    .handle_action(keys, tunings, action, time, forward);
+            }
+        }
+        self.solve(keys, tunings, time, forward)
+    }
+}
+
+impl<T: StackType> Strategy<T> for Walking<T> {
+    fn note_on<'a>(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &'a mut [Stack<T>; 128],
+        note: u8,
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> Option<(Semitones, &'a Stack<T>)> {
+        if self.solve(keys, tunings, time, forward) {
+            let stack = &tunings[note as usize];
+            Some((
+                stack.absolute_semitones(self.inner.tuning_reference.c4_semitones()),
+                stack,
+            ))
+        } else {
+            None {}
+        }
+    }
+
+    fn note_off(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        _note: u8,
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        self.solve(keys, tunings, time, forward)
+    }
+
+    fn handle_msg(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        msg: ToStrategy<T>,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) -> bool {
+        if self.temper_patterns {
+            self.temper_patterns_like(&msg);
+        }
+        match msg {
+            ToStrategy::ToHarmonyStrategy(msg, time) => {
+                self.patterns.handle_msg(msg) && self.solve(keys, tunings, time, forward)
+            }
+            ToStrategy::ReanchorOnMatch { reanchor } => {
+                self.walk = reanchor;
+                forward.push_back(FromStrategy::ReanchorOnMatch { reanchor });
+                true
+            }
+            ToStrategy::TemperPatterns { temper } => {
+                self.temper_patterns = temper;
+                true
+            }
+            ToStrategy::Action { action, time } => {
+                self.handle_action(keys, tunings, action, time, forward)
+            }
+            ToStrategy::SetGroupMs { .. }
+            | ToStrategy::SetSpringStiffness { .. }
+            | ToStrategy::SetAnchorStiffness { .. }
+            | ToStrategy::SetHysteresis { .. }
+            | ToStrategy::SetDriftCompensation { .. }
+            | ToStrategy::Tick { .. } => false,
+            _ => {
+                if let Some(time) = self
+                    .inner
+                    .handle_msg_but_dont_retune(keys, tunings, msg, forward)
+                {
+                    self.solve(keys, tunings, time, forward)
+                } else {
+                    true
+                }
+            }
+        }
+    }
+
+    fn start(
+        &mut self,
+        keys: &[KeyState; 128],
+        tunings: &mut [Stack<T>; 128],
+        time: Instant,
+        forward: &mut VecDeque<FromStrategy<T>>,
+    ) {
+        self.inner.start_but_dont_retune(forward);
+        self.inner.mark_all_tunings_as_outdated();
+        self.solve(keys, tunings, time, forward);
+    }
+}
+
+impl<T: StackType> ExtractConfig<StrategyConfig<T>> for Walking<T> {
+    fn extract_config(&self) -> StrategyConfig<T> {
+        let HarmonyStrategyConfig::ChordList(patterns) = self.patterns.extract_config();
+        match self.inner.extract_config() {
+            StrategyConfig::StaticTuning(inner) => StrategyConfig::Walking(WalkingConfig {
+                patterns,
+                inner,
+                walk: self.walk,
+                temper_patterns: self.temper_patterns,
+            }),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use midi_msg::Channel;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::interval::stacktype::fivelimit::mock::{
+        static_tuning_config, triad, MockFiveLimitStackType, TWELVE_NOTES,
+    };
+
+    fn walking(walk: bool) -> Walking<MockFiveLimitStackType> {
+        Walking::new(WalkingConfig {
+            patterns: ChordListConfig {
+                enable: true,
+                patterns: vec![triad([0, 0, 1]), triad([0, 1, -1])],
+            },
+            inner: static_tuning_config(&[TWELVE_NOTES]),
+            walk,
+            temper_patterns: false,
+        })
+    }
+
+    struct State {
+        start: Instant,
+        keys: [KeyState; 128],
+        tunings: [Stack<MockFiveLimitStackType>; 128],
+        forward: VecDeque<FromStrategy<MockFiveLimitStackType>>,
+    }
+
+    impl State {
+        fn new() -> Self {
+            let start = Instant::now();
+            Self {
+                start,
+                keys: core::array::from_fn(|_| KeyState::new(start)),
+                tunings: core::array::from_fn(|_| Stack::new_zero()),
+                forward: VecDeque::new(),
+            }
+        }
+
+        /// Press all `notes`, and returns the target coefficients of their tunings. Release them
+        /// afterwards, if `release` is set.
+        fn play(
+            &mut self,
+            s: &mut Walking<MockFiveLimitStackType>,
+            notes: &[u8],
+            release: bool,
+        ) -> Vec<Vec<StackCoeff>> {
+            for &note in notes {
+                self.keys[note as usize].note_on(Channel::Ch1, self.start);
+                assert!(s
+                    .note_on(
+                        &self.keys,
+                        &mut self.tunings,
+                        note,
+                        self.start,
+                        &mut self.forward
+                    )
+                    .is_some());
+            }
+            let res = notes
+                .iter()
+                .map(|&note| self.tunings[note as usize].target_coefficients().to_vec())
+                .collect();
+            if release {
+                for &note in notes {
+                    self.keys[note as usize].note_off(Channel::Ch1, false, self.start);
+                    assert!(s.note_off(
+                        &self.keys,
+                        &mut self.tunings,
+                        note,
+                        self.start,
+                        &mut self.forward
+                    ));
+                }
+            }
+            res
+        }
+    }
+
+    fn key_centre(s: &Walking<MockFiveLimitStackType>) -> Vec<StackCoeff> {
+        s.inner.reference.target_coefficients().to_vec()
+    }
+
+    #[test]
+    fn test_walking() {
+        let mut s = walking(true);
+        let mut state = State::new();
+
+        // A minor, D minor, G major, C major: each chord is tuned relative to the previous one,
+        // and the final C is a syntonic comma lower than the first key centre.
+        assert_eq!(
+            state.play(&mut s, &[57, 60, 64], true),
+            vec![vec![0, -1, 1], vec![0, 0, 0], vec![0, 0, 1]]
+        );
+        assert_eq!(key_centre(&s), vec![0, -1, 1]);
+        state.play(&mut s, &[62, 65, 69], true);
+        assert_eq!(key_centre(&s), vec![1, -2, 1]);
+        state.play(&mut s, &[55, 59, 62], true);
+        assert_eq!(key_centre(&s), vec![1, -3, 1]);
+        assert_eq!(
+            state.play(&mut s, &[60, 64, 67], true),
+            vec![vec![2, -4, 1], vec![2, -4, 2], vec![2, -3, 1]]
+        );
+        assert_eq!(key_centre(&s), vec![2, -4, 1]);
+
+        // single notes don't fit any pattern, and are tuned relative to the key centre
+        assert_eq!(state.play(&mut s, &[64], true), vec![vec![2, -4, 2]]);
+        assert_eq!(key_centre(&s), vec![2, -4, 1]);
+    }
+
+    #[test]
+    fn test_not_walking() {
+        let mut s = walking(false);
+        let mut state = State::new();
+
+        state.play(&mut s, &[57, 60, 64], true);
+        state.play(&mut s, &[62, 65, 69], true);
+        state.play(&mut s, &[55, 59, 62], true);
+        assert_eq!(
+            state.play(&mut s, &[60, 64, 67], true),
+            vec![vec![0, 0, 0], vec![0, 0, 1], vec![0, 1, 0]]
+        );
+        assert_eq!(key_centre(&s), vec![0, 0, 0]);
+
+        // moving the key centre explicitly
+        state.play(&mut s, &[57, 60, 64], false);
+        assert!(s.handle_msg(
+            &state.keys,
+            &mut state.tunings,
+            ToStrategy::Action {
+                action: StrategyAction::SetReferenceToCurrent,
+                time: state.start,
+            },
+            &mut state.forward,
+        ));
+        assert_eq!(key_centre(&s), vec![0, -1, 1]);
+    }
+}